@@ -0,0 +1,22 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+// Compresses bangs.json into OUT_DIR, where setup embeds it as the bang
+// list for a first run without network
+fn main() {
+    println!("cargo:rerun-if-changed=bangs.json");
+
+    let json = fs::read("bangs.json").expect("Failed to read bangs.json");
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("bangs.json.gz");
+
+    let mut encoder = GzEncoder::new(
+        File::create(&out).expect("Failed to create compressed bangs"),
+        Compression::best(),
+    );
+    encoder.write_all(&json).expect("Failed to compress bangs.json");
+    encoder.finish().expect("Failed to compress bangs.json");
+}
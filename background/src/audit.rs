@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use shared::config::{AuditConfig, Config};
+use shared::logging;
+use shared::models::{AuditEntry, AuditOutcome, Command};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// How often entries `[audit]` no longer keeps are dropped
+const PRUNE_EVERY: Duration = Duration::from_secs(60 * 60);
+
+// Every result run and whether it went through, one JSON object a line in
+// audit.jsonl in the data dir. Lines are only ever added to the end; the
+// file is rewritten only to drop the entries `[audit]` no longer keeps.
+// Lines are redacted like the log, so copied passwords stay out of it.
+pub struct AuditLog {
+    path: PathBuf,
+    // One append or prune at a time, so lines are never cut in two
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn open() -> Result<Self> {
+        let proj_dirs = directories::ProjectDirs::from("", "", "orion")
+            .context("Failed to get project directories")?;
+        Ok(Self {
+            path: proj_dirs.data_dir().join("audit.jsonl"),
+            lock: Mutex::new(()),
+        })
+    }
+
+    // A failure to record is logged, the command having run either way. The
+    // file is written on a blocking thread, off the async workers.
+    pub async fn record(self: &Arc<Self>, cmd: &Command, profile: &str, outcome: &Result<()>) {
+        let entry = AuditEntry {
+            at: chrono::Utc::now().timestamp(),
+            profile: profile.to_string(),
+            query: cmd.query.clone(),
+            title: cmd.name.clone(),
+            action: cmd.action.clone(),
+            outcome: match outcome {
+                Ok(()) => AuditOutcome::Succeeded,
+                Err(e) => AuditOutcome::Failed(format!("{:#}", e)),
+            },
+        };
+        let title = entry.title.clone();
+        let audit = self.clone();
+        match tokio::task::spawn_blocking(move || audit.append(&entry)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => logging::error(&format!("Failed to record {:?} in the audit trail: {:?}", title, e)),
+            Err(e) => logging::error(&format!("Failed to record {:?} in the audit trail: {:?}", title, e)),
+        }
+    }
+
+    fn append(&self, entry: &AuditEntry) -> Result<()> {
+        let line = serde_json::to_string(entry)?;
+        let _lock = self.lock.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open audit trail at {:?}", self.path))?;
+        writeln!(file, "{}", logging::redact(&line))?;
+        Ok(())
+    }
+
+    // The latest entries, newest first
+    pub async fn recent(self: &Arc<Self>, limit: usize) -> Result<Vec<AuditEntry>> {
+        let audit = self.clone();
+        tokio::task::spawn_blocking(move || audit.read_recent(limit)).await?
+    }
+
+    fn read_recent(&self, limit: usize) -> Result<Vec<AuditEntry>> {
+        let _lock = self.lock.lock().unwrap();
+        let mut entries = self.read()?;
+        entries.reverse();
+        entries.truncate(limit);
+        Ok(entries)
+    }
+
+    // Drops entries past `max_age_days` and the oldest past `max_entries`,
+    // returning how many went
+    pub fn prune(&self, config: &AuditConfig) -> Result<usize> {
+        let _lock = self.lock.lock().unwrap();
+        let mut entries = self.read()?;
+        let count = entries.len();
+
+        if let Some(days) = config.max_age_days {
+            let cutoff = chrono::Utc::now().timestamp() - (days * 24 * 60 * 60) as i64;
+            entries.retain(|entry| entry.at >= cutoff);
+        }
+        let excess = entries.len().saturating_sub(config.max_entries);
+        entries.drain(..excess);
+
+        let dropped = count - entries.len();
+        if dropped > 0 {
+            let mut content = String::new();
+            for entry in &entries {
+                content.push_str(&serde_json::to_string(entry)?);
+                content.push('\n');
+            }
+            // Replaced in one go, so a crash leaves the old file or the new
+            let temp = self.path.with_extension("jsonl.tmp");
+            fs::write(&temp, content).with_context(|| format!("Failed to write {:?}", temp))?;
+            fs::rename(&temp, &self.path)
+                .with_context(|| format!("Failed to replace audit trail at {:?}", self.path))?;
+        }
+        Ok(dropped)
+    }
+
+    // Oldest first. A missing file is an empty trail, and a line that
+    // doesn't parse, cut short by a crash, is skipped.
+    fn read(&self) -> Result<Vec<AuditEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read audit trail at {:?}", self.path))?;
+        Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+    }
+}
+
+// Applies `[audit]` retention now and every hour after, so a changed
+// config is followed without a restart
+pub fn spawn_pruning(audit: Arc<AuditLog>, config: Arc<tokio::sync::Mutex<Config>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_EVERY);
+        loop {
+            interval.tick().await;
+            let retention = config.lock().await.audit.clone();
+            let audit = audit.clone();
+            match tokio::task::spawn_blocking(move || audit.prune(&retention)).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(dropped)) => logging::info(&format!("Dropped {} old audit entries", dropped)),
+                Ok(Err(e)) => logging::error(&format!("Failed to prune the audit trail: {:?}", e)),
+                Err(e) => logging::error(&format!("Failed to prune the audit trail: {:?}", e)),
+            }
+        }
+    });
+}
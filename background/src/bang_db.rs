@@ -0,0 +1,315 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use shared::bangs::{self, LoadedBangs};
+use shared::models::Bang;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+// Bangs kept in SQLite next to the bang files, so startup doesn't parse
+// bangs.json and triggers are looked up through the table's index. The JSON
+// files stay the source of truth and are imported again once they change.
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS bangs (
+        trigger TEXT PRIMARY KEY,
+        category TEXT NOT NULL,
+        domain TEXT NOT NULL,
+        score INTEGER NOT NULL,
+        display_name TEXT NOT NULL,
+        subcategory TEXT NOT NULL,
+        url_template TEXT NOT NULL,
+        custom INTEGER NOT NULL,
+        -- Disabled bangs are only kept for listing
+        disabled INTEGER NOT NULL,
+        generation INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS aliases (
+        alias TEXT PRIMARY KEY,
+        trigger TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+// Bumped whenever the tables change. They only cache the bang files, so
+// older tables are dropped and imported again.
+const SCHEMA_VERSION: i64 = 2;
+
+// Bangs written per transaction. The connection is let go between batches,
+// so a lookup from a search waits on one batch at most, not the whole import.
+const IMPORT_BATCH: usize = 500;
+
+const UPSERT: &str = "
+    INSERT INTO bangs (category, domain, score, display_name, subcategory, trigger, url_template, custom, disabled, generation)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+    ON CONFLICT(trigger) DO UPDATE SET
+        category = excluded.category,
+        domain = excluded.domain,
+        score = excluded.score,
+        display_name = excluded.display_name,
+        subcategory = excluded.subcategory,
+        url_template = excluded.url_template,
+        custom = excluded.custom,
+        disabled = excluded.disabled,
+        generation = excluded.generation";
+
+const BANG_COLUMNS: &str = "category, domain, score, display_name, subcategory, trigger, url_template";
+
+pub struct BangDb {
+    // rusqlite connections aren't Sync, queries are short enough to share
+    // one behind a lock
+    conn: Mutex<Connection>,
+    // Held for a whole import, which takes the connection a batch at a
+    // time, so two imports don't delete each other's rows
+    importing: Mutex<()>,
+}
+
+impl BangDb {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open bang database at {:?}", path))?;
+        Self::with_connection(conn)
+    }
+
+    // For when the database file can't be used, bangs then get imported on
+    // every start
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version != SCHEMA_VERSION {
+            conn.execute_batch(
+                "DROP TABLE IF EXISTS bangs; DROP TABLE IF EXISTS aliases; DROP TABLE IF EXISTS meta;",
+            )?;
+            conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn: Mutex::new(conn), importing: Mutex::new(()) })
+    }
+
+    pub fn path() -> Result<PathBuf> {
+        Ok(bangs::bangs_path()?.with_file_name("bangs.db"))
+    }
+
+    // Imports the bang files when they changed since the last import, or
+    // always when `force` is set. Returns what was imported, or None when
+    // the database was already up to date.
+    pub fn sync(&self, force: bool) -> Result<Option<LoadedBangs>> {
+        let _importing = self.importing.lock().unwrap_or_else(|e| e.into_inner());
+        let signature = source_signature()?;
+        if !force && self.meta("signature")?.as_deref() == Some(signature.as_str()) {
+            return Ok(None);
+        }
+
+        let loaded = bangs::load_bangs()?;
+        self.import(&loaded)?;
+        self.set_meta("signature", &signature)?;
+        Ok(Some(loaded))
+    }
+
+    // Replaces the stored bangs with the loaded ones, updating rows in place
+    // and deleting the ones no longer listed. The first bang with a trigger
+    // wins. Until the last batch is in, lookups find each bang as either
+    // the old or the new version, and bangs no longer listed until the end.
+    fn import(&self, loaded: &LoadedBangs) -> Result<()> {
+        let generation: i64 = self.lock().query_row(
+            "SELECT COALESCE(MAX(generation), 0) + 1 FROM bangs",
+            [],
+            |row| row.get(0),
+        )?;
+
+        let enabled = loaded.bangs.iter().map(|b| (b, false));
+        let disabled = loaded.disabled.iter().map(|b| (b, true));
+        let mut seen = HashSet::with_capacity(loaded.bangs.len());
+        let rows: Vec<(&Bang, bool)> = enabled.chain(disabled).filter(|(b, _)| seen.insert(b.trigger.as_str())).collect();
+
+        for batch in rows.chunks(IMPORT_BATCH) {
+            let mut conn = self.lock();
+            let tx = conn.transaction()?;
+            {
+                let mut upsert = tx.prepare_cached(UPSERT)?;
+                for (bang, disabled) in batch {
+                    upsert.execute(params![
+                        bang.category,
+                        bang.domain,
+                        bang.score,
+                        bang.display_name,
+                        bang.subcategory,
+                        bang.trigger,
+                        bang.url_template,
+                        loaded.custom.contains(&bang.trigger),
+                        disabled,
+                        generation,
+                    ])?;
+                }
+            }
+            tx.commit()?;
+        }
+
+        let mut conn = self.lock();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM bangs WHERE generation != ?1", params![generation])?;
+        tx.execute("DELETE FROM aliases", [])?;
+        {
+            let mut insert = tx.prepare("INSERT INTO aliases (alias, trigger) VALUES (?1, ?2)")?;
+            for (alias, trigger) in &loaded.aliases {
+                insert.execute(params![alias, trigger])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn count(&self) -> Result<usize> {
+        let count: i64 = self.lock().query_row("SELECT COUNT(*) FROM bangs WHERE disabled = 0", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    // Looks up a trigger, then an alias from the bang overlay. A real
+    // trigger always wins over an alias with the same name.
+    pub fn get(&self, trigger: &str) -> Result<Option<Bang>> {
+        let conn = self.lock();
+        let bang = conn
+            .query_row(
+                &format!("SELECT {} FROM bangs WHERE trigger = ?1 AND disabled = 0", BANG_COLUMNS),
+                params![trigger],
+                row_to_bang,
+            )
+            .optional()?;
+        if bang.is_some() {
+            return Ok(bang);
+        }
+
+        let bang = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM bangs WHERE trigger = (SELECT trigger FROM aliases WHERE alias = ?1) AND disabled = 0",
+                    BANG_COLUMNS
+                ),
+                params![trigger],
+                row_to_bang,
+            )
+            .optional()?;
+        Ok(bang)
+    }
+
+    // Bangs whose trigger starts with `prefix`, ignoring ASCII case
+    pub fn with_prefix(&self, prefix: &str) -> Result<Vec<Bang>> {
+        self.query(
+            &format!("SELECT {} FROM bangs WHERE trigger LIKE ?1 ESCAPE '\\' AND disabled = 0", BANG_COLUMNS),
+            &format!("{}%", escape_like(prefix)),
+        )
+    }
+
+    // Bangs whose trigger or site name contains `text`, ignoring ASCII case
+    pub fn containing(&self, text: &str) -> Result<Vec<Bang>> {
+        self.query(
+            &format!(
+                "SELECT {} FROM bangs WHERE (trigger LIKE ?1 ESCAPE '\\' OR display_name LIKE ?1 ESCAPE '\\') AND disabled = 0",
+                BANG_COLUMNS
+            ),
+            &format!("%{}%", escape_like(text)),
+        )
+    }
+
+    // A page of bangs whose trigger, name or domain contains `filter`,
+    // disabled ones included, and how many match in all. An exact trigger
+    // comes first, then the user's own bangs, then the rest by trigger.
+    pub fn page(&self, filter: &str, offset: usize, limit: usize) -> Result<(Vec<Bang>, usize)> {
+        const MATCHES: &str = "trigger LIKE ?1 ESCAPE '\\' OR display_name LIKE ?1 ESCAPE '\\' OR domain LIKE ?1 ESCAPE '\\'";
+        let pattern = format!("%{}%", escape_like(filter));
+        let conn = self.lock();
+
+        let total: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM bangs WHERE {}", MATCHES),
+            params![pattern],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM bangs WHERE {}
+             ORDER BY trigger = ?2 DESC, custom DESC, trigger
+             LIMIT ?3 OFFSET ?4",
+            BANG_COLUMNS, MATCHES
+        ))?;
+        let bangs = stmt
+            .query_map(params![pattern, filter, limit as i64, offset as i64], row_to_bang)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok((bangs, total as usize))
+    }
+
+    fn query(&self, sql: &str, pattern: &str) -> Result<Vec<Bang>> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare_cached(sql)?;
+        let bangs = stmt
+            .query_map(params![pattern], row_to_bang)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(bangs)
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>> {
+        let value = self
+            .lock()
+            .query_row("SELECT value FROM meta WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()?;
+        Ok(value)
+    }
+
+    fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.lock().execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // A panic mid-query leaves nothing half done that SQLite wouldn't
+        // roll back, so a poisoned lock is still usable
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn row_to_bang(row: &Row) -> rusqlite::Result<Bang> {
+    Ok(Bang::new(
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+        row.get(6)?,
+    ))
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+// Size and modification time of both bang files, which change whenever
+// either file is written
+fn source_signature() -> Result<String> {
+    let mut signature = String::new();
+    for path in [bangs::bangs_path()?, bangs::custom_bangs_path()?] {
+        match std::fs::metadata(&path) {
+            Ok(meta) => {
+                let modified = meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_nanos());
+                signature.push_str(&format!("{}:{};", meta.len(), modified));
+            }
+            Err(_) => signature.push_str("-;"),
+        }
+    }
+    Ok(signature)
+}
@@ -0,0 +1,389 @@
+use anyhow::{Context, Result};
+use crate::bang_db::BangDb;
+use shared::bangs::{self, BangUsage};
+use shared::config::{self, BangPosition, Profile};
+use shared::logging;
+use shared::matcher::fuzzy_match;
+use shared::template::TemplateVars;
+use shared::models::{Action, Bang, BangListQuery, BangPage, BangRefresh, SearchResult};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
+
+const MAX_SUGGESTIONS: usize = 8;
+// Skipped entries logged individually, the rest are only counted
+const MAX_LOGGED_ERRORS: usize = 5;
+const MAX_DISCOVERED: usize = 2;
+// Largest page handed out by `list`, whatever the client asks for
+const MAX_LIST_PAGE: usize = 500;
+// Bang hints rank below close matches from the providers
+const DISCOVERY_WEIGHT: f32 = 0.6;
+
+// What a query's bangs may resolve to: the profile's allow and deny lists,
+// the config's deny list, the categories in effect and the config's aliases
+pub struct BangScope<'a> {
+    pub profile: &'a Profile,
+    pub deny: &'a [String],
+    // Empty allows every category
+    pub categories: &'a [String],
+    pub aliases: &'a BTreeMap<String, String>,
+}
+
+impl BangScope<'_> {
+    fn allows(&self, bang: &Bang) -> bool {
+        self.profile.allows_bang(&bang.trigger)
+            && !config::lists_trigger(self.deny, &bang.trigger)
+            && (self.categories.is_empty() || bangs::in_category(bang, self.categories))
+    }
+
+    // The trigger a query word stands for, None when the profile only
+    // takes triggers written with `!` and the word has none
+    fn trigger<'w>(&self, word: &'w str) -> Option<&'w str> {
+        match word.strip_prefix('!') {
+            Some(trigger) => Some(trigger),
+            None if self.profile.bang_require_prefix => None,
+            None => Some(word),
+        }
+    }
+}
+
+// Bangs in the bang database, imported from the bang files when they
+// change, rather than reading bangs.json for every query. SQLite is
+// blocking, so async callers reach the database on a blocking thread.
+pub struct BangStore {
+    db: Arc<BangDb>,
+    usage: RwLock<BangUsage>,
+    // Held while the counts are saved, so saves land in the order the
+    // counts changed
+    saving: Mutex<()>,
+}
+
+impl BangStore {
+    // Opens the bangs as the service last left them. Bringing them up to
+    // date with the bang files is left to `sync_in_background`, so startup
+    // doesn't wait on importing thousands of bangs.
+    pub fn load() -> Self {
+        let db = BangDb::path()
+            .and_then(|path| BangDb::open(&path))
+            .or_else(|e| {
+                logging::error(&format!("Failed to open bang database, keeping bangs in memory: {:?}", e));
+                BangDb::open_in_memory()
+            })
+            .expect("Failed to create an in-memory bang database");
+
+        let usage = BangUsage::load().unwrap_or_else(|e| {
+            logging::error(&format!("Failed to load bang usage: {:?}", e));
+            BangUsage::default()
+        });
+
+        Self {
+            db: Arc::new(db),
+            usage: RwLock::new(usage),
+            saving: Mutex::new(()),
+        }
+    }
+
+    // Imports the bang files on a blocking thread if they changed since the
+    // last import, searches using the bangs already stored meanwhile
+    pub fn sync_in_background(self: &Arc<Self>) {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || match store.db.sync(false) {
+            Ok(Some(loaded)) => log_summary(loaded.bangs.len(), &loaded.errors),
+            Ok(None) => logging::info(&format!("Loaded {} bangs from the bang database", store.db.count().unwrap_or(0))),
+            Err(e) => logging::error(&format!("Failed to load bangs: {:?}", e)),
+        });
+    }
+
+    // Runs a query on a blocking thread
+    async fn with_db<T: Send + 'static>(&self, query: impl FnOnce(&BangDb) -> Result<T> + Send + 'static) -> Result<T> {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || query(db.as_ref())).await?
+    }
+
+    // Imports the bang files again, keeping the current bangs if that fails
+    pub async fn reload(&self) -> Result<BangRefresh> {
+        let (loaded, count) = self
+            .with_db(|db| {
+                let loaded = db.sync(true)?.unwrap_or_default();
+                Ok((loaded, db.count()?))
+            })
+            .await?;
+        log_summary(loaded.bangs.len(), &loaded.errors);
+
+        Ok(BangRefresh {
+            count,
+            errors: loaded.errors,
+        })
+    }
+
+    // Imports the bang files only if they changed since the last import
+    pub async fn sync(&self) -> Result<()> {
+        if let Some(loaded) = self.with_db(|db| db.sync(false)).await? {
+            logging::info("Bang files changed, reloading");
+            log_summary(loaded.bangs.len(), &loaded.errors);
+        }
+        Ok(())
+    }
+
+    // Downloads a fresh bangs.json and swaps it in, leaving the current
+    // file and bangs alone unless the download has usable entries
+    pub async fn refresh(&self) -> Result<BangRefresh> {
+        let content = reqwest::get(bangs::BANGS_URL)
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let (downloaded, errors) = bangs::parse_bangs(&content)
+            .context("Downloaded bangs could not be parsed")?;
+        if downloaded.is_empty() {
+            return Err(anyhow::anyhow!("Downloaded bang list has no valid bangs"));
+        }
+
+        // Invalid entries are left out so the next load doesn't trip on them
+        std::fs::write(bangs::bangs_path()?, serde_json::to_string(&downloaded)?)?;
+        self.reload().await?;
+
+        Ok(BangRefresh {
+            count: downloaded.len(),
+            errors,
+        })
+    }
+
+    // A page of every bang for browsing, ignoring the profile's scope
+    pub async fn list(&self, query: BangListQuery) -> Result<BangPage> {
+        let limit = query.limit.min(MAX_LIST_PAGE);
+        let (filter, offset) = (query.filter.trim().to_string(), query.offset);
+        let (bangs, total) = self.with_db(move |db| db.page(&filter, offset, limit)).await?;
+        Ok(BangPage { query, bangs, total })
+    }
+
+    pub async fn usage(&self) -> RwLockReadGuard<'_, BangUsage> {
+        self.usage.read().await
+    }
+
+    // Counts a bang redirect, losing the count rather than the redirect
+    // when it can't be saved
+    pub async fn record_use(&self, trigger: &str) {
+        let _saving = self.saving.lock().await;
+        // Searches only wait for the count, not for the file
+        let usage = {
+            let mut usage = self.usage.write().await;
+            usage.record(trigger);
+            usage.clone()
+        };
+        let saved = tokio::task::spawn_blocking(move || usage.save()).await;
+        if let Err(e) = saved.map_err(anyhow::Error::from).and_then(|saved| saved) {
+            logging::error(&format!("Failed to save bang usage: {:?}", e));
+        }
+    }
+
+    // Looks up a trigger or alias, falling back to the config's aliases,
+    // and then to the trigger in lower case if the profile ignores case
+    pub async fn resolve(&self, trigger: &str, scope: &BangScope<'_>) -> Option<Bang> {
+        let mut bang = self.find(trigger, scope).await;
+        if bang.is_none() && scope.profile.bang_ignore_case {
+            let lower = trigger.to_lowercase();
+            if lower != trigger {
+                bang = self.find(&lower, scope).await;
+            }
+        }
+        bang.filter(|bang| scope.allows(bang))
+    }
+
+    async fn find(&self, trigger: &str, scope: &BangScope<'_>) -> Option<Bang> {
+        if let Some(bang) = self.lookup(trigger).await {
+            return Some(bang);
+        }
+        match scope.aliases.get(trigger) {
+            Some(target) => self.lookup(target).await,
+            None => None,
+        }
+    }
+
+    async fn lookup(&self, trigger: &str) -> Option<Bang> {
+        let owned = trigger.to_string();
+        self.with_db(move |db| db.get(&owned)).await.unwrap_or_else(|e| {
+            logging::error(&format!("Failed to look up !{}: {:?}", trigger, e));
+            None
+        })
+    }
+
+    // The bangs a query asks for and the text to search with them, from a
+    // chain of leading `!` triggers to a bare trigger anywhere in the query
+    pub async fn match_query(&self, text: &str, scope: &BangScope<'_>) -> Option<(Vec<Bang>, String)> {
+        // Leading bangs feed each other, `!translate !w quantum` hands the
+        // Wikipedia URL to the translator
+        let mut chain = Vec::new();
+        let mut rest = text.trim_start();
+        while let Some((word, tail)) = rest.split_once(' ') {
+            let Some(trigger) = word.strip_prefix('!') else {
+                break;
+            };
+            let Some(bang) = self.resolve(trigger, scope).await else {
+                break;
+            };
+            chain.push(bang);
+            rest = tail.trim_start();
+        }
+        if chain.len() > 1 {
+            return Some((chain, rest.to_string()));
+        }
+
+        // A bang on its own opens the bang's site
+        if let Some(trigger) = text.trim().strip_prefix('!') {
+            if !trigger.contains(' ') {
+                if let Some(bang) = self.resolve(trigger, scope).await {
+                    return Some((vec![bang], String::new()));
+                }
+            }
+        }
+
+        // Try to find a bang at the start of the query
+        if let Some((prefix, rest)) = text.split_once(' ') {
+            if let Some(bang) = self.resolve_word(prefix, scope).await {
+                return Some((vec![bang], rest.to_string()));
+            }
+        }
+
+        if scope.profile.bang_position == BangPosition::PrefixOnly {
+            return None;
+        }
+
+        // Try to find a bang at the end of the query
+        if let Some((search, trigger)) = text.rsplit_once(' ') {
+            if let Some(bang) = self.resolve_word(trigger, scope).await {
+                return Some((vec![bang], search.to_string()));
+            }
+        }
+
+        // Try to find a bang in the middle of the query
+        let words: Vec<&str> = text.split(' ').collect();
+        for i in 1..words.len().saturating_sub(1) {
+            if let Some(bang) = self.resolve_word(words[i], scope).await {
+                let search = format!("{} {}", words[..i].join(" "), words[i + 1..].join(" "));
+                return Some((vec![bang], search));
+            }
+        }
+
+        None
+    }
+
+    // A query word as a trigger, as the profile takes them
+    async fn resolve_word(&self, word: &str, scope: &BangScope<'_>) -> Option<Bang> {
+        let trigger = scope.trigger(word)?;
+        self.resolve(trigger, scope).await
+    }
+
+    // Bangs whose trigger starts with the typed prefix, exact match first,
+    // then the ones used most, then by popularity
+    pub async fn suggest(&self, prefix: &str, scope: &BangScope<'_>) -> Vec<Bang> {
+        let owned = prefix.to_string();
+        let mut matches = self.with_db(move |db| db.with_prefix(&owned)).await.unwrap_or_else(|e| {
+            logging::error(&format!("Failed to query bangs: {:?}", e));
+            Vec::new()
+        });
+        matches.retain(|b| scope.allows(b));
+
+        let usage = self.usage().await;
+        matches.sort_by(|a, b| {
+            let a_exact = a.trigger.eq_ignore_ascii_case(prefix);
+            let b_exact = b.trigger.eq_ignore_ascii_case(prefix);
+            b_exact
+                .cmp(&a_exact)
+                .then(usage.count(&b.trigger).cmp(&usage.count(&a.trigger)))
+                .then(b.score.cmp(&a.score))
+                .then(a.trigger.len().cmp(&b.trigger.len()))
+        });
+
+        matches.truncate(MAX_SUGGESTIONS);
+        matches
+    }
+
+    // Bangs whose site name or trigger contains the query, offered as
+    // results that complete the trigger into the input, e.g. `youtu` gives
+    // `!yt YouTube`
+    pub async fn discover(&self, query: &str, scope: &BangScope<'_>) -> Vec<SearchResult> {
+        let query = query.trim();
+        // Short or multi-word queries would match far too many of the bangs
+        if query.chars().count() < 3 || query.starts_with('!') || query.contains(char::is_whitespace) {
+            return Vec::new();
+        }
+
+        let owned = query.to_string();
+        let candidates = self.with_db(move |db| db.containing(&owned)).await.unwrap_or_else(|e| {
+            logging::error(&format!("Failed to query bangs: {:?}", e));
+            Vec::new()
+        });
+
+        let mut matches: Vec<(f32, Bang)> = candidates
+            .into_iter()
+            .filter(|bang| scope.allows(bang))
+            .filter_map(|bang| {
+                let score = [&bang.display_name, &bang.trigger]
+                    .into_iter()
+                    .filter_map(|text| fuzzy_match(query, text))
+                    // Only contiguous matches, scattered letters are noise here
+                    .filter(|m| m.ranges.len() == 1)
+                    .map(|m| m.score)
+                    .fold(None, |best: Option<f32>, score| Some(best.map_or(score, |b| b.max(score))))?;
+                Some((score, bang))
+            })
+            .collect();
+
+        matches.sort_by(|(a_score, a), (b_score, b)| {
+            b_score
+                .partial_cmp(a_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.score.cmp(&a.score))
+        });
+
+        matches
+            .into_iter()
+            .take(MAX_DISCOVERED)
+            .map(|(score, bang)| {
+                let title = format!("!{} {}", bang.trigger, bang.display_name);
+                let highlights = fuzzy_match(query, &title).map(|m| m.ranges).unwrap_or_default();
+                SearchResult::new(
+                    title,
+                    Some(format!("Search {} with !{}", bang.domain, bang.trigger)),
+                    Action::CompleteQuery(format!("!{} ", bang.trigger)),
+                    score * DISCOVERY_WEIGHT,
+                )
+                .with_highlights(highlights)
+                .with_category("Bang")
+                .with_completion(format!("!{} ", bang.trigger))
+            })
+            .collect()
+    }
+}
+
+// The result for a matched bang, its description the URL Enter will open
+pub fn preview(chain: &[Bang], search: &str, vars: &TemplateVars) -> SearchResult {
+    let url = bangs::expand_chain(chain, search, vars);
+    let names = chain.iter().map(|b| b.display_name.as_str()).collect::<Vec<_>>().join(" → ");
+    let title = match search.trim() {
+        "" => format!("Open {}", names),
+        search => format!("Search {} for \"{}\"", names, search),
+    };
+
+    let triggers: Vec<String> = chain.iter().map(|b| b.trigger.clone()).collect();
+    let subtitle = triggers.iter().map(|t| format!("!{}", t)).collect::<Vec<_>>().join(" ");
+
+    SearchResult::new(title, Some(url.clone()), Action::OpenBang { url: url.clone(), triggers }, 1.0)
+        .with_category("Bang")
+        .with_subtitle(subtitle)
+        .with_action("Copy URL", Action::CopyToClipboard(url))
+}
+
+fn log_summary(count: usize, errors: &[String]) {
+    logging::info(&format!("Loaded {} bangs", count));
+    if errors.is_empty() {
+        return;
+    }
+
+    logging::warn(&format!("Skipped {} invalid bangs", errors.len()));
+    for error in errors.iter().take(MAX_LOGGED_ERRORS) {
+        logging::warn(error);
+    }
+}
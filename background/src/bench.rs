@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use shared::config::Config;
+use shared::logging;
+use shared::models::SearchQuery;
+use shared::query;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::bangs::BangStore;
+use crate::file_index::FileIndex;
+use crate::history::HistoryStore;
+use crate::plugins::PluginHost;
+use crate::providers::ProviderRegistry;
+use crate::scripts::ScriptHost;
+use crate::usage_db::UsageDb;
+
+const USAGE: &str = "Usage: background --bench [corpus] [--rounds <n>]";
+
+// `background --bench`: replays queries against the same providers, bangs
+// and boosts a running service searches with, without starting one, and
+// reports how long searches took and how much memory they held on to.
+// Queries come from a corpus file, one per line with `#` starting a
+// comment, or else from those recorded in the history. Many rounds make it
+// a soak test, memory growing from round to round being a leak.
+pub async fn run(args: &[String], config_dir: &Path) -> Result<()> {
+    let mut corpus = None;
+    let mut rounds: usize = 3;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rounds" => {
+                let value = args.next().with_context(|| format!("--rounds needs a value\n\n{}", USAGE))?;
+                rounds = value
+                    .parse()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .with_context(|| format!("--rounds takes a number from 1, not '{}'", value))?;
+            }
+            _ if corpus.is_none() && !arg.starts_with("--") => corpus = Some(PathBuf::from(arg)),
+            other => anyhow::bail!("Unknown option '{}'\n\n{}", other, USAGE),
+        }
+    }
+
+    // Only problems are worth seeing among the report
+    logging::init(None)?;
+    logging::set_level_named("warn")?;
+
+    let mut config = Config::load(&config_dir.join("config.toml")).unwrap_or_default();
+    // Replaying a query would only measure the cache otherwise
+    config.search.cache_ttl = 0;
+    let started_with = memory();
+
+    let history = Arc::new(HistoryStore::load());
+    history.build_index();
+    let usage = Arc::new(match UsageDb::path().and_then(|path| UsageDb::open(&path)) {
+        Ok(usage) => usage,
+        Err(_) => UsageDb::open_in_memory()?,
+    });
+    let mut plugins = PluginHost::discover(&config_dir.join("plugins"));
+    let scripts = Arc::new(ScriptHost::new(&config_dir.join("scripts")));
+    // Compiled now, or the first query would measure that
+    scripts.scripts();
+    // Left unindexed, walking the roots being no part of a search
+    let files = Arc::new(FileIndex::new());
+    let providers = Arc::new(ProviderRegistry::new(history.clone(), usage.clone(), files, &mut plugins, scripts));
+    let bang_store = Arc::new(BangStore::load());
+    bang_store.sync().await?;
+
+    // Searches scan the history until it's indexed, which isn't what's
+    // being measured
+    while history.index().await.is_none() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let queries = match &corpus {
+        Some(path) => read_corpus(path)?,
+        None => recorded(&history).await,
+    };
+    if queries.is_empty() {
+        anyhow::bail!("No queries to replay, give a corpus file\n\n{}", USAGE);
+    }
+    let loaded = memory();
+
+    let mut pipeline = Vec::new();
+    let mut per_provider: BTreeMap<&'static str, Vec<Duration>> = BTreeMap::new();
+    let mut timeouts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut by_round = Vec::new();
+    let started = Instant::now();
+
+    let shared_config = Arc::new(Mutex::new(config.clone()));
+    // Replayed one after another, nothing replaces a search
+    let never = CancellationToken::new();
+    for _ in 0..rounds {
+        for text in &queries {
+            let query = SearchQuery {
+                text: text.clone(),
+                max_results: 10,
+                provider_filter: None,
+                profile: None,
+                session_id: None,
+                offset: 0,
+                request_id: None,
+                generation: None,
+            };
+
+            // The whole search as the popup sees it, bangs and all
+            let timer = Instant::now();
+            crate::handle_search(query.clone(), &shared_config, &providers, &bang_store, &usage, &never).await?;
+            pipeline.push(timer.elapsed());
+
+            // Then each provider on its own
+            let parsed = query::parse(&query.text);
+            let found = providers.search(&query, &parsed, &config, &never).await;
+            for (name, took) in found.timings {
+                per_provider.entry(name).or_default().push(took);
+            }
+            for name in found.timed_out {
+                *timeouts.entry(name).or_default() += 1;
+            }
+        }
+        by_round.push(memory());
+    }
+
+    println!(
+        "Replayed {} queries {} times in {:.1}s",
+        queries.len(),
+        rounds,
+        started.elapsed().as_secs_f64()
+    );
+    println!();
+    println!("{:<16} {:>6} {:>9} {:>9} {:>9} {:>9} {:>9}", "", "runs", "p50", "p95", "p99", "max", "timeouts");
+    print_row("search", &mut pipeline, 0);
+    for (name, durations) in &mut per_provider {
+        print_row(name, durations, timeouts.get(*name).copied().unwrap_or_default());
+    }
+
+    println!();
+    match (started_with, loaded) {
+        (Some(before), Some(after)) => println!(
+            "Resident memory: {} at start, {} with everything loaded",
+            megabytes(before.resident),
+            megabytes(after.resident)
+        ),
+        _ => println!("Resident memory isn't available on this system"),
+    }
+    let resident: Vec<String> = by_round.iter().flatten().map(|m| megabytes(m.resident)).collect();
+    if !resident.is_empty() {
+        println!("After each round: {}", resident.join(", "));
+    }
+    if let Some(peak) = by_round.last().copied().flatten() {
+        println!("Peak: {}", megabytes(peak.peak));
+    }
+    Ok(())
+}
+
+fn read_corpus(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read corpus {:?}", path))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+// What was typed before picking each result in the history, once each
+async fn recorded(history: &HistoryStore) -> Vec<String> {
+    let mut seen = HashSet::new();
+    history
+        .entries()
+        .await
+        .iter()
+        .filter_map(|entry| entry.query.clone())
+        .filter(|query| !query.trim().is_empty() && seen.insert(query.clone()))
+        .collect()
+}
+
+fn print_row(name: &str, durations: &mut [Duration], timeouts: usize) {
+    durations.sort();
+    println!(
+        "{:<16} {:>6} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        name,
+        durations.len(),
+        millis(percentile(durations, 50.0)),
+        millis(percentile(durations, 95.0)),
+        millis(percentile(durations, 99.0)),
+        millis(durations.last().copied().unwrap_or_default()),
+        timeouts
+    );
+}
+
+// Nearest rank, of durations already sorted
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn megabytes(kb: u64) -> String {
+    format!("{:.1} MB", kb as f64 / 1024.0)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Memory {
+    // Kilobytes, now and at most so far
+    resident: u64,
+    peak: u64,
+}
+
+// From /proc, so only on Linux
+fn memory() -> Option<Memory> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let field = |name: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.trim().trim_end_matches("kB").trim().parse().ok())
+    };
+    Some(Memory {
+        resident: field("VmRSS:")?,
+        peak: field("VmHWM:")?,
+    })
+}
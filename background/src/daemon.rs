@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use shared::config::Config;
+use shared::ipc::IpcClient;
+use shared::logging;
+use shared::models::{IpcMessage, ServiceStatus};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Notify;
+
+// How long `--stop` and `--daemonize` wait for the service to go or come up
+const WAIT: Duration = Duration::from_secs(5);
+const POLL: Duration = Duration::from_millis(100);
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static STOP: OnceLock<Notify> = OnceLock::new();
+
+// Managing the service from the shell, for users without systemd. The PID
+// file says which process is the service; asking it over IPC says whether
+// it's answering.
+//
+//   background                 run in this terminal, same as --foreground
+//   background --daemonize     start in the background and return
+//   background --stop          ask the running service to exit
+//   background --restart       --stop, then --daemonize
+//   background --status        whether it runs, exiting with 3 if not
+//
+// `--bench` is the other thing the binary does instead of running, see
+// bench.rs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    Foreground,
+    Daemonize,
+    Stop,
+    Restart,
+    Status,
+}
+
+impl Lifecycle {
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+            [] | ["--foreground"] => Ok(Lifecycle::Foreground),
+            ["--daemonize"] => Ok(Lifecycle::Daemonize),
+            ["--stop"] => Ok(Lifecycle::Stop),
+            ["--restart"] => Ok(Lifecycle::Restart),
+            ["--status"] => Ok(Lifecycle::Status),
+            _ => anyhow::bail!(
+                "Usage: background [--foreground | --daemonize | --stop | --restart | --status]\n       background --bench [corpus] [--rounds <n>]"
+            ),
+        }
+    }
+}
+
+// The PID file of the running service, removed again when it stops
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn path(config_dir: &Path) -> PathBuf {
+        config_dir.join("background.pid")
+    }
+
+    // Fails while another service is running, which would otherwise lose
+    // its socket to this one
+    pub fn create(config_dir: &Path) -> Result<Self> {
+        let path = Self::path(config_dir);
+        if let Some(pid) = running(config_dir) {
+            anyhow::bail!("The background service is already running (pid {})", pid);
+        }
+        std::fs::write(&path, std::process::id().to_string())
+            .with_context(|| format!("Failed to write PID file {:?}", path))?;
+        STARTED.get_or_init(Instant::now);
+        Ok(Self { path })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            logging::warn(&format!("Failed to remove PID file {:?}: {}", self.path, e));
+        }
+    }
+}
+
+// The service's pid, if the PID file names a process that's still alive
+pub fn running(config_dir: &Path) -> Option<u32> {
+    let pid: u32 = std::fs::read_to_string(PidFile::path(config_dir)).ok()?.trim().parse().ok()?;
+    (pid != std::process::id() && alive(pid)).then_some(pid)
+}
+
+fn alive(pid: u32) -> bool {
+    kill(pid, "0")
+}
+
+fn kill(pid: u32, signal: &str) -> bool {
+    Command::new("kill")
+        .arg(format!("-{}", signal))
+        .arg(pid.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+pub fn status(providers: usize, plugins: usize) -> ServiceStatus {
+    ServiceStatus {
+        pid: std::process::id(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_secs: STARTED.get().map(|started| started.elapsed().as_secs()).unwrap_or_default(),
+        providers,
+        plugins,
+    }
+}
+
+// Ends the main loop, for `Shutdown`
+pub fn request_stop() {
+    STOP.get_or_init(Notify::new).notify_one();
+}
+
+// Resolves once the service should exit: asked to over IPC, or sent
+// SIGTERM or SIGINT
+pub async fn stopped() -> &'static str {
+    let requested = STOP.get_or_init(Notify::new).notified();
+    let mut term = match signal(SignalKind::terminate()) {
+        Ok(term) => term,
+        Err(e) => {
+            logging::warn(&format!("Failed to listen for SIGTERM: {}", e));
+            requested.await;
+            return "Shutdown requested";
+        }
+    };
+    tokio::select! {
+        _ = requested => "Shutdown requested",
+        _ = term.recv() => "Received SIGTERM",
+        _ = tokio::signal::ctrl_c() => "Received SIGINT",
+    }
+}
+
+// Asks the service over IPC, falling back to SIGTERM when it doesn't
+// answer, and waits for it to exit
+pub fn stop(config_dir: &Path) -> Result<()> {
+    let Some(pid) = running(config_dir) else {
+        println!("The background service isn't running");
+        return Ok(());
+    };
+
+    let asked = connect(config_dir).and_then(|mut client| {
+        client.send_message(&IpcMessage::Shutdown)?;
+        Ok(client.receive_message()?)
+    });
+    if !matches!(asked, Ok(IpcMessage::Stopping)) {
+        // It may have exited before answering, so only waiting tells
+        kill(pid, "TERM");
+    }
+
+    if !wait_for(|| !alive(pid)) {
+        anyhow::bail!("The background service (pid {}) didn't stop within {:?}", pid, WAIT);
+    }
+    println!("Stopped the background service (pid {})", pid);
+    Ok(())
+}
+
+// Starts this binary again in the foreground, detached from the terminal,
+// and returns once it answers over IPC
+pub fn daemonize(config_dir: &Path) -> Result<()> {
+    if let Some(pid) = running(config_dir) {
+        anyhow::bail!("The background service is already running (pid {})", pid);
+    }
+
+    let exe = std::env::current_exe().context("Failed to find the background executable")?;
+    let mut command = Command::new(exe);
+    command
+        .arg("--foreground")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Its own process group, so closing the terminal doesn't take it along
+        .process_group(0);
+    let child = command.spawn().context("Failed to start the background service")?;
+
+    if !wait_for(|| query(config_dir).is_ok()) {
+        anyhow::bail!(
+            "The background service (pid {}) didn't come up within {:?}, see background.log",
+            child.id(),
+            WAIT
+        );
+    }
+    println!("Started the background service (pid {})", child.id());
+    Ok(())
+}
+
+// Prints what the service reports, returning false when it isn't running
+pub fn print_status(config_dir: &Path) -> bool {
+    let Some(pid) = running(config_dir) else {
+        println!("The background service isn't running");
+        return false;
+    };
+    match query(config_dir) {
+        Ok(status) => {
+            println!("The background service is running (pid {}, version {})", status.pid, status.version);
+            println!("  Up for {}", uptime(status.uptime_secs));
+            println!("  {} providers, {} plugins", status.providers, status.plugins);
+        }
+        Err(e) => println!("The background service is running (pid {}) but not answering: {:#}", pid, e),
+    }
+    true
+}
+
+fn query(config_dir: &Path) -> Result<ServiceStatus> {
+    let mut client = connect(config_dir)?;
+    client.send_message(&IpcMessage::GetStatus)?;
+    match client.receive_message()? {
+        IpcMessage::Status(status) => Ok(status),
+        IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+        other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    }
+}
+
+fn connect(config_dir: &Path) -> Result<IpcClient> {
+    // Without a readable config the service runs on the default socket too
+    let config = Config::load(&config_dir.join("config.toml")).unwrap_or_default();
+    Ok(IpcClient::new(&config.ipc_socket_path)?)
+}
+
+fn wait_for(mut done: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + WAIT;
+    while Instant::now() < deadline {
+        if done() {
+            return true;
+        }
+        std::thread::sleep(POLL);
+    }
+    done()
+}
+
+fn uptime(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
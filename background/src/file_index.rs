@@ -0,0 +1,323 @@
+use anyhow::{Context, Result};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use shared::config::Config;
+use shared::file_control::{FileControl, WalkOptions};
+use shared::index::Index;
+use shared::logging;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex, RwLock};
+
+// Files and folders indexed across all roots, plenty for a home directory
+const MAX_FILES: usize = 200_000;
+// Events are gathered this long before they're applied, saving a file or
+// unpacking an archive sending a burst of them
+const SETTLE: Duration = Duration::from_millis(500);
+// How often the roots in the config are compared with the watched ones
+const CONFIG_CHECK: Duration = Duration::from_secs(5);
+
+// Names of the files and folders under the `files` provider's roots. The
+// roots are walked once, then a filesystem watcher feeds what's created,
+// changed and deleted under them into the index, so new files show up
+// within a second or so instead of waiting for another walk.
+pub struct FileIndex {
+    // None until the first walk is done, or while the provider is disabled
+    state: RwLock<Option<Indexed>>,
+}
+
+struct Indexed {
+    index: Index,
+    // Every indexed path, to find what was under a deleted directory
+    paths: BTreeSet<PathBuf>,
+}
+
+// What's indexed, as the current profile's `files` settings say
+#[derive(Debug, Clone, PartialEq)]
+struct Roots {
+    roots: Vec<PathBuf>,
+    exclude: Vec<String>,
+}
+
+impl Roots {
+    // None when the provider is disabled, so nothing is walked or watched
+    fn from_config(config: &Config) -> Option<Self> {
+        let settings = config.profile_or_current(None).ok()?.provider_settings("files");
+        if settings.is_some_and(|s| !s.enabled) {
+            return None;
+        }
+        let option = |key: &str| -> Vec<String> {
+            settings
+                .and_then(|s| s.options.get(key))
+                .map(String::as_str)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        let home = directories::BaseDirs::new()?.home_dir().to_path_buf();
+        let mut roots: Vec<PathBuf> = option("roots")
+            .iter()
+            .map(|root| match root.strip_prefix('~') {
+                Some(rest) => home.join(rest.trim_start_matches(['/', '\\'])),
+                None => PathBuf::from(root),
+            })
+            .collect();
+        if roots.is_empty() {
+            roots.push(home);
+        }
+        Some(Self { roots, exclude: option("exclude") })
+    }
+
+    fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            exclude: self.exclude.clone(),
+            limit: Some(MAX_FILES),
+            ..Default::default()
+        }
+    }
+
+    // The root `path` is under, unless the walk would have left it out
+    fn root_of(&self, path: &Path, is_dir: bool) -> Option<&Path> {
+        let root = self.roots.iter().find(|root| path.starts_with(root))?;
+        (!self.walk_options().skips(root, path, is_dir)).then_some(root.as_path())
+    }
+}
+
+// A path the watcher reported, looked at once its burst of events is over
+enum Change {
+    // The path, and everything under it when it's a directory
+    Added(Vec<PathBuf>),
+    Removed(PathBuf),
+}
+
+impl FileIndex {
+    pub fn new() -> Self {
+        Self { state: RwLock::new(None) }
+    }
+
+    // Paths whose names match, best first; None until the roots have been
+    // walked
+    pub async fn search(&self, query: &str, limit: usize) -> Option<Vec<(PathBuf, f32)>> {
+        let state = self.state.read().await;
+        let indexed = state.as_ref()?;
+        Some(
+            indexed
+                .index
+                .search(query, limit)
+                .into_iter()
+                .map(|hit| (PathBuf::from(hit.key), hit.score))
+                .collect(),
+        )
+    }
+
+    // Walks the roots and watches them from then on, starting over when the
+    // config names other roots or the watcher lost track of events
+    pub fn spawn(self: &Arc<Self>, config: Arc<Mutex<Config>>) {
+        let files = self.clone();
+        tokio::spawn(async move {
+            let (events, mut received) = mpsc::unbounded_channel();
+            let mut watched: Option<Roots> = None;
+            let mut _watcher: Option<RecommendedWatcher> = None;
+            let mut rescan = true;
+            let mut check = tokio::time::interval(CONFIG_CHECK);
+
+            loop {
+                tokio::select! {
+                    _ = check.tick() => {
+                        let roots = Roots::from_config(&*config.lock().await);
+                        if !rescan && roots == watched {
+                            continue;
+                        }
+                        rescan = false;
+                        // Dropping the watcher stops it before the old
+                        // roots' index goes
+                        _watcher = None;
+                        *files.state.write().await = None;
+                        watched = roots.clone();
+                        if let Some(roots) = roots {
+                            _watcher = files.rebuild(&roots, events.clone()).await;
+                        }
+                    }
+                    Some(event) = received.recv() => {
+                        let mut paths = HashSet::new();
+                        rescan |= gather(event, &mut paths);
+                        tokio::time::sleep(SETTLE).await;
+                        while let Ok(event) = received.try_recv() {
+                            rescan |= gather(event, &mut paths);
+                        }
+                        if rescan {
+                            logging::warn("Lost track of file changes, indexing the roots again");
+                            continue;
+                        }
+                        if let Some(roots) = watched.clone() {
+                            files.update(roots, paths).await;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    // Watches before walking, so what changes during the walk isn't missed
+    async fn rebuild(&self, roots: &Roots, events: mpsc::UnboundedSender<notify::Result<Event>>) -> Option<RecommendedWatcher> {
+        let watcher = match watch(roots, events) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                logging::warn(&format!("Failed to watch the file roots, new files won't show up until a restart: {:#}", e));
+                None
+            }
+        };
+
+        let started = Instant::now();
+        let walking = roots.clone();
+        match tokio::task::spawn_blocking(move || walk(&walking)).await {
+            Ok(indexed) => {
+                logging::info(&format!("Indexed {} files in {:?}", indexed.paths.len(), started.elapsed()));
+                *self.state.write().await = Some(indexed);
+            }
+            Err(e) => logging::error(&format!("Failed to index files: {:?}", e)),
+        }
+        watcher
+    }
+
+    async fn update(&self, roots: Roots, paths: HashSet<PathBuf>) {
+        let changes = match tokio::task::spawn_blocking(move || changes(&roots, paths)).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                logging::error(&format!("Failed to read file changes: {:?}", e));
+                return;
+            }
+        };
+
+        let mut state = self.state.write().await;
+        let Some(indexed) = state.as_mut() else {
+            return;
+        };
+        for change in changes {
+            match change {
+                Change::Added(paths) => {
+                    for path in paths {
+                        if indexed.paths.len() >= MAX_FILES {
+                            break;
+                        }
+                        indexed.insert(path);
+                    }
+                }
+                Change::Removed(path) => indexed.remove(&path),
+            }
+        }
+        if indexed.index.needs_compaction() {
+            indexed.index.compact();
+        }
+    }
+}
+
+impl Indexed {
+    fn insert(&mut self, path: PathBuf) {
+        let Some(name) = path.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+            return;
+        };
+        self.index.insert(path.to_string_lossy(), &name);
+        self.paths.insert(path);
+    }
+
+    // The path and, for a directory, everything that was under it, which
+    // sort right after it
+    fn remove(&mut self, path: &Path) {
+        let gone: Vec<PathBuf> = self
+            .paths
+            .range(path.to_path_buf()..)
+            .take_while(|p| p.starts_with(path))
+            .cloned()
+            .collect();
+        for path in gone {
+            self.index.remove(&path.to_string_lossy());
+            self.paths.remove(&path);
+        }
+    }
+}
+
+fn watch(roots: &Roots, events: mpsc::UnboundedSender<notify::Result<Event>>) -> Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        // Only fails once the index is gone, along with the watcher
+        let _ = events.send(event);
+    })
+    .context("Failed to start the file watcher")?;
+    for root in &roots.roots {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {:?}", root))?;
+    }
+    Ok(watcher)
+}
+
+fn walk(roots: &Roots) -> Indexed {
+    let files = FileControl::new();
+    let mut indexed = Indexed { index: Index::new(), paths: BTreeSet::new() };
+    for root in &roots.roots {
+        for entry in files.walk(root, roots.walk_options()) {
+            if indexed.paths.len() >= MAX_FILES {
+                return indexed;
+            }
+            indexed.insert(entry.path);
+        }
+    }
+    indexed
+}
+
+// Adds the event's paths, returning whether the watcher missed events and
+// the roots have to be walked again
+fn gather(event: notify::Result<Event>, paths: &mut HashSet<PathBuf>) -> bool {
+    match event {
+        Ok(event) if event.need_rescan() => true,
+        Ok(event) => {
+            if !event.kind.is_access() {
+                paths.extend(event.paths);
+            }
+            false
+        }
+        Err(e) => {
+            logging::warn(&format!("File watcher error: {}", e));
+            false
+        }
+    }
+}
+
+// Whatever the event said, a path that's there now is added and one that
+// isn't is removed, which also covers both ends of a rename
+fn changes(roots: &Roots, paths: HashSet<PathBuf>) -> Vec<Change> {
+    let files = FileControl::new();
+    let mut changes = Vec::new();
+    for path in paths {
+        let Ok(meta) = std::fs::symlink_metadata(&path) else {
+            if roots.roots.iter().any(|root| path.starts_with(root)) {
+                changes.push(Change::Removed(path));
+            }
+            continue;
+        };
+        let is_dir = meta.is_dir();
+        let Some(root) = roots.root_of(&path, is_dir) else {
+            continue;
+        };
+
+        let mut added = vec![path.clone()];
+        // A directory moved in arrives as one event, for the directory alone;
+        // what's under it is held to the patterns relative to its root
+        if is_dir {
+            let options = roots.walk_options();
+            added.extend(
+                files
+                    .walk(&path, options.clone())
+                    .filter(|entry| !options.skips(root, &entry.path, entry.is_dir))
+                    .map(|entry| entry.path),
+            );
+        }
+        changes.push(Change::Added(added));
+    }
+    changes
+}
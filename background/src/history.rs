@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use shared::index::Index;
+use shared::logging;
+use shared::models::{Action, Command};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+const MAX_ENTRIES: usize = 500;
+
+// An executed result and the query it was picked from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub title: String,
+    pub description: String,
+    pub action: Action,
+    #[serde(default)]
+    pub query: Option<String>,
+    // Unix timestamp of the last run
+    pub used_at: i64,
+}
+
+impl HistoryEntry {
+    // Entries are told apart by what they show
+    pub fn key(&self) -> String {
+        format!("{}\n{}", self.title, self.description)
+    }
+
+    fn text(&self) -> String {
+        format!("{} {} {}", self.title, self.description, self.query.as_deref().unwrap_or_default())
+    }
+}
+
+// Executed results, newest first, kept in the data dir so they outlive the
+// popup's in-memory history
+pub struct HistoryStore {
+    entries: RwLock<Vec<HistoryEntry>>,
+    // None until `build_index` is done
+    index: RwLock<Option<Index>>,
+}
+
+impl HistoryStore {
+    pub fn load() -> Self {
+        let entries = read().unwrap_or_else(|e| {
+            logging::error(&format!("Failed to load history: {:?}", e));
+            Vec::new()
+        });
+
+        Self {
+            entries: RwLock::new(entries),
+            index: RwLock::new(None),
+        }
+    }
+
+    // Indexes the loaded entries on a blocking thread, searches scanning the
+    // entries until it's done
+    pub fn build_index(self: &Arc<Self>) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let docs: Vec<(String, String)> = store.entries().await.iter().map(|e| (e.key(), e.text())).collect();
+            let keys: Vec<String> = docs.iter().map(|(key, _)| key.clone()).collect();
+            match tokio::task::spawn_blocking(move || Index::build(docs)).await {
+                Ok(index) => {
+                    // Entries recorded meanwhile aren't in the index yet
+                    let entries = store.entries().await;
+                    let mut slot = store.index.write().await;
+                    let index = if entries.iter().map(HistoryEntry::key).eq(keys) {
+                        index
+                    } else {
+                        Index::build(entries.iter().map(|e| (e.key(), e.text())))
+                    };
+                    logging::debug(&format!("Indexed {} history entries", index.len()));
+                    *slot = Some(index);
+                }
+                Err(e) => logging::error(&format!("Failed to index history: {:?}", e)),
+            }
+        });
+    }
+
+    pub async fn index(&self) -> RwLockReadGuard<'_, Option<Index>> {
+        self.index.read().await
+    }
+
+    pub async fn entries(&self) -> RwLockReadGuard<'_, Vec<HistoryEntry>> {
+        self.entries.read().await
+    }
+
+    // Moves a command run again to the front rather than listing it twice,
+    // losing the entry rather than the command when it can't be saved
+    pub async fn record(&self, cmd: &Command) {
+        let query = cmd.query.as_deref().map(str::trim).filter(|q| !q.is_empty());
+        let entry = HistoryEntry {
+            title: cmd.name.clone(),
+            description: cmd.description.clone(),
+            action: cmd.action.clone(),
+            query: query.map(str::to_string),
+            used_at: chrono::Utc::now().timestamp(),
+        };
+
+        let mut entries = self.entries.write().await;
+        let key = entry.key();
+        entries.retain(|e| e.key() != key);
+        entries.insert(0, entry);
+        let kept = entries.len().min(MAX_ENTRIES);
+        let dropped = entries.split_off(kept);
+
+        if let Some(index) = self.index.write().await.as_mut() {
+            index.insert(key, &entries[0].text());
+            for entry in &dropped {
+                index.remove(&entry.key());
+            }
+            if index.needs_compaction() {
+                index.compact();
+            }
+        }
+
+        if let Err(e) = write(&entries) {
+            logging::error(&format!("Failed to save history: {:?}", e));
+        }
+    }
+
+    // Returns how many entries were removed
+    pub async fn clear(&self) -> Result<usize> {
+        let mut entries = self.entries.write().await;
+        let count = entries.len();
+        entries.clear();
+        if let Some(index) = self.index.write().await.as_mut() {
+            index.clear();
+        }
+        write(&entries)?;
+        Ok(count)
+    }
+}
+
+fn path() -> Result<PathBuf> {
+    let proj_dirs = directories::ProjectDirs::from("", "", "orion")
+        .context("Failed to get project directories")?;
+    Ok(proj_dirs.data_dir().join("history.json"))
+}
+
+// A missing history file means nothing has been run yet
+fn read() -> Result<Vec<HistoryEntry>> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read history at {:?}", path))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse history at {:?}", path))
+}
+
+fn write(entries: &[HistoryEntry]) -> Result<()> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_string(entries)?)
+        .with_context(|| format!("Failed to write history at {:?}", path))
+}
@@ -0,0 +1,213 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use shared::bangs;
+use shared::models::{Bang, Browser};
+use std::path::{Path, PathBuf};
+
+// Reads the keyword search engines set up in a browser and turns them into
+// custom bangs, the keyword becoming the trigger. Engines without a
+// keyword, or with URLs only the browser can fill in, are skipped and
+// described in the returned errors.
+pub fn search_engines(browser: Browser) -> Result<(Vec<Bang>, Vec<String>)> {
+    let profiles = profile_dirs(browser)?;
+    if profiles.is_empty() {
+        return Err(anyhow::anyhow!("No {} profile found", browser));
+    }
+
+    let mut engines = Vec::new();
+    for profile in &profiles {
+        let found = match browser {
+            Browser::Firefox => firefox_engines(profile),
+            Browser::Chrome => chrome_engines(profile),
+        };
+        engines.extend(found.with_context(|| format!("Failed to read search engines from {:?}", profile))?);
+    }
+
+    let mut imported: Vec<Bang> = Vec::new();
+    let mut errors = Vec::new();
+    for engine in engines {
+        let Some(keyword) = engine.keyword.as_deref().map(|k| k.trim_start_matches('@')).filter(|k| !k.is_empty()) else {
+            errors.push(format!("{}: no keyword", engine.name));
+            continue;
+        };
+        // Profiles often share engines
+        if imported.iter().any(|b| b.trigger == keyword) {
+            continue;
+        }
+
+        match convert_template(&engine.url).and_then(|url| bangs::custom_bang(keyword, &engine.name, &url)) {
+            Ok(bang) => imported.push(bang),
+            Err(e) => errors.push(format!("{} (!{}): {}", engine.name, keyword, e)),
+        }
+    }
+
+    Ok((imported, errors))
+}
+
+struct Engine {
+    name: String,
+    keyword: Option<String>,
+    url: String,
+}
+
+fn profile_dirs(browser: Browser) -> Result<Vec<PathBuf>> {
+    let dirs = directories::BaseDirs::new().context("Failed to get home directory")?;
+
+    let roots = match browser {
+        Browser::Firefox if cfg!(target_os = "macos") => vec![dirs.config_dir().join("Firefox/Profiles")],
+        Browser::Firefox if cfg!(windows) => vec![dirs.config_dir().join(r"Mozilla\Firefox\Profiles")],
+        Browser::Firefox => vec![dirs.home_dir().join(".mozilla/firefox")],
+        Browser::Chrome if cfg!(target_os = "macos") => vec![dirs.config_dir().join("Google/Chrome")],
+        Browser::Chrome if cfg!(windows) => vec![dirs.data_local_dir().join(r"Google\Chrome\User Data")],
+        Browser::Chrome => vec![dirs.config_dir().join("google-chrome"), dirs.config_dir().join("chromium")],
+    };
+    let marker = match browser {
+        Browser::Firefox => "search.json.mozlz4",
+        Browser::Chrome => "Web Data",
+    };
+
+    let mut profiles = Vec::new();
+    for root in roots {
+        let Ok(entries) = std::fs::read_dir(&root) else {
+            continue;
+        };
+        profiles.extend(
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.join(marker).is_file()),
+        );
+    }
+    profiles.sort();
+    Ok(profiles)
+}
+
+#[derive(Deserialize)]
+struct FirefoxSearch {
+    #[serde(default)]
+    engines: Vec<FirefoxEngine>,
+}
+
+#[derive(Deserialize)]
+struct FirefoxEngine {
+    #[serde(rename = "_name")]
+    name: String,
+    #[serde(rename = "_definedAliases", default)]
+    defined_aliases: Vec<String>,
+    #[serde(rename = "_metaData", default)]
+    meta: FirefoxMeta,
+    #[serde(rename = "_urls", default)]
+    urls: Vec<FirefoxUrl>,
+}
+
+#[derive(Deserialize, Default)]
+struct FirefoxMeta {
+    alias: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FirefoxUrl {
+    template: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    params: Vec<FirefoxParam>,
+}
+
+#[derive(Deserialize)]
+struct FirefoxParam {
+    name: String,
+    // Missing for parameters Firefox fills in from its preferences
+    #[serde(default)]
+    value: String,
+}
+
+// search.json.mozlz4 is JSON behind Mozilla's header and an LZ4 block
+// prefixed with its decompressed size
+fn firefox_engines(profile: &Path) -> Result<Vec<Engine>> {
+    const MAGIC: &[u8] = b"mozLz40\0";
+
+    let data = std::fs::read(profile.join("search.json.mozlz4"))?;
+    let block = data.strip_prefix(MAGIC).context("Not a mozLz4 file")?;
+    let json = lz4_flex::block::decompress_size_prepended(block).context("Failed to decompress")?;
+    let search: FirefoxSearch = serde_json::from_slice(&json)?;
+
+    Ok(search
+        .engines
+        .into_iter()
+        .filter_map(|engine| {
+            // Result pages, not suggestion feeds
+            let url = engine.urls.iter().find(|u| u.kind.as_deref().map_or(true, |k| k == "text/html"))?;
+            let mut template = url.template.clone();
+            for (idx, param) in url.params.iter().filter(|p| !p.value.is_empty()).enumerate() {
+                let separator = if idx == 0 && !template.contains('?') { '?' } else { '&' };
+                template.push_str(&format!("{}{}={}", separator, param.name, param.value));
+            }
+
+            // A keyword the user set wins over the engine's built-in `@` aliases
+            let keyword = engine.meta.alias.filter(|a| !a.is_empty()).or(engine.defined_aliases.into_iter().next());
+            Some(Engine {
+                name: engine.name,
+                keyword,
+                url: template,
+            })
+        })
+        .collect())
+}
+
+// Chrome keeps its engines in the keywords table of the Web Data database,
+// which it holds locked while running, so a copy is read instead
+fn chrome_engines(profile: &Path) -> Result<Vec<Engine>> {
+    let copy = std::env::temp_dir().join(format!("orion-web-data-{}", std::process::id()));
+    std::fs::copy(profile.join("Web Data"), &copy)?;
+
+    let read = || -> Result<Vec<Engine>> {
+        let conn = Connection::open_with_flags(&copy, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut stmt = conn.prepare("SELECT short_name, keyword, url FROM keywords")?;
+        let engines = stmt
+            .query_map([], |row| {
+                Ok(Engine {
+                    name: row.get(0)?,
+                    keyword: row.get(1)?,
+                    url: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(engines)
+    };
+    let engines = read();
+    let _ = std::fs::remove_file(&copy);
+    engines
+}
+
+// Rewrites an OpenSearch URL template into ours. Optional parameters are
+// dropped, anything else only the browser knows how to fill in is an error.
+fn convert_template(url: &str) -> Result<String> {
+    let url = url
+        .replace("{searchTerms}", "{query}")
+        .replace("{inputEncoding}", "UTF-8")
+        .replace("{outputEncoding}", "UTF-8")
+        .replace("{language}", "{lang}")
+        .replace("{moz:locale}", "{lang}");
+
+    let mut converted = String::with_capacity(url.len());
+    let mut rest = url.as_str();
+    while let Some(start) = rest.find('{') {
+        converted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+        let placeholder = &rest[..=end];
+        if placeholder.contains(':') {
+            return Err(anyhow::anyhow!("uses the browser's own {} placeholder", placeholder));
+        }
+        if !placeholder.ends_with("?}") {
+            converted.push_str(placeholder);
+        }
+        rest = &rest[end + 1..];
+    }
+    converted.push_str(rest);
+
+    Ok(converted)
+}
@@ -1,30 +1,53 @@
 use anyhow::{Result, Context};
-use shared::{config, ipc, logging, models};
+use shared::{config, ipc, logging, models, query};
+use std::env;
 use std::path::PathBuf;
 use std::process::Command;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tokio::time::sleep;
+use tracing::Instrument;
 
+mod audit;
+mod bang_db;
+mod bangs;
+mod bench;
+mod daemon;
+mod file_index;
+mod history;
 mod hotkey;
+mod import;
+mod mounts;
+mod operations;
+mod plugins;
 mod process;
+mod providers;
+mod scripts;
+mod searches;
 mod setup;
+mod usage_db;
+mod voice;
+mod watcher;
 
+use bangs::{BangScope, BangStore};
+use daemon::{Lifecycle, PidFile};
+use shared::template::TemplateVars;
+use shared::error::ErrorReply;
+use shared::file_control::FileControl;
+use shared::plugin::PluginState;
+use file_index::FileIndex;
+use history::HistoryStore;
+use operations::Operations;
+use audit::AuditLog;
+use plugins::PluginHost;
 use hotkey::HotkeyManager;
 use process::ProcessManager;
-
-#[derive(serde::Deserialize)]
-#[allow(dead_code)]
-struct Bang {
-    c: String,  // category
-    d: String,  // domain
-    r: u32,     // rank
-    s: String,  // site name
-    sc: String, // subcategory
-    t: String,  // trigger (prefix)
-    u: String,  // url template
-}
+use providers::ProviderRegistry;
+use scripts::{RunPolicy, ScriptHost};
+use searches::Searches;
+use tokio_util::sync::CancellationToken;
+use usage_db::UsageDb;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -35,13 +58,37 @@ async fn main() -> Result<()> {
     let config_dir = proj_dirs.config_dir();
     std::fs::create_dir_all(config_dir)?;
 
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--bench") {
+        return bench::run(&args[1..], config_dir).await;
+    }
+
+    // Everything but running the service talks to the one already running
+    match Lifecycle::from_args(&args)? {
+        Lifecycle::Foreground => {}
+        Lifecycle::Daemonize => return daemon::daemonize(config_dir),
+        Lifecycle::Stop => return daemon::stop(config_dir),
+        Lifecycle::Restart => {
+            daemon::stop(config_dir)?;
+            return daemon::daemonize(config_dir);
+        }
+        Lifecycle::Status => {
+            if !daemon::print_status(config_dir) {
+                std::process::exit(3);
+            }
+            return Ok(());
+        }
+    }
+
     let log_path = config_dir.join("background.log");
 
+    let started = Instant::now();
     logging::init(Some(log_path))?;
     logging::info("Background service starting...");
+    let _pid_file = PidFile::create(config_dir)?;
 
     // Setup configuration
-    setup::setup_config().await?;
+    let bangs_from_snapshot = setup::setup_config().await?;
     logging::info("Configuration setup complete");
 
     // Load configuration
@@ -51,6 +98,7 @@ async fn main() -> Result<()> {
     let config = match config_result {
         Ok(cfg) => {
             logging::info(&format!("Configuration loaded from {}", config_path.display()));
+            apply_logging(&cfg);
             Arc::new(Mutex::new(cfg))
         },
         Err(e) => {
@@ -81,19 +129,49 @@ async fn main() -> Result<()> {
     let process_manager = Arc::new(ProcessManager::new(&server_addr)?);
     logging::info("Process manager initialized");
 
+    let history = Arc::new(HistoryStore::load());
+    let usage = Arc::new(
+        UsageDb::path()
+            .and_then(|path| UsageDb::open(&path))
+            .or_else(|e| {
+                logging::error(&format!("Failed to open usage database, keeping clicks in memory: {:?}", e));
+                UsageDb::open_in_memory()
+            })
+            .expect("Failed to create an in-memory usage database"),
+    );
+    let mut plugins = PluginHost::discover(&config_dir.join("plugins"));
+    let scripts = Arc::new(ScriptHost::new(&config_dir.join("scripts")));
+    let file_index = Arc::new(FileIndex::new());
+    let providers = Arc::new(ProviderRegistry::new(history.clone(), usage.clone(), file_index.clone(), &mut plugins, scripts.clone()));
+    let plugins = Arc::new(plugins);
+    let bang_store = Arc::new(BangStore::load());
+    let operations = Arc::new(Operations::default());
+
+    // What's slow to load is loaded once the service is answering, searches
+    // meanwhile going without it: scripts compile, bangs import, the
+    // history and files are indexed, and on a first run the bundled bangs
+    // are replaced with a download
+    scripts.warm();
+    bang_store.sync_in_background();
+    if bangs_from_snapshot {
+        let bang_store = bang_store.clone();
+        tokio::spawn(async move {
+            match bang_store.refresh().await {
+                Ok(refresh) => logging::info(&format!("Downloaded {} bangs", refresh.count)),
+                Err(e) => logging::warn(&format!("Failed to download bangs, keeping the bundled ones: {:?}", e)),
+            }
+        });
+    }
+    let searches = Arc::new(Searches::default());
+    history.build_index();
+    file_index.spawn(config.clone());
+    let audit = Arc::new(AuditLog::open()?);
+    audit::spawn_pruning(audit.clone(), config.clone());
+
     // Initialize hotkey manager
     let mut hotkey_manager = HotkeyManager::new()?;
     logging::info("Hotkey manager initialized");
 
-    // Start IPC server in a separate task
-    let ipc_server = Arc::new(ipc_server);
-    let ipc_server_clone = ipc_server.clone();
-    tokio::spawn(async move {
-        if let Err(e) = ipc_server_clone.start_async().await {
-            logging::error(&format!("IPC server error: {:?}", e));
-        }
-    });
-    
     // Extract hotkey configuration
     let hotkey_config = {
         let cfg = config.lock().await;
@@ -138,54 +216,327 @@ async fn main() -> Result<()> {
     );
     logging::info("Hotkey listener started");
 
-    // Main event loop
+    watcher::spawn(config_path.clone(), config.clone(), bang_store.clone(), providers.clone());
+    voice::spawn(config.clone(), process_manager.clone());
+
+    logging::info(&format!("Ready in {:?}", started.elapsed()));
+
+    // Main event loop: each client connection is handled in its own task so
+    // replies can be written back on the same socket
+    let stopped = daemon::stopped();
+    tokio::pin!(stopped);
     loop {
-        match ipc_server.receive_message().await {
-            Ok(message) => {
-                match message {
-                    models::IpcMessage::SearchQuery(query) => {
-                        if let Err(e) = handle_search(query, &config, &process_manager).await {
-                            logging::error(&format!("Error handling search: {:?}", e));
-                        }
+        let accepted = tokio::select! {
+            accepted = ipc_server.accept() => accepted,
+            reason = &mut stopped => {
+                logging::info(&format!("{}, stopping", reason));
+                break;
+            }
+        };
+        match accepted {
+            Ok(connection) => {
+                let config = config.clone();
+                let process_manager = process_manager.clone();
+                let config_path = config_path.clone();
+                let providers = providers.clone();
+                let bang_store = bang_store.clone();
+                let history = history.clone();
+                let usage = usage.clone();
+                let operations = operations.clone();
+                let plugins = plugins.clone();
+                let scripts = scripts.clone();
+                let searches = searches.clone();
+                let audit = audit.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(connection, &config_path, &config, &process_manager, &providers, &bang_store, &history, &usage, &operations, &plugins, &scripts, &searches, &audit).await {
+                        logging::error(&format!("Error handling IPC connection: {:?}", e));
                     }
-                    models::IpcMessage::Command(cmd) => {
-                        if let Err(e) = handle_command(cmd, &config, &process_manager).await {
-                            logging::error(&format!("Error handling command: {:?}", e));
-                        }
+                });
+            }
+            Err(e) => {
+                logging::error(&format!("Error accepting connection: {:?}", e));
+                // Add delay to prevent tight loop on error
+                sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+
+    if let Err(e) = std::fs::remove_file(&server_addr) {
+        logging::warn(&format!("Failed to remove socket {}: {}", server_addr, e));
+    }
+    Ok(())
+}
+
+async fn handle_connection(
+    mut connection: ipc::IpcConnection,
+    config_path: &PathBuf,
+    config: &Arc<Mutex<config::Config>>,
+    process_manager: &Arc<ProcessManager>,
+    providers: &Arc<ProviderRegistry>,
+    bang_store: &Arc<BangStore>,
+    history: &Arc<HistoryStore>,
+    usage: &Arc<UsageDb>,
+    operations: &Arc<Operations>,
+    plugins: &Arc<PluginHost>,
+    scripts: &Arc<ScriptHost>,
+    searches: &Arc<Searches>,
+    audit: &Arc<AuditLog>,
+) -> Result<()> {
+    while let Some(message) = connection.receive_message().await? {
+        if let Some(reply) = handle_message(message, config_path, config, process_manager, providers, bang_store, history, usage, operations, plugins, scripts, searches, audit).await {
+            connection.send_message(&reply).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_message(
+    message: models::IpcMessage,
+    config_path: &PathBuf,
+    config: &Arc<Mutex<config::Config>>,
+    process_manager: &Arc<ProcessManager>,
+    providers: &Arc<ProviderRegistry>,
+    bang_store: &Arc<BangStore>,
+    history: &Arc<HistoryStore>,
+    usage: &Arc<UsageDb>,
+    operations: &Arc<Operations>,
+    plugins: &Arc<PluginHost>,
+    scripts: &Arc<ScriptHost>,
+    searches: &Arc<Searches>,
+    audit: &Arc<AuditLog>,
+) -> Option<models::IpcMessage> {
+    match message {
+        models::IpcMessage::SearchQuery(query) => {
+            // Carries the popup's request id, so its log lines and ours for
+            // the same search can be matched up
+            let span = tracing::info_span!("search", request_id = query.request_id.as_deref().unwrap_or_default());
+
+            // A newer search from the same session cancels this one
+            let running = match (&query.session_id, query.generation) {
+                (Some(session), Some(generation)) => match searches.start(session, generation) {
+                    Some(running) => Some(running),
+                    None => return Some(cancelled(query)),
+                },
+                _ => None,
+            };
+            let cancel = running.as_ref().map(|r| r.token.clone()).unwrap_or_default();
+
+            async {
+                match handle_search(query, config, providers, bang_store, usage, &cancel).await {
+                    Ok(reply) => Some(reply),
+                    Err(e) => {
+                        logging::error(&format!("Error handling search: {:?}", e));
+                        Some(models::IpcMessage::Error(ErrorReply::from(&e)))
                     }
-                    models::IpcMessage::ConfigUpdate => {
-                        if let Err(e) = handle_config_update(&config_path, &config).await {
-                            logging::error(&format!("Error updating config: {:?}", e));
+                }
+            }
+            .instrument(span)
+            .await
+        }
+        models::IpcMessage::BangQuery(prefix) => {
+            match handle_bang_query(&prefix, config, bang_store).await {
+                Ok(reply) => Some(reply),
+                Err(e) => {
+                    logging::error(&format!("Error handling bang query: {:?}", e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::Command(cmd) => {
+            let outcome = handle_command(cmd.clone(), config, process_manager, bang_store, operations, plugins, scripts).await;
+            record_audit(audit, config, &cmd, &outcome).await;
+            match outcome {
+                Ok(()) => {
+                    // SQLite writes and the directory checks of a visit block
+                    let usage = usage.clone();
+                    let clicked = cmd.clone();
+                    let recorded = tokio::task::spawn_blocking(move || {
+                        if let Some(query) = &clicked.query {
+                            if let Err(e) = usage.record_click(query, &clicked.name, &clicked.action) {
+                                logging::error(&format!("Failed to record click: {:?}", e));
+                            }
                         }
-                    }
-                    models::IpcMessage::Redirect(url) => {
-                        if let Err(e) = handle_command(
-                            models::Command::new(
-                                "Open URL".to_string(),
-                                url.clone(),
-                                models::Action::OpenUrl(url),
-                                vec![],
-                            ),
-                            &config,
-                            &process_manager,
-                        ).await {
-                            logging::error(&format!("Error handling redirect: {:?}", e));
+                        if let Err(e) = usage.record_visit(&clicked.action) {
+                            logging::error(&format!("Failed to record directory visit: {:?}", e));
                         }
+                    })
+                    .await;
+                    if let Err(e) = recorded {
+                        logging::error(&format!("Failed to record usage: {:?}", e));
                     }
-                    _ => {
-                        logging::warn("Received unexpected message type");
-                    }
+                    history.record(&cmd).await;
+                    // History and click boosts changed
+                    providers.invalidate();
                 }
+                Err(e) => logging::error(&format!("Error handling command: {:?}", e)),
             }
-            Err(e) => {
-                logging::error(&format!("Error receiving message: {:?}", e));
-                // Add delay to prevent tight loop on error
-                sleep(Duration::from_millis(100)).await;
+            None
+        }
+        models::IpcMessage::ConfigUpdate => {
+            if let Err(e) = handle_config_update(config_path, config, providers).await {
+                logging::error(&format!("Error updating config: {:?}", e));
             }
+            None
+        }
+        models::IpcMessage::ListBangs(query) => {
+            match bang_store.list(query).await {
+                Ok(page) => Some(models::IpcMessage::BangList(page)),
+                Err(e) => {
+                    logging::error(&format!("Error listing bangs: {:?}", e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::ImportBangs(browser) => {
+            match import_bangs(browser, bang_store).await {
+                Ok(imported) => {
+                    logging::info(&format!(
+                        "Imported {} search engines from {}, skipped {}",
+                        imported.count,
+                        browser,
+                        imported.errors.len()
+                    ));
+                    Some(models::IpcMessage::BangsImported(imported))
+                }
+                Err(e) => {
+                    logging::error(&format!("Error importing bangs from {}: {:?}", browser, e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::ClearHistory => {
+            match history.clear().await {
+                Ok(count) => {
+                    providers.invalidate();
+                    logging::info(&format!("Cleared {} history entries", count));
+                    Some(models::IpcMessage::HistoryCleared(count))
+                }
+                Err(e) => {
+                    logging::error(&format!("Error clearing history: {:?}", e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::ListPlugins => {
+            Some(models::IpcMessage::Plugins(plugins.statuses().to_vec()))
+        }
+        models::IpcMessage::ListProviders => {
+            Some(models::IpcMessage::Providers(providers.info()))
+        }
+        models::IpcMessage::SetLogLevel(name) => {
+            match logging::set_level_named(&name) {
+                Ok(level) => {
+                    logging::info(&format!("Log level set to {} until the config is reloaded", level));
+                    Some(models::IpcMessage::LogLevel(level.to_string().to_lowercase()))
+                }
+                Err(e) => Some(models::IpcMessage::Error(ErrorReply::from(&e))),
+            }
+        }
+        models::IpcMessage::GetStatus => {
+            let loaded = plugins.statuses().iter().filter(|s| s.state == PluginState::Loaded).count();
+            Some(models::IpcMessage::Status(daemon::status(providers.info().len(), loaded)))
+        }
+        models::IpcMessage::ListAudit(limit) => {
+            match audit.recent(limit).await {
+                Ok(entries) => Some(models::IpcMessage::Audit(entries)),
+                Err(e) => {
+                    logging::error(&format!("Error reading the audit trail: {:?}", e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::OpenPopup(text) => {
+            match process_manager.reopen_popup(&text, false).await {
+                Ok(()) => Some(models::IpcMessage::PopupOpened),
+                Err(e) => {
+                    logging::error(&format!("Error opening the popup: {:?}", e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::Shutdown => {
+            logging::info("Asked to shut down over IPC");
+            daemon::request_stop();
+            Some(models::IpcMessage::Stopping)
+        }
+        models::IpcMessage::ListOperations => {
+            Some(models::IpcMessage::OperationProgress(operations.list()))
+        }
+        models::IpcMessage::ReloadBangs => {
+            match bang_store.reload().await {
+                Ok(summary) => Some(models::IpcMessage::BangsRefreshed(summary)),
+                Err(e) => {
+                    logging::error(&format!("Error reloading bangs: {:?}", e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::RefreshBangs => {
+            match bang_store.refresh().await {
+                Ok(refresh) => {
+                    logging::info(&format!(
+                        "Refreshed {} bangs, skipped {} invalid entries",
+                        refresh.count,
+                        refresh.errors.len()
+                    ));
+                    Some(models::IpcMessage::BangsRefreshed(refresh))
+                }
+                Err(e) => {
+                    logging::error(&format!("Error refreshing bangs: {:?}", e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::AddBang(bang) => {
+            let trigger = bang.trigger.clone();
+            match add_bang(bang, bang_store).await {
+                Ok(()) => {
+                    logging::info(&format!("Added custom bang !{}", trigger));
+                    None
+                }
+                Err(e) => {
+                    logging::error(&format!("Error adding bang !{}: {:?}", trigger, e));
+                    Some(models::IpcMessage::Error(ErrorReply::from(&e)))
+                }
+            }
+        }
+        models::IpcMessage::Redirect(url) => {
+            let cmd = models::Command::new(
+                "Open URL".to_string(),
+                url.clone(),
+                models::Action::OpenUrl(url),
+                vec![],
+            );
+            let outcome = handle_command(cmd.clone(), config, process_manager, bang_store, operations, plugins, scripts).await;
+            record_audit(audit, config, &cmd, &outcome).await;
+            if let Err(e) = outcome {
+                logging::error(&format!("Error handling redirect: {:?}", e));
+            }
+            None
+        }
+        _ => {
+            logging::warn("Received unexpected message type");
+            None
         }
     }
 }
 
+async fn record_audit(
+    audit: &Arc<AuditLog>,
+    config: &Arc<Mutex<config::Config>>,
+    cmd: &models::Command,
+    outcome: &Result<()>,
+) {
+    let profile = {
+        let config = config.lock().await;
+        config.audit.enabled.then(|| config.current_profile.clone())
+    };
+    if let Some(profile) = profile {
+        audit.record(cmd, &profile, outcome).await;
+    }
+}
+
 async fn handle_hotkey_press(
     config: &Arc<Mutex<config::Config>>,
     process_manager: &Arc<ProcessManager>,
@@ -200,7 +551,7 @@ async fn handle_hotkey_press(
         process_manager.stop_popup().await?;
     } else {
         logging::info("Starting popup UI");
-        process_manager.start_popup().await?;
+        process_manager.start_popup(None, false).await?;
 
         // Send initial configuration to popup
         let message = models::IpcMessage::ConfigUpdate;
@@ -211,97 +562,195 @@ async fn handle_hotkey_press(
 }
 
 async fn handle_search(
-    query: models::SearchQuery,
+    mut query: models::SearchQuery,
     config: &Arc<Mutex<config::Config>>,
-    process_manager: &Arc<ProcessManager>,
-) -> Result<()> {
-    logging::info(&format!("Handling search query: {}", query.text));
+    providers: &Arc<ProviderRegistry>,
+    bang_store: &Arc<BangStore>,
+    usage: &Arc<UsageDb>,
+    cancel: &CancellationToken,
+) -> Result<models::IpcMessage> {
+    match &query.session_id {
+        Some(session) => logging::info(&format!("Handling search query from session {}: {}", session, query.text)),
+        None => logging::info(&format!("Handling search query: {}", query.text)),
+    }
 
-    let config = config.lock().await;
-    let current_profile = config.get_current_profile()?;
+    // A copy, so slow providers don't keep commands and config reloads
+    // waiting on the lock
+    let config = tokio::select! {
+        config = config.lock() => config.clone(),
+        _ = cancel.cancelled() => return Ok(cancelled(query)),
+    };
 
-    // Load bangs from file
-    let proj_dirs = directories::ProjectDirs::from("", "", "orion")
-        .context("Failed to get project directories")?;
+    let mut parsed = query::parse(&query.text);
 
-    let config_dir = proj_dirs.config_dir();
-    let bangs_path = config_dir.join("bangs.json");
-
-    if let Ok(bangs_content) = std::fs::read_to_string(&bangs_path) {
-        if let Ok(bangs) = serde_json::from_str::<Vec<models::Bang>>(&bangs_content) {
-            // Try to find a bang at the start of the query
-            if let Some((prefix, rest)) = query.text.split_once(' ') {
-                if let Some(bang) = bangs.iter().find(|b| b.trigger == prefix) {
-                    let url = bang.url_template.replace("{{{s}}}", rest);
-                    process_manager.send_message(models::IpcMessage::Redirect(url)).await?;
-                    return Ok(());
-                }
-            }
+    // A plugin's trigger scopes the search to the plugin, leaving the rest
+    // of the query for it to search
+    if query.provider_filter.is_none() && parsed.provider.is_none() {
+        if let Some((plugin, rest)) = providers.route(&query.text) {
+            parsed = query::parse(rest);
+            parsed.provider = Some(plugin.to_string());
+        }
+    }
 
-            // Try to find a bang at the end of the query
-            if let Some((search, bang)) = query.text.rsplit_once(' ') {
-                if let Some(bang) = bangs.iter().find(|b| b.trigger == bang) {
-                    let url = bang.url_template.replace("{{{s}}}", search);
-                    process_manager.send_message(models::IpcMessage::Redirect(url)).await?;
-                    return Ok(());
-                }
-            }
+    // `!cat:tech query` resolves bangs in the query against one category
+    // only, overriding the configured categories
+    let categories = match &parsed.category {
+        Some(category) => vec![category.clone()],
+        None => config.search.bang_categories.clone(),
+    };
+
+    // A typed `files:` or `>` prefix scopes the search like the popup's chips
+    if query.provider_filter.is_none() {
+        query.provider_filter = parsed.provider.clone();
+    }
+
+    // Bangs only apply to unscoped searches, and only lead the first page
+    let use_bangs = query.provider_filter.is_none();
+    let first_page = query.offset == 0;
+    let scope = BangScope {
+        profile: config.profile_or_current(query.profile.as_deref())?,
+        deny: &config.search.bang_deny,
+        categories: &categories,
+        aliases: &config.search.bang_aliases,
+    };
+
+    let vars = TemplateVars::from_config(&config.search);
 
-            // Try to find a bang in the middle of the query
-            let words: Vec<&str> = query.text.split(' ').collect();
-            for i in 1..words.len()-1 {
-                if let Some(bang) = bangs.iter().find(|b| b.trigger == words[i]) {
-                    let search = format!("{} {}",
-                        words[..i].join(" "),
-                        words[i+1..].join(" ")
-                    );
-                    let url = bang.url_template.replace("{{{s}}}", &search);
-                    process_manager.send_message(models::IpcMessage::Redirect(url)).await?;
-                    return Ok(());
+    // A matched bang is offered as the first result, showing where it
+    // leads, rather than opening while the query is still being typed
+    let matched = if use_bangs && first_page {
+        bang_store.match_query(&parsed.text, &scope).await
+    } else {
+        None
+    };
+    let preview = matched.map(|(chain, search)| bangs::preview(&chain, &search, &vars));
+
+    let found = providers.search(&query, &parsed, &config, cancel).await;
+    if cancel.is_cancelled() {
+        logging::debug(&format!("Search for '{}' was replaced by a newer one", query.text));
+        return Ok(cancelled(query));
+    }
+    let mut results = found.results;
+
+    if use_bangs {
+        results.extend(bang_store.discover(&parsed.text, &scope).await);
+    }
+
+    // The same URL or file found by several providers is listed once
+    let mut results = providers::dedupe(results);
+
+    // Results picked for similar queries before move up, looked up off the
+    // async workers
+    let usage = usage.clone();
+    let search_text = parsed.search_text();
+    let mut results = tokio::task::spawn_blocking(move || {
+        if let Err(e) = usage.boost(&search_text, &mut results) {
+            logging::error(&format!("Failed to apply click boosts: {:?}", e));
+        }
+        results
+    })
+    .await?;
+
+    // Sort results by score
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+
+    // Searching the web with the default bang always comes last, unless a
+    // matched bang leads instead
+    let default_search = match (&preview, &config.search.default_bang) {
+        (None, Some(trigger)) if use_bangs && first_page && !parsed.text.is_empty() => {
+            match bang_store.resolve(trigger, &scope).await {
+                Some(bang) => Some(models::SearchResult::new(
+                    format!("Search {} for \"{}\"", bang.display_name, parsed.text),
+                    Some(format!("!{}", bang.trigger)),
+                    models::Action::OpenBang {
+                        url: shared::bangs::expand(&bang, &parsed.text, &vars),
+                        triggers: vec![bang.trigger.clone()],
+                    },
+                    0.0,
+                ).with_category("Web search")),
+                None => {
+                    logging::warn(&format!("Default bang !{} not found", trigger));
+                    None
                 }
             }
         }
-    }
+        _ => None,
+    };
 
-    // Otherwise, perform normal search
-    let mut results = Vec::new();
-
-    // Search in commands
-    for cmd in &current_profile.commands {
-        // Convert config::Command to models::Command
-        let model_cmd = models::Command::new(
-            cmd.name.clone(),
-            cmd.description.clone(),
-            models::Action::OpenUrl(cmd.url.clone()),
-            Vec::new()
-        );
-
-        if model_cmd.matches_query(&query.text) {
-            results.push(models::SearchResult::new(
-                cmd.name.clone(),
-                Some(cmd.description.clone()),
-                models::Action::OpenUrl(cmd.url.clone()),
-                1.0
-            ));
+    // Either takes one of the page's slots and counts towards the total
+    let reserved = usize::from(preview.is_some() || default_search.is_some());
+    let total = results.len() + reserved;
+    let mut results: Vec<_> = results
+        .into_iter()
+        .skip(query.offset)
+        .take(query.max_results.saturating_sub(reserved))
+        .collect();
+
+    // Any web page can become a bang searching its site, from the popup's
+    // action menu
+    for result in &mut results {
+        if let models::Action::OpenUrl(url) = &result.action {
+            if url.starts_with("https://") || url.starts_with("http://") {
+                result.actions.push(models::ResultAction {
+                    label: "Create site-search bang".to_string(),
+                    action: models::Action::AddSiteBang(url.clone()),
+                });
+            }
         }
     }
 
-    // Sort results by score
-    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    if let Some(preview) = preview {
+        results.insert(0, preview);
+    }
+    results.extend(default_search);
 
     let response = models::SearchResponse {
         results,
         query,
+        total,
+        timed_out: found.timed_out,
+        cancelled: false,
     };
 
-    process_manager.send_message(models::IpcMessage::SearchResponse(response)).await?;
-    Ok(())
+    Ok(models::IpcMessage::SearchResponse(response))
+}
+
+// The answer to a search given up on for a newer one
+fn cancelled(query: models::SearchQuery) -> models::IpcMessage {
+    models::IpcMessage::SearchResponse(models::SearchResponse {
+        results: Vec::new(),
+        query,
+        total: 0,
+        timed_out: Vec::new(),
+        cancelled: true,
+    })
+}
+
+async fn handle_bang_query(
+    prefix: &str,
+    config: &Arc<Mutex<config::Config>>,
+    bang_store: &Arc<BangStore>,
+) -> Result<models::IpcMessage> {
+    logging::debug(&format!("Handling bang query: {}", prefix));
+
+    let config = config.lock().await;
+    let scope = BangScope {
+        profile: config.get_current_profile()?,
+        deny: &config.search.bang_deny,
+        categories: &config.search.bang_categories,
+        aliases: &config.search.bang_aliases,
+    };
+    Ok(models::IpcMessage::BangSuggestions(bang_store.suggest(prefix, &scope).await))
 }
 
 async fn handle_command(
     cmd: models::Command,
-    _config: &Arc<Mutex<config::Config>>,
+    config: &Arc<Mutex<config::Config>>,
     _process_manager: &Arc<ProcessManager>,
+    bang_store: &Arc<BangStore>,
+    operations: &Arc<Operations>,
+    plugins: &Arc<PluginHost>,
+    scripts: &Arc<ScriptHost>,
 ) -> Result<()> {
     logging::info(&format!("Handling command: {}", cmd.name));
 
@@ -327,57 +776,308 @@ async fn handle_command(
             }
         }
         models::Action::ExecuteCommand(command) => {
-            logging::info(&format!("Executing command: {}", command));
-            
-            #[cfg(target_os = "windows")]
-            let result = Command::new("cmd").arg("/C").arg(&command).spawn();
-            
-            #[cfg(not(target_os = "windows"))]
-            let result = Command::new("sh").arg("-c").arg(&command).spawn();
-            
-            match result {
-                Ok(_) => logging::info(&format!("Successfully executed command: {}", command)),
-                Err(e) => {
-                    logging::error(&format!("Failed to execute command {}: {}", command, e));
-                    return Err(anyhow::anyhow!("Failed to execute command: {}", e));
-                }
-            }
+            check_command(config, &command, cmd.confirmed).await?;
+            let exec = config.lock().await.exec.clone();
+            run_command(&command, &exec)?;
+        }
+        models::Action::RunCommand { command, .. } => {
+            check_command(config, &command, cmd.confirmed).await?;
+            let exec = configured_exec(config, &cmd.name, &command).await?;
+            run_command(&command, &exec)?;
         }
         models::Action::OpenUrl(url) => {
-            logging::info(&format!("Opening URL: {}", url));
-            
-            #[cfg(target_os = "windows")]
-            let result = Command::new("explorer").arg(&url).spawn();
-            
-            #[cfg(target_os = "macos")]
-            let result = Command::new("open").arg(&url).spawn();
-            
-            #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-            let result = Command::new("xdg-open").arg(&url).spawn();
-            
-            match result {
-                Ok(_) => logging::info(&format!("Successfully opened URL: {}", url)),
-                Err(e) => {
-                    logging::error(&format!("Failed to open URL {}: {}", url, e));
-                    return Err(anyhow::anyhow!("Failed to open URL: {}", e));
-                }
+            check_url(config, &url, cmd.confirmed).await?;
+            open_url(&url)?;
+        }
+        models::Action::OpenBang { url, triggers } => {
+            check_url(config, &url, cmd.confirmed).await?;
+            // Counted for suggestion ranking
+            for trigger in &triggers {
+                bang_store.record_use(trigger).await;
             }
+            open_url(&url)?;
         }
         models::Action::Custom(data) => {
             logging::info(&format!("Handling custom action with data: {:?}", data));
             // Implement custom action handling as needed
             logging::warn("Custom actions support is limited");
         }
+        models::Action::CompleteQuery(text) => {
+            // Completion happens in the popup, nothing to run here
+            logging::debug(&format!("Ignoring completion of '{}'", text));
+        }
+        models::Action::CopyToClipboard(text) => {
+            shared::desktop::copy_to_clipboard(&text)?;
+            logging::info("Copied result to the clipboard");
+        }
+        models::Action::PasteText(text) => {
+            shared::desktop::paste_text(&text)?;
+            logging::info("Pasted result");
+        }
+        models::Action::OpenWith { path, app } => {
+            // The app is started as a process like any other command
+            check_command(config, &app, cmd.confirmed).await?;
+            logging::info(&format!("Opening {:?} with {}", path, app));
+            shared::desktop::open_with(&path, &app)?;
+        }
+        models::Action::RevealInFolder(path) => {
+            logging::info(&format!("Revealing {:?}", path));
+            shared::desktop::reveal_in_folder(&path)?;
+        }
+        models::Action::MoveToTrash(path) => {
+            logging::info(&format!("Moving {:?} to the trash", path));
+            FileControl::new().trash_path(&path)?;
+        }
+        models::Action::DeletePermanently(path) => {
+            logging::info(&format!("Deleting {:?}", path));
+            FileControl::new().delete_path(&path)?;
+        }
+        models::Action::ExtractFile { path, to } => {
+            let extracted = FileControl::new().extract(&path, &to)?;
+            logging::info(&format!("Extracted {:?} to {:?}", path, extracted));
+        }
+        models::Action::CopyFiles { sources, to } => {
+            operations.start(operations::Kind::Copy, sources, to);
+        }
+        models::Action::MoveFiles { sources, to } => {
+            operations.start(operations::Kind::Move, sources, to);
+        }
+        models::Action::EjectVolume(path) => {
+            logging::info(&format!("Ejecting {:?}", path));
+            shared::desktop::eject(&path)?;
+        }
+        models::Action::OpenInTerminal { path, .. } => {
+            let command = providers::terminal_command(&config.lock().await, &path)?
+                .context("No terminal is set in the jump provider's options")?;
+            check_command(config, &command, cmd.confirmed).await?;
+            logging::info(&format!("Opening {:?} in a terminal: {}", path, command));
+
+            // Started in the directory, for terminals whose command doesn't take it
+            #[cfg(target_os = "windows")]
+            let result = Command::new("cmd").arg("/C").arg(&command).current_dir(&path).spawn();
+
+            #[cfg(not(target_os = "windows"))]
+            let result = Command::new("sh").arg("-c").arg(&command).current_dir(&path).spawn();
+
+            if let Err(e) = result {
+                logging::error(&format!("Failed to open {:?} in a terminal: {}", path, e));
+                return Err(anyhow::anyhow!("Failed to open terminal: {}", e));
+            }
+        }
+        models::Action::RunPlugin { plugin, data } => {
+            logging::info(&format!("Running a result from plugin '{}'", plugin));
+            plugins.execute(&plugin, data).await?;
+        }
+        models::Action::RunElevated(command) => {
+            check_command(config, &command, cmd.confirmed).await?;
+            check_elevated(config, &cmd.name, &command).await?;
+            logging::info(&format!("Running as administrator: {}", command));
+            shared::desktop::run_elevated(&command)?;
+        }
+        models::Action::LaunchApp(app) => {
+            logging::info(&format!("Launching {}", app));
+            shared::desktop::launch_app(&app)?;
+        }
+        models::Action::AddSiteBang(url) => {
+            let engine = {
+                let config = config.lock().await;
+                match &config.search.default_bang {
+                    Some(trigger) => {
+                        let scope = BangScope {
+                            profile: config.profile_or_current(None)?,
+                            deny: &config.search.bang_deny,
+                            categories: &config.search.bang_categories,
+                            aliases: &config.search.bang_aliases,
+                        };
+                        bang_store.resolve(trigger, &scope).await
+                    }
+                    None => None,
+                }
+            };
+            let bang = shared::bangs::site_search_bang(&url, "", engine.as_ref())?;
+            let trigger = bang.trigger.clone();
+            // Adding would replace a custom bang of the user's own
+            if shared::bangs::BangOverlay::load()?.bangs.iter().any(|b| b.trigger == trigger) {
+                anyhow::bail!("!{} is already a custom bang", trigger);
+            }
+            add_bang(bang, bang_store).await?;
+            logging::info(&format!("Added site search bang !{} for {}", trigger, url));
+        }
+        models::Action::RunScript { script, function } => {
+            logging::info(&format!("Running {} from script '{}'", function, script));
+            let run = RunPolicy::new(&config.lock().await, cmd.confirmed);
+            let output = scripts.call(&script, &function, Vec::new(), run).await?;
+            // Commands that produce text hand it over on the clipboard
+            if let Ok(text) = output.into_string() {
+                if !text.is_empty() {
+                    shared::desktop::copy_to_clipboard(&text)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_command(command: &str, exec: &models::ExecOptions) -> Result<()> {
+    logging::info(&format!("Executing command: {}", command));
+    match shared::desktop::shell_command(command, exec).spawn() {
+        Ok(_) => logging::info(&format!("Successfully executed command: {}", command)),
+        Err(e) => {
+            logging::error(&format!("Failed to execute command {}: {}", command, e));
+            return Err(anyhow::anyhow!("Failed to execute command: {}", e));
+        }
+    }
+    Ok(())
+}
+
+// `[security]` has the last word, whichever client sent the command
+async fn check_command(config: &Arc<Mutex<config::Config>>, command: &str, confirmed: bool) -> Result<()> {
+    check_policy(&config.lock().await.security, command, confirmed)
+}
+
+// The policy check itself, for callers that already hold the settings
+fn check_policy(security: &config::SecurityConfig, command: &str, confirmed: bool) -> Result<()> {
+    match security.policy(command) {
+        config::CommandPolicy::Allow => Ok(()),
+        config::CommandPolicy::Ask if confirmed => Ok(()),
+        config::CommandPolicy::Ask => {
+            logging::warn(&format!("Refused to run unconfirmed command: {}", command));
+            anyhow::bail!("'{}' has to be confirmed before it runs", command)
+        }
+        config::CommandPolicy::Deny => {
+            logging::warn(&format!("Refused to run denied command: {}", command));
+            anyhow::bail!("'{}' is not allowed to run", command)
+        }
+    }
+}
+
+// The shell, directory and environment of the profile command the client
+// named, never the ones it sent: `[security]` only sees the command line,
+// so a shell or `LD_PRELOAD` of the client's choosing would slip past it
+async fn configured_exec(config: &Arc<Mutex<config::Config>>, name: &str, command: &str) -> Result<models::ExecOptions> {
+    let config = config.lock().await;
+    let configured = config
+        .profiles
+        .iter()
+        .flat_map(|profile| &profile.commands)
+        .find(|cmd| cmd.name == name && cmd.run.as_deref() == Some(command))
+        .map(|cmd| cmd.exec.or(&config.exec));
+    match configured {
+        Some(exec) => Ok(exec),
+        None => {
+            logging::warn(&format!("Refused to run '{}' with options of no configured command", command));
+            anyhow::bail!("'{}' isn't a configured command", name)
+        }
+    }
+}
+
+// Only commands the profile itself marks `elevated` run as administrator,
+// whatever a client asks for
+async fn check_elevated(config: &Arc<Mutex<config::Config>>, name: &str, command: &str) -> Result<()> {
+    let config = config.lock().await;
+    let configured = config
+        .profiles
+        .iter()
+        .flat_map(|profile| &profile.commands)
+        .any(|cmd| cmd.name == name && cmd.elevated && cmd.run.as_deref() == Some(command));
+    if !configured {
+        logging::warn(&format!("Refused to run '{}' elevated, as no configured command does", command));
+        anyhow::bail!("'{}' isn't a configured elevated command", name);
+    }
+    Ok(())
+}
+
+// Like commands, URLs are held to `[security]` whichever client sent them
+async fn check_url(config: &Arc<Mutex<config::Config>>, url: &str, confirmed: bool) -> Result<()> {
+    match config.lock().await.security.url_policy(url) {
+        config::CommandPolicy::Allow => Ok(()),
+        config::CommandPolicy::Ask if confirmed => Ok(()),
+        config::CommandPolicy::Ask => {
+            logging::warn(&format!("Refused to open unconfirmed URL: {}", url));
+            anyhow::bail!("'{}' has to be confirmed before it opens", url)
+        }
+        config::CommandPolicy::Deny => {
+            logging::warn(&format!("Refused to open URL with a blocked scheme: {}", url));
+            anyhow::bail!("'{}' is not allowed to open", url)
+        }
     }
+}
+
+fn open_url(url: &str) -> Result<()> {
+    logging::info(&format!("Opening URL: {}", url));
+
+    #[cfg(target_os = "windows")]
+    let result = Command::new("explorer").arg(url).spawn();
+
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(url).spawn();
 
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = Command::new("xdg-open").arg(url).spawn();
+
+    match result {
+        Ok(_) => logging::info(&format!("Successfully opened URL: {}", url)),
+        Err(e) => {
+            logging::error(&format!("Failed to open URL {}: {}", url, e));
+            return Err(anyhow::anyhow!("Failed to open URL: {}", e));
+        }
+    }
     Ok(())
 }
 
-async fn handle_config_update(path: &PathBuf, config: &Arc<Mutex<config::Config>>) -> Result<()> {
+async fn add_bang(bang: models::Bang, bang_store: &Arc<BangStore>) -> Result<()> {
+    let mut overlay = shared::bangs::BangOverlay::load()?;
+    overlay.add(bang)?;
+    overlay.save()?;
+    bang_store.reload().await?;
+    Ok(())
+}
+
+// Adds a browser's search engines to the custom bangs, leaving the user's
+// own bangs alone when a keyword is already taken by one
+async fn import_bangs(browser: models::Browser, bang_store: &Arc<BangStore>) -> Result<models::BangRefresh> {
+    let (engines, mut errors) = import::search_engines(browser)?;
+
+    let mut overlay = shared::bangs::BangOverlay::load()?;
+    let mut count = 0;
+    for bang in engines {
+        if overlay.bangs.iter().any(|b| b.trigger == bang.trigger) {
+            errors.push(format!("{} (!{}): already a custom bang", bang.display_name, bang.trigger));
+            continue;
+        }
+        match overlay.add(bang) {
+            Ok(()) => count += 1,
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+
+    if count > 0 {
+        overlay.save()?;
+        bang_store.reload().await?;
+    }
+    Ok(models::BangRefresh { count, errors })
+}
+
+// A bad level is only worth a warning, logging goes on at the current one
+fn apply_logging(config: &config::Config) {
+    if let Err(e) = logging::set_level_named(&config.log_level) {
+        logging::warn(&format!("{:#}, keeping {}", e, logging::level()));
+    }
+    logging::set_rotation(&config.logging);
+}
+
+async fn handle_config_update(
+    path: &PathBuf,
+    config: &Arc<Mutex<config::Config>>,
+    providers: &Arc<ProviderRegistry>,
+) -> Result<()> {
     logging::info("Updating configuration");
 
     let new_config = config::Config::load(path)?;
+    apply_logging(&new_config);
     *config.lock().await = new_config;
+    providers.invalidate();
 
     logging::info("Configuration updated successfully");
     Ok(())
@@ -0,0 +1,255 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+// Mounted filesystems worth showing as volumes: the system drive, other
+// local disks, removable media and network shares, leaving out the pseudo
+// and system-internal mounts each platform has plenty of
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MountKind {
+    Local,
+    Removable,
+    Network,
+}
+
+impl MountKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            MountKind::Local => "Local disk",
+            MountKind::Removable => "Removable",
+            MountKind::Network => "Network share",
+        }
+    }
+
+    // Local disks stay mounted for as long as the system runs
+    pub fn can_eject(self) -> bool {
+        self != MountKind::Local
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mount {
+    pub path: PathBuf,
+    // e.g. `/dev/sdb1` or `//server/share`
+    pub device: String,
+    pub fs_type: String,
+    // The volume's own label where it has one, the mount point's name
+    // otherwise
+    pub label: String,
+    pub kind: MountKind,
+}
+
+// Network filesystems by type, FUSE ones included
+#[cfg(not(target_os = "windows"))]
+const NETWORK_FS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "afpfs", "webdav", "davfs", "fuse.sshfs", "fuse.rclone", "9p",
+];
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn list() -> Result<Vec<Mount>> {
+    use anyhow::Context;
+    use std::collections::HashMap;
+    use std::fs;
+
+    let table = fs::read_to_string("/proc/self/mounts").context("Failed to read /proc/self/mounts")?;
+
+    // Devices by label, from the symlinks udev keeps
+    let labels: HashMap<PathBuf, String> = fs::read_dir("/dev/disk/by-label")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let device = fs::canonicalize(entry.path()).ok()?;
+            Some((device, unescape(&entry.file_name().to_string_lossy())))
+        })
+        .collect();
+
+    let mut mounts = Vec::new();
+    for line in table.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(device), Some(path), Some(fs_type)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let device = unescape(device);
+        let path = unescape(path);
+
+        let kind = if NETWORK_FS.contains(&fs_type) {
+            MountKind::Network
+        } else if !device.starts_with("/dev/") || fs_type == "squashfs" || is_system_path(&path) {
+            // Pseudo filesystems, snaps and the like
+            continue;
+        } else if is_removable(&device) || path.starts_with("/media/") || path.starts_with("/run/media/") {
+            MountKind::Removable
+        } else {
+            MountKind::Local
+        };
+
+        let label = fs::canonicalize(&device)
+            .ok()
+            .and_then(|dev| labels.get(&dev).cloned())
+            .unwrap_or_else(|| name_of(&path));
+        mounts.push(Mount {
+            path: PathBuf::from(path),
+            device,
+            fs_type: fs_type.to_string(),
+            label,
+            kind,
+        });
+    }
+
+    Ok(dedupe(mounts))
+}
+
+#[cfg(target_os = "macos")]
+pub fn list() -> Result<Vec<Mount>> {
+    use anyhow::Context;
+
+    // Lines look like `/dev/disk2s1 on /Volumes/USB (msdos, local, nodev)`
+    let output = std::process::Command::new("mount").output().context("Failed to run mount")?;
+    let mut mounts = Vec::new();
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((device, rest)) = line.split_once(" on ") else {
+            continue;
+        };
+        let Some((path, options)) = rest.rsplit_once(" (") else {
+            continue;
+        };
+        let fs_type = options.split(',').next().unwrap_or_default().trim_end_matches(')').trim();
+
+        let kind = if NETWORK_FS.contains(&fs_type) {
+            MountKind::Network
+        } else if path == "/" {
+            MountKind::Local
+        } else if path.starts_with("/Volumes/") && device.starts_with("/dev/") {
+            // Other internal disks mount under /Volumes too, but ejecting
+            // works the same for them
+            MountKind::Removable
+        } else {
+            continue;
+        };
+
+        mounts.push(Mount {
+            path: PathBuf::from(path),
+            device: device.to_string(),
+            fs_type: fs_type.to_string(),
+            label: name_of(path),
+            kind,
+        });
+    }
+
+    Ok(dedupe(mounts))
+}
+
+#[cfg(target_os = "windows")]
+pub fn list() -> Result<Vec<Mount>> {
+    use anyhow::Context;
+
+    // One `C:|3|NTFS|Windows|` line per drive; DriveType 2 is removable, 4 a
+    // network drive and 5 an optical disc
+    let script = "Get-CimInstance Win32_LogicalDisk | ForEach-Object { \"$($_.DeviceID)|$($_.DriveType)|$($_.FileSystem)|$($_.VolumeName)|$($_.ProviderName)\" }";
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-Command", script])
+        .output()
+        .context("Failed to list drives")?;
+    let mut mounts = Vec::new();
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields: Vec<&str> = line.trim().split('|').collect();
+        let [drive, drive_type, fs_type, volume, provider] = fields[..] else {
+            continue;
+        };
+        let kind = match drive_type {
+            "2" | "5" => MountKind::Removable,
+            "4" => MountKind::Network,
+            "3" => MountKind::Local,
+            _ => continue,
+        };
+
+        let label = if volume.is_empty() { drive.to_string() } else { format!("{} ({})", volume, drive) };
+        mounts.push(Mount {
+            path: PathBuf::from(format!("{}\\", drive)),
+            device: if provider.is_empty() { drive.to_string() } else { provider.to_string() },
+            fs_type: fs_type.to_string(),
+            label,
+            kind,
+        });
+    }
+
+    Ok(mounts)
+}
+
+// Bind mounts and btrfs subvolumes show the same device several times; the
+// shortest mount point is the one people know
+#[cfg(not(target_os = "windows"))]
+fn dedupe(mut mounts: Vec<Mount>) -> Vec<Mount> {
+    mounts.sort_by_key(|m| m.path.as_os_str().len());
+    let mut seen = std::collections::HashSet::new();
+    mounts.retain(|m| m.kind == MountKind::Network || seen.insert(m.device.clone()));
+    mounts
+}
+
+#[cfg(not(target_os = "windows"))]
+fn name_of(path: &str) -> String {
+    match path.rsplit('/').find(|part| !part.is_empty()) {
+        Some(name) => name.to_string(),
+        None => "File System".to_string(),
+    }
+}
+
+// Mount points the system keeps for itself
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn is_system_path(path: &str) -> bool {
+    ["/boot", "/snap", "/var", "/proc", "/sys", "/dev", "/run", "/tmp", "/efi"]
+        .iter()
+        .any(|prefix| path == *prefix || path.starts_with(&format!("{}/", prefix)))
+        && !path.starts_with("/run/media/")
+}
+
+// Asks sysfs whether the disk a partition is on can be taken out
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn is_removable(device: &str) -> bool {
+    let Ok(device) = std::fs::canonicalize(device) else {
+        return false;
+    };
+    let Some(name) = device.file_name() else {
+        return false;
+    };
+    let Ok(block) = std::fs::canonicalize(std::path::Path::new("/sys/class/block").join(name)) else {
+        return false;
+    };
+
+    // USB disks often don't claim to be removable, but sit on the USB bus
+    let removable = |dir: &std::path::Path| {
+        std::fs::read_to_string(dir.join("removable")).is_ok_and(|flag| flag.trim() == "1")
+    };
+    removable(&block)
+        || block.parent().is_some_and(removable)
+        || block.components().any(|c| c.as_os_str().to_string_lossy().starts_with("usb"))
+}
+
+// /proc/self/mounts escapes spaces and such as `\040`, udev labels as `\x20`
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn unescape(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let raw = text.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        let escaped = match raw.get(i + 1) {
+            Some(b'x') if raw[i] == b'\\' => text.get(i + 2..i + 4).and_then(|hex| u8::from_str_radix(hex, 16).ok()).map(|b| (b, 4)),
+            Some(b'0'..=b'3') if raw[i] == b'\\' => text.get(i + 1..i + 4).and_then(|oct| u8::from_str_radix(oct, 8).ok()).map(|b| (b, 4)),
+            _ => None,
+        };
+        match escaped {
+            Some((byte, len)) => {
+                bytes.push(byte);
+                i += len;
+            }
+            None => {
+                bytes.push(raw[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
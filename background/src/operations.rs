@@ -0,0 +1,109 @@
+use shared::file_control::{FileControl, Progress};
+use shared::logging;
+use shared::models::OperationProgress;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    Copy,
+    Move,
+}
+
+// Copies and moves running on blocking threads, tracked so clients can poll
+// their progress with `ListOperations`
+#[derive(Default)]
+pub struct Operations {
+    next_id: AtomicU64,
+    list: Mutex<Vec<OperationProgress>>,
+}
+
+impl Operations {
+    pub fn start(self: &Arc<Self>, kind: Kind, sources: Vec<PathBuf>, to: PathBuf) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let label = label(kind, &sources, &to);
+        logging::info(&format!("{} (operation {})", label, id));
+
+        self.lock().push(OperationProgress {
+            id,
+            label,
+            files_done: 0,
+            files_total: 0,
+            bytes_done: 0,
+            bytes_total: 0,
+            finished: false,
+            error: None,
+        });
+
+        let operations = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let (sender, receiver) = mpsc::channel::<Progress>();
+            let worker = std::thread::spawn(move || {
+                let files = FileControl::new();
+                match kind {
+                    Kind::Copy => files.copy_many(&sources, &to, &sender),
+                    Kind::Move => files.move_many(&sources, &to, &sender),
+                }
+            });
+
+            // Ends once the worker is done and drops the sender
+            for progress in receiver {
+                operations.update(id, |op| {
+                    op.files_done = progress.files_done;
+                    op.files_total = progress.files_total;
+                    op.bytes_done = progress.bytes_done;
+                    op.bytes_total = progress.bytes_total;
+                });
+            }
+
+            let error = match worker.join() {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(format!("{:#}", e)),
+                Err(_) => Some("The operation crashed".to_string()),
+            };
+            match &error {
+                Some(e) => logging::error(&format!("Operation {} failed: {}", id, e)),
+                None => logging::info(&format!("Operation {} finished", id)),
+            }
+            operations.update(id, |op| {
+                op.finished = true;
+                op.error = error;
+            });
+        });
+
+        id
+    }
+
+    // Every running operation, plus finished ones, which are dropped once
+    // they have been listed
+    pub fn list(&self) -> Vec<OperationProgress> {
+        let mut list = self.lock();
+        let listed = list.clone();
+        list.retain(|op| !op.finished);
+        listed
+    }
+
+    fn update(&self, id: u64, f: impl FnOnce(&mut OperationProgress)) {
+        if let Some(op) = self.lock().iter_mut().find(|op| op.id == id) {
+            f(op);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<OperationProgress>> {
+        self.list.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn label(kind: Kind, sources: &[PathBuf], to: &Path) -> String {
+    let verb = match kind {
+        Kind::Copy => "Copying",
+        Kind::Move => "Moving",
+    };
+    let what = match sources {
+        [single] => single.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        _ => format!("{} items", sources.len()),
+    };
+    let dest = to.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| to.display().to_string());
+    format!("{} {} to {}", verb, what, dest)
+}
@@ -0,0 +1,280 @@
+use anyhow::{Context, Result};
+use shared::plugin::{PluginManifest, PluginRequest, PluginResponse, PluginResult, PluginState, PluginStatus};
+use shared::{logging, models, query};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+
+// External plugins: directories under the plugins directory, each with a
+// `plugin.toml` naming the program to run as a provider speaking the
+// `shared::plugin` protocol
+pub struct PluginHost {
+    plugins: Vec<Arc<Plugin>>,
+    // Every directory found, including those that didn't load
+    statuses: Vec<PluginStatus>,
+}
+
+impl PluginHost {
+    // Reads and checks the manifests without starting anything; each plugin
+    // starts on its first query. Of two plugins with the same name or
+    // trigger, the one whose directory sorts first keeps it.
+    pub fn discover(dir: &Path) -> Self {
+        let mut host = Self {
+            plugins: Vec::new(),
+            statuses: Vec::new(),
+        };
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    logging::warn(&format!("Failed to read plugins from {:?}: {}", dir, e));
+                }
+                return host;
+            }
+        };
+
+        let mut dirs: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+
+        for dir in dirs {
+            let status = host.load(dir);
+            match &status.state {
+                PluginState::Loaded => logging::info(&format!("Loaded plugin '{}' from {:?}", status.name(), status.dir)),
+                PluginState::Invalid(reason) | PluginState::Conflict(reason) => {
+                    logging::warn(&format!("Skipping plugin {:?}: {}", status.dir, reason))
+                }
+            }
+            for warning in &status.warnings {
+                logging::warn(&format!("Plugin '{}': {}", status.name(), warning));
+            }
+            host.statuses.push(status);
+        }
+
+        host
+    }
+
+    fn load(&mut self, dir: PathBuf) -> PluginStatus {
+        let mut status = PluginStatus {
+            dir: dir.clone(),
+            manifest: None,
+            state: PluginState::Loaded,
+            warnings: Vec::new(),
+        };
+
+        let manifest = match PluginManifest::load(&dir.join("plugin.toml")) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                status.state = PluginState::Invalid(e.to_string());
+                return status;
+            }
+        };
+        let exec = manifest.exec_path(&dir);
+        status.manifest = Some(manifest.clone());
+
+        if !exec.is_file() {
+            status.state = PluginState::Invalid(format!("{:?} doesn't exist", exec));
+            return status;
+        }
+        if self.plugins.iter().any(|p| p.name == manifest.name) {
+            status.state = PluginState::Conflict(format!("Another plugin is already named '{}'", manifest.name));
+            return status;
+        }
+
+        // Triggers clashing with the popup's modes or another plugin's are
+        // dropped, leaving the rest of the plugin usable
+        let mut triggers = Vec::new();
+        for trigger in &manifest.triggers {
+            let taken_by_mode = models::SEARCH_MODES.iter().any(|mode| mode.prefix == trigger.as_str())
+                || query::PROVIDER_ALIASES.iter().any(|(alias, _)| *alias == trigger.as_str())
+                || trigger.starts_with(query::COMMANDS_PREFIX);
+            let taken_by_plugin = self.plugins.iter().find(|p| p.triggers.contains(trigger));
+            if taken_by_mode {
+                status.warnings.push(format!("Trigger '{}' is a built-in search mode", trigger));
+            } else if let Some(other) = taken_by_plugin {
+                status.warnings.push(format!("Trigger '{}' is already used by plugin '{}'", trigger, other.name));
+            } else {
+                triggers.push(trigger.clone());
+            }
+        }
+
+        self.plugins.push(Arc::new(Plugin {
+            // Plugins are found once, and providers are named for good
+            name: Box::leak(manifest.name.clone().into_boxed_str()),
+            exec,
+            dir,
+            triggers,
+            manifest,
+            process: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        }));
+        status
+    }
+
+    // Leaves out plugins named like a built-in provider, which keeps its name
+    pub fn reserve(&mut self, names: &[&str]) {
+        self.plugins.retain(|plugin| {
+            if !names.contains(&plugin.name) {
+                return true;
+            }
+            let reason = format!("A built-in provider is already named '{}'", plugin.name);
+            logging::warn(&format!("Skipping plugin {:?}: {}", plugin.dir, reason));
+            if let Some(status) = self.statuses.iter_mut().find(|s| s.dir == plugin.dir) {
+                status.state = PluginState::Conflict(reason);
+            }
+            false
+        });
+    }
+
+    pub fn plugins(&self) -> &[Arc<Plugin>] {
+        &self.plugins
+    }
+
+    pub fn statuses(&self) -> &[PluginStatus] {
+        &self.statuses
+    }
+
+    pub async fn execute(&self, plugin: &str, data: serde_json::Value) -> Result<()> {
+        let plugin = self
+            .plugins
+            .iter()
+            .find(|p| p.name == plugin)
+            .with_context(|| format!("No plugin named '{}'", plugin))?;
+        plugin.send(&PluginRequest::Execute { data }).await
+    }
+}
+
+pub struct Plugin {
+    name: &'static str,
+    exec: PathBuf,
+    dir: PathBuf,
+    // The manifest's triggers, less any taken before it loaded
+    triggers: Vec<String>,
+    manifest: PluginManifest,
+    process: Mutex<Option<Running>>,
+    next_id: AtomicU64,
+}
+
+struct Running {
+    // Killed when dropped
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+impl Plugin {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn triggers(&self) -> &[String] {
+        &self.triggers
+    }
+
+    pub fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+
+    pub async fn query(&self, text: &str, max_results: usize, settings: HashMap<String, String>) -> Result<Vec<PluginResult>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut process = self.process.lock().await;
+        let request = PluginRequest::Query {
+            id,
+            text: text.to_string(),
+            max_results,
+            settings,
+        };
+        let running = self.write(&mut process, &request).await?;
+
+        // Earlier queries may still be answered first, if they timed out
+        // while the plugin was working on them
+        loop {
+            let line = match running.stdout.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => {
+                    *process = None;
+                    anyhow::bail!("Plugin '{}' exited", self.name);
+                }
+                Err(e) => {
+                    *process = None;
+                    return Err(e).with_context(|| format!("Failed to read from plugin '{}'", self.name));
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<PluginResponse>(&line) {
+                Ok(PluginResponse::Results { id: answered, results }) if answered == id => return Ok(results),
+                Ok(PluginResponse::Error { id: Some(answered), message }) if answered == id => {
+                    anyhow::bail!("Plugin '{}' failed: {}", self.name, message)
+                }
+                Ok(PluginResponse::Error { id: None, message }) => {
+                    logging::warn(&format!("Plugin '{}': {}", self.name, message))
+                }
+                Ok(_) => logging::debug(&format!("Dropping a late answer from plugin '{}'", self.name)),
+                Err(e) => logging::warn(&format!("Plugin '{}' wrote an invalid line ({}): {}", self.name, e, line)),
+            }
+        }
+    }
+
+    async fn send(&self, request: &PluginRequest) -> Result<()> {
+        let mut process = self.process.lock().await;
+        self.write(&mut process, request).await.map(|_| ())
+    }
+
+    // Writes the request, starting the plugin first if it isn't running
+    async fn write<'a>(&self, process: &'a mut Option<Running>, request: &PluginRequest) -> Result<&'a mut Running> {
+        let exited = match process.as_mut() {
+            Some(running) => !matches!(running.child.try_wait(), Ok(None)),
+            None => true,
+        };
+        if exited {
+            *process = Some(self.start()?);
+        }
+
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        let running = process.as_mut().expect("plugin was just started");
+        let written = async {
+            running.stdin.write_all(line.as_bytes()).await?;
+            running.stdin.flush().await
+        }
+        .await;
+
+        if let Err(e) = written {
+            *process = None;
+            return Err(e).with_context(|| format!("Failed to write to plugin '{}'", self.name));
+        }
+        Ok(process.as_mut().expect("plugin is running"))
+    }
+
+    fn start(&self) -> Result<Running> {
+        logging::info(&format!("Starting plugin '{}'", self.name));
+        let mut child = tokio::process::Command::new(&self.exec)
+            .args(&self.manifest.args)
+            .current_dir(&self.dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start plugin {:?}", self.exec))?;
+
+        let stdin = child.stdin.take().context("Plugin has no stdin")?;
+        let stdout = child.stdout.take().context("Plugin has no stdout")?;
+        Ok(Running {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        })
+    }
+}
@@ -38,7 +38,9 @@ impl ProcessManager {
         })
     }
 
-    pub async fn start_popup(&self) -> Result<()> {
+    // Opens the popup, with `query` already typed in when given and its top
+    // result read out loud with `speak`
+    pub async fn start_popup(&self, query: Option<&str>, speak: bool) -> Result<()> {
         let mut process = self.popup_process.lock().await;
 
         if process.is_none() {
@@ -65,8 +67,15 @@ impl ProcessManager {
             for path in &self.executable_paths {
                 logging::info(&format!("Trying to start from path: {}", path));
 
-                let result = Command::new(path)
-                    .arg(&ipc_addr)  // Pass the socket path as an argument
+                let mut command = Command::new(path);
+                command.arg(&ipc_addr);  // Pass the socket path as an argument
+                if let Some(query) = query {
+                    command.arg("--query").arg(query);
+                }
+                if speak {
+                    command.arg("--speak");
+                }
+                let result = command
                     .stdin(Stdio::null())
                     .stdout(Stdio::null())
                     .stderr(Stdio::null())
@@ -196,12 +205,21 @@ impl ProcessManager {
 
         self.stop_popup().await?;
         sleep(Duration::from_millis(300)).await;
-        self.start_popup().await?;
+        self.start_popup(None, false).await?;
 
         logging::info("Popup UI process restarted successfully");
         Ok(())
     }
 
+    // Opens the popup with `query` typed in, in place of one already open,
+    // which may be showing another search
+    pub async fn reopen_popup(&self, query: &str, speak: bool) -> Result<()> {
+        if self.is_popup_running().await {
+            self.stop_popup().await?;
+        }
+        self.start_popup(Some(query), speak).await
+    }
+
     pub async fn is_popup_running(&self) -> bool {
         let process = self.popup_process.lock().await;
         process.is_some()
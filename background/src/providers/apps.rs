@@ -0,0 +1,396 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use shared::config::Config;
+use shared::file_control::{FileControl, WalkOptions};
+use shared::logging;
+use shared::matcher;
+use shared::models::{Action, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::Provider;
+
+// How long the list of apps is used before it's read again in the
+// background, apps being installed now and then rather than all the time
+const REFRESH_EVERY: Duration = Duration::from_secs(5 * 60);
+// Shortcuts are only this deep in the Start Menu's folders
+const MAX_DEPTH: usize = 4;
+
+// Lists Start Menu apps and Store packages, asked for by PowerShell, with
+// what installs them and the rest
+const STORE_APPS_SCRIPT: &str = "ConvertTo-Json -Compress -Depth 3 -InputObject @{ \
+    apps = @(Get-StartApps | Where-Object AppID -like '*!*' | Select-Object Name, AppID); \
+    packages = @(Get-AppxPackage | Select-Object PackageFamilyName, InstallLocation) }";
+
+// Applications installed on Windows: the shortcuts in the Start Menu, for
+// desktop apps, and Store (UWP) apps, which have no shortcut and start by
+// their application user model id through `shell:AppsFolder`. Icons of
+// shortcuts are saved as PNGs in the cache dir the first time they're
+// seen; Store apps bring their own as images in their package.
+pub struct AppsProvider {
+    index: Arc<Mutex<Option<Index>>>,
+    // One read of the apps at a time, so searches made while it runs
+    // don't start more
+    loading: Arc<tokio::sync::Mutex<()>>,
+}
+
+#[derive(Clone)]
+struct Index {
+    apps: Arc<Vec<App>>,
+    loaded: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct App {
+    name: String,
+    // What `launch_app` gets: a shortcut's path or `shell:AppsFolder\id`
+    target: String,
+    // The shortcut itself, shown in Explorer on request
+    shortcut: Option<PathBuf>,
+    icon: Option<String>,
+}
+
+impl AppsProvider {
+    pub fn new() -> Self {
+        Self {
+            index: Arc::new(Mutex::new(None)),
+            loading: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    // The first search waits for the apps; later ones get them as last
+    // read while a stale list is read again
+    async fn apps(&self) -> Result<Arc<Vec<App>>> {
+        let cached = self.index.lock().unwrap().clone();
+        match cached {
+            Some(index) if index.loaded.elapsed() < REFRESH_EVERY => Ok(index.apps),
+            Some(index) => {
+                self.refresh();
+                Ok(index.apps)
+            }
+            None => self.refresh().await?,
+        }
+    }
+
+    // Runs apart from the search, so a search given up on for taking too
+    // long still leaves the apps read for the next one
+    fn refresh(&self) -> tokio::task::JoinHandle<Result<Arc<Vec<App>>>> {
+        let index = self.index.clone();
+        let loading = self.loading.clone();
+        tokio::spawn(async move {
+            let _loading = loading.lock().await;
+            if let Some(current) = index.lock().unwrap().clone() {
+                if current.loaded.elapsed() < REFRESH_EVERY {
+                    return Ok(current.apps);
+                }
+            }
+
+            let started = Instant::now();
+            let apps = Arc::new(load().await?);
+            logging::info(&format!("Found {} apps in {:?}", apps.len(), started.elapsed()));
+            *index.lock().unwrap() = Some(Index { apps: apps.clone(), loaded: Instant::now() });
+            Ok(apps)
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for AppsProvider {
+    fn name(&self) -> &'static str {
+        "apps"
+    }
+
+    // Ranked ahead of files and history, apps being what most searches
+    // are after
+    fn default_weight(&self) -> f32 {
+        1.2
+    }
+
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, _config: &Config) -> Result<Vec<SearchResult>> {
+        let text = parsed.search_text();
+        let scoped = query.provider_filter.as_deref() == Some(self.name());
+        if !scoped && text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let apps = self.apps().await?;
+        let mut results = Vec::new();
+        for app in apps.iter() {
+            let (score, highlights) = if text.trim().is_empty() {
+                (0.5, Vec::new())
+            } else {
+                match matcher::fuzzy_match(&text, &app.name) {
+                    Some(m) => (m.score, m.ranges),
+                    None => continue,
+                }
+            };
+            results.push(result(app, score, highlights));
+        }
+        Ok(results)
+    }
+}
+
+fn result(app: &App, score: f32, highlights: Vec<(usize, usize)>) -> SearchResult {
+    let kind = if app.shortcut.is_some() { "Application" } else { "Store app" };
+    let mut result = SearchResult::new(app.name.clone(), None, Action::LaunchApp(app.target.clone()), score)
+        .with_highlights(highlights)
+        .with_icon(app.icon.clone().unwrap_or_else(|| "application-x-executable".to_string()))
+        .with_category("Application")
+        .with_subtitle(kind);
+    if let Some(shortcut) = &app.shortcut {
+        result = result.with_action("Show in folder", Action::RevealInFolder(shortcut.clone()));
+    }
+    result
+}
+
+// Shortcuts first, so a Store app with a shortcut of the same name, as some
+// have, is listed once
+async fn load() -> Result<Vec<App>> {
+    let mut apps = tokio::task::spawn_blocking(shortcuts).await?;
+    extract_icons(&mut apps).await;
+
+    match store_apps().await {
+        Ok(store) => {
+            let names: HashSet<String> = apps.iter().map(|app| app.name.to_lowercase()).collect();
+            apps.extend(store.into_iter().filter(|app| !names.contains(&app.name.to_lowercase())));
+        }
+        // Desktop apps are still worth listing
+        Err(e) => logging::warn(&format!("Failed to list Store apps: {:#}", e)),
+    }
+    Ok(apps)
+}
+
+// The Start Menu of all users and of this one
+fn start_menus() -> Vec<PathBuf> {
+    let mut menus = Vec::new();
+    if let Some(data) = std::env::var_os("ProgramData") {
+        menus.push(PathBuf::from(data).join(r"Microsoft\Windows\Start Menu\Programs"));
+    }
+    if let Some(dirs) = directories::BaseDirs::new() {
+        menus.push(dirs.config_dir().join(r"Microsoft\Windows\Start Menu\Programs"));
+    }
+    menus
+}
+
+fn shortcuts() -> Vec<App> {
+    let files = FileControl::new();
+    let options = WalkOptions { max_depth: Some(MAX_DEPTH), ..Default::default() };
+    let mut seen = HashSet::new();
+    let mut apps = Vec::new();
+
+    for menu in start_menus() {
+        for entry in files.walk(&menu, options.clone()) {
+            if entry.is_dir || !entry.path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("lnk")) {
+                continue;
+            }
+            let Some(name) = entry.path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            // Uninstallers and readmes sit next to many apps' shortcuts
+            let lower = name.to_lowercase();
+            if lower.contains("uninstall") || lower.contains("readme") || !seen.insert(lower) {
+                continue;
+            }
+            apps.push(App {
+                name,
+                target: entry.path.display().to_string(),
+                shortcut: Some(entry.path),
+                icon: None,
+            });
+        }
+    }
+    apps
+}
+
+// Icons of the apps shortcuts start, as PNGs named after the shortcut and
+// when it last changed, so a changed shortcut gets its icon again. Ones not
+// saved yet are extracted in one go, PowerShell being slow to start, by a
+// script file: a Start Menu's worth of them doesn't fit on a command line.
+async fn extract_icons(apps: &mut [App]) {
+    let Some(dir) = directories::ProjectDirs::from("", "", "orion").map(|dirs| dirs.cache_dir().join("app-icons")) else {
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        logging::warn(&format!("Failed to create {:?}: {}", dir, e));
+        return;
+    }
+
+    let mut missing = Vec::new();
+    for app in apps.iter_mut() {
+        let Some(shortcut) = &app.shortcut else {
+            continue;
+        };
+        let png = dir.join(format!("{:016x}.png", icon_key(shortcut)));
+        if !png.exists() {
+            missing.push((shortcut.clone(), png.clone()));
+        }
+        app.icon = Some(png.display().to_string());
+    }
+    if missing.is_empty() {
+        return;
+    }
+
+    // With a byte order mark, or Windows PowerShell reads it as ANSI and
+    // mangles paths outside that code page
+    let mut script = String::from("\u{feff}Add-Type -AssemblyName System.Drawing\r\n");
+    for (shortcut, png) in &missing {
+        script.push_str(&format!(
+            "try {{ [System.Drawing.Icon]::ExtractAssociatedIcon('{}').ToBitmap().Save('{}') }} catch {{ }}\r\n",
+            ps_quote(&shortcut.to_string_lossy()),
+            ps_quote(&png.to_string_lossy())
+        ));
+    }
+    let script_path = dir.join("extract-icons.ps1");
+    let extracted = match std::fs::write(&script_path, script) {
+        Ok(()) => {
+            let file = script_path.to_string_lossy();
+            powershell(&["-ExecutionPolicy", "Bypass", "-File", &file]).await
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to write {:?}", script_path)),
+    };
+    let _ = std::fs::remove_file(&script_path);
+    if let Err(e) = extracted {
+        logging::warn(&format!("Failed to extract app icons: {:#}", e));
+    }
+
+    // Ones that couldn't be extracted fall back to the generic icon
+    for app in apps.iter_mut() {
+        if app.icon.as_deref().is_some_and(|icon| !Path::new(icon).exists()) {
+            app.icon = None;
+        }
+    }
+}
+
+fn icon_key(shortcut: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    shortcut.hash(&mut hasher);
+    if let Ok(modified) = std::fs::metadata(shortcut).and_then(|m| m.modified()) {
+        modified.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[derive(Deserialize)]
+struct StoreListing {
+    apps: Vec<StartApp>,
+    packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StartApp {
+    name: String,
+    #[serde(rename = "AppID")]
+    app_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Package {
+    package_family_name: String,
+    install_location: Option<String>,
+}
+
+// Store apps as the Start Menu lists them, by application user model id,
+// `PackageFamilyName!AppId`
+async fn store_apps() -> Result<Vec<App>> {
+    let output = powershell(&["-Command", STORE_APPS_SCRIPT]).await?;
+    let listing: StoreListing = serde_json::from_str(&output).context("Failed to read the list of Store apps")?;
+    let locations: HashMap<String, PathBuf> = listing
+        .packages
+        .into_iter()
+        .filter_map(|p| Some((p.package_family_name, PathBuf::from(p.install_location?))))
+        .collect();
+
+    Ok(listing
+        .apps
+        .into_iter()
+        .map(|app| {
+            let icon = app
+                .app_id
+                .split_once('!')
+                .and_then(|(family, id)| store_icon(locations.get(family)?, id));
+            App {
+                name: app.name,
+                target: format!(r"shell:AppsFolder\{}", app.app_id),
+                shortcut: None,
+                icon: icon.map(|icon| icon.display().to_string()),
+            }
+        })
+        .collect())
+}
+
+// The small logo the app's manifest names, which is on disk under a name
+// with its scale or size added, e.g. `Square44x44Logo.scale-200.png` for
+// `Square44x44Logo.png`
+fn store_icon(location: &Path, app_id: &str) -> Option<PathBuf> {
+    let manifest = std::fs::read_to_string(location.join("AppxManifest.xml")).ok()?;
+    let application = manifest
+        .split("<Application ")
+        .skip(1)
+        .find(|element| attribute(element, "Id") == Some(app_id))?;
+    let logo = attribute(application, "Square44x44Logo").or_else(|| attribute(application, "Logo"))?;
+
+    let logo = location.join(logo.replace('/', "\\"));
+    if logo.exists() {
+        return Some(logo);
+    }
+    let (dir, stem, ext) = (logo.parent()?, logo.file_stem()?.to_str()?, logo.extension()?.to_str()?);
+    let mut variants: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name().and_then(|n| n.to_str()).is_some_and(|name| {
+                name.starts_with(&format!("{}.", stem)) && name.ends_with(&format!(".{}", ext))
+            })
+        })
+        .collect();
+    // Sized for lists rather than tiles, and drawn for light and dark
+    // backgrounds alike, where the package has such
+    variants.sort_by_key(|path| {
+        let name = path.to_string_lossy().to_lowercase();
+        (!name.contains("targetsize-32"), name.contains("contrast"), !name.contains("scale-100"), name)
+    });
+    variants.into_iter().next()
+}
+
+// The value of `name="value"` within an element, up to where it closes
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let element = &element[..element.find('>').unwrap_or(element.len())];
+    let start = element
+        .match_indices(&format!("{}=\"", name))
+        .map(|(idx, _)| idx)
+        .find(|&idx| idx == 0 || element[..idx].ends_with(char::is_whitespace))?
+        + name.len()
+        + 2;
+    let len = element[start..].find('"')?;
+    Some(&element[start..start + len])
+}
+
+async fn powershell(args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive"])
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .context("Failed to run powershell")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "powershell exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn ps_quote(text: &str) -> String {
+    text.replace('\'', "''")
+}
@@ -0,0 +1,49 @@
+use shared::models::{SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Queries remembered across every provider; the oldest go first
+const CAPACITY: usize = 512;
+
+// Each provider's results for recent queries, as they came from the
+// provider and before weighting, so a profile's weights apply to cached
+// results too. Entries expire after the configured time and are all
+// dropped when what providers search changes: the config, or the history
+// and clicks after a result is run.
+#[derive(Default)]
+pub struct ResultCache {
+    entries: Mutex<HashMap<String, (Instant, Vec<SearchResult>)>>,
+}
+
+impl ResultCache {
+    // Everything a provider's results depend on
+    pub fn key(provider: &str, query: &SearchQuery, parsed: &ParsedQuery) -> String {
+        format!(
+            "{}\u{0}{:?}\u{0}{:?}\u{0}{:?}\u{0}{}",
+            provider, parsed, query.provider_filter, query.profile, query.max_results
+        )
+    }
+
+    pub fn get(&self, key: &str, ttl: Duration) -> Option<Vec<SearchResult>> {
+        let entries = self.entries.lock().unwrap();
+        let (stored, results) = entries.get(key)?;
+        (stored.elapsed() < ttl).then(|| results.clone())
+    }
+
+    pub fn insert(&self, key: String, results: Vec<SearchResult>, ttl: Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (stored, _)| stored.elapsed() < ttl);
+        if entries.len() >= CAPACITY {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, (stored, _))| *stored).map(|(key, _)| key.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, (Instant::now(), results));
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
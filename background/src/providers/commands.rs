@@ -0,0 +1,68 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::matcher;
+use shared::models::{self, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+
+use super::Provider;
+
+// Commands configured in the profile the query runs under
+pub struct CommandsProvider;
+
+#[async_trait]
+impl Provider for CommandsProvider {
+    fn name(&self) -> &'static str {
+        "commands"
+    }
+
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, config: &Config) -> Result<Vec<SearchResult>> {
+        let profile = config.profile_or_current(query.profile.as_deref())?;
+        let text = parsed.search_text();
+        // `--exact` drops fuzzy matches, keeping names that contain the text
+        let exact = parsed.has_flag("exact");
+        let mut results = Vec::new();
+
+        for cmd in &profile.commands {
+            let action = match &cmd.run {
+                Some(run) if cmd.elevated => models::Action::RunElevated(run.clone()),
+                Some(run) if !cmd.exec.is_empty() => models::Action::RunCommand {
+                    command: run.clone(),
+                    exec: cmd.exec.clone(),
+                },
+                Some(run) => models::Action::ExecuteCommand(run.clone()),
+                None => models::Action::OpenUrl(cmd.url.clone()),
+            };
+            // Convert config::Command to models::Command
+            let model_cmd = models::Command::new(
+                cmd.name.clone(),
+                cmd.description.clone(),
+                action.clone(),
+                cmd.keywords.clone()
+            );
+
+            // Quoted phrases have to appear as typed
+            if !parsed.phrases().all(|phrase| model_cmd.matches_query(phrase)) {
+                continue;
+            }
+
+            // Name matches carry highlights, description or keyword matches rank lower
+            let (score, highlights) = match matcher::fuzzy_match(&text, &cmd.name) {
+                Some(m) if !exact || m.ranges.len() <= 1 => (m.score, m.ranges),
+                _ if model_cmd.matches_query(&text) => (0.2, Vec::new()),
+                _ => continue,
+            };
+
+            results.push(SearchResult::new(
+                cmd.name.clone(),
+                Some(cmd.description.clone()),
+                action,
+                score
+            )
+            .with_highlights(highlights)
+            .with_category("Command"));
+        }
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::matcher;
+use shared::models::{Action, ProviderOption, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::file_index::FileIndex;
+
+use super::Provider;
+
+const MAX_RESULTS: usize = 50;
+
+// Files and folders under the configured roots whose names match, looked up
+// in an index the background keeps current as files come and go, rather
+// than walked per search
+pub struct FilesProvider {
+    pub index: Arc<FileIndex>,
+}
+
+#[async_trait]
+impl Provider for FilesProvider {
+    fn name(&self) -> &'static str {
+        "files"
+    }
+
+    fn options(&self) -> Vec<ProviderOption> {
+        [
+            ("roots", "Folders to index, separated by commas; the home folder when empty"),
+            ("exclude", "Patterns to skip, e.g. node_modules/, *.tmp"),
+        ]
+        .into_iter()
+        .map(|(key, label)| ProviderOption {
+            key: key.to_string(),
+            label: label.to_string(),
+        })
+        .collect()
+    }
+
+    // The index changes as files are created and deleted
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    async fn search(&self, _query: &SearchQuery, parsed: &ParsedQuery, _config: &Config) -> Result<Vec<SearchResult>> {
+        let text = parsed.search_text();
+        let text = text.trim();
+        if text.chars().count() < 2 {
+            return Ok(Vec::new());
+        }
+
+        // Nothing until the roots have been walked once
+        let hits = self.index.search(text, MAX_RESULTS).await.unwrap_or_default();
+        Ok(hits.into_iter().filter_map(|(path, score)| result(text, path, score)).collect())
+    }
+}
+
+fn result(text: &str, path: PathBuf, score: f32) -> Option<SearchResult> {
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    let highlights = matcher::fuzzy_match(text, &name).map(|m| m.ranges).unwrap_or_default();
+    let folder = path.parent().map(|dir| dir.display().to_string()).unwrap_or_default();
+    let icon = if path.is_dir() { "folder" } else { "text-x-generic" };
+
+    Some(
+        SearchResult::new(name, Some(path.display().to_string()), Action::OpenFile(path.clone()), score * 0.8)
+            .with_highlights(highlights)
+            .with_icon(icon)
+            .with_category("File")
+            .with_subtitle(folder)
+            .with_action("Show in folder", Action::RevealInFolder(path)),
+    )
+}
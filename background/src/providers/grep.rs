@@ -0,0 +1,267 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::file_control::{FileControl, WalkOptions};
+use shared::models::{Action, ProviderOption, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::Provider;
+
+// Files larger than this are skipped unless `max_file_kb` says otherwise
+const DEFAULT_MAX_FILE_KB: u64 = 1024;
+// Files read per search, however many roots there are
+const MAX_FILES: usize = 20_000;
+const MAX_MATCHES: usize = 200;
+const MAX_MATCHES_PER_FILE: usize = 5;
+// A NUL byte in this much of the start of a file marks it as binary
+const BINARY_SNIFF_LEN: usize = 8192;
+const MAX_LINE_CHARS: usize = 200;
+
+// Lines of files under the configured roots that contain the query. Reading
+// files is slow, so this only runs for searches scoped with `grep:` or `in:`.
+pub struct GrepProvider;
+
+#[async_trait]
+impl Provider for GrepProvider {
+    fn name(&self) -> &'static str {
+        "grep"
+    }
+
+    fn options(&self) -> Vec<ProviderOption> {
+        [
+            ("roots", "Folders to search, separated by commas"),
+            ("exclude", "Patterns to skip, e.g. target/, *.min.js"),
+            ("editor", "Editor command, e.g. code -g {path}:{line}"),
+            ("max_file_kb", "Largest file to read, in KB"),
+        ]
+        .into_iter()
+        .map(|(key, label)| ProviderOption {
+            key: key.to_string(),
+            label: label.to_string(),
+        })
+        .collect()
+    }
+
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, config: &Config) -> Result<Vec<SearchResult>> {
+        let text = parsed.search_text();
+        if query.provider_filter.as_deref() != Some(self.name()) || text.trim().chars().count() < 2 {
+            return Ok(Vec::new());
+        }
+
+        let options = config
+            .profile_or_current(query.profile.as_deref())?
+            .providers
+            .iter()
+            .find(|s| s.name == self.name())
+            .map(|s| s.options.clone())
+            .unwrap_or_default();
+        let search = GrepSearch::new(&text, &options)?;
+
+        // The walk runs on a blocking thread that isn't stopped by the
+        // provider timeout, so it checks a flag set once this future is gone
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let _cancel = CancelOnDrop(cancelled.clone());
+        let matches = tokio::task::spawn_blocking(move || search.run(&cancelled)).await?;
+
+        let editor = options.get("editor").map(|e| e.trim()).filter(|e| !e.is_empty());
+        Ok(matches.into_iter().map(|m| m.into_result(editor)).collect())
+    }
+}
+
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+struct GrepSearch {
+    roots: Vec<PathBuf>,
+    walk: WalkOptions,
+    max_file_size: u64,
+    needle: String,
+    // Smart case: only a query with capitals is matched case-sensitively
+    ignore_case: bool,
+}
+
+impl GrepSearch {
+    fn new(text: &str, options: &BTreeMap<String, String>) -> Result<Self> {
+        let home = directories::BaseDirs::new()
+            .context("Failed to get home directory")?
+            .home_dir()
+            .to_path_buf();
+        let mut roots: Vec<PathBuf> = list(options.get("roots"))
+            .map(|root| match root.strip_prefix('~') {
+                Some(rest) => home.join(rest.trim_start_matches(['/', '\\'])),
+                None => PathBuf::from(root),
+            })
+            .collect();
+        if roots.is_empty() {
+            roots.push(home);
+        }
+
+        let max_file_kb = options
+            .get("max_file_kb")
+            .and_then(|kb| kb.trim().parse().ok())
+            .unwrap_or(DEFAULT_MAX_FILE_KB);
+        let needle = text.trim().to_string();
+        let ignore_case = !needle.chars().any(char::is_uppercase);
+
+        Ok(Self {
+            roots,
+            walk: WalkOptions {
+                exclude: list(options.get("exclude")).map(str::to_string).collect(),
+                limit: Some(MAX_FILES),
+                ..Default::default()
+            },
+            max_file_size: max_file_kb * 1024,
+            needle: if ignore_case { needle.to_lowercase() } else { needle },
+            ignore_case,
+        })
+    }
+
+    fn run(&self, cancelled: &AtomicBool) -> Vec<LineMatch> {
+        let files = FileControl::new();
+        let mut matches = Vec::new();
+        let mut read = 0;
+
+        for root in &self.roots {
+            for entry in files.walk(root, self.walk.clone()) {
+                if cancelled.load(Ordering::Relaxed) || matches.len() >= MAX_MATCHES || read >= MAX_FILES {
+                    return matches;
+                }
+                if entry.is_dir {
+                    continue;
+                }
+                let readable = std::fs::metadata(&entry.path).is_ok_and(|m| m.is_file() && m.len() <= self.max_file_size);
+                if !readable {
+                    continue;
+                }
+
+                read += 1;
+                self.search_file(&entry.path, &mut matches);
+            }
+        }
+
+        matches
+    }
+
+    // Unreadable and binary files are skipped silently, like grep -I
+    fn search_file(&self, path: &Path, matches: &mut Vec<LineMatch>) {
+        let Ok(mut file) = File::open(path) else {
+            return;
+        };
+        let mut head = vec![0; BINARY_SNIFF_LEN];
+        let Ok(len) = file.read(&mut head) else {
+            return;
+        };
+        if head[..len].contains(&0) {
+            return;
+        }
+
+        let reader = BufReader::new(head[..len].chain(file));
+        let mut found = 0;
+        for (idx, line) in reader.lines().enumerate() {
+            // Stops at the first line that isn't UTF-8
+            let Ok(line) = line else {
+                return;
+            };
+            let Some(column) = self.find(&line) else {
+                continue;
+            };
+
+            matches.push(LineMatch {
+                path: path.to_path_buf(),
+                line_number: idx + 1,
+                line,
+                column,
+                len: self.needle.chars().count(),
+            });
+            found += 1;
+            if found >= MAX_MATCHES_PER_FILE || matches.len() >= MAX_MATCHES {
+                return;
+            }
+        }
+    }
+
+    // Byte offset of the first match
+    fn find(&self, line: &str) -> Option<usize> {
+        if !self.ignore_case {
+            return line.find(&self.needle);
+        }
+        // Lowercasing can change byte lengths, so map back by characters
+        let lower = line.to_lowercase();
+        let at = lower.find(&self.needle)?;
+        let chars = lower[..at].chars().count();
+        Some(line.char_indices().nth(chars).map_or(line.len(), |(i, _)| i))
+    }
+}
+
+struct LineMatch {
+    path: PathBuf,
+    line_number: usize,
+    line: String,
+    // Byte offset of the match in `line`, and its length in characters
+    column: usize,
+    len: usize,
+}
+
+impl LineMatch {
+    fn into_result(self, editor: Option<&str>) -> SearchResult {
+        let leading = self.line.len() - self.line.trim_start().len();
+        let trimmed = self.line.trim();
+        let title: String = trimmed.chars().take(MAX_LINE_CHARS).collect();
+
+        // Highlight in characters, the title being trimmed and maybe cut short
+        let start = self.line[leading.min(self.column)..self.column].chars().count();
+        let end = start + self.len;
+        let highlights = if end <= title.chars().count() { vec![(start, end)] } else { Vec::new() };
+
+        let action = match editor {
+            Some(editor) => Action::ExecuteCommand(
+                editor
+                    .replace("{path}", &shell_quote(&self.path.to_string_lossy()))
+                    .replace("{line}", &self.line_number.to_string()),
+            ),
+            None => Action::OpenFile(self.path.clone()),
+        };
+        let name = self.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+
+        SearchResult::new(
+            if title.is_empty() { name.clone() } else { title },
+            Some(format!("{}:{}", self.path.display(), self.line_number)),
+            action,
+            0.6,
+        )
+        .with_highlights(highlights)
+        .with_subtitle(format!("{}:{}", name, self.line_number))
+        .with_category("File contents")
+        .with_action("Show in folder", Action::RevealInFolder(self.path))
+    }
+}
+
+// Comma separated option values, blanks dropped
+fn list(value: Option<&String>) -> impl Iterator<Item = &str> {
+    value
+        .map(String::as_str)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+// Quotes a path for the shell the editor command runs in
+pub(super) fn shell_quote(text: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("\"{}\"", text)
+    } else {
+        format!("'{}'", text.replace('\'', "'\\''"))
+    }
+}
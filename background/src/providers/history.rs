@@ -0,0 +1,76 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::matcher;
+use shared::models::{SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::Provider;
+use crate::history::HistoryStore;
+
+// Recently executed results, offered while the query is empty and searched
+// once the query is scoped with `hist`
+pub struct HistoryProvider {
+    pub store: Arc<HistoryStore>,
+}
+
+#[async_trait]
+impl Provider for HistoryProvider {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, _config: &Config) -> Result<Vec<SearchResult>> {
+        let text = parsed.search_text();
+        let scoped = query.provider_filter.as_deref() == Some(self.name());
+        if !scoped && !text.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let entries = self.store.entries().await;
+        let index = self.store.index().await;
+        let hits: Option<HashMap<&str, f32>> = (!text.is_empty())
+            .then(|| index.as_ref())
+            .flatten()
+            .map(|index| index.search(&text, entries.len()).into_iter().map(|hit| (hit.key, hit.score)).collect());
+        let mut results = Vec::new();
+
+        for (idx, entry) in entries.iter().enumerate() {
+            let title_match = matcher::fuzzy_match(&text, &entry.title);
+            let (score, highlights) = if text.is_empty() {
+                // Newest first on an empty query, just below an exact match
+                (0.95 - idx as f32 * 0.001, Vec::new())
+            } else if let Some(hits) = &hits {
+                match hits.get(entry.key().as_str()) {
+                    Some(score) => (*score, title_match.map(|m| m.ranges).unwrap_or_default()),
+                    None => continue,
+                }
+            } else {
+                // Still indexing
+                let by_query = entry.query.as_deref().and_then(|q| matcher::fuzzy_match(&text, q));
+                match (title_match, by_query) {
+                    (Some(m), _) => (m.score, m.ranges),
+                    (None, Some(m)) => (m.score * 0.8, Vec::new()),
+                    (None, None) => continue,
+                }
+            };
+
+            let mut result = SearchResult::new(
+                entry.title.clone(),
+                Some(entry.description.clone()).filter(|d| !d.is_empty()),
+                entry.action.clone(),
+                score,
+            )
+            .with_highlights(highlights)
+            .with_category("History");
+            if let Some(query) = &entry.query {
+                result = result.with_subtitle(format!("“{}”", query));
+            }
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+}
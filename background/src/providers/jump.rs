@@ -0,0 +1,143 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::matcher;
+use shared::models::{Action, ProviderOption, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::grep::shell_quote;
+use super::Provider;
+use crate::usage_db::UsageDb;
+
+const MAX_DIRS: usize = 50;
+
+// Directories opened from results before, ranked by how often and how
+// recently, for searches scoped with `cd` or `j:`. Words match the path in
+// order and the last one the directory's own name, like zoxide, so `pro orion`
+// finds `~/projects/orion` and Enter jumps to the best match.
+pub struct JumpProvider {
+    pub usage: Arc<UsageDb>,
+}
+
+#[async_trait]
+impl Provider for JumpProvider {
+    fn name(&self) -> &'static str {
+        "jump"
+    }
+
+    fn options(&self) -> Vec<ProviderOption> {
+        vec![ProviderOption {
+            key: "terminal".to_string(),
+            label: "Terminal command, e.g. kitty --directory {path}".to_string(),
+        }]
+    }
+
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, config: &Config) -> Result<Vec<SearchResult>> {
+        if query.provider_filter.as_deref() != Some(self.name()) {
+            return Ok(Vec::new());
+        }
+
+        let terminal = terminal(config, query.profile.as_deref())?;
+
+        // SQLite and the directory checks block
+        let usage = self.usage.clone();
+        let text = parsed.search_text().to_lowercase();
+        tokio::task::spawn_blocking(move || -> Result<Vec<SearchResult>> {
+            let words: Vec<&str> = text.split_whitespace().collect();
+            let dirs = usage.dirs()?;
+            let mut results = Vec::new();
+
+            // Most frecent first, so the first match sets the scale
+            let mut top = None;
+            for dir in dirs {
+                if results.len() >= MAX_DIRS {
+                    break;
+                }
+                if !matches(&words, &dir.path) || !dir.path.is_dir() {
+                    continue;
+                }
+                let top = *top.get_or_insert(dir.frecency);
+                let score = 0.5 + 0.5 * (dir.frecency / top) as f32;
+                results.push(result(&dir.path, score, words.last().copied(), terminal.as_deref()));
+            }
+
+            Ok(results)
+        })
+        .await?
+    }
+}
+
+// Every word appears in the path, in order, and the last one in the final
+// component
+fn matches(words: &[&str], path: &Path) -> bool {
+    let full = path.to_string_lossy().to_lowercase();
+    let mut rest = full.as_str();
+    for word in words {
+        match rest.find(word) {
+            Some(at) => rest = &rest[at + word.len()..],
+            None => return false,
+        }
+    }
+
+    match words.last() {
+        Some(last) => path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().to_lowercase().contains(last)),
+        None => true,
+    }
+}
+
+fn result(path: &Path, score: f32, last_word: Option<&str>, terminal: Option<&str>) -> SearchResult {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+    let highlights = last_word
+        .and_then(|word| matcher::fuzzy_match(word, &name))
+        .map(|m| m.ranges)
+        .unwrap_or_default();
+    let open_folder = Action::OpenFile(path.to_path_buf());
+
+    let result = match terminal {
+        Some(terminal) => SearchResult::new(name, Some(path.display().to_string()), in_terminal(path, terminal), score)
+            .with_action("Open folder", open_folder),
+        None => SearchResult::new(name, Some(path.display().to_string()), open_folder, score),
+    };
+    result
+        .with_highlights(highlights)
+        .with_icon("folder")
+        .with_category("Directory")
+}
+
+// The profile's `terminal` option, None when it's unset or blank
+fn terminal(config: &Config, profile: Option<&str>) -> Result<Option<String>> {
+    Ok(config
+        .profile_or_current(profile)?
+        .providers
+        .iter()
+        .find(|s| s.name == "jump")
+        .and_then(|s| s.options.get("terminal"))
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty()))
+}
+
+// The command that opens `path` in the current profile's terminal. The
+// service builds it here rather than running whatever a client sent along
+pub fn terminal_command(config: &Config, path: &Path) -> Result<Option<String>> {
+    Ok(terminal(config, None)?.map(|terminal| fill(&terminal, path)))
+}
+
+// `{path}` in the command is replaced by the quoted path; the command runs
+// in the directory either way
+fn fill(terminal: &str, path: &Path) -> String {
+    terminal.replace("{path}", &shell_quote(&path.to_string_lossy()))
+}
+
+fn in_terminal(path: &Path, terminal: &str) -> Action {
+    Action::OpenInTerminal {
+        path: path.to_path_buf(),
+        command: fill(terminal, path),
+    }
+}
@@ -0,0 +1,308 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future::join_all;
+use shared::config::Config;
+use shared::logging;
+use shared::models::{ProviderInfo, ProviderOption, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::file_index::FileIndex;
+use crate::history::HistoryStore;
+use crate::plugins::PluginHost;
+use crate::scripts::ScriptHost;
+use crate::usage_db::UsageDb;
+use cache::ResultCache;
+
+pub use jump::terminal_command;
+
+#[cfg(target_os = "windows")]
+mod apps;
+mod cache;
+mod commands;
+mod files;
+mod grep;
+mod history;
+mod jump;
+mod paths;
+mod plugin;
+mod scripts;
+#[cfg(target_os = "macos")]
+mod spotlight;
+mod volumes;
+
+// A source of search results, addressed by name from `SearchQuery::provider_filter`
+#[async_trait]
+pub trait Provider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Options this provider reads from its `ProviderSettings`
+    fn options(&self) -> Vec<ProviderOption> {
+        Vec::new()
+    }
+
+    // Weight and timeout in milliseconds used until a profile sets them
+    fn default_weight(&self) -> f32 {
+        1.0
+    }
+
+    fn default_timeout(&self) -> Option<u64> {
+        None
+    }
+
+    // Whether results can be reused for the same query, which providers
+    // reading what changes from one moment to the next opt out of
+    fn cacheable(&self) -> bool {
+        true
+    }
+
+    // Scores run from 0.0 to 1.0, 1.0 being an exact match; anything
+    // outside is clamped before results from different providers are merged
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, config: &Config) -> Result<Vec<SearchResult>>;
+}
+
+pub struct ProviderRegistry {
+    providers: Vec<Box<dyn Provider>>,
+    // Plugin triggers and the plugin each scopes a search to
+    triggers: Vec<(String, &'static str)>,
+    cache: ResultCache,
+}
+
+impl ProviderRegistry {
+    pub fn new(history: Arc<HistoryStore>, usage: Arc<UsageDb>, files: Arc<FileIndex>, plugins: &mut PluginHost, scripts: Arc<ScriptHost>) -> Self {
+        let mut providers: Vec<Box<dyn Provider>> = vec![
+            Box::new(commands::CommandsProvider),
+            Box::new(history::HistoryProvider { store: history }),
+            Box::new(grep::GrepProvider),
+            Box::new(paths::PathsProvider),
+            Box::new(files::FilesProvider { index: files }),
+            Box::new(jump::JumpProvider { usage }),
+            Box::new(volumes::VolumesProvider),
+            Box::new(scripts::ScriptsProvider { host: scripts }),
+        ];
+        #[cfg(target_os = "macos")]
+        providers.push(Box::new(spotlight::SpotlightProvider));
+        #[cfg(target_os = "windows")]
+        providers.push(Box::new(apps::AppsProvider::new()));
+
+        // Plugins come after the built-in providers, which keep their names
+        let built_in: Vec<&str> = providers.iter().map(|p| p.name()).collect();
+        plugins.reserve(&built_in);
+
+        let mut triggers = Vec::new();
+        for plugin in plugins.plugins() {
+            providers.push(Box::new(plugin::PluginProvider { plugin: plugin.clone() }));
+            triggers.extend(plugin.triggers().iter().map(|trigger| (trigger.clone(), plugin.name())));
+        }
+
+        Self {
+            providers,
+            triggers,
+            cache: ResultCache::default(),
+        }
+    }
+
+    // Forgets cached results, for when what providers search has changed
+    pub fn invalidate(&self) {
+        self.cache.clear();
+    }
+
+    // The plugin a query starting with one of its triggers is for, as in
+    // `w berlin` or `w: berlin`, and the rest of the query
+    pub fn route<'a>(&self, text: &'a str) -> Option<(&'static str, &'a str)> {
+        let text = text.trim_start();
+        self.triggers.iter().find_map(|(trigger, plugin)| {
+            let rest = text.strip_prefix(trigger.as_str())?;
+            let rest = rest.strip_prefix(':').or_else(|| rest.strip_prefix(' '))?;
+            Some((*plugin, rest))
+        })
+    }
+
+    pub fn info(&self) -> Vec<ProviderInfo> {
+        self.providers
+            .iter()
+            .map(|p| ProviderInfo {
+                name: p.name().to_string(),
+                options: p.options(),
+                weight: p.default_weight(),
+            })
+            .collect()
+    }
+
+    // Enabled providers in the query profile's priority order, followed by
+    // any the profile doesn't mention
+    fn ordered(&self, query: &SearchQuery, config: &Config) -> Vec<Scheduled<'_>> {
+        let settings = config
+            .profile_or_current(query.profile.as_deref())
+            .map(|p| p.providers.as_slice())
+            .unwrap_or_default();
+
+        let position = |name: &str| settings.iter().position(|s| s.name == name).unwrap_or(settings.len());
+        let default_timeout = config.search.provider_timeout;
+
+        let mut providers: Vec<Scheduled> = self.providers
+            .iter()
+            .map(|p| p.as_ref())
+            .filter_map(|provider| {
+                let (weight, timeout) = match settings.iter().find(|s| s.name == provider.name()) {
+                    Some(s) if !s.enabled => return None,
+                    Some(s) => (s.weight, s.timeout.or(provider.default_timeout())),
+                    None => (provider.default_weight(), provider.default_timeout()),
+                };
+                let timeout = timeout.unwrap_or(default_timeout);
+                Some(Scheduled {
+                    provider,
+                    weight,
+                    timeout: Duration::from_millis(timeout),
+                })
+            })
+            .collect();
+        providers.sort_by_key(|s| position(s.provider.name()));
+        providers
+    }
+
+    // Runs every provider allowed by the query's filter at once, each under
+    // its own timeout, so a search takes as long as its slowest provider
+    // rather than all of them together. Providers that fail are skipped and
+    // those that time out named. Scores are held to the 0.0 to 1.0 contract,
+    // then scaled by the provider's weight. Results come back in provider
+    // priority order whichever finished first, so a stable sort by score
+    // keeps higher priority providers first among equal scores. Cancelling
+    // drops the providers still running, which stops their work. Results
+    // for a query searched moments ago come from the cache instead.
+    pub async fn search(
+        &self,
+        query: &SearchQuery,
+        parsed: &ParsedQuery,
+        config: &Config,
+        cancel: &CancellationToken,
+    ) -> ProviderResults {
+        let mut found = ProviderResults::default();
+        let ttl = Duration::from_millis(config.search.cache_ttl);
+
+        let running = self
+            .ordered(query, config)
+            .into_iter()
+            .filter(|s| match &query.provider_filter {
+                Some(filter) => filter == s.provider.name(),
+                None => true,
+            })
+            .map(|Scheduled { provider, weight, timeout }| {
+                let span = tracing::debug_span!("provider", name = provider.name());
+                async move {
+                    let started = Instant::now();
+                    let cached = provider.cacheable() && !ttl.is_zero();
+                    let key = ResultCache::key(provider.name(), query, parsed);
+                    if let Some(results) = cached.then(|| self.cache.get(&key, ttl)).flatten() {
+                        return (provider, weight, timeout, started.elapsed(), Some(Ok(Ok(results))));
+                    }
+
+                    let outcome = tokio::select! {
+                        outcome = tokio::time::timeout(timeout, provider.search(query, parsed, config)) => Some(outcome),
+                        _ = cancel.cancelled() => None,
+                    };
+                    if let (true, Some(Ok(Ok(results)))) = (cached, &outcome) {
+                        self.cache.insert(key, results.clone(), ttl);
+                    }
+                    (provider, weight, timeout, started.elapsed(), outcome)
+                }
+                .instrument(span)
+            });
+
+        for (provider, weight, timeout, took, outcome) in join_all(running).await {
+            let Some(outcome) = outcome else {
+                continue;
+            };
+            found.timings.push((provider.name(), took));
+            match outcome {
+                Ok(Ok(provider_results)) => {
+                    if provider_results.iter().any(|r| !(0.0..=1.0).contains(&r.score)) {
+                        logging::debug(&format!("Provider '{}' scored outside 0.0 to 1.0", provider.name()));
+                    }
+                    found.results.extend(provider_results.into_iter().map(|mut result| {
+                        result.score = normalize(result.score) * weight;
+                        result
+                    }));
+                }
+                Ok(Err(e)) => logging::error(&format!("Provider '{}' failed: {:?}", provider.name(), e)),
+                Err(_) => {
+                    logging::warn(&format!("Provider '{}' timed out after {:?}", provider.name(), timeout));
+                    found.timed_out.push(provider.name().to_string());
+                }
+            }
+        }
+
+        found
+    }
+}
+
+struct Scheduled<'a> {
+    provider: &'a dyn Provider,
+    weight: f32,
+    timeout: Duration,
+}
+
+#[derive(Default)]
+pub struct ProviderResults {
+    pub results: Vec<SearchResult>,
+    // Providers left out for taking too long
+    pub timed_out: Vec<String>,
+    // How long each provider that ran took, timed out or not
+    pub timings: Vec<(&'static str, Duration)>,
+}
+
+// Folds results leading to the same URL or file into one, keeping the best
+// scored and adding the others' extra actions to it. Results keep the order
+// of their first appearance, so provider priority still breaks ties.
+pub fn dedupe(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut merged: Vec<SearchResult> = Vec::with_capacity(results.len());
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for result in results {
+        let Some(target) = result.action.target() else {
+            merged.push(result);
+            continue;
+        };
+        let Some(&idx) = seen.get(&target) else {
+            seen.insert(target, merged.len());
+            merged.push(result);
+            continue;
+        };
+
+        let existing = &mut merged[idx];
+        if result.score > existing.score {
+            let other = std::mem::replace(existing, result);
+            merge_into(existing, other);
+        } else {
+            merge_into(existing, result);
+        }
+    }
+
+    merged
+}
+
+fn merge_into(kept: &mut SearchResult, other: SearchResult) {
+    for action in other.actions {
+        if !kept.actions.iter().any(|a| a.label == action.label) {
+            kept.actions.push(action);
+        }
+    }
+    if kept.icon.is_none() {
+        kept.icon = other.icon;
+    }
+    if kept.subtitle.is_none() {
+        kept.subtitle = other.subtitle;
+    }
+}
+
+fn normalize(score: f32) -> f32 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score.clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::file_control::{FileControl, FileInfo};
+use shared::logging;
+use shared::matcher;
+use shared::models::{Action, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::path::{Path, PathBuf};
+
+use super::Provider;
+
+// Entries offered from a single directory, however many match
+const MAX_ENTRIES: usize = 200;
+
+// Entries of the directory a path-like query points into, `~/Doc` listing
+// what starts with `Doc` in the home directory. Tab completes into the
+// entry, so directories can be browsed without leaving the popup.
+pub struct PathsProvider;
+
+#[async_trait]
+impl Provider for PathsProvider {
+    fn name(&self) -> &'static str {
+        "paths"
+    }
+
+    // Listing a directory is quick, and a file just created should show
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    async fn search(&self, query: &SearchQuery, _parsed: &ParsedQuery, _config: &Config) -> Result<Vec<SearchResult>> {
+        // The raw text, as paths can hold spaces, quotes and dashes that the
+        // query parser would take apart
+        let Some(typed) = TypedPath::parse(query.text.trim_start()) else {
+            return Ok(Vec::new());
+        };
+
+        let dir = typed.directory()?;
+        tokio::task::spawn_blocking(move || typed.complete(&dir)).await?
+    }
+}
+
+// A query split at its last separator into the directory, as typed, and
+// the start of a name in it
+struct TypedPath {
+    dir: String,
+    partial: String,
+}
+
+impl TypedPath {
+    fn parse(text: &str) -> Option<Self> {
+        if text == "~" {
+            return Some(Self { dir: format!("~{}", separator(text)), partial: String::new() });
+        }
+        if !looks_like_path(text) {
+            return None;
+        }
+
+        let split = text.rfind(is_separator)? + 1;
+        Some(Self {
+            dir: text[..split].to_string(),
+            partial: text[split..].to_string(),
+        })
+    }
+
+    // Where the typed directory is on disk, with `~` expanded
+    fn directory(&self) -> Result<PathBuf> {
+        match self.dir.strip_prefix('~') {
+            Some(rest) => Ok(directories::BaseDirs::new()
+                .context("Failed to get home directory")?
+                .home_dir()
+                .join(rest.trim_start_matches(is_separator))),
+            None => Ok(PathBuf::from(&self.dir)),
+        }
+    }
+
+    fn complete(&self, dir: &Path) -> Result<Vec<SearchResult>> {
+        let listing = FileControl::new().list_directory(dir)?;
+        if !listing.skipped.is_empty() {
+            logging::debug(&format!("Skipped {} entries listing {:?}", listing.skipped.len(), dir));
+        }
+
+        // Dot files only show up once a dot is typed
+        let show_hidden = self.partial.starts_with('.');
+        let mut entries = Vec::new();
+
+        if self.partial.is_empty() {
+            // Enter on a bare `~/Documents/` opens the folder itself
+            let name = dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| self.dir.clone());
+            let result = SearchResult::new(name, Some(dir.display().to_string()), Action::OpenFile(dir.to_path_buf()), 1.0)
+                .with_icon("folder")
+                .with_category("Path")
+                .with_subtitle("Open folder");
+            entries.push((true, result));
+        }
+
+        for info in &listing.files {
+            let Some(name) = info.path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            if name.starts_with('.') && !show_hidden {
+                continue;
+            }
+
+            let (score, highlights) = if self.partial.is_empty() {
+                (0.5, Vec::new())
+            } else {
+                match matcher::fuzzy_match(&self.partial, &name) {
+                    Some(m) => (m.score, m.ranges),
+                    None => continue,
+                }
+            };
+            entries.push((info.is_directory, self.result(info, name, score, highlights)));
+        }
+
+        // Directories first among equal scores, then by name
+        entries.sort_by(|(a_dir, a), (b_dir, b)| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b_dir.cmp(a_dir))
+                .then_with(|| a.title.cmp(&b.title))
+        });
+        Ok(entries.into_iter().take(MAX_ENTRIES).map(|(_, result)| result).collect())
+    }
+
+    fn result(&self, info: &FileInfo, name: String, score: f32, highlights: Vec<(usize, usize)>) -> SearchResult {
+        // Completes in the form it was typed, `~` and separators kept
+        let mut completion = format!("{}{}", self.dir, name);
+        if info.is_directory {
+            completion.push(separator(&self.dir));
+        }
+
+        let mut result = SearchResult::new(
+            name,
+            Some(info.path.display().to_string()),
+            Action::OpenFile(info.path.clone()),
+            score,
+        )
+        .with_highlights(highlights)
+        .with_icon(info.icon.clone())
+        .with_category("Path")
+        .with_completion(completion);
+        if info.is_file {
+            result = result
+                .with_subtitle(format_size(info.size))
+                .with_action("Show in folder", Action::RevealInFolder(info.path.clone()));
+        }
+        result
+    }
+}
+
+// `~/...`, `/...`, and on Windows `C:\...` or `\\server\...`
+fn looks_like_path(text: &str) -> bool {
+    if text.starts_with("~/") || text.starts_with('/') {
+        return true;
+    }
+    if cfg!(target_os = "windows") {
+        let bytes = text.as_bytes();
+        return text.starts_with("~\\")
+            || text.starts_with("\\\\")
+            || (bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && is_separator(bytes[2] as char));
+    }
+    false
+}
+
+fn is_separator(c: char) -> bool {
+    c == '/' || (cfg!(target_os = "windows") && c == '\\')
+}
+
+// The separator the text was typed with, `/` unless it only has `\`
+fn separator(text: &str) -> char {
+    if cfg!(target_os = "windows") && text.contains('\\') && !text.contains('/') {
+        '\\'
+    } else {
+        '/'
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
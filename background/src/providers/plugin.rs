@@ -0,0 +1,96 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::models::{Action, ProviderOption, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::sync::Arc;
+
+use super::Provider;
+use crate::plugins::Plugin;
+
+// An external plugin, named in its manifest. Picking one of its results hands
+// the result's data back to the plugin to act on.
+pub struct PluginProvider {
+    pub plugin: Arc<Plugin>,
+}
+
+#[async_trait]
+impl Provider for PluginProvider {
+    fn name(&self) -> &'static str {
+        self.plugin.name()
+    }
+
+    fn default_weight(&self) -> f32 {
+        self.plugin.manifest().weight.unwrap_or(1.0)
+    }
+
+    fn default_timeout(&self) -> Option<u64> {
+        self.plugin.manifest().timeout
+    }
+
+    // The manifest's settings, edited per profile like any provider's options
+    fn options(&self) -> Vec<ProviderOption> {
+        self.plugin
+            .manifest()
+            .settings
+            .iter()
+            .map(|setting| ProviderOption {
+                key: setting.key.clone(),
+                label: setting.label.clone(),
+            })
+            .collect()
+    }
+
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, config: &Config) -> Result<Vec<SearchResult>> {
+        let text = parsed.search_text();
+        let scoped = query.provider_filter.as_deref() == Some(self.name());
+        if !scoped && text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let options = config
+            .profile_or_current(query.profile.as_deref())?
+            .provider_settings(self.name())
+            .map(|s| s.options.clone())
+            .unwrap_or_default();
+        let settings = self
+            .plugin
+            .manifest()
+            .settings
+            .iter()
+            .map(|setting| {
+                let value = options
+                    .get(&setting.key)
+                    .filter(|value| !value.trim().is_empty())
+                    .unwrap_or(&setting.default);
+                (setting.key.clone(), value.clone())
+            })
+            .collect();
+
+        let found = self.plugin.query(&text, query.max_results, settings).await?;
+        Ok(found
+            .into_iter()
+            .filter(|r| !r.title.trim().is_empty())
+            .map(|r| {
+                // Results leading somewhere Orion can open go through the
+                // same dedupe and click boosts as built-in ones
+                let action = match (r.url, r.path) {
+                    (Some(url), _) => Action::OpenUrl(url),
+                    (None, Some(path)) => Action::OpenFile(path),
+                    (None, None) => Action::RunPlugin {
+                        plugin: self.name().to_string(),
+                        data: r.data,
+                    },
+                };
+                let mut result = SearchResult::new(r.title, r.description, action, r.score).with_category("Plugin");
+                if let Some(icon) = r.icon {
+                    result = result.with_icon(icon);
+                }
+                if let Some(subtitle) = r.subtitle {
+                    result = result.with_subtitle(subtitle);
+                }
+                result
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::logging;
+use shared::matcher;
+use shared::models::{Action, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::sync::Arc;
+
+use super::Provider;
+use crate::scripts::{RunPolicy, ScriptHost};
+
+// Commands registered by scripts, matched by title, and the output of every
+// script transform for what was typed
+pub struct ScriptsProvider {
+    pub host: Arc<ScriptHost>,
+}
+
+#[async_trait]
+impl Provider for ScriptsProvider {
+    fn name(&self) -> &'static str {
+        "scripts"
+    }
+
+    async fn search(&self, _query: &SearchQuery, parsed: &ParsedQuery, config: &Config) -> Result<Vec<SearchResult>> {
+        let text = parsed.search_text();
+        if text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        // Nothing until `warm` has compiled the scripts on a blocking thread
+        let Some(scripts) = self.host.loaded() else {
+            return Ok(Vec::new());
+        };
+        let mut results = Vec::new();
+        let run = RunPolicy::new(config, false);
+
+        for script in scripts {
+            for command in &script.commands {
+                let Some(m) = matcher::fuzzy_match(&text, &command.title) else {
+                    continue;
+                };
+                let action = Action::RunScript {
+                    script: script.name.clone(),
+                    function: command.function.clone(),
+                };
+                results.push(
+                    SearchResult::new(command.title.clone(), Some(command.description.clone()), action, m.score)
+                        .with_highlights(m.ranges)
+                        .with_category("Script"),
+                );
+            }
+
+            for transform in &script.transforms {
+                let output = match self.host.call(&script.name, &transform.function, vec![text.clone().into()], run.clone()).await {
+                    Ok(output) => output,
+                    Err(e) => {
+                        logging::warn(&format!("{:#}", e));
+                        continue;
+                    }
+                };
+                // Transforms return nothing for text they don't apply to
+                let Ok(output) = output.into_string() else {
+                    continue;
+                };
+                if output.is_empty() || output == text {
+                    continue;
+                }
+                results.push(
+                    SearchResult::new(output.clone(), None, Action::CopyToClipboard(output.clone()), 0.6)
+                        .with_subtitle(transform.title.clone())
+                        .with_category("Transform")
+                        .with_action("Paste", Action::PasteText(output)),
+                );
+            }
+        }
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::matcher;
+use shared::models::{Action, ProviderOption, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+use super::Provider;
+
+// Paths read from mdfind per search, which lists every match it has
+const MAX_PATHS: usize = 200;
+const MAX_RESULTS: usize = 50;
+
+// Files, folders and applications whose name contains the query, as
+// Spotlight's index on macOS has them, so nothing is walked on disk.
+// Applications launch through Launch Services rather than being opened as
+// folders, which is what their bundles are.
+pub struct SpotlightProvider;
+
+#[async_trait]
+impl Provider for SpotlightProvider {
+    fn name(&self) -> &'static str {
+        "spotlight"
+    }
+
+    fn options(&self) -> Vec<ProviderOption> {
+        vec![ProviderOption {
+            key: "roots".to_string(),
+            label: "Folders to search, separated by commas; everything indexed when empty".to_string(),
+        }]
+    }
+
+    fn default_timeout(&self) -> Option<u64> {
+        Some(800)
+    }
+
+    // Files are added and removed all the time
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, config: &Config) -> Result<Vec<SearchResult>> {
+        let text = parsed.search_text();
+        let text = text.trim();
+        if text.chars().count() < 2 {
+            return Ok(Vec::new());
+        }
+
+        let roots = config
+            .profile_or_current(query.profile.as_deref())?
+            .provider_settings(self.name())
+            .and_then(|s| s.options.get("roots").cloned())
+            .unwrap_or_default();
+        let roots: Vec<PathBuf> = roots
+            .split(',')
+            .map(str::trim)
+            .filter(|root| !root.is_empty())
+            .map(expand_home)
+            .collect::<Result<_>>()?;
+
+        let mut results: Vec<SearchResult> = mdfind(text, &roots)
+            .await?
+            .into_iter()
+            .filter_map(|path| result(text, path))
+            .collect();
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(MAX_RESULTS);
+        Ok(results)
+    }
+}
+
+// mdfind keeps going through the index after the paths wanted, so it's
+// stopped once they've been read, or when the search times out
+async fn mdfind(text: &str, roots: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut command = Command::new("mdfind");
+    for root in roots {
+        command.arg("-onlyin").arg(root);
+    }
+    let mut child = command
+        .arg("-name")
+        .arg(text)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to run mdfind")?;
+
+    let stdout = child.stdout.take().context("mdfind has no stdout")?;
+    let mut lines = BufReader::new(stdout).lines();
+    let mut paths = Vec::new();
+    while let Some(line) = lines.next_line().await? {
+        paths.push(PathBuf::from(line));
+        if paths.len() >= MAX_PATHS {
+            break;
+        }
+    }
+    Ok(paths)
+}
+
+fn result(text: &str, path: PathBuf) -> Option<SearchResult> {
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    let is_app = path.extension().is_some_and(|ext| ext == "app");
+    // Apps are matched and shown without `.app`
+    let title = if is_app { name.trim_end_matches(".app").to_string() } else { name };
+    let found = matcher::fuzzy_match(text, &title)?;
+    let folder = path.parent().map(|dir| dir.display().to_string()).unwrap_or_default();
+
+    let result = if is_app {
+        SearchResult::new(
+            title,
+            Some(path.display().to_string()),
+            Action::LaunchApp(path.display().to_string()),
+            // Ahead of files of the same name, apps being what's looked for
+            // far more often
+            (found.score + 0.1).min(1.0),
+        )
+        .with_icon("application-x-executable")
+        .with_category("Application")
+        .with_action("Show in Finder", Action::RevealInFolder(path))
+    } else {
+        let icon = if path.is_dir() { "folder" } else { "text-x-generic" };
+        SearchResult::new(title, Some(path.display().to_string()), Action::OpenFile(path.clone()), found.score * 0.8)
+            .with_icon(icon)
+            .with_category("File")
+            .with_action("Show in Finder", Action::RevealInFolder(path))
+    };
+    Some(result.with_highlights(found.ranges).with_subtitle(folder))
+}
+
+fn expand_home(root: &str) -> Result<PathBuf> {
+    match root.strip_prefix('~') {
+        Some(rest) => Ok(directories::BaseDirs::new()
+            .context("Failed to get home directory")?
+            .home_dir()
+            .join(rest.trim_start_matches('/'))),
+        None => Ok(Path::new(root).to_path_buf()),
+    }
+}
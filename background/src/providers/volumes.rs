@@ -0,0 +1,80 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use shared::config::Config;
+use shared::matcher;
+use shared::models::{Action, SearchQuery, SearchResult};
+use shared::query::ParsedQuery;
+
+use super::Provider;
+use crate::mounts::{self, Mount, MountKind};
+
+// Mounted drives, removable media and network shares, matched by label or
+// mount point, or all listed when the search is scoped with `vol`
+pub struct VolumesProvider;
+
+#[async_trait]
+impl Provider for VolumesProvider {
+    fn name(&self) -> &'static str {
+        "volumes"
+    }
+
+    // Drives come and go
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    async fn search(&self, query: &SearchQuery, parsed: &ParsedQuery, _config: &Config) -> Result<Vec<SearchResult>> {
+        let text = parsed.search_text();
+        let scoped = query.provider_filter.as_deref() == Some(self.name());
+        if !scoped && text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mounts = tokio::task::spawn_blocking(mounts::list).await??;
+        let mut results = Vec::new();
+
+        for (idx, mount) in mounts.iter().enumerate() {
+            let (score, highlights) = if text.trim().is_empty() {
+                // Listed as the platform lists them, system drive first
+                (0.9 - idx as f32 * 0.001, Vec::new())
+            } else if let Some(m) = matcher::fuzzy_match(&text, &mount.label) {
+                (m.score, m.ranges)
+            } else if matcher::fuzzy_match(&text, &mount.path.to_string_lossy()).is_some() {
+                (0.3, Vec::new())
+            } else {
+                continue;
+            };
+            results.push(result(mount, score, highlights));
+        }
+
+        Ok(results)
+    }
+}
+
+fn result(mount: &Mount, score: f32, highlights: Vec<(usize, usize)>) -> SearchResult {
+    let icon = match mount.kind {
+        MountKind::Local => "drive-harddisk",
+        MountKind::Removable => "drive-removable-media",
+        MountKind::Network => "folder-remote",
+    };
+    let subtitle = if mount.fs_type.is_empty() {
+        mount.kind.label().to_string()
+    } else {
+        format!("{} · {}", mount.kind.label(), mount.fs_type)
+    };
+
+    let mut result = SearchResult::new(
+        mount.label.clone(),
+        Some(format!("{} on {}", mount.device, mount.path.display())),
+        Action::OpenFile(mount.path.clone()),
+        score,
+    )
+    .with_highlights(highlights)
+    .with_icon(icon)
+    .with_category("Volume")
+    .with_subtitle(subtitle);
+    if mount.kind.can_eject() {
+        result = result.with_action("Eject", Action::EjectVolume(mount.path.clone()));
+    }
+    result
+}
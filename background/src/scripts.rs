@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use rhai::{CallFnOptions, Dynamic, Engine, FuncArgs, Scope, AST};
+use shared::config::{Config, SecurityConfig};
+use shared::desktop;
+use shared::logging;
+use shared::models::ExecOptions;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// How long a script function may run before it's stopped, unless the script
+// asks for longer with `time_limit(ms)`
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_millis(250);
+const MAX_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+// Rhai scripts from the `scripts` config directory. Each registers what it
+// offers when loaded, naming functions of its own to call later:
+//
+//   command("Lorem ipsum", "Copy some filler text", "lorem");
+//   transform("Upper case", "upper");
+//
+//   fn lorem() { "Lorem ipsum dolor sit amet" }
+//   fn upper(text) { text.to_upper() }
+//
+// A command shows up in search like a configured one and runs its function
+// with no arguments when picked. A transform runs on everything typed, its
+// output offered to copy. Either way a string returned is copied to the
+// clipboard, and `run(command)` starts a shell command, held to the
+// `[security]` policy like any other.
+//
+// Scripts are compiled by `warm` once the service is up, rather than
+// holding up startup, and searched once that's done.
+pub struct ScriptHost {
+    dir: PathBuf,
+    scripts: OnceLock<Vec<Script>>,
+}
+
+pub struct Script {
+    pub name: String,
+    ast: AST,
+    time_limit: Duration,
+    pub commands: Vec<ScriptCommand>,
+    pub transforms: Vec<ScriptCommand>,
+}
+
+// What `run(command)` is held to while a function runs. Transforms run on
+// every keystroke with nobody to confirm anything, so commands the policy
+// asks about are only run from a command that was confirmed when picked
+#[derive(Clone)]
+pub struct RunPolicy {
+    security: SecurityConfig,
+    exec: ExecOptions,
+    confirmed: bool,
+}
+
+impl RunPolicy {
+    pub fn new(config: &Config, confirmed: bool) -> Self {
+        Self {
+            security: config.security.clone(),
+            exec: config.exec.clone(),
+            confirmed,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScriptCommand {
+    pub title: String,
+    pub description: String,
+    pub function: String,
+}
+
+// What a script registers while it's loaded
+#[derive(Default)]
+struct Registered {
+    commands: Vec<ScriptCommand>,
+    transforms: Vec<ScriptCommand>,
+    time_limit: Option<Duration>,
+}
+
+impl ScriptHost {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            scripts: OnceLock::new(),
+        }
+    }
+
+    // Compiles the scripts on a blocking thread, so the first search
+    // doesn't have to
+    pub fn warm(self: &Arc<Self>) {
+        let host = self.clone();
+        tokio::task::spawn_blocking(move || {
+            host.scripts();
+        });
+    }
+
+    pub fn scripts(&self) -> &[Script] {
+        self.scripts.get_or_init(|| load_all(&self.dir))
+    }
+
+    // The scripts once `warm` has compiled them, for async callers that
+    // mustn't compile them or wait on the thread that is
+    pub fn loaded(&self) -> Option<&[Script]> {
+        self.scripts.get().map(Vec::as_slice)
+    }
+
+    // Calls one of a script's functions on a blocking thread, as scripts can
+    // keep a thread busy for up to their time limit
+    pub async fn call(self: &Arc<Self>, script: &str, function: &str, args: Vec<Dynamic>, run: RunPolicy) -> Result<Dynamic> {
+        let host = self.clone();
+        let script = script.to_string();
+        let function = function.to_string();
+        tokio::task::spawn_blocking(move || {
+            let script = host
+                .scripts()
+                .iter()
+                .find(|s| s.name == script)
+                .with_context(|| format!("No script named '{}'", script))?;
+            script.call(&function, args, run)
+        })
+        .await?
+    }
+}
+
+fn load_all(dir: &Path) -> Vec<Script> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                logging::warn(&format!("Failed to read scripts from {:?}: {}", dir, e));
+            }
+            return Vec::new();
+        }
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|path| match Script::load(path) {
+            Ok(script) => {
+                logging::info(&format!(
+                    "Loaded script '{}' with {} commands and {} transforms",
+                    script.name,
+                    script.commands.len(),
+                    script.transforms.len()
+                ));
+                Some(script)
+            }
+            Err(e) => {
+                logging::error(&format!("Failed to load script {:?}: {:#}", path, e));
+                None
+            }
+        })
+        .collect()
+}
+
+impl Script {
+    fn load(path: &Path) -> Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .context("Script has no name")?;
+        let registered = Arc::new(Mutex::new(Registered::default()));
+
+        let mut engine = engine(Instant::now() + DEFAULT_TIME_LIMIT, None);
+        let commands = registered.clone();
+        engine.register_fn("command", move |title: &str, description: &str, function: &str| {
+            commands.lock().unwrap().commands.push(ScriptCommand {
+                title: title.to_string(),
+                description: description.to_string(),
+                function: function.to_string(),
+            });
+        });
+        let transforms = registered.clone();
+        engine.register_fn("transform", move |title: &str, function: &str| {
+            transforms.lock().unwrap().transforms.push(ScriptCommand {
+                title: title.to_string(),
+                description: String::new(),
+                function: function.to_string(),
+            });
+        });
+        let limit = registered.clone();
+        engine.register_fn("time_limit", move |ms: i64| {
+            limit.lock().unwrap().time_limit = Some(Duration::from_millis(ms.max(0) as u64).min(MAX_TIME_LIMIT));
+        });
+
+        let ast = engine.compile_file(path.to_path_buf()).map_err(|e| anyhow::anyhow!("{}", e))?;
+        engine.run_ast(&ast).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let registered = std::mem::take(&mut *registered.lock().unwrap());
+        let script = Self {
+            name,
+            ast,
+            time_limit: registered.time_limit.unwrap_or(DEFAULT_TIME_LIMIT),
+            commands: registered.commands,
+            transforms: registered.transforms,
+        };
+
+        // Caught now rather than when the command is picked
+        for command in script.commands.iter().chain(&script.transforms) {
+            if !script.ast.iter_functions().any(|f| f.name == command.function) {
+                anyhow::bail!("'{}' registers '{}', which isn't a function in the script", command.title, command.function);
+            }
+        }
+        Ok(script)
+    }
+
+    fn call(&self, function: &str, args: impl FuncArgs, run: RunPolicy) -> Result<Dynamic> {
+        let engine = engine(Instant::now() + self.time_limit, Some(run));
+        // The top level only registers things, which was done when loading
+        let options = CallFnOptions::new().eval_ast(false);
+        engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, function, args)
+            .map_err(|e| anyhow::anyhow!("Script '{}' failed in {}: {}", self.name, function, e))
+    }
+}
+
+// An engine that stops whatever it's running once the deadline passes.
+// Without a policy, as while a script is loaded, `run` refuses everything
+fn engine(deadline: Instant, run: Option<RunPolicy>) -> Engine {
+    let mut engine = Engine::new();
+    engine.on_progress(move |_| (Instant::now() > deadline).then_some(Dynamic::UNIT));
+    engine.on_print(|text| logging::info(&format!("Script: {}", text)));
+    engine.register_fn("run", move |command: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+        shell(command, run.as_ref()).map_err(|e| e.to_string().into())
+    });
+    engine
+}
+
+fn shell(command: &str, run: Option<&RunPolicy>) -> Result<()> {
+    let run = run.with_context(|| format!("'{}' can't run while scripts are loading", command))?;
+    crate::check_policy(&run.security, command, run.confirmed)?;
+    desktop::shell_command(command, &run.exec)
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", command))?;
+    Ok(())
+}
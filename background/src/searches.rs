@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+// The search each session has running, so one typed after it can stop it.
+// The popup sends every search on a connection of its own, so an older
+// search that's still working doesn't hold up the newer ones; stopping it
+// frees its providers, the config lock and any files still being read.
+#[derive(Default)]
+pub struct Searches {
+    running: Mutex<HashMap<String, (u64, CancellationToken)>>,
+}
+
+impl Searches {
+    // Cancels whatever the session still has running from before this
+    // generation. None when the session has already sent a newer search,
+    // this one arriving late.
+    pub fn start(&self, session: &str, generation: u64) -> Option<Running<'_>> {
+        let mut running = self.running.lock().unwrap();
+        if let Some((current, token)) = running.get(session) {
+            if *current > generation {
+                return None;
+            }
+            token.cancel();
+        }
+        let token = CancellationToken::new();
+        running.insert(session.to_string(), (generation, token.clone()));
+        Some(Running {
+            searches: self,
+            session: session.to_string(),
+            generation,
+            token,
+        })
+    }
+}
+
+// A search that was started, forgotten again once it's done
+pub struct Running<'a> {
+    searches: &'a Searches,
+    session: String,
+    generation: u64,
+    pub token: CancellationToken,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let mut running = self.searches.running.lock().unwrap();
+        if running.get(&self.session).is_some_and(|(generation, _)| *generation == self.generation) {
+            running.remove(&self.session);
+        }
+    }
+}
@@ -1,12 +1,18 @@
 use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
 use std::fs;
+use std::io::Read;
 use std::path::PathBuf;
 use directories::ProjectDirs;
 use shared::{config::Config, logging};
 
-const BANGS_URL: &str = "https://gist.githubusercontent.com/GrishMahat/9500aa4a883650d21bc428abf1adb0d7/raw/723868e88db267fada918f8143e55cca36d10e97/bangs.json";
+// bangs.json as of the build, gzipped by build.rs
+const BANGS_SNAPSHOT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/bangs.json.gz"));
 
-pub async fn setup_config() -> Result<()> {
+// Returns whether bangs.json is only the bundled snapshot, left for the
+// service to replace with a download once it's up, so a first start doesn't
+// wait on the network
+pub async fn setup_config() -> Result<bool> {
     // Get the config directory
     let proj_dirs = ProjectDirs::from("", "", "orion")
         .context("Failed to get project directories")?;
@@ -35,6 +41,7 @@ pub async fn setup_config() -> Result<()> {
 
     // Handle bangs.json setup
     let bangs_path = config_dir.join("bangs.json");
+    let mut from_snapshot = false;
     if !bangs_path.exists() {
         logging::info("Setting up bangs.json file...");
         
@@ -46,13 +53,16 @@ pub async fn setup_config() -> Result<()> {
             logging::info(&format!("Copying local bangs.json to: {}", bangs_path.display()));
             fs::copy(&local_bangs_path, &bangs_path)?;
         } else {
-            // Download from remote if local doesn't exist
-            logging::info("Downloading bangs.json from remote source...");
-            match download_bangs(&bangs_path).await {
-                Ok(_) => logging::info(&format!("Downloaded bangs.json to: {}", bangs_path.display())),
+            // The bundled snapshot for now, and no bangs at all if even that
+            // can't be unpacked, until the download replaces it
+            from_snapshot = true;
+            match snapshot_bangs() {
+                Ok(content) => {
+                    fs::write(&bangs_path, content)?;
+                    logging::info(&format!("Wrote bundled bangs.json to: {}", bangs_path.display()));
+                }
                 Err(e) => {
-                    logging::error(&format!("Failed to download bangs.json: {}", e));
-                    // Create an empty bangs.json file as fallback
+                    logging::error(&format!("Failed to unpack bundled bangs: {:?}", e));
                     fs::write(&bangs_path, "[]")?;
                     logging::warn(&format!("Created empty bangs.json at: {}", bangs_path.display()));
                 }
@@ -63,18 +73,13 @@ pub async fn setup_config() -> Result<()> {
     logging::info("Configuration setup complete!");
     logging::info(&format!("Config directory: {}", config_dir.display()));
 
-    Ok(())
+    Ok(from_snapshot)
 }
 
-async fn download_bangs(path: &PathBuf) -> Result<()> {
-    // Download bangs.json
-    let response = reqwest::get(BANGS_URL).await?;
-    let content = response.text().await?;
-
-    // Save the file
-    fs::write(path, content)?;
-
-    logging::info(&format!("bangs.json downloaded and saved to: {}", path.display()));
-
-    Ok(())
+fn snapshot_bangs() -> Result<Vec<u8>> {
+    let mut content = Vec::new();
+    GzDecoder::new(BANGS_SNAPSHOT)
+        .read_to_end(&mut content)
+        .context("Failed to decompress bundled bangs")?;
+    Ok(content)
 }
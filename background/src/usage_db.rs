@@ -0,0 +1,198 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use shared::models::{Action, SearchResult};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Which result was run for which query, kept in SQLite in the data dir so
+// results picked before rank higher the next time a similar query is typed,
+// along with the directories opened from results, for jumping back to
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS clicks (
+        query TEXT NOT NULL,
+        target TEXT NOT NULL,
+        count INTEGER NOT NULL,
+        last_used INTEGER NOT NULL,
+        PRIMARY KEY (query, target)
+    );
+    CREATE TABLE IF NOT EXISTS dirs (
+        path TEXT PRIMARY KEY,
+        rank REAL NOT NULL,
+        last_used INTEGER NOT NULL
+    );
+";
+
+// A result's boost grows with the log of its clicks and stops here
+const MAX_BOOST: f32 = 0.3;
+// Once directory ranks add up past this they are all scaled down, and
+// those falling below 1 are forgotten, like zoxide's aging
+const MAX_DIR_RANK_TOTAL: f64 = 10_000.0;
+const DIR_AGING: f64 = 0.9;
+
+// A directory results were opened in, and how much it's used
+#[derive(Debug, Clone)]
+pub struct DirVisit {
+    pub path: PathBuf,
+    pub frecency: f64,
+}
+
+pub struct UsageDb {
+    conn: Mutex<Connection>,
+}
+
+impl UsageDb {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open usage database at {:?}", path))?;
+        Self::with_connection(conn)
+    }
+
+    // For when the database file can't be used, clicks then only count
+    // until the service stops
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    pub fn path() -> Result<PathBuf> {
+        let proj_dirs = directories::ProjectDirs::from("", "", "orion")
+            .context("Failed to get project directories")?;
+        Ok(proj_dirs.data_dir().join("usage.db"))
+    }
+
+    pub fn record_click(&self, query: &str, title: &str, action: &Action) -> Result<()> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Ok(());
+        }
+
+        self.lock().execute(
+            "INSERT INTO clicks (query, target, count, last_used) VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT(query, target) DO UPDATE SET count = count + 1, last_used = excluded.last_used",
+            params![query, target(title, action), chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    // Raises the scores of results clicked for this query before, or for a
+    // query that starts this one or that this one starts, so `rep` and
+    // `report` share what was picked
+    pub fn boost(&self, query: &str, results: &mut [SearchResult]) -> Result<()> {
+        let query = normalize(query);
+        if query.is_empty() || results.is_empty() {
+            return Ok(());
+        }
+
+        let clicks = self.clicks(&query)?;
+        for result in results {
+            if let Some(count) = clicks.get(&target(&result.title, &result.action)) {
+                result.score += ((1.0 + *count as f32).ln() * 0.1).min(MAX_BOOST);
+            }
+        }
+        Ok(())
+    }
+
+    // Counts a visit to the directory the action opens or opens something
+    // in, if any
+    pub fn record_visit(&self, action: &Action) -> Result<()> {
+        let Some(dir) = visited_dir(action) else {
+            return Ok(());
+        };
+
+        let conn = self.lock();
+        conn.execute(
+            "INSERT INTO dirs (path, rank, last_used) VALUES (?1, 1, ?2)
+             ON CONFLICT(path) DO UPDATE SET rank = rank + 1, last_used = excluded.last_used",
+            params![dir.to_string_lossy(), chrono::Utc::now().timestamp()],
+        )?;
+
+        let total: f64 = conn.query_row("SELECT COALESCE(SUM(rank), 0) FROM dirs", [], |row| row.get(0))?;
+        if total > MAX_DIR_RANK_TOTAL {
+            conn.execute("UPDATE dirs SET rank = rank * ?1", [DIR_AGING])?;
+            conn.execute("DELETE FROM dirs WHERE rank < 1", [])?;
+        }
+        Ok(())
+    }
+
+    // Visited directories, most frecent first: the visit count weighted by
+    // how long ago the last visit was
+    pub fn dirs(&self) -> Result<Vec<DirVisit>> {
+        let now = chrono::Utc::now().timestamp();
+        let conn = self.lock();
+        let mut stmt = conn.prepare_cached("SELECT path, rank, last_used FROM dirs")?;
+        let mut dirs = stmt
+            .query_map([], |row| {
+                let path: String = row.get(0)?;
+                let rank: f64 = row.get(1)?;
+                let last_used: i64 = row.get(2)?;
+                Ok(DirVisit {
+                    path: PathBuf::from(path),
+                    frecency: rank * recency_weight(now - last_used),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        dirs.sort_by(|a, b| b.frecency.total_cmp(&a.frecency));
+        Ok(dirs)
+    }
+
+    fn clicks(&self, query: &str) -> Result<HashMap<String, i64>> {
+        let conn = self.lock();
+        let mut stmt = conn.prepare_cached(
+            "SELECT target, SUM(count) FROM clicks
+             WHERE substr(query, 1, length(?1)) = ?1 OR substr(?1, 1, length(query)) = query
+             GROUP BY target",
+        )?;
+        let clicks = stmt
+            .query_map([query], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(clicks)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn normalize(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// Results are recognized by the URL or file they open, by title otherwise
+fn target(title: &str, action: &Action) -> String {
+    action.target().unwrap_or_else(|| format!("title:{}", title))
+}
+
+// Directories themselves, or the folder a file was opened or shown in
+fn visited_dir(action: &Action) -> Option<PathBuf> {
+    let path = match action {
+        Action::OpenFile(path)
+        | Action::RevealInFolder(path)
+        | Action::OpenWith { path, .. }
+        | Action::OpenInTerminal { path, .. } => path,
+        _ => return None,
+    };
+    if path.is_dir() {
+        return Some(path.clone());
+    }
+    // Not for files inside archives, whose parent isn't a real directory
+    path.parent().filter(|dir| dir.is_dir()).map(Path::to_path_buf)
+}
+
+// Seconds since the last visit to how much the visits count
+fn recency_weight(age: i64) -> f64 {
+    match age {
+        ..=3_600 => 4.0,
+        ..=86_400 => 2.0,
+        ..=604_800 => 0.5,
+        _ => 0.25,
+    }
+}
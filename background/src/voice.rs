@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use shared::config::{Config, VoiceConfig};
+use shared::logging;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::process::ProcessManager;
+
+// Searching by voice, opt-in through `[voice]`. Audio comes from a recorder
+// program writing 16 kHz mono 16-bit samples to stdout and is split into
+// utterances by loudness, `sensitivity` setting how loud counts as speech.
+// Each utterance is transcribed by whisper.cpp's command line program with
+// a local model, so nothing said leaves the machine. "Hey Orion, weather in
+// Paris" opens the popup with "weather in Paris" typed in; the wake word on
+// its own makes the next utterance the search. A profile's `speech` can
+// have the top result read back.
+
+const SAMPLE_RATE: usize = 16_000;
+// 30 ms
+const FRAME: usize = SAMPLE_RATE * 3 / 100;
+// Quiet frames that end an utterance, 750 ms
+const END_AFTER: usize = 25;
+// Loud frames an utterance needs, shorter ones being clicks and knocks
+const MIN_LOUD: usize = 5;
+// Longest utterance, cut off there even while still loud
+const MAX_SAMPLES: usize = SAMPLE_RATE * 10;
+// How long the wake word on its own waits for the search
+const AWAKE_FOR: Duration = Duration::from_secs(8);
+// How often `[voice]` is checked for changes while listening or not
+const CHECK_EVERY: Duration = Duration::from_secs(2);
+
+// Listens while `[voice]` is enabled, starting over whenever it changes.
+// A failure, like whisper.cpp or the model missing, is logged once and
+// tried again once the config changes.
+pub fn spawn(config: Arc<Mutex<Config>>, process_manager: Arc<ProcessManager>) {
+    tokio::spawn(async move {
+        let mut failed: Option<VoiceConfig> = None;
+        loop {
+            let voice = config.lock().await.voice.clone();
+            if !voice.enabled || failed.as_ref() == Some(&voice) {
+                tokio::time::sleep(CHECK_EVERY).await;
+                continue;
+            }
+            failed = None;
+
+            logging::info(&format!("Listening for \"{}\"", voice.wake_word));
+            match listen(&voice, &config, &process_manager).await {
+                Ok(()) => logging::info("Voice settings changed, listening again"),
+                Err(e) => {
+                    logging::error(&format!("Voice activation stopped: {:#}", e));
+                    failed = Some(voice);
+                }
+            }
+        }
+    });
+}
+
+// Returns once `[voice]` changes
+async fn listen(voice: &VoiceConfig, config: &Arc<Mutex<Config>>, process_manager: &Arc<ProcessManager>) -> Result<()> {
+    // Checked up front, rather than after the first thing said
+    voice.model.as_deref().context("voice.model has to name a whisper.cpp model")?;
+
+    let mut recorder = start_recorder(voice)?;
+    let stdout = recorder.stdout.take().context("The recorder has no stdout")?;
+    let (utterances, mut heard) = mpsc::channel(4);
+    let threshold = loudness_threshold(voice.sensitivity);
+    tokio::task::spawn_blocking(move || split_utterances(stdout, threshold, utterances));
+
+    let mut awake_until: Option<Instant> = None;
+    let mut check = tokio::time::interval(CHECK_EVERY);
+    let result = loop {
+        tokio::select! {
+            utterance = heard.recv() => {
+                let Some(samples) = utterance else {
+                    break Err(anyhow::anyhow!("The recorder stopped: {}", exit_status(&mut recorder)));
+                };
+                let transcribing = voice.clone();
+                let text = match tokio::task::spawn_blocking(move || transcribe(&transcribing, &samples)).await? {
+                    Ok(text) => text,
+                    Err(e) => break Err(e),
+                };
+                let words = normalize(&text);
+                if words.is_empty() {
+                    continue;
+                }
+                logging::debug(&format!("Heard \"{}\"", words));
+
+                let awake = awake_until.take().is_some_and(|until| Instant::now() < until);
+                let query = if awake {
+                    Some(words.as_str())
+                } else {
+                    words
+                        .strip_prefix(normalize(&voice.wake_word).as_str())
+                        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                        .map(str::trim)
+                };
+                match query {
+                    Some("") => {
+                        logging::info("Wake word heard, listening for a search");
+                        awake_until = Some(Instant::now() + AWAKE_FOR);
+                    }
+                    Some(query) => {
+                        // Read out as the profile in use says
+                        let speak = config.lock().await.get_current_profile().is_ok_and(|p| p.speech.after_voice);
+                        logging::info("Opening the popup for a voice search");
+                        if let Err(e) = process_manager.reopen_popup(query, speak).await {
+                            logging::error(&format!("Failed to open the popup for a voice search: {:?}", e));
+                        }
+                    }
+                    None => {}
+                }
+            }
+            _ = check.tick() => {
+                if config.lock().await.voice != *voice {
+                    break Ok(());
+                }
+            }
+        }
+    };
+
+    // Ends the reading thread too, its stdout closing
+    let _ = recorder.kill();
+    let _ = recorder.wait();
+    result
+}
+
+fn start_recorder(voice: &VoiceConfig) -> Result<Child> {
+    let default: &[&str] = if cfg!(target_os = "linux") {
+        &["arecord", "-q", "-f", "S16_LE", "-r", "16000", "-c", "1", "-t", "raw"]
+    } else {
+        &["sox", "-q", "-d", "-t", "raw", "-b", "16", "-e", "signed", "-c", "1", "-r", "16000", "-"]
+    };
+    let recorder: Vec<&str> = if voice.recorder.is_empty() {
+        default.to_vec()
+    } else {
+        voice.recorder.iter().map(String::as_str).collect()
+    };
+    let (program, args) = recorder.split_first().context("voice.recorder is empty")?;
+
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start {} to record from the microphone", program))
+}
+
+fn exit_status(recorder: &mut Child) -> String {
+    match recorder.wait() {
+        Ok(status) => status.to_string(),
+        Err(e) => e.to_string(),
+    }
+}
+
+// The quietest a frame's RMS can be and still count as speech: 3000 at
+// sensitivity 0, for a loud room, down to 200 at 1
+fn loudness_threshold(sensitivity: f32) -> f64 {
+    200.0 + (1.0 - sensitivity.clamp(0.0, 1.0) as f64) * 2800.0
+}
+
+// Reads until the recorder stops or nobody listens anymore, sending on runs
+// of loud frames together with the quiet that ends them
+fn split_utterances(mut audio: impl Read, threshold: f64, utterances: mpsc::Sender<Vec<i16>>) {
+    let mut bytes = vec![0u8; FRAME * 2];
+    let mut speech: Vec<i16> = Vec::new();
+    let mut loud_frames = 0;
+    let mut quiet_frames = 0;
+
+    while audio.read_exact(&mut bytes).is_ok() {
+        let frame: Vec<i16> = bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+        let loud = rms(&frame) >= threshold;
+        if speech.is_empty() && !loud {
+            continue;
+        }
+
+        speech.extend_from_slice(&frame);
+        if loud {
+            loud_frames += 1;
+            quiet_frames = 0;
+        } else {
+            quiet_frames += 1;
+        }
+
+        if quiet_frames >= END_AFTER || speech.len() >= MAX_SAMPLES {
+            let utterance = std::mem::take(&mut speech);
+            if loud_frames >= MIN_LOUD && utterances.blocking_send(utterance).is_err() {
+                return;
+            }
+            loud_frames = 0;
+            quiet_frames = 0;
+        }
+    }
+}
+
+fn rms(frame: &[i16]) -> f64 {
+    let sum: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / frame.len().max(1) as f64).sqrt()
+}
+
+// Goes through a WAV file, which whisper.cpp's program reads
+fn transcribe(voice: &VoiceConfig, samples: &[i16]) -> Result<String> {
+    let model = voice.model.as_deref().context("voice.model has to name a whisper.cpp model")?;
+    let path = std::env::temp_dir().join(format!("orion-voice-{}.wav", std::process::id()));
+    std::fs::write(&path, wav(samples)).with_context(|| format!("Failed to write {:?}", path))?;
+
+    let output = Command::new(&voice.whisper)
+        .args(["-m", model, "-nt", "-np", "-f"])
+        .arg(&path)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run {}", voice.whisper));
+    let _ = std::fs::remove_file(&path);
+
+    let output = output?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} exited with {}: {}",
+            voice.whisper,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// 16-bit mono PCM with the 44 byte header in front
+fn wav(samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, one channel
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE as u32).to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE as u32 * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+// Lowercase words without punctuation, dropping what whisper.cpp puts in
+// brackets for sounds that aren't speech, like `[BLANK_AUDIO]`
+fn normalize(text: &str) -> String {
+    let mut words = String::new();
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            _ if depth > 0 => {}
+            c if c.is_alphanumeric() => words.extend(c.to_lowercase()),
+            _ => words.push(' '),
+        }
+    }
+    words.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,47 @@
+use crate::bangs::BangStore;
+use crate::providers::ProviderRegistry;
+use shared::{config, logging};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+
+// Often enough that saved edits feel immediate, checking only costs a few
+// stat calls when nothing changed
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Watches config.toml and the bang files, so editing them by hand takes
+// effect without restarting the background
+pub fn spawn(
+    config_path: PathBuf,
+    config: Arc<Mutex<config::Config>>,
+    bang_store: Arc<BangStore>,
+    providers: Arc<ProviderRegistry>,
+) {
+    tokio::spawn(async move {
+        let mut config_modified = modified(&config_path);
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let modified = modified(&config_path);
+            if modified != config_modified {
+                config_modified = modified;
+                // A half-written or invalid file keeps the running config
+                if let Err(e) = crate::handle_config_update(&config_path, &config, &providers).await {
+                    logging::error(&format!("Ignoring changed config: {:?}", e));
+                }
+            }
+
+            if let Err(e) = bang_store.sync().await {
+                logging::error(&format!("Failed to reload changed bang files: {:?}", e));
+            }
+        }
+    });
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
@@ -23,6 +23,8 @@ mkdir -p dist/bin
 cp target/release/background dist/bin/
 cp target/release/popup_ui dist/bin/
 cp target/release/settings_app dist/bin/
+cp target/release/orion-ctl dist/bin/
+cp target/release/orion-native-host dist/bin/
 
 # Create a distribution archive
 echo -e "${GREEN}Creating distribution archive...${NC}"
@@ -0,0 +1,385 @@
+use anyhow::{Context, Result};
+use shared::config::Config;
+use shared::ipc::IpcClient;
+use shared::matcher;
+use shared::models::{self, AuditEntry, AuditOutcome, BangRefresh, Browser, IpcMessage, SearchQuery, SearchResult};
+use std::env;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+// Command line control of a running background service, for scripts and
+// for things the settings app does that are handy without it
+
+const USAGE: &str = "Usage: orion-ctl <command>
+
+Commands:
+  query [options] <text>         Search like the popup and list the results
+      --json                     Print the results as JSON
+      --limit <n>                Results to list, 10 unless given
+      --provider <name>          Search one provider only, e.g. files
+      --execute <n>              Run the nth result listed instead
+  dmenu [options]                Pick one of the lines on stdin in the popup
+                                 and print it, like dmenu or rofi -dmenu
+      -p <prompt>                Shown where the search is typed
+      --filter <text>            Print the lines matching <text>, best
+                                 first, without opening the popup
+  refresh-bangs                  Download the bang list again and load it
+  import-bangs --from <browser>  Add a browser's keyword search engines as
+                                 custom bangs, <browser> is firefox or chrome
+  clear-history                  Forget every executed search result
+  audit [options]                List the results run most recently, what
+                                 they did and whether it went through
+      --json                     Print the entries as JSON
+      --limit <n>                Entries to list, 20 unless given
+  log-level <level>              Change the service's log level until the
+                                 config is reloaded: off, error, warn,
+                                 info, debug or trace";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("query") => query(&args[1..]),
+        Some("dmenu") => return dmenu(&args[1..]),
+        Some("refresh-bangs") => refresh_bangs(),
+        Some("import-bangs") => import_bangs(&args[1..]),
+        Some("clear-history") => clear_history(),
+        Some("audit") => audit(&args[1..]),
+        Some("log-level") => set_log_level(&args[1..]),
+        Some("-h" | "--help" | "help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => Err(anyhow::anyhow!("Unknown command '{}'\n\n{}", other, USAGE)),
+        None => Err(anyhow::anyhow!("{}", USAGE)),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn connect() -> Result<IpcClient> {
+    let proj_dirs = directories::ProjectDirs::from("", "", "orion")
+        .context("Failed to get project directories")?;
+    let config = Config::load(&proj_dirs.config_dir().join("config.toml"))
+        .context("Failed to load config")?;
+
+    IpcClient::new(&config.ipc_socket_path).context("Is the background service running?")
+}
+
+// Sends a message to the background service and waits for its reply
+fn request(message: &IpcMessage) -> Result<IpcMessage> {
+    let mut client = connect()?;
+    client.send_message(message)?;
+    Ok(client.receive_message()?)
+}
+
+fn query(args: &[String]) -> Result<()> {
+    let mut json = false;
+    let mut limit = 10;
+    let mut provider = None;
+    let mut execute = None;
+    let mut words = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next().with_context(|| format!("{} needs a value\n\n{}", flag, USAGE))
+        };
+        match arg.as_str() {
+            "--json" => json = true,
+            "--limit" => limit = parse_count("--limit", value("--limit")?)?,
+            "--provider" => provider = Some(value("--provider")?.clone()),
+            "--execute" => execute = Some(parse_count("--execute", value("--execute")?)?),
+            // Lets a query start with `--`
+            "--" => {
+                words.extend(args.by_ref().cloned());
+                break;
+            }
+            _ => words.push(arg.clone()),
+        }
+    }
+
+    let text = words.join(" ");
+    if text.trim().is_empty() {
+        return Err(anyhow::anyhow!("Nothing to search for\n\n{}", USAGE));
+    }
+    let query = SearchQuery {
+        text: text.clone(),
+        // The result to run has to be among those listed
+        max_results: limit.max(execute.unwrap_or(0)).min(100),
+        provider_filter: provider,
+        profile: None,
+        session_id: None,
+        offset: 0,
+        request_id: Some(format!("ctl-{}", std::process::id())),
+        generation: None,
+    };
+
+    let response = match request(&IpcMessage::SearchQuery(query))? {
+        IpcMessage::SearchResponse(response) => response,
+        IpcMessage::Error(e) => return Err(anyhow::anyhow!(e)),
+        other => return Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    };
+    if !response.timed_out.is_empty() {
+        eprintln!("Timed out: {}", response.timed_out.join(", "));
+    }
+
+    if let Some(n) = execute {
+        let result = response
+            .results
+            .get(n - 1)
+            .with_context(|| format!("There are only {} results", response.results.len()))?;
+        return run(result, &text);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response.results)?);
+    } else {
+        print_results(&response.results);
+    }
+    Ok(())
+}
+
+// Exits with 1 when nothing was picked or matched, as dmenu does
+fn dmenu(args: &[String]) -> ExitCode {
+    match pick(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("{:#}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn pick(args: &[String]) -> Result<bool> {
+    let mut prompt = None;
+    let mut filter = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next().with_context(|| format!("{} needs a value\n\n{}", flag, USAGE))
+        };
+        match arg.as_str() {
+            "-p" => prompt = Some(value("-p")?.clone()),
+            "--filter" => filter = Some(value("--filter")?.clone()),
+            other => return Err(anyhow::anyhow!("Unknown option '{}'\n\n{}", other, USAGE)),
+        }
+    }
+
+    if let Some(text) = filter {
+        let items = io::stdin()
+            .lock()
+            .lines()
+            .collect::<io::Result<Vec<String>>>()
+            .context("Failed to read items from stdin")?;
+        let matches = matcher::rank(&text, &items);
+        for (idx, _) in &matches {
+            println!("{}", items[*idx]);
+        }
+        return Ok(!matches.is_empty());
+    }
+
+    // The popup reads stdin and prints the pick itself
+    let mut command = std::process::Command::new(popup_path());
+    command.arg("--dmenu");
+    if let Some(prompt) = prompt {
+        command.arg("-p").arg(prompt);
+    }
+    let status = command.status().context("Failed to start popup_ui")?;
+    Ok(status.success())
+}
+
+// The popup installed next to orion-ctl, or else the one in PATH
+fn popup_path() -> PathBuf {
+    let name = if cfg!(target_os = "windows") { "popup_ui.exe" } else { "popup_ui" };
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+// 1 or more
+fn parse_count(flag: &str, value: &str) -> Result<usize> {
+    value
+        .parse()
+        .ok()
+        .filter(|n| *n > 0)
+        .with_context(|| format!("{} takes a number from 1, not '{}'", flag, value))
+}
+
+// Runs the result as if picked in the popup, so it counts towards history
+// and ranking the same way
+fn run(result: &SearchResult, text: &str) -> Result<()> {
+    let command = models::Command::new(
+        result.title.clone(),
+        result.description.clone().unwrap_or_default(),
+        result.action.clone(),
+        Vec::new(),
+    )
+    .with_query(text.to_string());
+
+    // Commands aren't answered
+    connect()?.send_message(&IpcMessage::Command(command))?;
+    println!("Ran {}", result.title);
+    Ok(())
+}
+
+fn print_results(results: &[SearchResult]) {
+    if results.is_empty() {
+        println!("No results");
+        return;
+    }
+
+    const TITLE_WIDTH: usize = 50;
+    let title_width = results
+        .iter()
+        .map(|r| r.title.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(TITLE_WIDTH);
+
+    for (idx, result) in results.iter().enumerate() {
+        let title = truncate(&result.title, title_width);
+        let detail = result
+            .subtitle
+            .as_deref()
+            .or(result.description.as_deref())
+            .unwrap_or_default();
+        let category = result.category.as_deref().unwrap_or_default();
+        let line = format!("{:>3}  {:<width$}  {:<10}  {}", idx + 1, title, category, detail, width = title_width);
+        println!("{}", line.trim_end());
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+fn refresh_bangs() -> Result<()> {
+    match request(&IpcMessage::RefreshBangs)? {
+        IpcMessage::BangsRefreshed(refresh) => {
+            println!("Loaded {} bangs", refresh.count);
+            print_skipped(&refresh);
+            Ok(())
+        }
+        IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+        other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    }
+}
+
+fn import_bangs(args: &[String]) -> Result<()> {
+    let browser = match args {
+        [flag, browser] if flag == "--from" => Browser::parse(browser)
+            .with_context(|| format!("Unknown browser '{}', expected firefox or chrome", browser))?,
+        _ => return Err(anyhow::anyhow!("{}", USAGE)),
+    };
+
+    match request(&IpcMessage::ImportBangs(browser))? {
+        IpcMessage::BangsImported(imported) => {
+            println!("Imported {} search engines from {}", imported.count, browser);
+            print_skipped(&imported);
+            Ok(())
+        }
+        IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+        other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    }
+}
+
+fn clear_history() -> Result<()> {
+    match request(&IpcMessage::ClearHistory)? {
+        IpcMessage::HistoryCleared(count) => {
+            println!("Cleared {} history entries", count);
+            Ok(())
+        }
+        IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+        other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    }
+}
+
+fn audit(args: &[String]) -> Result<()> {
+    let mut json = false;
+    let mut limit = 20;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--limit" => {
+                let value = args.next().with_context(|| format!("--limit needs a value\n\n{}", USAGE))?;
+                limit = parse_count("--limit", value)?;
+            }
+            other => return Err(anyhow::anyhow!("Unknown option '{}'\n\n{}", other, USAGE)),
+        }
+    }
+
+    let entries = match request(&IpcMessage::ListAudit(limit))? {
+        IpcMessage::Audit(entries) => entries,
+        IpcMessage::Error(e) => return Err(anyhow::anyhow!(e)),
+        other => return Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else {
+        print_audit(&entries);
+    }
+    Ok(())
+}
+
+// Oldest at the top, like a log
+fn print_audit(entries: &[AuditEntry]) {
+    if entries.is_empty() {
+        println!("Nothing run yet");
+        return;
+    }
+
+    for entry in entries.iter().rev() {
+        let at = chrono::DateTime::from_timestamp(entry.at, 0)
+            .map(|at| at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let outcome = match &entry.outcome {
+            AuditOutcome::Succeeded => "ok".to_string(),
+            AuditOutcome::Failed(reason) => format!("failed: {}", reason),
+        };
+        let query = entry.query.as_deref().map(|q| format!(" for '{}'", q)).unwrap_or_default();
+        println!("{}  {:<12}  {}{}  {}", at, truncate(&entry.profile, 12), entry.title, query, outcome);
+    }
+}
+
+fn set_log_level(args: &[String]) -> Result<()> {
+    let [level] = args else {
+        return Err(anyhow::anyhow!("{}", USAGE));
+    };
+
+    match request(&IpcMessage::SetLogLevel(level.clone()))? {
+        IpcMessage::LogLevel(level) => {
+            println!("Logging at {}", level);
+            Ok(())
+        }
+        IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+        other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    }
+}
+
+fn print_skipped(summary: &BangRefresh) {
+    if !summary.errors.is_empty() {
+        eprintln!("Skipped {} entries:", summary.errors.len());
+        for error in &summary.errors {
+            eprintln!("  {}", error);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use shared::config::Config;
+use shared::ipc::IpcClient;
+use shared::models::{self, Action, IpcMessage, SearchQuery, SearchResult};
+use std::env;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+mod manifest;
+
+// Bridges a browser extension to the background service over the browser's
+// native messaging: the browser starts this program and talks to it through
+// stdin and stdout, each message being JSON behind its length as 4 bytes in
+// native byte order. Searching from the omnibox and sending the current tab
+// to Orion go through here.
+
+const USAGE: &str = "Usage: orion-native-host <command>
+
+Started by the browser with no command, or with the extension's origin,
+to pass messages between the extension and the background service.
+
+Commands:
+  install <browser> <extension-id>  Register the host with a browser so
+                                    the extension can start it; <browser>
+                                    is chrome, chromium, brave, edge or
+                                    firefox
+  uninstall <browser>               Remove the registration again";
+
+// Browsers refuse messages from the host over 1 MB; messages to it are
+// held to the same so a broken extension can't make it allocate gigabytes
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+// Suggestions the omnibox has room for
+const DEFAULT_LIMIT: usize = 6;
+
+// What the extension sends, e.g. `{"type": "search", "text": "rust"}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    // Answered with `Version`, for the extension to check the host works
+    Ping,
+    // Searches like the popup, answered with `Results`
+    Search {
+        text: String,
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    // Searches again and runs the result at `index` of those listed,
+    // answered with `Ran`
+    Run { text: String, index: usize },
+    // Opens the popup with the text typed in, answered with `Opened`
+    Open { text: String },
+    // Opens the popup with the tab's address typed in, so any result for
+    // it, like bangs or copying, is a key press away; answered with `Opened`
+    SendTab { url: String },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply {
+    Version { version: &'static str },
+    Results { results: Vec<Suggestion> },
+    Ran { title: String },
+    Opened,
+    Error { message: String },
+}
+
+// A result as the omnibox lists it
+#[derive(Debug, Serialize)]
+struct Suggestion {
+    index: usize,
+    title: String,
+    description: Option<String>,
+    // Set for results that open an address, which the extension can then
+    // open in the tab itself
+    url: Option<String>,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // Chrome passes the extension's origin and Firefox the manifest path
+    // and extension id, neither of which is needed here
+    let result = match args.first().map(String::as_str) {
+        Some("install") => install(&args[1..]),
+        Some("uninstall") => uninstall(&args[1..]),
+        Some("-h" | "--help" | "help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => serve(),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // The browser logs the host's stderr to its console
+            eprintln!("{:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn install(args: &[String]) -> Result<()> {
+    let [browser, extension_id] = args else {
+        return Err(anyhow::anyhow!("{}", USAGE));
+    };
+    let browser = parse_browser(browser)?;
+    let path = manifest::install(browser, extension_id)?;
+    println!("Registered with {}, manifest at {}", browser, path.display());
+    Ok(())
+}
+
+fn uninstall(args: &[String]) -> Result<()> {
+    let [browser] = args else {
+        return Err(anyhow::anyhow!("{}", USAGE));
+    };
+    let browser = parse_browser(browser)?;
+    manifest::uninstall(browser)?;
+    println!("Removed the registration with {}", browser);
+    Ok(())
+}
+
+fn parse_browser(name: &str) -> Result<manifest::Browser> {
+    manifest::Browser::parse(name).with_context(|| {
+        format!("Unknown browser '{}', expected chrome, chromium, brave, edge or firefox", name)
+    })
+}
+
+// Answers messages until the browser closes stdin, which it does once the
+// extension lets go of the connection
+fn serve() -> Result<()> {
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+
+    while let Some(message) = read_message(&mut stdin)? {
+        let reply = match serde_json::from_slice::<Request>(&message) {
+            Ok(request) => handle(request).unwrap_or_else(|e| Reply::Error { message: format!("{:#}", e) }),
+            Err(e) => Reply::Error { message: format!("Invalid message: {}", e) },
+        };
+        write_message(&mut stdout, &reply)?;
+    }
+    Ok(())
+}
+
+fn handle(request: Request) -> Result<Reply> {
+    match request {
+        Request::Ping => Ok(Reply::Version { version: env!("CARGO_PKG_VERSION") }),
+        Request::Search { text, limit } => {
+            let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, 100);
+            let results = search(&text, limit)?
+                .into_iter()
+                .enumerate()
+                .map(|(index, result)| Suggestion {
+                    index,
+                    url: match &result.action {
+                        Action::OpenUrl(url) => Some(url.clone()),
+                        _ => None,
+                    },
+                    description: result.subtitle.or(result.description),
+                    title: result.title,
+                })
+                .collect();
+            Ok(Reply::Results { results })
+        }
+        Request::Run { text, index } => {
+            let result = search(&text, index + 1)?
+                .into_iter()
+                .nth(index)
+                .with_context(|| format!("No result {} for '{}'", index, text))?;
+            run(&result, &text)?;
+            Ok(Reply::Ran { title: result.title })
+        }
+        Request::Open { text } => open_popup(text),
+        Request::SendTab { url } => open_popup(url),
+    }
+}
+
+fn connect() -> Result<IpcClient> {
+    let proj_dirs = directories::ProjectDirs::from("", "", "orion")
+        .context("Failed to get project directories")?;
+    let config = Config::load(&proj_dirs.config_dir().join("config.toml"))
+        .context("Failed to load config")?;
+
+    IpcClient::new(&config.ipc_socket_path).context("Is the background service running?")
+}
+
+// Sends a message to the background service and waits for its reply. Each
+// goes over a connection of its own, so a restarted service is picked up.
+fn request(message: &IpcMessage) -> Result<IpcMessage> {
+    let mut client = connect()?;
+    client.send_message(message)?;
+    Ok(client.receive_message()?)
+}
+
+fn search(text: &str, limit: usize) -> Result<Vec<SearchResult>> {
+    let query = SearchQuery {
+        text: text.to_string(),
+        max_results: limit,
+        provider_filter: None,
+        profile: None,
+        // Messages are answered one at a time, so there is never an older
+        // search of ours still running to cancel
+        session_id: None,
+        offset: 0,
+        request_id: Some(format!("browser-{}", std::process::id())),
+        generation: None,
+    };
+
+    match request(&IpcMessage::SearchQuery(query))? {
+        IpcMessage::SearchResponse(response) => Ok(response.results),
+        IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+        other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    }
+}
+
+// Runs the result as if picked in the popup, so it counts towards history
+// and ranking and goes through `[security]` the same way
+fn run(result: &SearchResult, text: &str) -> Result<()> {
+    let command = models::Command::new(
+        result.title.clone(),
+        result.description.clone().unwrap_or_default(),
+        result.action.clone(),
+        Vec::new(),
+    )
+    .with_query(text.to_string());
+
+    // Commands aren't answered
+    connect()?.send_message(&IpcMessage::Command(command))?;
+    Ok(())
+}
+
+fn open_popup(text: String) -> Result<Reply> {
+    match request(&IpcMessage::OpenPopup(text))? {
+        IpcMessage::PopupOpened => Ok(Reply::Opened),
+        IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+        other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    }
+}
+
+// None once stdin is closed
+fn read_message(input: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match input.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e).context("Failed to read from the browser"),
+    }
+    let len = u32::from_ne_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(anyhow::anyhow!("Message of {} bytes is over the {} byte limit", len, MAX_MESSAGE_SIZE));
+    }
+
+    let mut message = vec![0u8; len];
+    input.read_exact(&mut message).context("Failed to read from the browser")?;
+    Ok(Some(message))
+}
+
+fn write_message(output: &mut impl Write, reply: &Reply) -> Result<()> {
+    let mut message = serde_json::to_vec(reply)?;
+    if message.len() > MAX_MESSAGE_SIZE {
+        message = serde_json::to_vec(&Reply::Error { message: "Reply too large for the browser".to_string() })?;
+    }
+    output.write_all(&(message.len() as u32).to_ne_bytes())?;
+    output.write_all(&message)?;
+    output.flush().context("Failed to write to the browser")
+}
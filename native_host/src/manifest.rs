@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+// Registers the host with a browser, which only starts native hosts that a
+// manifest names and that the manifest lets the extension use. On Linux and
+// macOS the manifest goes where the browser looks for it; on Windows it can
+// be anywhere, with a registry key pointing at it.
+
+// The name the extension connects to, as in
+// `browser.runtime.connectNative("com.orion.native_host")`
+pub const HOST_NAME: &str = "com.orion.native_host";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Browser {
+    Chrome,
+    Chromium,
+    Brave,
+    Edge,
+    Firefox,
+}
+
+impl Browser {
+    pub const ALL: [Browser; 5] = [
+        Browser::Chrome,
+        Browser::Chromium,
+        Browser::Brave,
+        Browser::Edge,
+        Browser::Firefox,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Browser::Chrome => "chrome",
+            Browser::Chromium => "chromium",
+            Browser::Brave => "brave",
+            Browser::Edge => "edge",
+            Browser::Firefox => "firefox",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.as_str() == s.to_lowercase())
+    }
+
+    // Where the browser looks for host manifests, relative to the config
+    // dir on Linux and Application Support on macOS
+    #[cfg(not(windows))]
+    fn manifest_dir(self) -> Result<PathBuf> {
+        let dirs = directories::BaseDirs::new().context("Failed to get home directory")?;
+
+        #[cfg(target_os = "macos")]
+        let dir = {
+            let support = dirs.home_dir().join("Library/Application Support");
+            match self {
+                Browser::Chrome => support.join("Google/Chrome"),
+                Browser::Chromium => support.join("Chromium"),
+                Browser::Brave => support.join("BraveSoftware/Brave-Browser"),
+                Browser::Edge => support.join("Microsoft Edge"),
+                Browser::Firefox => support.join("Mozilla"),
+            }
+            .join("NativeMessagingHosts")
+        };
+
+        #[cfg(not(target_os = "macos"))]
+        let dir = match self {
+            Browser::Chrome => dirs.config_dir().join("google-chrome/NativeMessagingHosts"),
+            Browser::Chromium => dirs.config_dir().join("chromium/NativeMessagingHosts"),
+            Browser::Brave => dirs.config_dir().join("BraveSoftware/Brave-Browser/NativeMessagingHosts"),
+            Browser::Edge => dirs.config_dir().join("microsoft-edge/NativeMessagingHosts"),
+            Browser::Firefox => dirs.home_dir().join(".mozilla/native-messaging-hosts"),
+        };
+
+        Ok(dir)
+    }
+
+    // The key whose default value is the manifest's path
+    #[cfg(windows)]
+    fn registry_key(self) -> String {
+        let vendor = match self {
+            Browser::Chrome => r"Google\Chrome",
+            Browser::Chromium => "Chromium",
+            Browser::Brave => r"BraveSoftware\Brave-Browser",
+            Browser::Edge => r"Microsoft\Edge",
+            Browser::Firefox => "Mozilla",
+        };
+        format!(r"HKCU\Software\{}\NativeMessagingHosts\{}", vendor, HOST_NAME)
+    }
+}
+
+impl std::fmt::Display for Browser {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Browser::Chrome => write!(f, "Chrome"),
+            Browser::Chromium => write!(f, "Chromium"),
+            Browser::Brave => write!(f, "Brave"),
+            Browser::Edge => write!(f, "Edge"),
+            Browser::Firefox => write!(f, "Firefox"),
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn manifest_path(browser: Browser) -> Result<PathBuf> {
+    Ok(browser.manifest_dir()?.join(format!("{}.json", HOST_NAME)))
+}
+
+// Kept with Orion's own data, one per browser as their manifests differ
+#[cfg(windows)]
+fn manifest_path(browser: Browser) -> Result<PathBuf> {
+    let proj_dirs = directories::ProjectDirs::from("", "", "orion")
+        .context("Failed to get project directories")?;
+    Ok(proj_dirs
+        .data_dir()
+        .join("native-messaging")
+        .join(format!("{}.{}.json", HOST_NAME, browser.as_str())))
+}
+
+// Chrome and its relatives list the extensions allowed by origin, Firefox
+// by the id in the extension's own manifest
+fn contents(browser: Browser, extension_id: &str, exe: &str) -> serde_json::Value {
+    let mut manifest = serde_json::json!({
+        "name": HOST_NAME,
+        "description": "Orion launcher",
+        "path": exe,
+        "type": "stdio",
+    });
+    match browser {
+        Browser::Firefox => manifest["allowed_extensions"] = serde_json::json!([extension_id]),
+        _ => manifest["allowed_origins"] = serde_json::json!([format!("chrome-extension://{}/", extension_id)]),
+    }
+    manifest
+}
+
+// Writes the manifest for this executable, returning where it went
+pub fn install(browser: Browser, extension_id: &str) -> Result<PathBuf> {
+    let extension_id = extension_id.trim();
+    if extension_id.is_empty() || extension_id.contains(|c: char| c.is_whitespace() || c == '/') {
+        return Err(anyhow::anyhow!("'{}' is not an extension id", extension_id));
+    }
+
+    // Browsers want the full path, and start the host from their own
+    // working directory
+    let exe = std::env::current_exe().context("Failed to locate orion-native-host")?;
+    let path = manifest_path(browser)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let manifest = contents(browser, extension_id, &exe.to_string_lossy());
+    std::fs::write(&path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Failed to write {:?}", path))?;
+
+    #[cfg(windows)]
+    run_reg(&["add", &browser.registry_key(), "/ve", "/t", "REG_SZ", "/d", &path.to_string_lossy(), "/f"])?;
+
+    Ok(path)
+}
+
+pub fn uninstall(browser: Browser) -> Result<()> {
+    #[cfg(windows)]
+    {
+        // Already gone when there's nothing to delete
+        let _ = run_reg(&["delete", &browser.registry_key(), "/f"]);
+    }
+
+    let path = manifest_path(browser)?;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {:?}", path))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+fn run_reg(args: &[&str]) -> Result<()> {
+    let output = std::process::Command::new("reg")
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .context("Failed to run reg")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "reg exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use std::time::{Duration, Instant};
+
+// A value easing from one number to another over a fixed duration
+#[derive(Debug, Clone, Copy)]
+pub struct Animation {
+    from: f32,
+    to: f32,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Animation {
+    // An animation already settled at `value`
+    pub fn new(value: f32) -> Self {
+        Self {
+            from: value,
+            to: value,
+            start: Instant::now(),
+            duration: Duration::ZERO,
+        }
+    }
+
+    // Retargets the animation, continuing from wherever it currently is
+    pub fn animate_to(&mut self, to: f32, duration: Duration) {
+        let now = Instant::now();
+        self.from = self.value(now);
+        self.to = to;
+        self.start = now;
+        self.duration = duration;
+    }
+
+    pub fn target(&self) -> f32 {
+        self.to
+    }
+
+    pub fn value(&self, now: Instant) -> f32 {
+        let progress = self.progress(now);
+        let eased = 1.0 - (1.0 - progress).powi(3);
+        self.from + (self.to - self.from) * eased
+    }
+
+    pub fn is_running(&self, now: Instant) -> bool {
+        self.progress(now) < 1.0
+    }
+
+    fn progress(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+
+        let elapsed = now.saturating_duration_since(self.start);
+        (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+}
@@ -1,7 +1,10 @@
 use anyhow::{Result, Context};
-use shared::models::{Command, Action};
+use shared::desktop;
+use shared::file_control::FileControl;
+use shared::models::{Command, Action, ExecOptions};
 use std::process;
 use std::path::Path;
+use std::sync::mpsc;
 
 pub struct CommandExecutor;
 
@@ -13,13 +16,40 @@ impl CommandExecutor {
     pub fn execute(&self, command: &Command) -> Result<()> {
         match &command.action {
             Action::OpenFile(path) => self.open_file(path),
-            Action::ExecuteCommand(cmd) => self.execute_shell_command(cmd),
-            Action::OpenUrl(url) => self.open_url(url),
+            Action::ExecuteCommand(cmd) => self.execute_shell_command(cmd, &ExecOptions::default()),
+            Action::RunCommand { command, exec } => self.execute_shell_command(command, exec),
+            Action::OpenUrl(url) | Action::OpenBang { url, .. } => self.open_url(url),
             Action::Custom(custom) => {
                 // For now, just log that we received a custom command
                 println!("Custom command received: {}", custom);
                 Ok(())
             }
+            // Handled by the popup before anything is executed
+            Action::CompleteQuery(_) => Ok(()),
+            Action::CopyToClipboard(text) => desktop::copy_to_clipboard(text),
+            Action::PasteText(text) => desktop::paste_text(text),
+            Action::OpenWith { path, app } => desktop::open_with(path, app),
+            Action::RevealInFolder(path) => desktop::reveal_in_folder(path),
+            Action::MoveToTrash(path) => Ok(FileControl::new().trash_path(path)?),
+            Action::DeletePermanently(path) => Ok(FileControl::new().delete_path(path)?),
+            Action::ExtractFile { path, to } => {
+                FileControl::new().extract(path, to)?;
+                Ok(())
+            }
+            // Nothing here watches the progress, so it goes nowhere
+            Action::CopyFiles { sources, to } => Ok(FileControl::new().copy_many(sources, to, &mpsc::channel().0)?),
+            Action::MoveFiles { sources, to } => Ok(FileControl::new().move_many(sources, to, &mpsc::channel().0)?),
+            Action::OpenInTerminal { path, command } => self.open_in_terminal(path, command),
+            Action::EjectVolume(path) => desktop::eject(path),
+            Action::RunElevated(cmd) => desktop::run_elevated(cmd),
+            Action::LaunchApp(app) => desktop::launch_app(app),
+            Action::RunPlugin { plugin, .. } => {
+                anyhow::bail!("Plugin '{}' can only be run by the background service", plugin)
+            }
+            Action::RunScript { script, .. } => {
+                anyhow::bail!("Script '{}' can only be run by the background service", script)
+            }
+            Action::AddSiteBang(_) => anyhow::bail!("Bangs can only be added by the background service"),
         }
     }
 
@@ -53,25 +83,34 @@ impl CommandExecutor {
         Ok(())
     }
 
-    fn execute_shell_command(&self, command: &str) -> Result<()> {
+    fn execute_shell_command(&self, command: &str, exec: &ExecOptions) -> Result<()> {
+        desktop::shell_command(command, exec)
+            .spawn()
+            .with_context(|| format!("Failed to execute command: {}", command))?;
+        Ok(())
+    }
+
+    // Started in the directory, for terminals whose command doesn't take it
+    fn open_in_terminal(&self, path: &Path, command: &str) -> Result<()> {
         #[cfg(target_os = "windows")]
-        {
-            process::Command::new("cmd")
-                .arg("/C")
-                .arg(command)
-                .spawn()
-                .with_context(|| format!("Failed to execute command: {}", command))?;
-        }
+        let mut shell = {
+            let mut shell = process::Command::new("cmd");
+            shell.arg("/C");
+            shell
+        };
 
         #[cfg(not(target_os = "windows"))]
-        {
-            process::Command::new("sh")
-                .arg("-c")
-                .arg(command)
-                .spawn()
-                .with_context(|| format!("Failed to execute command: {}", command))?;
-        }
-
+        let mut shell = {
+            let mut shell = process::Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+
+        shell
+            .arg(command)
+            .current_dir(path)
+            .spawn()
+            .with_context(|| format!("Failed to open {:?} in a terminal", path))?;
         Ok(())
     }
 
@@ -0,0 +1,57 @@
+use anyhow::{Context, Result};
+use shared::matcher;
+use shared::models::{Action, SearchResult};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Most items listed at once; typing narrows the rest down
+const MAX_SHOWN: usize = 50;
+
+// Set once an item was printed, so closing without one can exit with 1 the
+// way dmenu does
+static PICKED: AtomicBool = AtomicBool::new(false);
+
+// `--dmenu`: lines read from stdin, matched in the popup instead of searched
+// for through the background, the one picked printed to stdout
+pub struct Dmenu {
+    items: Vec<String>,
+    pub prompt: Option<String>,
+}
+
+impl Dmenu {
+    pub fn from_stdin(prompt: Option<String>) -> Result<Self> {
+        let items = io::stdin()
+            .lock()
+            .lines()
+            .collect::<io::Result<Vec<String>>>()
+            .context("Failed to read items from stdin")?
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .collect();
+        Ok(Self { items, prompt })
+    }
+
+    // Items in their own order until something is typed
+    pub fn search(&self, text: &str) -> Vec<SearchResult> {
+        matcher::rank(text, &self.items)
+            .into_iter()
+            .take(MAX_SHOWN)
+            .map(|(idx, m)| {
+                let item = &self.items[idx];
+                SearchResult::new(item.clone(), None, Action::Custom(item.clone()), m.score)
+                    .with_highlights(m.ranges)
+            })
+            .collect()
+    }
+
+    pub fn pick(choice: &str) {
+        let mut stdout = io::stdout().lock();
+        if writeln!(stdout, "{}", choice).and_then(|_| stdout.flush()).is_ok() {
+            PICKED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn picked() -> bool {
+        PICKED.load(Ordering::Relaxed)
+    }
+}
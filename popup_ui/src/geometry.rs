@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use serde::{Serialize, Deserialize};
+use shared::config::AppearanceConfig;
+use std::path::PathBuf;
+use iced::{window, Point, Size};
+
+const DEFAULT_WIDTH: f32 = 700.0;
+const DEFAULT_HEIGHT: f32 = 420.0;
+
+// Last known popup size and position, remembered across launches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            x: None,
+            y: None,
+        }
+    }
+}
+
+impl WindowGeometry {
+    fn path() -> Result<PathBuf> {
+        let proj_dirs = directories::ProjectDirs::from("", "", "orion")
+            .context("Failed to get project directories")?;
+
+        Ok(proj_dirs.data_dir().join("popup_geometry.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to save popup geometry to {:?}", path))
+    }
+
+    // Builds the initial window settings, letting explicit appearance values
+    // override what was remembered
+    pub fn window_settings(&self, appearance: &AppearanceConfig) -> window::Settings {
+        let size = Size::new(
+            appearance.width.unwrap_or(self.width),
+            appearance.height.unwrap_or(self.height),
+        );
+
+        // With a configured y offset the window opens centered and is moved
+        // down once it is open, see `opened_position`
+        let position = match (self.x, self.y, appearance.y_offset) {
+            (Some(x), Some(y), None) => window::Position::Specific(Point::new(x as f32, y as f32)),
+            _ => window::Position::Centered,
+        };
+
+        window::Settings {
+            size,
+            position,
+            // A bare panel as Spotlight's is, the content reaching up under
+            // a title bar that's hidden
+            #[cfg(target_os = "macos")]
+            platform_specific: window::settings::PlatformSpecific {
+                title_hidden: true,
+                titlebar_transparent: true,
+                fullsize_content_view: true,
+            },
+            ..window::Settings::default()
+        }
+    }
+
+    // Where to move the freshly opened window to honor the configured y offset
+    pub fn opened_position(position: Option<Point>, appearance: &AppearanceConfig) -> Option<Point> {
+        match (position, appearance.y_offset) {
+            (Some(position), Some(y_offset)) => Some(Point::new(position.x, y_offset)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use iced::keyboard::{key, Key, Modifiers};
+use shared::config::PopupKeysConfig;
+use shared::hotkey::KeyCombo;
+use shared::logging;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Next,
+    Previous,
+    Execute,
+    ExecuteStayOpen,
+    Complete,
+    Actions,
+    Copy,
+    Close,
+    Speak,
+}
+
+// Popup key bindings resolved from the `[popup.keys]` config section
+pub struct KeyBindings {
+    bindings: Vec<(KeyCombo, KeyAction)>,
+}
+
+impl KeyBindings {
+    pub fn from_config(keys: &PopupKeysConfig) -> Self {
+        let mut bindings = Vec::new();
+
+        let groups = [
+            (&keys.next, KeyAction::Next),
+            (&keys.previous, KeyAction::Previous),
+            (&keys.execute, KeyAction::Execute),
+            (&keys.execute_stay_open, KeyAction::ExecuteStayOpen),
+            (&keys.complete, KeyAction::Complete),
+            (&keys.actions, KeyAction::Actions),
+            (&keys.copy, KeyAction::Copy),
+            (&keys.close, KeyAction::Close),
+            (&keys.speak, KeyAction::Speak),
+        ];
+
+        for (combos, action) in groups {
+            for combo in combos {
+                match KeyCombo::parse(combo) {
+                    Ok(combo) => bindings.push((combo, action)),
+                    Err(e) => logging::warn(&format!("Ignoring key binding: {}", e)),
+                }
+            }
+        }
+
+        Self { bindings }
+    }
+
+    // Every action bound to the key, in the order the config lists them, as
+    // one key can do different things depending on the selection
+    pub fn actions(&self, key: &Key, modifiers: Modifiers) -> Vec<KeyAction> {
+        let Some(key) = key_name(key) else {
+            return Vec::new();
+        };
+        let pressed = KeyCombo {
+            ctrl: modifiers.control(),
+            alt: modifiers.alt(),
+            shift: modifiers.shift(),
+            meta: modifiers.logo(),
+            key,
+        };
+
+        self.bindings
+            .iter()
+            .filter(|(combo, _)| *combo == pressed)
+            .map(|(_, action)| *action)
+            .collect()
+    }
+
+    // Footer label for the first key bound to an action
+    pub fn hint(&self, action: KeyAction) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(combo, _)| match combo.to_string().as_str() {
+                "Enter" => "↵".to_string(),
+                "Escape" => "Esc".to_string(),
+                "Up" => "↑".to_string(),
+                "Down" => "↓".to_string(),
+                other => other.to_string(),
+            })
+    }
+}
+
+// Whether a key press is text entry for the search input rather than a
+// shortcut: characters typed without Ctrl/Alt/Super (including those composed
+// from dead keys or committed by an input method) and the dead keys themselves
+pub fn is_text_entry(key: &Key, modifiers: Modifiers, text: Option<&str>) -> bool {
+    if let Key::Unidentified = key {
+        return true;
+    }
+
+    let has_command_modifier = modifiers.control() || modifiers.alt() || modifiers.logo();
+    let produces_text = text.map_or(false, |t| t.chars().any(|c| !c.is_control()));
+
+    produces_text && !has_command_modifier
+}
+
+// Maps an iced key onto the canonical names used by KeyCombo
+fn key_name(key: &Key) -> Option<String> {
+    match key {
+        Key::Character(c) => KeyCombo::canonical_key(c.as_str()),
+        Key::Named(named) => {
+            let name = match named {
+                key::Named::Space => "Space",
+                key::Named::Enter => "Enter",
+                key::Named::Escape => "Escape",
+                key::Named::Tab => "Tab",
+                key::Named::Backspace => "Backspace",
+                key::Named::Delete => "Delete",
+                key::Named::ArrowUp => "Up",
+                key::Named::ArrowDown => "Down",
+                key::Named::ArrowLeft => "Left",
+                key::Named::ArrowRight => "Right",
+                key::Named::Home => "Home",
+                key::Named::End => "End",
+                key::Named::PageUp => "PageUp",
+                key::Named::PageDown => "PageDown",
+                key::Named::F1 => "F1",
+                key::Named::F2 => "F2",
+                key::Named::F3 => "F3",
+                key::Named::F4 => "F4",
+                key::Named::F5 => "F5",
+                key::Named::F6 => "F6",
+                key::Named::F7 => "F7",
+                key::Named::F8 => "F8",
+                key::Named::F9 => "F9",
+                key::Named::F10 => "F10",
+                key::Named::F11 => "F11",
+                key::Named::F12 => "F12",
+                _ => return None,
+            };
+            Some(name.to_string())
+        }
+        Key::Unidentified => None,
+    }
+}
@@ -0,0 +1,125 @@
+use iced::{Command, Element, Subscription, Theme};
+use iced_layershell::actions::LayershellCustomActions;
+use iced_layershell::reexport::{Anchor, KeyboardInteractivity, Layer};
+use iced_layershell::settings::{LayerShellSettings, Settings};
+use shared::config::{AppearanceConfig, PopupSurface};
+
+use crate::dmenu::Dmenu;
+use crate::{AppMessage, OrionApp, OrionSettings};
+
+// The popup as a wlr layer-shell surface rather than an xdg-toplevel
+// window. Compositors draw it above everything, fullscreen windows
+// included, and hand it the keyboard the moment it maps, neither of which
+// a toplevel can ask for on Wayland. The compositor places it, so the
+// remembered position and the move on open are left out.
+
+// Compositors built on wlroots, and others known to have layer-shell, as
+// XDG_CURRENT_DESKTOP names them
+const LAYER_SHELL_DESKTOPS: &[&str] = &["sway", "hyprland", "river", "wayfire", "labwc", "niri", "dwl", "qtile"];
+// Set by compositors that don't always set XDG_CURRENT_DESKTOP
+const LAYER_SHELL_SOCKETS: &[&str] = &["SWAYSOCK", "HYPRLAND_INSTANCE_SIGNATURE", "WAYFIRE_SOCKET", "NIRI_SOCKET"];
+// Pixels between the top of the screen and the popup without a `y_offset`,
+// the surface having no centered position to fall back on
+const DEFAULT_TOP_MARGIN: i32 = 200;
+
+// Whether the popup should open as a layer surface, as `popup.surface`
+// says and the session allows
+pub fn wanted(surface: PopupSurface) -> bool {
+    if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return false;
+    }
+    match surface {
+        PopupSurface::Window => false,
+        PopupSurface::LayerShell => true,
+        PopupSurface::Auto => {
+            let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+            desktop.split(':').any(|name| LAYER_SHELL_DESKTOPS.contains(&name))
+                || LAYER_SHELL_SOCKETS.iter().any(|var| std::env::var_os(var).is_some())
+        }
+    }
+}
+
+pub fn run(settings: OrionSettings, font: iced::Font) -> anyhow::Result<()> {
+    let size = settings.geometry.window_settings(&settings.appearance).size;
+    let layer_settings = LayerShellSettings {
+        // Centered across the top, as the window opens; overlay rather
+        // than top, which is drawn under fullscreen windows
+        anchor: Anchor::Top,
+        layer: Layer::Overlay,
+        margin: (top_margin(&settings.appearance), 0, 0, 0),
+        // Other surfaces' exclusive zones, like a bar's, still push it down
+        exclusive_zone: 0,
+        keyboard_interactivity: KeyboardInteractivity::Exclusive,
+        size: Some((size.width as u32, size.height as u32)),
+        ..LayerShellSettings::default()
+    };
+
+    LayerPopup::run(Settings {
+        id: Some("orion".to_string()),
+        layer_settings,
+        flags: settings,
+        fonts: Vec::new(),
+        default_font: font,
+        default_text_size: iced::Pixels(16.0),
+        antialiasing: true,
+    })
+    .map_err(|e| anyhow::anyhow!("Failed to run the layer surface: {}", e))
+}
+
+// `y_offset` is from the top of the screen, as for the window
+fn top_margin(appearance: &AppearanceConfig) -> i32 {
+    appearance.y_offset.map_or(DEFAULT_TOP_MARGIN, |offset| offset.max(0.0) as i32)
+}
+
+// A layer surface has no window to close, so closing ends the process,
+// exiting as `main` would after the window closed
+pub fn exit(dmenu: bool) -> ! {
+    std::process::exit(if dmenu && !Dmenu::picked() { 1 } else { 0 })
+}
+
+struct LayerPopup(OrionApp);
+
+impl iced_layershell::Application for LayerPopup {
+    type Message = AppMessage;
+    type Flags = OrionSettings;
+    type Theme = Theme;
+    type Executor = iced::executor::Default;
+
+    fn new(flags: OrionSettings) -> (Self, Command<AppMessage>) {
+        let (app, command) = <OrionApp as iced::Application>::new(flags);
+        (Self(app), command)
+    }
+
+    fn namespace(&self) -> String {
+        "orion".to_string()
+    }
+
+    fn update(&mut self, message: AppMessage) -> Command<AppMessage> {
+        iced::Application::update(&mut self.0, message)
+    }
+
+    fn view(&self) -> Element<AppMessage, Theme> {
+        iced::Application::view(&self.0)
+    }
+
+    fn theme(&self) -> Theme {
+        iced::Application::theme(&self.0)
+    }
+
+    fn style(&self) -> iced::theme::Application {
+        iced::Application::style(&self.0)
+    }
+
+    fn subscription(&self) -> Subscription<AppMessage> {
+        iced::Application::subscription(&self.0)
+    }
+}
+
+// The popup never changes the surface's anchor, size or layer once open
+impl TryInto<LayershellCustomActions> for AppMessage {
+    type Error = Self;
+
+    fn try_into(self) -> Result<LayershellCustomActions, Self::Error> {
+        Err(self)
+    }
+}
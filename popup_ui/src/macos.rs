@@ -0,0 +1,41 @@
+use objc::runtime::{Object, YES};
+use objc::{class, msg_send, sel, sel_impl};
+
+// Makes the popup behave like Spotlight's panel rather than an app window:
+// no Dock icon or menu bar of its own, shown on whichever Space is in use,
+// full screen ones included, and in front of and focused over the app in
+// use, which gets focus back when the popup closes. winit owns the window's
+// class, so it stays an NSWindow dressed as a panel rather than an NSPanel.
+
+// NSApplicationActivationPolicyAccessory
+const ACTIVATION_POLICY_ACCESSORY: isize = 1;
+// NSWindowCollectionBehaviorCanJoinAllSpaces, Transient and
+// FullScreenAuxiliary
+const COLLECTION_BEHAVIOR: usize = (1 << 0) | (1 << 3) | (1 << 8);
+// NSPopUpMenuWindowLevel, above the menu bar and full screen windows
+const POPUP_LEVEL: isize = 101;
+
+// Called once the window is open, on the main thread where iced runs
+// `update`, as AppKit has to be
+pub fn present_as_panel() {
+    // SAFETY: plain AppKit messages to the shared application and its
+    // windows, sent from the main thread
+    unsafe {
+        let app: *mut Object = msg_send![class!(NSApplication), sharedApplication];
+        // winit sets the regular policy once launched, so this comes after
+        let _: () = msg_send![app, setActivationPolicy: ACTIVATION_POLICY_ACCESSORY];
+
+        let windows: *mut Object = msg_send![app, windows];
+        let count: usize = msg_send![windows, count];
+        for idx in 0..count {
+            let window: *mut Object = msg_send![windows, objectAtIndex: idx];
+            let _: () = msg_send![window, setCollectionBehavior: COLLECTION_BEHAVIOR];
+            let _: () = msg_send![window, setLevel: POPUP_LEVEL];
+            let _: () = msg_send![window, makeKeyAndOrderFront: std::ptr::null_mut::<Object>()];
+        }
+
+        // Started by the background service rather than from the Dock or
+        // Finder, the popup isn't made active on its own
+        let _: () = msg_send![app, activateIgnoringOtherApps: YES];
+    }
+}
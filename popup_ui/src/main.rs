@@ -1,19 +1,39 @@
 use anyhow::{Result, Context};
 use iced::{
     Application, Command, Element, executor, Theme, keyboard, event, window,
-    Event, Subscription, Settings,
+    Event, Subscription, Settings, Color, Length, Font,
 };
-use iced::keyboard::{Key, key};
+use iced::theme::Palette;
+use iced::keyboard::{Key, Modifiers};
+use iced::widget::{container, text_input};
 use shared::{ipc, models, logging};
 use std::env;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tracing::Instrument;
 use directories;
 
 mod ui;
+mod animation;
 mod commands;
+mod dmenu;
+mod geometry;
+mod keys;
+#[cfg(target_os = "linux")]
+mod layer;
+#[cfg(target_os = "macos")]
+mod macos;
 mod state;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
 
+use animation::Animation;
+use dmenu::Dmenu;
+use shared::config::SpeechConfig;
+use shared::theme::PopupTheme;
+use geometry::WindowGeometry;
+use keys::KeyAction;
 use state::AppState;
 
 fn main() -> Result<()> {
@@ -26,6 +46,22 @@ fn main() -> Result<()> {
 
     let log_path = config_dir.join("popup.log");
 
+    // `--dmenu [-p prompt]` picks from lines on stdin instead of searching;
+    // otherwise the first argument is the socket to use, and `--query text`
+    // opens the popup with the text typed in, as voice searches do. With
+    // `--speak` the top result of that search is read out loud.
+    let args: Vec<String> = env::args().skip(1).collect();
+    let query = args.iter().position(|arg| arg == "--query").and_then(|idx| args.get(idx + 1)).cloned();
+    let speak = args.iter().any(|arg| arg == "--speak");
+    let dmenu = match args.iter().position(|arg| arg == "--dmenu") {
+        Some(_) => {
+            logging::set_echo(false);
+            let prompt = args.iter().position(|arg| arg == "-p").and_then(|idx| args.get(idx + 1)).cloned();
+            Some(Dmenu::from_stdin(prompt)?)
+        }
+        None => None,
+    };
+
     logging::init(Some(log_path))?;
     logging::info("Popup UI starting...");
 
@@ -34,6 +70,10 @@ fn main() -> Result<()> {
     let config = match shared::config::Config::load(&config_path) {
         Ok(config) => {
             logging::info(&format!("Loaded config from {}", config_path.display()));
+            if let Err(e) = logging::set_level_named(&config.log_level) {
+                logging::warn(&format!("{:#}, keeping {}", e, logging::level()));
+            }
+            logging::set_rotation(&config.logging);
             config
         },
         Err(e) => {
@@ -43,9 +83,10 @@ fn main() -> Result<()> {
     };
 
     // Use socket path from config or from command line
-    let server_addr = env::args()
-        .nth(1)
-        .unwrap_or_else(|| config.ipc_socket_path.clone());
+    let server_addr = match &dmenu {
+        Some(_) => config.ipc_socket_path.clone(),
+        None => args.first().cloned().unwrap_or_else(|| config.ipc_socket_path.clone()),
+    };
 
     logging::info(&format!("Using IPC server at: {}", server_addr));
 
@@ -63,35 +104,315 @@ fn main() -> Result<()> {
     // Use a global variable to store the runtime
     let _rt_guard = rt.enter();
     
+    let geometry = WindowGeometry::load();
+    let popup_theme = PopupTheme::from_appearance(&config.appearance);
+    let mut window_settings = geometry.window_settings(&config.appearance);
+    window_settings.transparent = popup_theme.is_translucent();
+
+    // iced wants a 'static family name; the popup lives for one search, so
+    // leaking the configured name once is fine
+    let font = match &popup_theme.font {
+        Some(name) => Font::with_name(Box::leak(name.clone().into_boxed_str())),
+        None => Font::DEFAULT,
+    };
+
+    // Read out loud as the profile in use says
+    let speech = config.get_current_profile().map(|p| p.speech.clone()).unwrap_or_default();
+
+    #[cfg(target_os = "linux")]
+    let layer_shell = layer::wanted(config.popup.surface);
+    #[cfg(not(target_os = "linux"))]
+    let layer_shell = false;
+
+    // The compositor maps and places layer surfaces itself, so they open
+    // and close without the reveal animation
+    let mut appearance = config.appearance;
+    if layer_shell {
+        logging::info("Opening the popup as a layer surface");
+        appearance.reduce_motion = true;
+    }
+
     // Start the Iced application
-    let result = OrionApp::run(Settings::with_flags(OrionSettings {
+    let is_dmenu = dmenu.is_some();
+    let flags = OrionSettings {
         server_addr,
-        flags: (),
-    }));
-    
-    // Convert the result
-    result.map_err(|e| anyhow::anyhow!("Failed to run application: {}", e))
+        popup: config.popup,
+        security: config.security,
+        appearance,
+        popup_theme,
+        font,
+        geometry,
+        dmenu: dmenu.map(Arc::new),
+        query,
+        speech,
+        speak,
+        layer_shell,
+    };
+
+    #[cfg(target_os = "linux")]
+    if layer_shell {
+        return layer::run(flags, font);
+    }
+
+    let result = OrionApp::run(Settings {
+        window: window_settings,
+        default_font: font,
+        ..Settings::with_flags(flags)
+    });
+    let result = result.map_err(|e| anyhow::anyhow!("Failed to run application: {}", e));
+
+    // Closed without picking anything
+    if is_dmenu && !Dmenu::picked() {
+        std::process::exit(1);
+    }
+    result
 }
 
 struct OrionSettings {
     server_addr: String,
-    flags: (),
+    popup: shared::config::PopupConfig,
+    security: shared::config::SecurityConfig,
+    appearance: shared::config::AppearanceConfig,
+    popup_theme: PopupTheme,
+    font: Font,
+    geometry: WindowGeometry,
+    dmenu: Option<Arc<Dmenu>>,
+    // Typed in when the popup opens
+    query: Option<String>,
+    speech: SpeechConfig,
+    speak: bool,
+    // Shown as a wlr layer-shell surface instead of a window
+    layer_shell: bool,
 }
 
 struct OrionApp {
     state: AppState,
-    ipc_client: Arc<Mutex<ipc::IpcClient>>,
+    // Not connected in dmenu mode, which doesn't need the background
+    ipc_client: Option<Arc<Mutex<ipc::IpcClient>>>,
+    dmenu: Option<Arc<Dmenu>>,
+    appearance: shared::config::AppearanceConfig,
+    popup_theme: PopupTheme,
+    geometry: WindowGeometry,
+    close_on_focus_loss: bool,
+    stay_open: bool,
+    // Set after launching something without closing, so the launched
+    // window taking focus doesn't close the popup
+    launched: bool,
+    // Fades and insets the whole popup, 0.0 hidden to 1.0 shown
+    reveal: Animation,
+    list_height: Animation,
+    now: Instant,
+    closing: bool,
+    speech: SpeechConfig,
+    // Reads out the results of the first search, for searches by voice
+    speak_results: bool,
+    layer_shell: bool,
+}
+
+const REVEAL_DURATION: Duration = Duration::from_millis(150);
+const HIDE_DURATION: Duration = Duration::from_millis(100);
+const LIST_DURATION: Duration = Duration::from_millis(150);
+// How far the content is pulled in while the popup is hidden
+const REVEAL_INSET: f32 = 12.0;
+const OPERATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Background, corner radius and opacity of the popup, faded by the reveal
+// animation
+struct FrameStyle {
+    radius: f32,
+    opacity: f32,
+    reveal: f32,
+}
+
+impl container::StyleSheet for FrameStyle {
+    type Style = Theme;
+
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
+        let palette = style.palette();
+        container::Appearance {
+            background: Some(Color { a: self.opacity * self.reveal, ..palette.background }.into()),
+            text_color: Some(Color { a: palette.text.a * self.reveal, ..palette.text }),
+            border: iced::Border {
+                radius: self.radius.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+// Leaves the window itself transparent so the frame's corners and opacity show
+struct TransparentWindow;
+
+impl iced::application::StyleSheet for TransparentWindow {
+    type Style = Theme;
+
+    fn appearance(&self, style: &Self::Style) -> iced::application::Appearance {
+        iced::application::Appearance {
+            background_color: Color::TRANSPARENT,
+            text_color: style.palette().text,
+        }
+    }
+}
+
+impl OrionApp {
+    fn close(&self) -> Command<AppMessage> {
+        if self.layer_shell {
+            #[cfg(target_os = "linux")]
+            layer::exit(self.dmenu.is_some());
+        }
+
+        if let Err(e) = self.geometry.save() {
+            logging::warn(&format!("Failed to save popup geometry: {}", e));
+        }
+
+        window::close(window::Id::MAIN)
+    }
+
+    // Plays the hide animation before closing, unless motion is reduced
+    fn hide(&mut self) -> Command<AppMessage> {
+        if self.appearance.reduce_motion {
+            return self.close();
+        }
+
+        self.closing = true;
+        self.reveal.animate_to(0.0, HIDE_DURATION);
+        Command::none()
+    }
+
+    // Reads out the selected result, or the first when none is selected
+    fn speak(&self) {
+        if let Err(e) = shared::speech::speak(&self.state.spoken_result(), &self.speech) {
+            logging::warn(&format!("Failed to read out the result: {:#}", e));
+        }
+    }
+
+    fn is_animating(&self) -> bool {
+        self.reveal.is_running(self.now)
+            || self.list_height.is_running(self.now)
+            || self.list_height.target() != self.state.results_height()
+    }
+
+    // Names of the registered providers, so mode chips for ones that aren't,
+    // like `apps` off Windows, stay plain text
+    fn list_providers(&self) -> Command<AppMessage> {
+        let Some(ipc_client) = self.ipc_client.clone() else {
+            return Command::none();
+        };
+        Command::perform(
+            async move {
+                let mut client = ipc_client.lock().await;
+                client.send_message_async(&models::IpcMessage::ListProviders).await?;
+                Ok::<_, anyhow::Error>(client.receive_message_async().await?)
+            },
+            |result| match result {
+                Ok(models::IpcMessage::Providers(providers)) => {
+                    AppMessage::ProvidersListed(Some(providers.into_iter().map(|info| info.name).collect()))
+                }
+                Ok(other) => {
+                    logging::error(&format!("Unexpected reply to ListProviders: {:?}", other));
+                    AppMessage::ProvidersListed(None)
+                }
+                Err(e) => {
+                    logging::error(&format!("Failed to list providers: {}", e));
+                    AppMessage::ProvidersListed(None)
+                }
+            }
+        )
+    }
+
+    // Sends the current input to the background, either as a bang lookup
+    // or as a regular search
+    fn search(&self) -> Command<AppMessage> {
+        if let Some(dmenu) = &self.dmenu {
+            let Some(query) = self.state.get_search_query() else {
+                return Command::none();
+            };
+            let results = dmenu.search(&query.text);
+            return Command::perform(async move { results }, AppMessage::SearchCompleted);
+        }
+        let Some(ipc_client) = self.ipc_client.clone() else {
+            return Command::none();
+        };
+
+        if let Some(prefix) = self.state.get_bang_query() {
+            return Command::perform(
+                async move {
+                    let mut client = ipc_client.lock().await;
+                    let message = models::IpcMessage::BangQuery(prefix);
+                    client.send_message_async(&message).await?;
+
+                    let response = client.receive_message_async().await?;
+                    Ok::<_, anyhow::Error>(response)
+                },
+                |result| match result {
+                    Ok(models::IpcMessage::BangSuggestions(suggestions)) => {
+                        AppMessage::BangSuggestions(suggestions)
+                    }
+                    Ok(msg) => AppMessage::IpcMessage(msg),
+                    Err(e) => {
+                        logging::error(&format!("IPC error: {}", e));
+                        AppMessage::BangSuggestions(vec![])
+                    }
+                }
+            );
+        }
+
+        if let Some(query) = self.state.get_search_query() {
+            let span = tracing::info_span!("ipc", request_id = query.request_id.as_deref().unwrap_or_default());
+            return Command::perform(
+                async move {
+                    // Each search gets a connection of its own, so a slow
+                    // one doesn't hold up the next and can be cancelled by it
+                    let mut client = ipc_client.lock().await.connect_again()?;
+                    let message = models::IpcMessage::SearchQuery(query);
+                    client.send_message_async(&message).await?;
+
+                    // Wait for response
+                    let response = client.receive_message_async().await?;
+                    Ok::<_, anyhow::Error>(response)
+                }
+                .instrument(span),
+                |result| match result {
+                    // Replaced by a search typed since, whose results follow
+                    Ok(models::IpcMessage::SearchResponse(response)) if response.cancelled => {
+                        AppMessage::SearchCancelled
+                    }
+                    Ok(models::IpcMessage::SearchResponse(response)) => {
+                        AppMessage::SearchCompleted(response.results)
+                    }
+                    Ok(msg) => AppMessage::IpcMessage(msg),
+                    Err(e) => {
+                        logging::error(&format!("IPC error: {}", e));
+                        AppMessage::SearchCompleted(vec![])
+                    }
+                }
+            );
+        }
+
+        Command::none()
+    }
 }
 
 #[derive(Debug, Clone)]
 enum AppMessage {
     UiMessage(ui::Message),
-    KeyPressed(Key),
+    KeyPressed(Key, Modifiers),
     WindowEvent(window::Event),
     SearchCompleted(Vec<models::SearchResult>),
-    ExecuteCommand(models::Command),
+    SearchCancelled,
+    BangSuggestions(Vec<models::Bang>),
+    // The command to run and whether the popup closes once it was sent
+    ExecuteCommand(models::Command, bool),
+    CommandSent,
     CloseRequested,
     IpcMessage(models::IpcMessage),
+    Tick(Instant),
+    // Asks the background how copies and moves are getting on
+    PollOperations,
+    OperationsUpdated(Vec<models::OperationProgress>),
+    // None when they couldn't be listed, leaving every mode available
+    ProvidersListed(Option<Vec<String>>),
 }
 
 impl Application for OrionApp {
@@ -101,14 +422,19 @@ impl Application for OrionApp {
     type Flags = OrionSettings;
 
     fn new(settings: Self::Flags) -> (Self, Command<Self::Message>) {
-        let ipc_client = match ipc::IpcClient::new(&settings.server_addr) {
-            Ok(client) => Arc::new(Mutex::new(client)),
-            Err(e) => {
+        let connected = match &settings.dmenu {
+            Some(_) => None,
+            None => Some(ipc::IpcClient::new(&settings.server_addr)),
+        };
+        let ipc_client = match connected {
+            None => None,
+            Some(Ok(client)) => Some(Arc::new(Mutex::new(client))),
+            Some(Err(e)) => {
                 logging::error(&format!("Failed to connect to IPC server: {}. Attempting to use default socket path...", e));
 
                 // Try default path as fallback
                 match ipc::IpcClient::connect_to_default() {
-                    Ok(client) => Arc::new(Mutex::new(client)),
+                    Ok(client) => Some(Arc::new(Mutex::new(client))),
                     Err(e) => {
                         logging::error(&format!("Failed to connect to default IPC server: {}", e));
                         std::process::exit(1);
@@ -117,29 +443,52 @@ impl Application for OrionApp {
             }
         };
 
-        let app = Self {
-            state: AppState::new(),
+        let close_on_focus_loss = settings.popup.close_on_focus_loss;
+        let stay_open = settings.popup.stay_open;
+        let mut state = AppState::new(settings.popup, settings.security, &settings.popup_theme, settings.font);
+        if let Some(dmenu) = &settings.dmenu {
+            state.set_dmenu(dmenu.prompt.clone());
+        }
+        if let Some(query) = settings.query.clone() {
+            state.update_search_ui(ui::Message::SearchInputChanged(query));
+        }
+
+        let mut reveal = Animation::new(1.0);
+        if !settings.appearance.reduce_motion {
+            reveal = Animation::new(0.0);
+            reveal.animate_to(1.0, REVEAL_DURATION);
+        }
+
+        let mut app = Self {
+            list_height: Animation::new(state.results_height()),
+            state,
             ipc_client,
+            dmenu: settings.dmenu,
+            appearance: settings.appearance,
+            popup_theme: settings.popup_theme,
+            geometry: settings.geometry,
+            close_on_focus_loss,
+            stay_open,
+            launched: false,
+            reveal,
+            now: Instant::now(),
+            closing: false,
+            speech: settings.speech,
+            speak_results: settings.speak && settings.query.is_some(),
+            layer_shell: settings.layer_shell,
         };
 
         // Send initial query to get default results
-        let cmd = Command::perform(
-            async move { models::SearchQuery { text: String::new(), max_results: 10 } },
-            |query| {
-                AppMessage::ExecuteCommand(models::Command::new(
-                    "Initial Query".to_string(),
-                    "".to_string(),
-                    models::Action::Custom(query.text),
-                    vec![]
-                ))
-            }
-        );
+        app.state.queue_search();
+        let cmd = Command::batch([app.search(), app.list_providers()]);
 
         (app, cmd)
     }
 
+    // iced has no accessibility tree yet, so the window title carries the
+    // selected result for screen readers that announce title changes
     fn title(&self) -> String {
-        "Orion".to_string()
+        format!("Orion - {}", self.state.accessible_summary())
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
@@ -147,73 +496,135 @@ impl Application for OrionApp {
             AppMessage::UiMessage(ui_msg) => {
                 match ui_msg {
                     ui::Message::CloseRequested => {
-                        // Close directly without async operations
-                        return window::close(window::Id::MAIN);
+                        return self.hide();
+                    }
+                    ui::Message::BangSelected(idx) => {
+                        if self.state.complete_bang(idx) {
+                            return Command::batch(vec![
+                                text_input::focus(ui::search_input_id()),
+                                text_input::move_cursor_to_end(ui::search_input_id()),
+                            ]);
+                        }
+                    }
+                    ui::Message::ClearMode => {
+                        self.state.update_search_ui(ui_msg);
+                        return Command::batch(vec![
+                            text_input::focus(ui::search_input_id()),
+                            self.search(),
+                        ]);
                     }
                     _ => {
-                        let should_search = self.state.update_search_ui(ui_msg);
-
-                        if should_search {
-                            if let Some(query) = self.state.get_search_query() {
-                                let ipc_client = self.ipc_client.clone();
-                                return Command::perform(
-                                    async move {
-                                        let mut client = ipc_client.lock().await;
-                                        let message = models::IpcMessage::SearchQuery(query);
-                                        client.send_message_async(&message).await?;
-
-                                        // Wait for response
-                                        let response = client.receive_message_async().await?;
-                                        Ok::<_, anyhow::Error>(response)
-                                    },
-                                    |result| match result {
-                                        Ok(models::IpcMessage::SearchResponse(response)) => {
-                                            AppMessage::SearchCompleted(response.results)
-                                        }
-                                        Ok(msg) => AppMessage::IpcMessage(msg),
-                                        Err(e) => {
-                                            logging::error(&format!("IPC error: {}", e));
-                                            AppMessage::SearchCompleted(vec![])
-                                        }
-                                    }
-                                );
-                            }
+                        if self.state.update_search_ui(ui_msg) {
+                            return self.search();
                         }
                     }
                 }
 
                 Command::none()
             }
-            AppMessage::KeyPressed(key) => {
-                match key {
-                    Key::Named(key::Named::Escape) => {
-                        return Command::perform(async {}, |_| AppMessage::CloseRequested);
+            AppMessage::KeyPressed(key, modifiers) => {
+                match self.state.key_action(&key, modifiers) {
+                    Some(KeyAction::Close) => {
+                        if self.state.handle_escape() {
+                            return Command::perform(async {}, |_| AppMessage::CloseRequested);
+                        }
+
+                        // The text input drops focus on Escape, so take it back
+                        return text_input::focus(ui::search_input_id());
                     }
-                    Key::Named(key::Named::ArrowUp) |
-                    Key::Named(key::Named::ArrowDown) |
-                    Key::Named(key::Named::Enter) => {
-                        if let Some(cmd) = self.state.handle_keypress(key) {
-                            return Command::perform(async { cmd }, AppMessage::ExecuteCommand);
+                    Some(KeyAction::Complete) => {
+                        if self.state.complete_bang(0) {
+                            return text_input::move_cursor_to_end(ui::search_input_id());
+                        }
+                        // A completed directory lists its own entries
+                        if self.state.complete_with_selected() {
+                            return Command::batch(vec![
+                                text_input::move_cursor_to_end(ui::search_input_id()),
+                                self.search(),
+                            ]);
                         }
                     }
-                    _ => {}
+                    Some(KeyAction::Execute | KeyAction::ExecuteStayOpen) if self.dmenu.is_some() => {
+                        if let Some(choice) = self.state.dmenu_choice() {
+                            Dmenu::pick(&choice);
+                            return self.close();
+                        }
+                    }
+                    Some(KeyAction::Execute | KeyAction::ExecuteStayOpen) if self.state.complete_selected() => {
+                        return text_input::move_cursor_to_end(ui::search_input_id());
+                    }
+                    Some(KeyAction::Speak) => self.speak(),
+                    Some(action) => {
+                        if let Some(cmd) = self.state.handle_key_action(action) {
+                            let close = action == KeyAction::Execute && !self.stay_open;
+                            return Command::perform(async { cmd }, move |cmd| AppMessage::ExecuteCommand(cmd, close));
+                        }
+                    }
+                    None => {}
                 }
 
                 Command::none()
             }
             AppMessage::WindowEvent(event) => {
-                if let window::Event::CloseRequested = event {
-                    return Command::perform(async {}, |_| AppMessage::CloseRequested);
+                match event {
+                    window::Event::CloseRequested => {
+                        return Command::perform(async {}, |_| AppMessage::CloseRequested);
+                    }
+                    window::Event::Focused => {
+                        self.launched = false;
+                    }
+                    window::Event::Unfocused if self.close_on_focus_loss && !self.launched => {
+                        logging::info("Popup lost focus, closing");
+                        return Command::perform(async {}, |_| AppMessage::CloseRequested);
+                    }
+                    window::Event::Opened { position, size } => {
+                        self.geometry.width = size.width;
+                        self.geometry.height = size.height;
+
+                        #[cfg(all(unix, not(target_os = "macos")))]
+                        if !self.layer_shell && x11::is_session() {
+                            x11::focus_popup();
+                        }
+                        #[cfg(target_os = "macos")]
+                        macos::present_as_panel();
+
+                        if let Some(target) = WindowGeometry::opened_position(position, &self.appearance) {
+                            return window::move_to(window::Id::MAIN, target);
+                        }
+                    }
+                    window::Event::Moved { x, y } => {
+                        self.geometry.x = Some(x);
+                        self.geometry.y = Some(y);
+                    }
+                    window::Event::Resized { width, height } => {
+                        self.geometry.width = width as f32;
+                        self.geometry.height = height as f32;
+                    }
+                    _ => {}
                 }
 
                 Command::none()
             }
             AppMessage::SearchCompleted(results) => {
                 self.state.process_search_results(results);
+                if std::mem::take(&mut self.speak_results) {
+                    self.speak();
+                }
+                Command::none()
+            }
+            AppMessage::SearchCancelled => Command::none(),
+            AppMessage::BangSuggestions(suggestions) => {
+                self.state.process_bang_suggestions(suggestions);
                 Command::none()
             }
-            AppMessage::ExecuteCommand(cmd) => {
-                let ipc_client = self.ipc_client.clone();
+            AppMessage::ExecuteCommand(cmd, close) => {
+                let Some(ipc_client) = self.ipc_client.clone() else {
+                    return Command::none();
+                };
+                // Copies and moves keep the popup open to show their progress
+                let watch = cmd.action.is_file_operation();
+                let close = close && !watch;
+                self.launched = !close;
 
                 Command::perform(
                     async move {
@@ -224,42 +635,159 @@ impl Application for OrionApp {
                         // Don't wait for response for commands
                         Ok::<_, anyhow::Error>(())
                     },
-                    |result| {
+                    move |result| {
                         if let Err(e) = result {
                             logging::error(&format!("Error executing command: {}", e));
                         }
-                        AppMessage::CloseRequested
+                        if close {
+                            AppMessage::CloseRequested
+                        } else if watch {
+                            AppMessage::PollOperations
+                        } else {
+                            AppMessage::CommandSent
+                        }
+                    }
+                )
+            }
+            AppMessage::CommandSent => text_input::focus(ui::search_input_id()),
+            AppMessage::PollOperations => {
+                let Some(ipc_client) = self.ipc_client.clone() else {
+                    return Command::none();
+                };
+                Command::perform(
+                    async move {
+                        let mut client = ipc_client.lock().await;
+                        client.send_message_async(&models::IpcMessage::ListOperations).await?;
+                        Ok::<_, anyhow::Error>(client.receive_message_async().await?)
+                    },
+                    |result| match result {
+                        Ok(models::IpcMessage::OperationProgress(operations)) => AppMessage::OperationsUpdated(operations),
+                        Ok(other) => {
+                            logging::error(&format!("Unexpected reply to ListOperations: {:?}", other));
+                            AppMessage::OperationsUpdated(Vec::new())
+                        }
+                        Err(e) => {
+                            logging::error(&format!("Failed to poll operations: {}", e));
+                            AppMessage::OperationsUpdated(Vec::new())
+                        }
                     }
                 )
             }
+            AppMessage::OperationsUpdated(operations) => {
+                self.state.set_operations(operations);
+                if self.state.has_running_operations() {
+                    return Command::perform(delay(OPERATION_POLL_INTERVAL), |_| AppMessage::PollOperations);
+                }
+                Command::none()
+            }
+            AppMessage::ProvidersListed(providers) => {
+                if let Some(providers) = providers {
+                    self.state.set_providers(providers);
+                }
+                Command::none()
+            }
             AppMessage::CloseRequested => {
                 logging::info("Close requested, exiting...");
-                
-                // Close window directly, no async operations needed
-                window::close(window::Id::MAIN)
+                self.hide()
             }
             AppMessage::IpcMessage(msg) => {
                 self.state.process_ipc_message(msg);
                 Command::none()
             }
+            AppMessage::Tick(now) => {
+                self.now = now;
+
+                let target = self.state.results_height();
+                if self.list_height.target() != target {
+                    self.list_height.animate_to(target, LIST_DURATION);
+                }
+
+                if self.closing && !self.reveal.is_running(now) {
+                    return self.close();
+                }
+
+                Command::none()
+            }
         }
     }
 
     fn view(&self) -> Element<Self::Message, Theme> {
-        self.state.view().map(AppMessage::UiMessage)
+        let (reveal, results_height) = if self.appearance.reduce_motion {
+            (1.0, None)
+        } else {
+            (self.reveal.value(self.now), Some(self.list_height.value(self.now)))
+        };
+
+        let content = self.state.view(results_height).map(AppMessage::UiMessage);
+        let frame = container(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(iced::theme::Container::Custom(Box::new(FrameStyle {
+                radius: self.popup_theme.corner_radius,
+                opacity: self.popup_theme.opacity,
+                reveal,
+            })));
+
+        container(frame)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding((1.0 - reveal) * REVEAL_INSET)
+            .into()
+    }
+
+    fn theme(&self) -> Theme {
+        let theme = &self.popup_theme;
+        Theme::custom("Orion".to_string(), Palette {
+            background: ui::to_color(theme.background),
+            text: ui::to_color(theme.text),
+            primary: ui::to_color(theme.accent),
+            ..Palette::LIGHT
+        })
+    }
+
+    fn style(&self) -> iced::theme::Application {
+        if self.popup_theme.is_translucent() {
+            iced::theme::Application::Custom(Box::new(TransparentWindow))
+        } else {
+            iced::theme::Application::Default
+        }
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        Subscription::batch(vec![
-            event::listen().map(|event| {
+        // Key presses are taken even when the search input captured them,
+        // otherwise Escape and Enter never arrive while it has focus. Plain
+        // text entry is left to the input so typed or composed characters
+        // never trigger a binding.
+        let mut subscriptions = vec![
+            event::listen_with(|event, _status| {
                 match event {
-                    Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) => {
-                        AppMessage::KeyPressed(key)
+                    Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, text, .. }) => {
+                        if keys::is_text_entry(&key, modifiers, text.as_deref()) {
+                            None
+                        } else {
+                            Some(AppMessage::KeyPressed(key, modifiers))
+                        }
                     }
-                    Event::Window(_id, window_event) => AppMessage::WindowEvent(window_event),
-                    _ => AppMessage::UiMessage(ui::Message::CloseRequested),
+                    Event::Window(_id, window_event) => Some(AppMessage::WindowEvent(window_event)),
+                    _ => None,
                 }
             }),
-        ])
+        ];
+
+        if !self.appearance.reduce_motion && self.is_animating() {
+            subscriptions.push(window::frames().map(AppMessage::Tick));
+        }
+
+        Subscription::batch(subscriptions)
     }
 }
+
+// Waits on a plain thread, iced's executor having no timer of its own
+async fn delay(duration: Duration) {
+    let (done, waited) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        std::thread::sleep(duration);
+        let _ = done.send(());
+    });
+    let _ = waited.await;
+}
@@ -1,12 +1,20 @@
 use anyhow::Result;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
-use shared::models::{SearchQuery, SearchResult, IpcMessage, Command};
-use crate::ui::SearchUI;
+use shared::config::{CommandPolicy, EscapeBehavior, PopupConfig, SecurityConfig};
+use shared::models::{Action, Bang, OperationProgress, SearchQuery, SearchResult, IpcMessage, Command};
+use crate::ui::{self, KeyHint, SearchUI};
 use crate::commands::CommandExecutor;
-use iced::keyboard::Key;
+use crate::keys::{KeyAction, KeyBindings};
+use iced::keyboard::{Key, Modifiers};
+use iced::Font;
+use shared::theme::PopupTheme;
 
 const SEARCH_DELAY: Duration = Duration::from_millis(200);
 
+// Numbers the searches sent, for their `request_id`
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(1);
+
 pub struct AppState {
     search_ui: SearchUI,
     command_executor: CommandExecutor,
@@ -16,24 +24,44 @@ pub struct AppState {
     search_results: Vec<SearchResult>,
     command_history: Vec<String>,
     max_history: usize,
+    escape_behavior: EscapeBehavior,
+    key_bindings: KeyBindings,
+    // Sent with every query so the background can tell popup windows apart
+    session_id: String,
+    // A permanent delete, or a command or URL `[security]` says to ask about,
+    // waiting for Execute to be pressed again on the same result
+    confirming: Option<Action>,
+    security: SecurityConfig,
+    // Copies and moves started from this popup, as last polled
+    operations: Vec<OperationProgress>,
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    pub fn new(popup_config: PopupConfig, security: SecurityConfig, theme: &PopupTheme, font: Font) -> Self {
         Self {
-            search_ui: SearchUI::new(),
+            search_ui: SearchUI::with_theme(theme, font),
             command_executor: CommandExecutor::new(),
-            last_search_time: None,
+            // Searching the empty query on open lists recent history
+            last_search_time: Some(Instant::now()),
             current_query: None,
-            is_searching: false,
+            is_searching: true,
             search_results: Vec::new(),
             command_history: Vec::new(),
             max_history: 100,
+            escape_behavior: popup_config.escape_behavior,
+            key_bindings: KeyBindings::from_config(&popup_config.keys),
+            session_id: new_session_id(),
+            confirming: None,
+            security,
+            operations: Vec::new(),
         }
     }
 
     pub fn update_search_ui(&mut self, message: crate::ui::Message) -> bool {
         let should_search = self.search_ui.update(message);
+        if should_search {
+            self.confirming = None;
+        }
 
         if should_search {
             self.queue_search();
@@ -42,8 +70,74 @@ impl AppState {
         should_search
     }
 
-    pub fn view(&self) -> iced::Element<'_, crate::ui::Message, iced::Theme> {
-        self.search_ui.view()
+    pub fn view(&self, results_height: Option<f32>) -> iced::Element<'_, crate::ui::Message, iced::Theme> {
+        self.search_ui.view(self.key_hints(), &self.operations, results_height)
+    }
+
+    // The background reports a finished operation once, so finished ones
+    // stay listed here for as long as the popup is open
+    pub fn set_operations(&mut self, operations: Vec<OperationProgress>) {
+        let finished: Vec<OperationProgress> = self.operations.drain(..).filter(|op| op.finished).collect();
+        self.operations = finished.into_iter().filter(|op| operations.iter().all(|o| o.id != op.id)).collect();
+        self.operations.extend(operations);
+    }
+
+    pub fn has_running_operations(&self) -> bool {
+        self.operations.iter().any(|op| !op.finished)
+    }
+
+    pub fn results_height(&self) -> f32 {
+        self.search_ui.results_height()
+    }
+
+    // Shortcuts shown in the footer for the current input and selection
+    pub fn key_hints(&self) -> Vec<KeyHint> {
+        let mut hints = Vec::new();
+        let bindings = &self.key_bindings;
+
+        if let (Some(result), Some(key)) = (self.search_ui.get_selected_result(), bindings.hint(KeyAction::Execute)) {
+            let label = if self.confirming.as_ref() != Some(&result.action) {
+                ui::action_label(&result.action)
+            } else if matches!(result.action, Action::DeletePermanently(_)) {
+                "Press again to delete"
+            } else if matches!(result.action, Action::OpenUrl(_) | Action::OpenBang { .. }) {
+                "Press again to open"
+            } else {
+                "Press again to run"
+            };
+            hints.push((key, label));
+        }
+        if self.can_complete() {
+            if let Some(key) = bindings.hint(KeyAction::Complete) {
+                hints.push((key, "Complete"));
+            }
+        } else if self.has_actions() && !self.search_ui.has_actions_open() {
+            if let Some(key) = bindings.hint(KeyAction::Actions) {
+                hints.push((key, "Actions"));
+            }
+        }
+        if self.copy_text().is_some() {
+            if let Some(key) = bindings.hint(KeyAction::Copy) {
+                hints.push((key, "Copy"));
+            }
+        }
+        if self.search_ui.result_count() > 1 {
+            if let (Some(previous), Some(next)) = (bindings.hint(KeyAction::Previous), bindings.hint(KeyAction::Next)) {
+                let separator = if previous.chars().count() > 1 || next.chars().count() > 1 { "/" } else { "" };
+                hints.push((format!("{}{}{}", previous, separator, next), "Navigate"));
+            }
+        }
+
+        let escape_label = match self.escape_behavior {
+            _ if self.search_ui.has_actions_open() => "Back",
+            EscapeBehavior::ClearThenClose if !self.search_ui.is_empty() => "Clear",
+            _ => "Close",
+        };
+        if let Some(key) = bindings.hint(KeyAction::Close) {
+            hints.push((key, escape_label));
+        }
+
+        hints
     }
 
     pub fn should_perform_search(&self) -> bool {
@@ -65,31 +159,117 @@ impl AppState {
 
     pub fn get_search_query(&self) -> Option<SearchQuery> {
         if self.is_searching {
-            Some(self.search_ui.get_search_query())
+            let mut query = self.search_ui.get_search_query();
+            let generation = NEXT_REQUEST.fetch_add(1, Ordering::Relaxed);
+            query.session_id = Some(self.session_id.clone());
+            query.request_id = Some(format!("{}-{}", self.session_id, generation));
+            query.generation = Some(generation);
+            Some(query)
         } else {
             None
         }
     }
 
+    pub fn get_bang_query(&self) -> Option<String> {
+        if self.is_searching {
+            self.search_ui.bang_prefix().map(|prefix| prefix.to_string())
+        } else {
+            None
+        }
+    }
+
+    pub fn process_bang_suggestions(&mut self, suggestions: Vec<Bang>) {
+        self.is_searching = false;
+        self.search_ui.set_bang_suggestions(suggestions);
+    }
+
+    pub fn complete_bang(&mut self, idx: usize) -> bool {
+        self.search_ui.complete_bang(idx)
+    }
+
+    pub fn complete_selected(&mut self) -> bool {
+        self.search_ui.complete_selected()
+    }
+
+    pub fn complete_with_selected(&mut self) -> bool {
+        self.search_ui.complete_with_selected()
+    }
+
     pub fn process_search_results(&mut self, results: Vec<SearchResult>) {
         self.is_searching = false;
         self.search_results = results.clone();
         self.search_ui.set_results(results);
     }
 
-    pub fn handle_keypress(&mut self, key: Key) -> Option<Command> {
-        match key {
-            Key::Named(iced::keyboard::key::Named::ArrowDown) => {
+    pub fn accessible_summary(&self) -> String {
+        self.search_ui.accessible_summary()
+    }
+
+    // What reading out the results says: the selected or first result with
+    // the line that goes with it, which for answers like a calculation is
+    // the answer itself
+    pub fn spoken_result(&self) -> String {
+        let Some(result) = self.search_ui.get_selected_result().or(self.search_results.first()) else {
+            return "No results".to_string();
+        };
+        match result.subtitle.as_deref().or(result.description.as_deref()) {
+            Some(detail) if !detail.trim().is_empty() => format!("{}. {}", result.title, detail),
+            _ => result.title.clone(),
+        }
+    }
+
+    // The first action bound to the key that does something right now, so
+    // Tab completes when there's a completion and opens actions otherwise
+    pub fn key_action(&self, key: &Key, modifiers: Modifiers) -> Option<KeyAction> {
+        self.key_bindings.actions(key, modifiers).into_iter().find(|action| match action {
+            KeyAction::Complete => self.can_complete(),
+            KeyAction::Actions => self.has_actions(),
+            KeyAction::Copy => self.copy_text().is_some(),
+            _ => true,
+        })
+    }
+
+    fn can_complete(&self) -> bool {
+        !self.search_ui.has_actions_open()
+            && (self.search_ui.has_bang_suggestions()
+                || self.search_ui.get_selected_result().is_some_and(|r| r.completion.is_some()))
+    }
+
+    // Whether the actions key opens or closes a menu
+    fn has_actions(&self) -> bool {
+        self.search_ui.has_actions_open()
+            || self.search_ui.get_selected_result().is_some_and(|r| !r.actions.is_empty())
+    }
+
+    fn copy_text(&self) -> Option<String> {
+        let result = self.search_ui.menu_result().or(self.search_ui.get_selected_result())?;
+        ui::copy_text(&result.action)
+    }
+
+    pub fn handle_key_action(&mut self, action: KeyAction) -> Option<Command> {
+        let confirmed = self.confirming.take();
+
+        match action {
+            KeyAction::Next => {
                 self.search_ui.select_next();
                 None
             }
-            Key::Named(iced::keyboard::key::Named::ArrowUp) => {
+            KeyAction::Previous => {
                 self.search_ui.select_previous();
                 None
             }
-            Key::Named(iced::keyboard::key::Named::Enter) => {
+            KeyAction::Execute | KeyAction::ExecuteStayOpen => {
                 // Get the selected result and convert to a command
-                if let Some(result) = self.search_ui.get_selected_result() {
+                if let Some(selected) = self.search_ui.get_selected_result() {
+                    // An action picked from the menu still runs as its result
+                    let origin = self.search_ui.menu_result().unwrap_or(selected);
+                    let result = SearchResult { action: selected.action.clone(), ..origin.clone() };
+                    let confirmed = confirmed.as_ref() == Some(&result.action);
+                    if self.needs_confirming(&result.action) && !confirmed {
+                        self.confirming = Some(result.action.clone());
+                        return None;
+                    }
+
                     // Add to command history
                     if self.command_history.len() >= self.max_history {
                         self.command_history.remove(0);
@@ -97,21 +277,94 @@ impl AppState {
                     self.command_history.push(result.title.clone());
 
                     // Create a command from the result
-                    Some(Command::new(
+                    let command = Command::new(
                         result.title.clone(),
                         result.description.clone().unwrap_or_default(),
                         result.action.clone(),
                         Vec::new(),
+                    ).with_query(self.search_ui.get_search_query().text);
+                    Some(if confirmed { command.confirmed() } else { command })
+                } else if let Some(bang) = self.search_ui.typed_bang() {
+                    // A complete trigger with nothing after it opens the site
+                    let url = shared::bangs::expand(bang, "", &shared::template::TemplateVars::default());
+                    Some(Command::new(
+                        bang.display_name.clone(),
+                        url.clone(),
+                        Action::OpenBang { url, triggers: vec![bang.trigger.clone()] },
+                        Vec::new(),
                     ))
                 } else {
                     None
                 }
             }
-            Key::Named(iced::keyboard::key::Named::Escape) => {
-                // Signal to close the UI
+            KeyAction::Actions => {
+                if !self.search_ui.close_actions() {
+                    self.search_ui.open_actions();
+                }
                 None
             }
-            _ => None,
+            KeyAction::Copy => {
+                let result = self.search_ui.menu_result().or(self.search_ui.get_selected_result())?;
+                let text = ui::copy_text(&result.action)?;
+                Some(Command::new(result.title.clone(), text.clone(), Action::CopyToClipboard(text), Vec::new()))
+            }
+            // Completion and closing are handled by the application
+            KeyAction::Complete | KeyAction::Close | KeyAction::Speak => None,
+        }
+    }
+
+    // Actions run only when Execute is pressed twice
+    fn needs_confirming(&self, action: &Action) -> bool {
+        match action {
+            Action::DeletePermanently(_) => true,
+            Action::ExecuteCommand(command)
+            | Action::RunCommand { command, .. }
+            | Action::RunElevated(command)
+            | Action::OpenInTerminal { command, .. }
+            | Action::OpenWith { app: command, .. } => self.security.policy(command) == CommandPolicy::Ask,
+            Action::OpenUrl(url) | Action::OpenBang { url, .. } => {
+                self.security.url_policy(url) == CommandPolicy::Ask
+            }
+            _ => false,
+        }
+    }
+
+    // Returns true when Escape should close the popup
+    pub fn handle_escape(&mut self) -> bool {
+        // Cancels a pending confirmation first
+        if self.confirming.take().is_some() {
+            return false;
+        }
+        if self.search_ui.close_actions() {
+            return false;
+        }
+
+        match self.escape_behavior {
+            EscapeBehavior::ClearThenClose if !self.search_ui.is_empty() => {
+                self.search_ui.clear();
+                self.search_results.clear();
+                self.is_searching = false;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    pub fn set_dmenu(&mut self, prompt: Option<String>) {
+        self.search_ui.set_plain(prompt);
+    }
+
+    // Mode chips are only offered for providers the background runs
+    pub fn set_providers(&mut self, providers: Vec<String>) {
+        self.search_ui.set_providers(providers);
+    }
+
+    // What `--dmenu` prints: the selected item, or what was typed when
+    // nothing matches it
+    pub fn dmenu_choice(&self) -> Option<String> {
+        match self.search_ui.get_selected_result() {
+            Some(result) => Some(result.title.clone()),
+            None => Some(self.search_ui.get_search_query().text).filter(|text| !text.is_empty()),
         }
     }
 
@@ -124,11 +377,19 @@ impl AppState {
             IpcMessage::SearchResponse(response) => {
                 self.process_search_results(response.results);
             }
+            IpcMessage::BangSuggestions(suggestions) => {
+                self.process_bang_suggestions(suggestions);
+            }
             IpcMessage::Redirect(url) => {
+                // Nobody is asked first, so only URLs safe without asking
+                if self.security.url_policy(&url) != CommandPolicy::Allow {
+                    eprintln!("Refusing to open redirect to {}", url);
+                    return;
+                }
                 let cmd = Command::new(
                     "Open URL".to_string(),
                     url.clone(),
-                    shared::models::Action::OpenUrl(url),
+                    Action::OpenUrl(url),
                     Vec::new(),
                 );
 
@@ -146,3 +407,10 @@ impl AppState {
         &self.command_history
     }
 }
+
+fn new_session_id() -> String {
+    let started = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}-{}", std::process::id(), started.as_millis())
+}
@@ -1,8 +1,27 @@
 use iced::{
-    widget::{column, container, scrollable, Row, Text, TextInput},
-    Length, Element, Alignment, Color, Theme,
+    widget::{button, column, container, progress_bar, scrollable, text_input, Column, Row, Text, TextInput},
+    font, Font, Length, Element, Alignment, Color, Theme,
 };
-use shared::models::{SearchResult, SearchQuery};
+use shared::models::{Action, Bang, OperationProgress, SearchMode, SearchResult, SearchQuery, SEARCH_MODES};
+use shared::theme::{PopupTheme, Rgb, DEFAULT_ACCENT, DEFAULT_FONT_SIZE};
+
+const HINT_COLOR: Color = Color::from_rgb(0.55, 0.55, 0.6);
+
+// Approximate padding around a line of text, used with the text size to
+// estimate rendered heights when animating the result list
+const RESULT_ROW_PADDING: f32 = 17.0;
+const EMPTY_STATE_PADDING: f32 = 8.0;
+
+pub fn to_color(Rgb(r, g, b): Rgb) -> Color {
+    Color::from_rgb8(r, g, b)
+}
+
+// A shortcut shown in the footer, e.g. ("Esc", "Close")
+pub type KeyHint = (String, &'static str);
+
+pub fn search_input_id() -> text_input::Id {
+    text_input::Id::new("search-input")
+}
 
 // Custom style for selected items
 struct SelectedItemStyle;
@@ -10,9 +29,9 @@ struct SelectedItemStyle;
 impl container::StyleSheet for SelectedItemStyle {
     type Style = Theme;
 
-    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
         container::Appearance {
-            background: Some(Color::from_rgb(0.2, 0.4, 0.8).into()),
+            background: Some(style.palette().primary.into()),
             text_color: Some(Color::WHITE),
             ..Default::default()
         }
@@ -23,14 +42,38 @@ impl container::StyleSheet for SelectedItemStyle {
 pub enum Message {
     SearchInputChanged(String),
     ResultSelected(usize),
+    BangSelected(usize),
+    ClearMode,
     CloseRequested,
     ExecuteCommand,
 }
 
+// What the selected result can do, listed in place of the results until
+// one is picked or the menu is closed
+struct ActionMenu {
+    result: SearchResult,
+    results: Vec<SearchResult>,
+    selected_idx: Option<usize>,
+}
+
 pub struct SearchUI {
     input_value: String,
     results: Vec<SearchResult>,
     selected_idx: Option<usize>,
+    menu: Option<ActionMenu>,
+    bang_suggestions: Vec<Bang>,
+    mode: Option<SearchMode>,
+    // Providers the background has registered, None until it says; only
+    // modes for one of them become chips once it has
+    providers: Option<Vec<String>>,
+    // Input taken as typed, with no modes or bangs, for `--dmenu`
+    plain: bool,
+    placeholder: String,
+    // Color of matched characters in result titles
+    accent: Color,
+    // Base size of result titles, other text is sized relative to it
+    text_size: f32,
+    font: Font,
 }
 
 impl Default for SearchUI {
@@ -39,6 +82,15 @@ impl Default for SearchUI {
             input_value: String::new(),
             results: Vec::new(),
             selected_idx: None,
+            menu: None,
+            bang_suggestions: Vec::new(),
+            mode: None,
+            providers: None,
+            plain: false,
+            placeholder: "Type to search...".to_string(),
+            accent: to_color(DEFAULT_ACCENT),
+            text_size: DEFAULT_FONT_SIZE,
+            font: Font::DEFAULT,
         }
     }
 }
@@ -48,10 +100,35 @@ impl SearchUI {
         Self::default()
     }
 
+    pub fn with_theme(theme: &PopupTheme, font: Font) -> Self {
+        Self {
+            accent: to_color(theme.accent),
+            text_size: theme.font_size,
+            font,
+            ..Self::default()
+        }
+    }
+
+    pub fn set_plain(&mut self, placeholder: Option<String>) {
+        self.plain = true;
+        if let Some(placeholder) = placeholder {
+            self.placeholder = placeholder;
+        }
+    }
+
+    pub fn set_providers(&mut self, providers: Vec<String>) {
+        self.providers = Some(providers);
+    }
+
     pub fn update(&mut self, message: Message) -> bool {
         match message {
             Message::SearchInputChanged(value) => {
+                self.close_actions();
                 self.input_value = value;
+                self.enter_mode();
+                if self.bang_prefix().is_none() {
+                    self.bang_suggestions.clear();
+                }
                 true // Trigger search
             }
             Message::ResultSelected(idx) => {
@@ -60,27 +137,76 @@ impl SearchUI {
                 }
                 false
             }
+            Message::BangSelected(idx) => {
+                self.complete_bang(idx);
+                false
+            }
+            Message::ClearMode => {
+                self.mode = None;
+                true
+            }
             Message::CloseRequested => false,
             Message::ExecuteCommand => false,
         }
     }
 
-    pub fn view(&self) -> Element<Message, Theme> {
+    // `results_height` caps the result list while its height is animating
+    pub fn view(
+        &self,
+        hints: Vec<KeyHint>,
+        operations: &[OperationProgress],
+        results_height: Option<f32>,
+    ) -> Element<Message, Theme> {
         let search_input = TextInput::new(
-            "Type to search...",
+            &self.placeholder,
             &self.input_value,
         )
+        .id(search_input_id())
         .on_input(Message::SearchInputChanged)
         .padding(10)
-        .size(20);
+        .size(self.text_size + 4.0);
+        let size = self.text_size;
+
+        let search_row = match self.mode {
+            Some(mode) => Row::new()
+                .spacing(8)
+                .align_items(Alignment::Center)
+                .push(
+                    button(Text::<Theme>::new(format!("{} ✕", mode.label)).size(size - 2.0))
+                        .on_press(Message::ClearMode)
+                        .padding([6, 10])
+                )
+                .push(search_input),
+            None => Row::new().push(search_input),
+        };
+
+        let suggestion_widgets: Vec<Element<Message, Theme>> = self.bang_suggestions
+            .iter()
+            .enumerate()
+            .map(|(idx, bang)| {
+                let suggestion_row = Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(Text::<Theme>::new(format!("!{}", bang.trigger)).size(size))
+                    .push(Text::<Theme>::new(&bang.display_name).size(size - 2.0));
+
+                button(suggestion_row)
+                    .on_press(Message::BangSelected(idx))
+                    .style(iced::theme::Button::Text)
+                    .width(Length::Fill)
+                    .padding(5)
+                    .into()
+            })
+            .collect();
+        let bang_suggestions = column(suggestion_widgets).spacing(2);
 
         let results_list: Element<Message, Theme> = if self.results.is_empty() {
             if !self.input_value.is_empty() {
-                column![Text::<Theme>::new("No results found").size(16)]
+                column![Text::<Theme>::new("No results found").size(size)]
                     .spacing(10)
                     .into()
             } else {
-                column![Text::<Theme>::new("Start typing to search").size(16)]
+                column![Text::<Theme>::new("Start typing to search").size(size)]
                     .spacing(10)
                     .into()
             }
@@ -93,11 +219,15 @@ impl SearchUI {
                     let result_row = Row::new()
                         .spacing(10)
                         .align_items(Alignment::Center)
-                        .push(Text::<Theme>::new(&result.title).size(16))
+                        .push(highlighted_title(result, is_selected, self.accent, size, self.font))
+                        .push_maybe(result.subtitle.as_ref().map(|subtitle| {
+                            let subtitle = Text::<Theme>::new(subtitle).size(size - 2.0);
+                            if is_selected { subtitle } else { subtitle.style(HINT_COLOR) }
+                        }))
                         .push(if let Some(desc) = &result.description {
-                            Text::<Theme>::new(desc).size(14)
+                            Text::<Theme>::new(desc).size(size - 2.0)
                         } else {
-                            Text::<Theme>::new("").size(14)
+                            Text::<Theme>::new("").size(size - 2.0)
                         });
 
                     if is_selected {
@@ -125,9 +255,40 @@ impl SearchUI {
             .into()
         };
 
+        let mut results_list = container(results_list).height(Length::Fill);
+        if let Some(height) = results_height {
+            results_list = results_list.max_height(height);
+        }
+
+        let footer = hints
+            .into_iter()
+            .enumerate()
+            .fold(Row::new().spacing(6).align_items(Alignment::Center), |row, (idx, (key, label))| {
+                let row = if idx > 0 {
+                    row.push(Text::<Theme>::new("·").size(size - 4.0).style(HINT_COLOR))
+                } else {
+                    row
+                };
+                row.push(Text::<Theme>::new(format!("{} {}", key, label)).size(size - 4.0).style(HINT_COLOR))
+            });
+
+        // Copies and moves started from the popup, above the footer
+        let operations = operations.iter().fold(Column::new().spacing(4), |col, op| {
+            let status = match (&op.error, op.finished) {
+                (Some(error), _) => format!("{}: {}", op.label, error),
+                (None, true) => format!("{}: done", op.label),
+                (None, false) => format!("{} ({}/{} files)", op.label, op.files_done, op.files_total),
+            };
+            col.push(Text::<Theme>::new(status).size(size - 4.0))
+                .push_maybe((!op.finished).then(|| progress_bar(0.0..=1.0, op.fraction()).height(4)))
+        });
+
         column![
-            search_input,
+            search_row,
+            bang_suggestions,
             results_list,
+            operations,
+            footer,
         ]
         .spacing(10)
         .padding(20)
@@ -137,6 +298,9 @@ impl SearchUI {
     }
 
     pub fn set_results(&mut self, results: Vec<SearchResult>) {
+        // Results of a newer search replace the menu along with the ones it
+        // was opened over
+        self.menu = None;
         self.results = results;
         if !self.results.is_empty() && self.selected_idx.is_none() {
             self.selected_idx = Some(0);
@@ -149,10 +313,152 @@ impl SearchUI {
         }
     }
 
+    // Typing a mode prefix followed by a space turns it into a chip
+    fn enter_mode(&mut self) {
+        if self.mode.is_some() || self.plain {
+            return;
+        }
+
+        let mode = SEARCH_MODES.iter().find(|mode| {
+            self.input_value
+                .strip_prefix(mode.prefix)
+                .map_or(false, |rest| rest.starts_with(' '))
+                && self
+                    .providers
+                    .as_ref()
+                    .map_or(true, |providers| providers.iter().any(|name| name == mode.provider))
+        });
+
+        if let Some(mode) = mode {
+            self.input_value = self.input_value[mode.prefix.len() + 1..].to_string();
+            self.mode = Some(*mode);
+        }
+    }
+
+    // The trigger typed so far while the input is still a bare `!trigger`
+    pub fn bang_prefix(&self) -> Option<&str> {
+        if self.mode.is_some() || self.plain {
+            return None;
+        }
+
+        let rest = self.input_value.strip_prefix('!')?;
+        if rest.contains(char::is_whitespace) {
+            None
+        } else {
+            Some(rest)
+        }
+    }
+
+    pub fn set_bang_suggestions(&mut self, suggestions: Vec<Bang>) {
+        self.bang_suggestions = suggestions;
+    }
+
+    // Replaces the input with the chosen trigger, ready for the search term
+    pub fn complete_bang(&mut self, idx: usize) -> bool {
+        if let Some(bang) = self.bang_suggestions.get(idx) {
+            self.input_value = format!("!{} ", bang.trigger);
+            self.bang_suggestions.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    // The suggested bang whose trigger is exactly what was typed after `!`
+    pub fn typed_bang(&self) -> Option<&Bang> {
+        let prefix = self.bang_prefix()?;
+        self.bang_suggestions.iter().find(|b| b.trigger.eq_ignore_ascii_case(prefix))
+    }
+
+    // Applies the selected result when it completes the query rather than
+    // running something
+    pub fn complete_selected(&mut self) -> bool {
+        match self.get_selected_result().map(|r| &r.action) {
+            Some(Action::CompleteQuery(text)) => {
+                self.input_value = text.clone();
+                self.bang_suggestions.clear();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Puts the selected result's completion in the input, e.g. a bang's
+    // trigger, leaving the result's own action for Enter
+    pub fn complete_with_selected(&mut self) -> bool {
+        match self.get_selected_result().and_then(|r| r.completion.clone()) {
+            Some(text) => {
+                self.input_value = text;
+                self.bang_suggestions.clear();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn has_bang_suggestions(&self) -> bool {
+        !self.bang_suggestions.is_empty()
+    }
+
+    pub fn result_count(&self) -> usize {
+        self.results.len()
+    }
+
+    // Height the result list settles at for the current results
+    pub fn results_height(&self) -> f32 {
+        if self.results.is_empty() {
+            self.text_size + EMPTY_STATE_PADDING
+        } else {
+            self.results.len() as f32 * (self.text_size + RESULT_ROW_PADDING)
+        }
+    }
+
+    // What a screen reader should announce for the current state, e.g.
+    // "Firefox, Open, result 2 of 5"
+    pub fn accessible_summary(&self) -> String {
+        if self.results.is_empty() {
+            return if self.input_value.is_empty() {
+                "Search".to_string()
+            } else {
+                "No results".to_string()
+            };
+        }
+
+        match self.selected_idx.and_then(|idx| self.results.get(idx).map(|r| (idx, r))) {
+            Some((idx, result)) => format!(
+                "{}, {}, result {} of {}",
+                result.title,
+                action_label(&result.action),
+                idx + 1,
+                self.results.len()
+            ),
+            None => format!("{} results", self.results.len()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input_value.is_empty() && self.mode.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.menu = None;
+        self.input_value.clear();
+        self.results.clear();
+        self.selected_idx = None;
+        self.bang_suggestions.clear();
+        self.mode = None;
+    }
+
     pub fn get_search_query(&self) -> SearchQuery {
         SearchQuery {
             text: self.input_value.clone(),
             max_results: 10,
+            provider_filter: self.mode.map(|mode| mode.provider.to_string()),
+            profile: None,
+            session_id: None,
+            offset: 0,
+            request_id: None,
+            generation: None,
         }
     }
 
@@ -160,6 +466,52 @@ impl SearchUI {
         self.selected_idx.and_then(|idx| self.results.get(idx))
     }
 
+    // Lists the selected result's main action followed by its others, each
+    // picked like a result
+    pub fn open_actions(&mut self) -> bool {
+        if self.menu.is_some() {
+            return false;
+        }
+        let Some(result) = self.get_selected_result().filter(|r| !r.actions.is_empty()).cloned() else {
+            return false;
+        };
+
+        let entry = |label: &str, action: &Action| {
+            let mut entry = SearchResult::new(label.to_string(), None, action.clone(), result.score);
+            entry.icon = result.icon.clone();
+            entry
+        };
+        let entries = std::iter::once(entry(action_label(&result.action), &result.action))
+            .chain(result.actions.iter().map(|a| entry(&a.label, &a.action)))
+            .collect();
+
+        self.menu = Some(ActionMenu {
+            result,
+            results: std::mem::replace(&mut self.results, entries),
+            selected_idx: self.selected_idx.replace(0),
+        });
+        true
+    }
+
+    // Puts back the results the menu was opened over
+    pub fn close_actions(&mut self) -> bool {
+        let Some(menu) = self.menu.take() else {
+            return false;
+        };
+        self.results = menu.results;
+        self.selected_idx = menu.selected_idx;
+        true
+    }
+
+    pub fn has_actions_open(&self) -> bool {
+        self.menu.is_some()
+    }
+
+    // The result whose action menu is open
+    pub fn menu_result(&self) -> Option<&SearchResult> {
+        self.menu.as_ref().map(|menu| &menu.result)
+    }
+
     pub fn select_next(&mut self) {
         if self.results.is_empty() {
             return;
@@ -188,3 +540,88 @@ impl SearchUI {
         }
     }
 }
+
+// Splits the title into plain and matched runs, matched ones drawn bold and
+// accent-colored (just bold on the selected row, which is already colored)
+fn highlighted_title(
+    result: &SearchResult,
+    is_selected: bool,
+    accent: Color,
+    size: f32,
+    font: Font,
+) -> Element<'static, Message, Theme> {
+    let match_font = Font { weight: font::Weight::Bold, ..font };
+    let chars: Vec<char> = result.title.chars().collect();
+    let mut row = Row::new();
+    let mut pos = 0;
+
+    for &(start, end) in &result.highlights {
+        let start = start.min(chars.len()).max(pos);
+        let end = end.min(chars.len());
+        if start >= end {
+            continue;
+        }
+
+        if start > pos {
+            let plain: String = chars[pos..start].iter().collect();
+            row = row.push(Text::<Theme>::new(plain).size(size));
+        }
+
+        let matched: String = chars[start..end].iter().collect();
+        let matched = Text::<Theme>::new(matched).size(size).font(match_font);
+        row = row.push(if is_selected { matched } else { matched.style(accent) });
+        pos = end;
+    }
+
+    if pos < chars.len() {
+        let plain: String = chars[pos..].iter().collect();
+        row = row.push(Text::<Theme>::new(plain).size(size));
+    }
+
+    row.into()
+}
+
+// What copying a result puts on the clipboard: the text it stands for, or
+// the path, URL or command it opens or runs
+pub fn copy_text(action: &Action) -> Option<String> {
+    match action {
+        Action::CopyToClipboard(text) | Action::PasteText(text) => Some(text.clone()),
+        Action::OpenUrl(url) | Action::OpenBang { url, .. } => Some(url.clone()),
+        Action::ExecuteCommand(command) | Action::RunCommand { command, .. } | Action::RunElevated(command) => {
+            Some(command.clone())
+        }
+        Action::OpenFile(path)
+        | Action::RevealInFolder(path)
+        | Action::MoveToTrash(path)
+        | Action::DeletePermanently(path)
+        | Action::EjectVolume(path)
+        | Action::OpenWith { path, .. }
+        | Action::OpenInTerminal { path, .. } => Some(path.display().to_string()),
+        Action::LaunchApp(app) => Some(app.clone()),
+        _ => None,
+    }
+}
+
+pub fn action_label(action: &Action) -> &'static str {
+    match action {
+        Action::OpenFile(_) | Action::OpenUrl(_) | Action::OpenBang { .. } => "Open",
+        Action::ExecuteCommand(_) | Action::RunCommand { .. } => "Run",
+        Action::Custom(_) => "Select",
+        Action::CompleteQuery(_) => "Complete",
+        Action::CopyToClipboard(_) => "Copy",
+        Action::PasteText(_) => "Paste",
+        Action::OpenWith { .. } => "Open with",
+        Action::RevealInFolder(_) => "Show in folder",
+        Action::MoveToTrash(_) => "Move to Trash",
+        Action::DeletePermanently(_) => "Delete permanently",
+        Action::ExtractFile { .. } => "Extract",
+        Action::CopyFiles { .. } => "Copy here",
+        Action::MoveFiles { .. } => "Move here",
+        Action::OpenInTerminal { .. } => "Open in terminal",
+        Action::EjectVolume(_) => "Eject",
+        Action::RunElevated(_) => "Run as admin",
+        Action::LaunchApp(_) => "Launch",
+        Action::RunPlugin { .. } | Action::RunScript { .. } => "Run",
+        Action::AddSiteBang(_) => "Create site-search bang",
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use shared::logging;
+use std::time::Duration;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    AtomEnum, ClientMessageEvent, ConnectionExt as _, CreateWindowAux, EventMask, InputFocus, PropMode, Window,
+    WindowClass,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+// Taking keyboard focus on X11. The popup is started by the background
+// rather than by a click or key press the window manager saw, so window
+// managers preventing focus stealing map it without focus, or behind the
+// window in use. Asking for it through `_NET_ACTIVE_WINDOW` as a pager
+// would, with a current server timestamp, is honored by those window
+// managers; where that doesn't take, focus is set directly.
+
+// How long the window manager gets to list the freshly mapped popup, and
+// then to act on the request
+const FIND_ATTEMPTS: usize = 20;
+const RETRY_DELAY: Duration = Duration::from_millis(25);
+const ACTIVATE_WAIT: Duration = Duration::from_millis(100);
+
+// `_NET_ACTIVE_WINDOW` source indication for pagers and other tools acting
+// for the user, which focus stealing prevention leaves alone
+const SOURCE_PAGER: u32 = 2;
+
+// Whether the popup runs on an X server, as opposed to Wayland where the
+// compositor settles focus on its own
+pub fn is_session() -> bool {
+    std::env::var_os("DISPLAY").is_some() && std::env::var_os("WAYLAND_DISPLAY").is_none()
+}
+
+// Runs on a thread of its own once the window has opened, logging failures
+// as the popup works without focus, just less conveniently
+pub fn focus_popup() {
+    std::thread::spawn(|| {
+        if let Err(e) = activate() {
+            logging::warn(&format!("Failed to focus the popup: {:#}", e));
+        }
+    });
+}
+
+fn activate() -> Result<()> {
+    let (conn, screen) = x11rb::connect(None).context("Failed to connect to the X server")?;
+    let root = conn.setup().roots[screen].root;
+    let atoms = Atoms::intern(&conn)?;
+
+    let mut popup = None;
+    for _ in 0..FIND_ATTEMPTS {
+        popup = find_own_window(&conn, root, &atoms)?;
+        if popup.is_some() {
+            break;
+        }
+        std::thread::sleep(RETRY_DELAY);
+    }
+    let popup = popup.context("The window manager doesn't list the popup window")?;
+    let time = server_time(&conn, root)?;
+
+    // Marks the popup as used just now, which is what window managers
+    // compare against the active window's last use
+    conn.change_property32(PropMode::REPLACE, popup, atoms.net_wm_user_time, AtomEnum::CARDINAL, &[time])?;
+    let request = ClientMessageEvent::new(32, popup, atoms.net_active_window, [SOURCE_PAGER, time, 0, 0, 0]);
+    conn.send_event(
+        false,
+        root,
+        EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+        request,
+    )?;
+    conn.flush()?;
+
+    std::thread::sleep(ACTIVATE_WAIT);
+    if active_window(&conn, root, &atoms)? != Some(popup) {
+        logging::debug("Window manager didn't activate the popup, setting focus directly");
+        conn.set_input_focus(InputFocus::PARENT, popup, time)?.check()?;
+    }
+    Ok(())
+}
+
+struct Atoms {
+    net_active_window: u32,
+    net_client_list: u32,
+    net_wm_pid: u32,
+    net_wm_user_time: u32,
+}
+
+impl Atoms {
+    fn intern(conn: &RustConnection) -> Result<Self> {
+        let atom = |name: &[u8]| -> Result<u32> { Ok(conn.intern_atom(false, name)?.reply()?.atom) };
+        Ok(Self {
+            net_active_window: atom(b"_NET_ACTIVE_WINDOW")?,
+            net_client_list: atom(b"_NET_CLIENT_LIST")?,
+            net_wm_pid: atom(b"_NET_WM_PID")?,
+            net_wm_user_time: atom(b"_NET_WM_USER_TIME")?,
+        })
+    }
+}
+
+// The managed window whose `_NET_WM_PID` is this process
+fn find_own_window(conn: &RustConnection, root: Window, atoms: &Atoms) -> Result<Option<Window>> {
+    let pid = std::process::id();
+    let clients = conn
+        .get_property(false, root, atoms.net_client_list, AtomEnum::WINDOW, 0, u32::MAX)?
+        .reply()?;
+    let Some(clients) = clients.value32() else {
+        return Ok(None);
+    };
+
+    for window in clients {
+        let owner = conn
+            .get_property(false, window, atoms.net_wm_pid, AtomEnum::CARDINAL, 0, 1)?
+            .reply()?;
+        if owner.value32().and_then(|mut values| values.next()) == Some(pid) {
+            return Ok(Some(window));
+        }
+    }
+    Ok(None)
+}
+
+fn active_window(conn: &RustConnection, root: Window, atoms: &Atoms) -> Result<Option<Window>> {
+    let active = conn
+        .get_property(false, root, atoms.net_active_window, AtomEnum::WINDOW, 0, 1)?
+        .reply()?;
+    Ok(active.value32().and_then(|mut values| values.next()))
+}
+
+// The X server's current time, which requests have to carry for window
+// managers to order them against user input. It comes back on the
+// notification for an empty change to a property of a window of our own.
+fn server_time(conn: &RustConnection, root: Window) -> Result<u32> {
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )?;
+    conn.change_property8(PropMode::APPEND, window, AtomEnum::WM_NAME, AtomEnum::STRING, &[])?;
+    conn.flush()?;
+
+    let time = loop {
+        if let Event::PropertyNotify(event) = conn.wait_for_event()? {
+            if event.window == window {
+                break event.time;
+            }
+        }
+    };
+    conn.destroy_window(window)?;
+    Ok(time)
+}
@@ -24,15 +24,12 @@ fi
 echo -e "${GREEN}Using binaries from: ${BIN_DIR}${NC}"
 
 # Check if background service is already running
-if pgrep -f "${BIN_DIR}/background" > /dev/null; then
+if ${BIN_DIR}/background --status > /dev/null; then
     echo -e "${GREEN}Orion background service is already running.${NC}"
 else
     echo -e "${GREEN}Starting background service...${NC}"
-    ${BIN_DIR}/background &
-    BACKGROUND_PID=$!
-    echo -e "${GREEN}Background service started with PID: ${BACKGROUND_PID}${NC}"
-    # Wait a moment for the background service to initialize
-    sleep 1
+    # Returns once the service answers
+    ${BIN_DIR}/background --daemonize
 fi
 
 # Start the UI
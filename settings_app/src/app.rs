@@ -1,28 +1,115 @@
-use iced::{Application, Command, Element, executor, Theme};
+use anyhow::Context;
+use iced::{Application, Command, Element, executor, Theme, Event, Subscription, event, keyboard, window};
+use std::time::Duration;
+use iced::keyboard::{Key, Modifiers};
+use iced::widget::text_editor;
 use shared::config;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use iced::Color;
 
-use crate::state::{State, Tab, AppTheme};
+use shared::bangs::{BangOverlay, BangUsage};
+use shared::config::ProviderSettings;
+use shared::models::{Bang, BangListQuery, BangPage, BangRefresh, Browser, IpcMessage, ProviderInfo};
+use shared::plugin::PluginStatus;
+
+use crate::logs::{LogLevel, LogLine, LogSource};
+use crate::state::{State, Tab, AppTheme, BangDraft, CommandDraft, PendingAction, SiteBangDraft};
 use crate::ui::TabUI;
 
 #[derive(Debug, Clone)]
 pub enum AppMessage {
     TabSelected(Tab),
     ToggleVoice(bool),
-    UpdateHotkey(String),
+    ToggleAutostart(bool),
+    AutostartUpdated(Result<bool, String>),
+    RecordHotkey,
+    CancelHotkeyRecording,
+    HotkeyKeyPressed(Key, Modifiers),
     SetTheme(AppTheme),
     SetAccentColor(Color),
+    PopupBackgroundChanged(String),
+    PopupTextChanged(String),
+    PopupFontChanged(String),
+    PopupFontSizeChanged(f32),
+    PopupRadiusChanged(f32),
+    PopupOpacityChanged(f32),
     AdjustSensitivity(f32),
     SelectProfile(String),
+    CloseRequested(window::Id),
+    SavePending,
+    DiscardPending,
+    CancelPending,
     AddProfile,
     UpdateNewProfileName(String),
     DeleteProfile(String),
+    StartProfileRename(String),
+    ProfileRenameChanged(String),
+    CancelProfileRename,
+    RenameProfile,
+    ProfileRenamed(Result<(String, String), String>),
     SaveSettings,
     ResetSettings,
     LoadConfig(Arc<Mutex<config::Config>>),
+    SettingsLoaded(Box<State>),
+    SettingsSaved(Result<(), String>),
+    UpdateSetting(String, String),
+    ProfilesUpdated(Vec<String>, String),
+    SelectEditingProfile(String),
+    CommandsLoaded(String, Vec<config::Command>),
+    CommandsSaved(Result<Vec<config::Command>, String>),
+    EditCommand(usize),
+    CancelCommandEdit,
+    CommandNameChanged(String),
+    CommandUrlChanged(String),
+    CommandRunChanged(String),
+    CommandElevatedToggled(bool),
+    CommandDescriptionChanged(String),
+    CommandKeywordsChanged(String),
+    SaveCommand,
+    DeleteCommand(usize),
+    MoveCommand(usize, bool),
+    ProvidersLoaded(String, Vec<ProviderSettings>, Result<Vec<ProviderInfo>, String>),
+    ToggleProvider(usize, bool),
+    MoveProvider(usize, bool),
+    ProviderOptionChanged(usize, String, String),
+    ProviderWeightChanged(usize, f32),
+    SaveProviders,
+    ProvidersSaved(Result<(), String>),
+    PluginsLoaded(Result<Vec<PluginStatus>, String>),
+    BangsLoaded(Result<(BangOverlay, BangUsage), String>),
+    BangPageLoaded(Result<BangPage, String>),
+    BangPageRequested(usize),
+    BangEngineFound(Option<Bang>),
+    BangFilterChanged(String),
+    BangTriggerChanged(String),
+    BangNameChanged(String),
+    BangUrlChanged(String),
+    BangAliasesChanged(String),
+    SiteBangDomainChanged(String),
+    SiteBangTriggerChanged(String),
+    AddSiteBang,
+    AddCustomBang,
+    RemoveCustomBang(String),
+    SetBangDisabled(String, bool),
+    BangsSaved(Result<BangRefresh, String>),
+    DownloadBangs,
+    BangsDownloaded(Result<BangRefresh, String>),
+    ImportBangs(Browser),
+    BangsImported(Result<BangRefresh, String>),
+    RawConfigLoaded(String),
+    RawConfigAction(text_editor::Action),
+    ValidateRawConfig,
+    SaveRawConfig,
+    RevertRawConfig,
+    LogSourceSelected(LogSource),
+    LogLevelSelected(LogLevel),
+    LogSearchChanged(String),
+    RefreshLogs,
+    LogsLoaded(Result<Vec<LogLine>, String>),
+    WriteDiagnostics,
+    DiagnosticsWritten(Result<PathBuf, String>),
 }
 
 pub struct App {
@@ -31,6 +118,222 @@ pub struct App {
     ui: TabUI,
 }
 
+impl App {
+    // Loads a profile's commands for the commands tab, falling back to the
+    // current profile when it no longer exists
+    fn load_commands(&self, profile: String) -> Command<AppMessage> {
+        let config = self.state.config.clone();
+
+        Command::perform(
+            async move {
+                let config = config.lock().await;
+                let profile = if config.profiles.iter().any(|p| p.name == profile) {
+                    profile
+                } else {
+                    config.current_profile.clone()
+                };
+                let commands = crate::profiles::profile_commands(&config, &profile)
+                    .unwrap_or_default();
+                (profile, commands)
+            },
+            |(profile, commands)| AppMessage::CommandsLoaded(profile, commands)
+        )
+    }
+
+    // Loads a profile's provider settings along with the providers the
+    // background actually has registered
+    fn load_providers(&self, profile: String) -> Command<AppMessage> {
+        let config = self.state.config.clone();
+
+        Command::perform(
+            async move {
+                let (profile, settings) = {
+                    let config = config.lock().await;
+                    let profile = if config.profiles.iter().any(|p| p.name == profile) {
+                        profile
+                    } else {
+                        config.current_profile.clone()
+                    };
+                    let settings = crate::profiles::profile_providers(&config, &profile)
+                        .unwrap_or_default();
+                    (profile, settings)
+                };
+
+                let info = match crate::background::request(&config, IpcMessage::ListProviders).await {
+                    Ok(IpcMessage::Providers(info)) => Ok(info),
+                    Ok(other) => Err(format!("Unexpected reply from the background: {:?}", other)),
+                    Err(e) => Err(format!("Could not reach the background: {}", e)),
+                };
+                (profile, settings, info)
+            },
+            |(profile, settings, info)| AppMessage::ProvidersLoaded(profile, settings, info)
+        )
+    }
+
+    // Plugins are found when the background starts, so it's asked rather
+    // than the plugins directory read here
+    fn load_plugins(&self) -> Command<AppMessage> {
+        let config = self.state.config.clone();
+
+        Command::perform(
+            async move {
+                match crate::background::request(&config, IpcMessage::ListPlugins).await {
+                    Ok(IpcMessage::Plugins(plugins)) => Ok(plugins),
+                    Ok(other) => Err(format!("Unexpected reply from the background: {:?}", other)),
+                    Err(e) => Err(format!("Could not reach the background: {}", e)),
+                }
+            },
+            AppMessage::PluginsLoaded
+        )
+    }
+
+    // Applies a change to the commands of the profile being edited, saves
+    // the config and hands back that profile's updated command list
+    fn update_commands(
+        &self,
+        change: impl FnOnce(&mut config::Config, &str) -> anyhow::Result<()> + Send + 'static,
+    ) -> Command<AppMessage> {
+        let config = self.state.config.clone();
+        let config_path = self.config_path.clone();
+        let profile = self.state.editing_profile.clone();
+
+        Command::perform(
+            async move {
+                let mut config = config.lock().await;
+                change(&mut config, &profile)?;
+                config.save(&config_path)?;
+                crate::profiles::profile_commands(&config, &profile)
+            },
+            |result| AppMessage::CommandsSaved(result.map_err(|e| e.to_string()))
+        )
+    }
+}
+
+impl App {
+    fn load_bangs(&self) -> Command<AppMessage> {
+        Command::batch([
+            Command::perform(
+                async { crate::bangs::load() },
+                |result| AppMessage::BangsLoaded(result.map_err(|e| e.to_string()))
+            ),
+            self.list_bangs(self.state.bang_page.query.offset),
+            self.find_bang_engine(),
+        ])
+    }
+
+    fn list_bangs(&self, offset: usize) -> Command<AppMessage> {
+        let config = self.state.config.clone();
+        let query = BangListQuery {
+            filter: self.state.bang_filter.trim().to_string(),
+            offset,
+            limit: crate::bangs::PAGE_SIZE,
+        };
+
+        Command::perform(
+            async move { crate::bangs::list(&config, query).await },
+            |result| AppMessage::BangPageLoaded(result.map_err(|e| format!("{:#}", e)))
+        )
+    }
+
+    // Looks up the bang the default bang setting names, which site
+    // searches go through
+    fn find_bang_engine(&self) -> Command<AppMessage> {
+        let config = self.state.config.clone();
+        let trigger = self.state.setting("default_bang").trim().trim_start_matches('!').to_string();
+        if trigger.is_empty() {
+            return Command::none();
+        }
+
+        Command::perform(
+            async move { crate::bangs::find(&config, &trigger).await },
+            |result| AppMessage::BangEngineFound(result.ok().flatten())
+        )
+    }
+
+    // Picks up what was put on hold for unsaved changes
+    fn run_pending(&mut self, action: PendingAction) -> Command<AppMessage> {
+        match action {
+            PendingAction::Exit => window::close(window::Id::MAIN),
+            PendingAction::SelectProfile(profile) => {
+                let config = self.state.config.clone();
+
+                // Reloaded afterwards, which also drops discarded edits
+                Command::perform(
+                    async move {
+                        if let Err(e) = crate::profiles::select_profile(&mut *config.lock().await, &profile).await {
+                            eprintln!("Failed to select profile: {}", e);
+                        }
+                        config
+                    },
+                    AppMessage::LoadConfig
+                )
+            }
+        }
+    }
+
+    // Reads config.toml as written, comments included, falling back to the
+    // loaded config when there is no file yet
+    fn load_raw_config(&self) -> Command<AppMessage> {
+        let config_path = self.config_path.clone();
+        let config = self.state.config.clone();
+
+        Command::perform(
+            async move {
+                match std::fs::read_to_string(&config_path) {
+                    Ok(text) => text,
+                    Err(_) => toml::to_string_pretty(&*config.lock().await).unwrap_or_default(),
+                }
+            },
+            AppMessage::RawConfigLoaded
+        )
+    }
+
+    fn load_logs(&self) -> Command<AppMessage> {
+        let dir = self.config_dir();
+        let source = self.state.log_source;
+
+        Command::perform(
+            async move { crate::logs::tail(&dir, source) },
+            |result| AppMessage::LogsLoaded(result.map_err(|e| format!("{:#}", e)))
+        )
+    }
+
+    // Logs sit next to config.toml
+    fn config_dir(&self) -> PathBuf {
+        self.config_path
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_default()
+    }
+
+    // Writes the bang overlay and has the background pick it up, reporting
+    // any bangs it had to skip
+    fn save_bang_overlay(&self) -> Command<AppMessage> {
+        let overlay = self.state.bang_overlay.clone();
+        let config = self.state.config.clone();
+
+        Command::perform(
+            async move {
+                overlay.save()?;
+                let reply = crate::background::request(&config, IpcMessage::ReloadBangs).await
+                    .context("Saved, but the background could not be notified")?;
+                match reply {
+                    IpcMessage::BangsRefreshed(summary) => Ok(summary),
+                    IpcMessage::Error(e) => Err(anyhow::anyhow!("Saved, but reloading failed: {}", e)),
+                    other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+                }
+            },
+            |result: anyhow::Result<BangRefresh>| AppMessage::BangsSaved(result.map_err(|e| format!("{:#}", e)))
+        )
+    }
+}
+
+// Describes the bangs the background skipped, None when it loaded them all
+fn skipped_bangs(summary: &BangRefresh) -> Option<String> {
+    let first = summary.errors.first()?;
+    Some(format!("Skipped {} invalid bangs, e.g. {}", summary.errors.len(), first))
+}
+
 impl Application for App {
     type Message = AppMessage;
     type Theme = Theme;
@@ -39,7 +342,7 @@ impl Application for App {
 
     fn new(_flags: ()) -> (Self, Command<Self::Message>) {
         // Get config path
-        let config_path = directories::ProjectDirs::from("com", "orion", "config")
+        let config_path = directories::ProjectDirs::from("", "", "orion")
             .map(|proj_dirs| proj_dirs.config_dir().join("config.toml"))
             .unwrap_or_else(|| PathBuf::from("config.toml"));
 
@@ -76,12 +379,65 @@ impl Application for App {
         match message {
             AppMessage::TabSelected(tab) => {
                 self.state.active_tab = tab;
+                match tab {
+                    Tab::Commands => return self.load_commands(self.state.editing_profile.clone()),
+                    Tab::Providers => return self.load_providers(self.state.editing_profile.clone()),
+                    Tab::Plugins => return self.load_plugins(),
+                    Tab::Bangs => return self.load_bangs(),
+                    Tab::Logs => return self.load_logs(),
+                    // Keep edits made before switching away
+                    Tab::Advanced if self.state.raw_config.content.text().trim().is_empty() => {
+                        return self.load_raw_config();
+                    }
+                    _ => {}
+                }
             }
             AppMessage::ToggleVoice(enabled) => {
                 self.state.voice_enabled = enabled;
             }
-            AppMessage::UpdateHotkey(hotkey) => {
-                self.state.hotkey = hotkey;
+            AppMessage::ToggleAutostart(enabled) => {
+                return Command::perform(
+                    async move { crate::autostart::set_enabled(enabled) },
+                    |result| AppMessage::AutostartUpdated(result.map_err(|e| format!("{:#}", e)))
+                );
+            }
+            AppMessage::AutostartUpdated(result) => match result {
+                Ok(installed) => {
+                    self.state.autostart = installed;
+                    self.state.autostart_error = None;
+                }
+                Err(e) => {
+                    self.state.autostart = crate::autostart::is_installed();
+                    self.state.autostart_error = Some(e);
+                }
+            },
+            AppMessage::RecordHotkey => {
+                self.state.recording_hotkey = true;
+            }
+            AppMessage::CancelHotkeyRecording => {
+                self.state.recording_hotkey = false;
+            }
+            AppMessage::HotkeyKeyPressed(key, modifiers) => {
+                if !self.state.recording_hotkey {
+                    return Command::none();
+                }
+
+                if key == Key::Named(keyboard::key::Named::Escape) && modifiers.is_empty() {
+                    self.state.recording_hotkey = false;
+                    return Command::none();
+                }
+
+                if let Some(combo) = crate::hotkey::combo_from_key(&key, modifiers) {
+                    match crate::hotkey::check_global(&combo) {
+                        Ok(()) => {
+                            self.state.hotkey = combo.to_string();
+                            self.state.hotkey_error = None;
+                            self.state.recording_hotkey = false;
+                        }
+                        // Keep recording so another combo can be tried
+                        Err(e) => self.state.hotkey_error = Some(e),
+                    }
+                }
             }
             AppMessage::SetTheme(theme) => {
                 self.state.theme = theme;
@@ -89,9 +445,30 @@ impl Application for App {
             AppMessage::SetAccentColor(color) => {
                 self.state.accent_color = color;
             }
+            AppMessage::PopupBackgroundChanged(value) => {
+                self.state.popup_background = value;
+            }
+            AppMessage::PopupTextChanged(value) => {
+                self.state.popup_text = value;
+            }
+            AppMessage::PopupFontChanged(value) => {
+                self.state.popup_font = value;
+            }
+            AppMessage::PopupFontSizeChanged(value) => {
+                self.state.popup_font_size = value;
+            }
+            AppMessage::PopupRadiusChanged(value) => {
+                self.state.popup_radius = value;
+            }
+            AppMessage::PopupOpacityChanged(value) => {
+                self.state.popup_opacity = value;
+            }
             AppMessage::AdjustSensitivity(value) => {
                 self.state.sensitivity = value;
             }
+            AppMessage::SelectProfile(profile) if self.state.is_dirty() && profile != self.state.current_profile => {
+                self.state.pending_action = Some(PendingAction::SelectProfile(profile));
+            }
             AppMessage::SelectProfile(profile) => {
                 let profile_clone = profile.clone();
                 let config = self.state.config.clone();
@@ -102,97 +479,589 @@ impl Application for App {
                         if let Err(e) = crate::profiles::select_profile(&mut config_guard, &profile_clone).await {
                             eprintln!("Failed to select profile: {}", e);
                         }
-                        profile_clone
+                        (config_guard.get_profile_names(), config_guard.current_profile.clone())
                     },
-                    |name| AppMessage::SelectProfile(name)
+                    |(profiles, current)| AppMessage::ProfilesUpdated(profiles, current)
                 );
             }
+            AppMessage::CloseRequested(id) => {
+                if !self.state.is_dirty() {
+                    return window::close(id);
+                }
+                self.state.pending_action = Some(PendingAction::Exit);
+            }
+            // Carried on with once the save succeeds
+            AppMessage::SavePending => {
+                return self.update(AppMessage::SaveSettings);
+            }
+            AppMessage::DiscardPending => {
+                if let Some(action) = self.state.pending_action.take() {
+                    return self.run_pending(action);
+                }
+            }
+            AppMessage::CancelPending => {
+                self.state.pending_action = None;
+            }
             AppMessage::AddProfile => {
-                if !self.state.new_profile_name.trim().is_empty()
-                   && !self.state.profiles.contains(&self.state.new_profile_name) {
-                    let name = self.state.new_profile_name.clone();
-                    let config_for_async = self.state.config.clone();
-                    let config_for_callback = self.state.config.clone();
+                if self.state.profile_name_error(&self.state.new_profile_name, None).is_none() {
+                    let name = self.state.new_profile_name.trim().to_string();
+                    let config = self.state.config.clone();
                     
                     self.state.new_profile_name.clear();
                     
                     return Command::perform(
                         async move {
-                            let mut config_guard = config_for_async.lock().await;
+                            let mut config_guard = config.lock().await;
                             if let Err(e) = crate::profiles::add_profile(&mut config_guard, name.clone()).await {
                                 eprintln!("Failed to add profile: {}", e);
                             }
+                            (config_guard.get_profile_names(), config_guard.current_profile.clone())
                         },
-                        move |_| AppMessage::LoadConfig(config_for_callback.clone())
+                        |(profiles, current)| AppMessage::ProfilesUpdated(profiles, current)
                     );
                 }
             }
             AppMessage::UpdateNewProfileName(name) => {
                 self.state.new_profile_name = name;
             }
+            AppMessage::StartProfileRename(profile) => {
+                self.state.renaming_profile = Some((profile.clone(), profile));
+                self.state.profile_error = None;
+            }
+            AppMessage::ProfileRenameChanged(name) => {
+                if let Some((_, draft)) = &mut self.state.renaming_profile {
+                    *draft = name;
+                }
+            }
+            AppMessage::CancelProfileRename => {
+                self.state.renaming_profile = None;
+                self.state.profile_error = None;
+            }
+            AppMessage::RenameProfile => {
+                if let Some((old, new)) = self.state.renaming_profile.clone() {
+                    let config = self.state.config.clone();
+
+                    return Command::perform(
+                        async move {
+                            let mut config_guard = config.lock().await;
+                            crate::profiles::rename_profile(&mut config_guard, &old, &new).await?;
+                            Ok((old, new.trim().to_string()))
+                        },
+                        |result: anyhow::Result<(String, String)>| AppMessage::ProfileRenamed(result.map_err(|e| e.to_string()))
+                    );
+                }
+            }
+            AppMessage::ProfileRenamed(result) => match result {
+                Ok((old, new)) => {
+                    for name in &mut self.state.profiles {
+                        if *name == old {
+                            *name = new.clone();
+                        }
+                    }
+                    if self.state.current_profile == old {
+                        self.state.current_profile = new.clone();
+                    }
+                    if self.state.editing_profile == old {
+                        self.state.editing_profile = new;
+                    }
+                    self.state.renaming_profile = None;
+                    self.state.profile_error = None;
+                }
+                Err(e) => self.state.profile_error = Some(e),
+            },
             AppMessage::DeleteProfile(profile) => {
                 if profile != "Default" && self.state.profiles.contains(&profile) {
                     let profile_clone = profile.clone();
-                    let config_for_async = self.state.config.clone();
-                    let config_for_callback = self.state.config.clone();
+                    let config = self.state.config.clone();
                     
                     return Command::perform(
                         async move {
-                            let mut config_guard = config_for_async.lock().await;
+                            let mut config_guard = config.lock().await;
                             if let Err(e) = crate::profiles::remove_profile(&mut config_guard, &profile_clone).await {
                                 eprintln!("Failed to remove profile: {}", e);
                             }
+                            (config_guard.get_profile_names(), config_guard.current_profile.clone())
                         },
-                        move |_| AppMessage::LoadConfig(config_for_callback.clone())
+                        |(profiles, current)| AppMessage::ProfilesUpdated(profiles, current)
                     );
                 }
             }
             AppMessage::SaveSettings => {
+                if self.state.has_errors() {
+                    return Command::none();
+                }
                 let config_path = self.config_path.clone();
                 let state = self.state.clone();
                 
                 return Command::perform(
                     async move {
-                        let mut config_guard = state.config.lock().await;
-                        
-                        // Update config with state values
-                        config_guard.hotkey.key_combination = state.hotkey.clone();
-                        // Update other settings here as needed
-                        
-                        if let Err(e) = config_guard.save(&config_path) {
-                            eprintln!("Failed to save config: {}", e);
+                        {
+                            let mut config_guard = state.config.lock().await;
+
+                            // Update config with state values
+                            state.apply(&mut config_guard)?;
+                            config_guard.save(&config_path)?;
                         }
-                        
-                        AppMessage::LoadConfig(state.config.clone())
+                        crate::background::notify(&state.config, IpcMessage::ConfigUpdate).await
+                            .context("Saved, but the background could not be notified")
                     },
-                    |msg| msg
+                    |result: anyhow::Result<()>| AppMessage::SettingsSaved(result.map_err(|e| format!("{:#}", e)))
                 );
             }
+            AppMessage::SettingsSaved(result) => match result {
+                Ok(()) => {
+                    self.state.status = Some("Settings saved".to_string());
+                    self.state.mark_saved();
+                    if let Some(action) = self.state.pending_action.take() {
+                        return Command::batch(vec![self.run_pending(action), self.load_raw_config()]);
+                    }
+                    let config = self.state.config.clone();
+                    return Command::batch(vec![
+                        self.update(AppMessage::LoadConfig(config)),
+                        self.load_raw_config(),
+                    ]);
+                }
+                Err(e) => {
+                    eprintln!("Failed to save config: {}", e);
+                    self.state.status = Some(format!("Failed to save: {}", e));
+                }
+            },
+            AppMessage::UpdateSetting(key, value) => {
+                let engine_changed = key == "default_bang";
+                self.state.set_setting(key, value);
+                if engine_changed {
+                    return self.find_bang_engine();
+                }
+            }
+            AppMessage::ProfilesUpdated(profiles, current) => {
+                self.state.profiles = profiles;
+                self.state.current_profile = current;
+            }
             AppMessage::ResetSettings => {
                 // Make a copy of the existing config
                 let config = self.state.config.clone();
+                let active_tab = self.state.active_tab;
+                let baseline = std::mem::take(&mut self.state.baseline);
                 self.state = State::new(config);
+                self.state.active_tab = active_tab;
+                // Still compared with what's saved, so the reset shows as unsaved
+                self.state.baseline = baseline;
+            }
+            AppMessage::SelectEditingProfile(profile) => {
+                return Command::batch(vec![
+                    self.load_commands(profile.clone()),
+                    self.load_providers(profile),
+                ]);
             }
+            AppMessage::ProvidersLoaded(profile, mut settings, info) => {
+                self.state.editing_profile = profile;
+                match info {
+                    Ok(info) => {
+                        // Registered providers the profile doesn't mention yet
+                        for provider in &info {
+                            if !settings.iter().any(|s| s.name == provider.name) {
+                                settings.push(ProviderSettings {
+                                    name: provider.name.clone(),
+                                    enabled: true,
+                                    options: Default::default(),
+                                    weight: provider.weight,
+                                    timeout: None,
+                                });
+                            }
+                        }
+                        self.state.provider_info = info;
+                        self.state.provider_status = None;
+                    }
+                    Err(e) => {
+                        self.state.provider_info.clear();
+                        self.state.provider_status = Some(e);
+                    }
+                }
+                self.state.provider_settings = settings;
+            }
+            AppMessage::ToggleProvider(index, enabled) => {
+                if let Some(provider) = self.state.provider_settings.get_mut(index) {
+                    provider.enabled = enabled;
+                }
+            }
+            AppMessage::MoveProvider(index, up) => {
+                let providers = &mut self.state.provider_settings;
+                let target = if up { index.checked_sub(1) } else { Some(index + 1) };
+                if let Some(target) = target.filter(|t| *t < providers.len() && index < providers.len()) {
+                    providers.swap(index, target);
+                }
+            }
+            AppMessage::ProviderOptionChanged(index, key, value) => {
+                if let Some(provider) = self.state.provider_settings.get_mut(index) {
+                    if value.is_empty() {
+                        provider.options.remove(&key);
+                    } else {
+                        provider.options.insert(key, value);
+                    }
+                }
+            }
+            AppMessage::ProviderWeightChanged(index, weight) => {
+                if let Some(provider) = self.state.provider_settings.get_mut(index) {
+                    provider.weight = weight;
+                }
+            }
+            AppMessage::SaveProviders => {
+                let config = self.state.config.clone();
+                let config_path = self.config_path.clone();
+                let profile = self.state.editing_profile.clone();
+                let providers = self.state.provider_settings.clone();
+
+                return Command::perform(
+                    async move {
+                        {
+                            let mut config = config.lock().await;
+                            crate::profiles::set_providers(&mut config, &profile, providers)?;
+                            config.save(&config_path)?;
+                        }
+                        crate::background::notify(&config, IpcMessage::ConfigUpdate).await
+                            .context("Saved, but the background could not be notified")
+                    },
+                    |result: anyhow::Result<()>| AppMessage::ProvidersSaved(result.map_err(|e| format!("{:#}", e)))
+                );
+            }
+            AppMessage::ProvidersSaved(result) => {
+                self.state.provider_status = Some(match result {
+                    Ok(()) => "Providers saved".to_string(),
+                    Err(e) => e,
+                });
+            }
+            AppMessage::CommandsLoaded(profile, commands) => {
+                self.state.editing_profile = profile;
+                self.state.commands = commands;
+                self.state.command_draft = CommandDraft::default();
+                self.state.editing_command = None;
+                self.state.command_error = None;
+            }
+            AppMessage::CommandsSaved(result) => match result {
+                Ok(commands) => {
+                    self.state.commands = commands;
+                    self.state.command_draft = CommandDraft::default();
+                    self.state.editing_command = None;
+                    self.state.command_error = None;
+                }
+                Err(e) => self.state.command_error = Some(e),
+            },
+            AppMessage::EditCommand(index) => {
+                if let Some(command) = self.state.commands.get(index) {
+                    self.state.command_draft = CommandDraft::from_command(command);
+                    self.state.editing_command = Some(index);
+                    self.state.command_error = None;
+                }
+            }
+            AppMessage::CancelCommandEdit => {
+                self.state.command_draft = CommandDraft::default();
+                self.state.editing_command = None;
+                self.state.command_error = None;
+            }
+            AppMessage::CommandNameChanged(value) => {
+                self.state.command_draft.name = value;
+            }
+            AppMessage::CommandUrlChanged(value) => {
+                self.state.command_draft.url = value;
+            }
+            AppMessage::CommandRunChanged(value) => {
+                self.state.command_draft.run = value;
+            }
+            AppMessage::CommandElevatedToggled(elevated) => {
+                self.state.command_draft.elevated = elevated;
+            }
+            AppMessage::CommandDescriptionChanged(value) => {
+                self.state.command_draft.description = value;
+            }
+            AppMessage::CommandKeywordsChanged(value) => {
+                self.state.command_draft.keywords = value;
+            }
+            AppMessage::SaveCommand => {
+                let command = self.state.command_draft.to_command();
+                if let Err(e) = command.validate() {
+                    self.state.command_error = Some(e.to_string());
+                    return Command::none();
+                }
+
+                let index = self.state.editing_command;
+                return self.update_commands(move |config, profile| {
+                    crate::profiles::save_command(config, profile, index, command)
+                });
+            }
+            AppMessage::DeleteCommand(index) => {
+                return self.update_commands(move |config, profile| {
+                    crate::profiles::remove_command(config, profile, index)
+                });
+            }
+            AppMessage::MoveCommand(index, up) => {
+                return self.update_commands(move |config, profile| {
+                    crate::profiles::move_command(config, profile, index, up)
+                });
+            }
+            AppMessage::BangsLoaded(result) => match result {
+                Ok((overlay, usage)) => {
+                    self.state.bang_overlay = overlay;
+                    self.state.bang_usage = usage;
+                }
+                Err(e) => self.state.bang_status = Some(e),
+            },
+            AppMessage::BangPageLoaded(result) => match result {
+                // Replies can overtake each other while the filter is typed
+                Ok(page) if page.query.filter == self.state.bang_filter.trim() => {
+                    self.state.bang_page = page;
+                }
+                Ok(_) => {}
+                Err(e) => self.state.bang_status = Some(format!("Could not list bangs: {}", e)),
+            },
+            AppMessage::BangPageRequested(offset) => {
+                return self.list_bangs(offset);
+            }
+            AppMessage::BangEngineFound(engine) => {
+                self.state.bang_engine = engine;
+            }
+            AppMessage::BangFilterChanged(filter) => {
+                self.state.bang_filter = filter;
+                return self.list_bangs(0);
+            }
+            AppMessage::BangTriggerChanged(value) => {
+                self.state.bang_draft.trigger = value;
+            }
+            AppMessage::BangNameChanged(value) => {
+                self.state.bang_draft.name = value;
+            }
+            AppMessage::BangUrlChanged(value) => {
+                self.state.bang_draft.url = value;
+            }
+            AppMessage::BangAliasesChanged(value) => {
+                self.state.bang_draft.aliases = value;
+            }
+            AppMessage::AddCustomBang => {
+                let draft = &self.state.bang_draft;
+                let aliases: Vec<String> = draft.aliases
+                    .split(',')
+                    .map(|a| a.trim().trim_start_matches('!').to_string())
+                    .filter(|a| !a.is_empty())
+                    .collect();
+
+                // Work on a copy so a bad alias doesn't leave the bang half added
+                let mut overlay = self.state.bang_overlay.clone();
+                let added = shared::bangs::custom_bang(&draft.trigger, &draft.name, &draft.url)
+                    .and_then(|bang| {
+                        let trigger = bang.trigger.clone();
+                        overlay.add(bang)?;
+                        overlay.set_aliases(&trigger, &aliases)
+                    });
+                match added {
+                    Ok(()) => {
+                        self.state.bang_overlay = overlay;
+                        self.state.bang_draft = BangDraft::default();
+                        return self.save_bang_overlay();
+                    }
+                    Err(e) => self.state.bang_status = Some(e.to_string()),
+                }
+            }
+            AppMessage::SiteBangDomainChanged(value) => {
+                self.state.site_bang_draft.domain = value;
+            }
+            AppMessage::SiteBangTriggerChanged(value) => {
+                self.state.site_bang_draft.trigger = value;
+            }
+            AppMessage::AddSiteBang => {
+                let draft = &self.state.site_bang_draft;
+                let added = shared::bangs::site_search_bang(&draft.domain, &draft.trigger, self.state.default_engine())
+                    .and_then(|bang| self.state.bang_overlay.add(bang));
+                match added {
+                    Ok(()) => {
+                        self.state.site_bang_draft = SiteBangDraft::default();
+                        return self.save_bang_overlay();
+                    }
+                    Err(e) => self.state.bang_status = Some(e.to_string()),
+                }
+            }
+            AppMessage::RemoveCustomBang(trigger) => {
+                let overlay = &mut self.state.bang_overlay;
+                overlay.bangs.retain(|b| b.trigger != trigger);
+                overlay.aliases.retain(|_, target| *target != trigger);
+                return self.save_bang_overlay();
+            }
+            AppMessage::SetBangDisabled(trigger, disabled) => {
+                let overlay = &mut self.state.bang_overlay;
+                overlay.disabled.retain(|t| *t != trigger);
+                if disabled {
+                    overlay.disabled.push(trigger);
+                }
+                return self.save_bang_overlay();
+            }
+            AppMessage::BangsSaved(result) => {
+                self.state.bang_status = match result {
+                    Ok(summary) => skipped_bangs(&summary),
+                    Err(e) => Some(e),
+                };
+            }
+            AppMessage::DownloadBangs => {
+                self.state.downloading_bangs = true;
+                self.state.bang_status = None;
+                let config = self.state.config.clone();
+
+                // The background downloads and swaps in the list itself, so
+                // it never searches a half-written bangs.json
+                return Command::perform(
+                    async move {
+                        match crate::background::request(&config, IpcMessage::RefreshBangs).await? {
+                            IpcMessage::BangsRefreshed(refresh) => Ok(refresh),
+                            IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+                            other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+                        }
+                    },
+                    |result: anyhow::Result<BangRefresh>| AppMessage::BangsDownloaded(result.map_err(|e| format!("{:#}", e)))
+                );
+            }
+            AppMessage::BangsDownloaded(result) => {
+                self.state.downloading_bangs = false;
+                self.state.bang_status = Some(match result {
+                    Ok(refresh) => match skipped_bangs(&refresh) {
+                        None => format!("Downloaded {} bangs", refresh.count),
+                        Some(skipped) => format!("Downloaded {} bangs. {}", refresh.count, skipped),
+                    },
+                    Err(e) => e,
+                });
+                return self.load_bangs();
+            }
+            AppMessage::ImportBangs(browser) => {
+                self.state.importing_bangs = true;
+                self.state.bang_status = None;
+                let config = self.state.config.clone();
+
+                return Command::perform(
+                    async move {
+                        match crate::background::request(&config, IpcMessage::ImportBangs(browser)).await? {
+                            IpcMessage::BangsImported(imported) => Ok(imported),
+                            IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+                            other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+                        }
+                    },
+                    |result: anyhow::Result<BangRefresh>| AppMessage::BangsImported(result.map_err(|e| format!("{:#}", e)))
+                );
+            }
+            AppMessage::BangsImported(result) => {
+                self.state.importing_bangs = false;
+                self.state.bang_status = Some(match result {
+                    Ok(imported) => match imported.errors.first() {
+                        None => format!("Imported {} search engines", imported.count),
+                        Some(first) => format!(
+                            "Imported {} search engines, skipped {}, e.g. {}",
+                            imported.count,
+                            imported.errors.len(),
+                            first
+                        ),
+                    },
+                    Err(e) => e,
+                });
+                return self.load_bangs();
+            }
+            AppMessage::RawConfigLoaded(text) => {
+                self.state.raw_config = crate::raw_config::RawConfig::new(&text);
+            }
+            AppMessage::RawConfigAction(action) => {
+                self.state.raw_config.perform(action);
+            }
+            AppMessage::ValidateRawConfig => {
+                self.state.raw_config.validate();
+            }
+            AppMessage::RevertRawConfig => {
+                return self.load_raw_config();
+            }
+            AppMessage::SaveRawConfig => {
+                // Checked again, the text may have changed since Validate
+                let Some(parsed) = self.state.raw_config.validate() else {
+                    return Command::none();
+                };
+                let text = self.state.raw_config.content.text();
+                let config = self.state.config.clone();
+                let config_path = self.config_path.clone();
+
+                return Command::perform(
+                    async move {
+                        std::fs::write(&config_path, text)
+                            .with_context(|| format!("Failed to write {:?}", config_path))?;
+                        *config.lock().await = parsed;
+                        crate::background::notify(&config, IpcMessage::ConfigUpdate).await
+                            .context("Saved, but the background could not be notified")
+                    },
+                    |result: anyhow::Result<()>| AppMessage::SettingsSaved(result.map_err(|e| format!("{:#}", e)))
+                );
+            }
+            AppMessage::LogSourceSelected(source) => {
+                self.state.log_source = source;
+                self.state.log_lines.clear();
+                return self.load_logs();
+            }
+            AppMessage::LogLevelSelected(level) => {
+                self.state.log_level = level;
+            }
+            AppMessage::LogSearchChanged(search) => {
+                self.state.log_search = search;
+            }
+            AppMessage::RefreshLogs => {
+                return self.load_logs();
+            }
+            AppMessage::PluginsLoaded(result) => match result {
+                Ok(plugins) => {
+                    self.state.plugins = plugins;
+                    self.state.plugin_error = None;
+                }
+                Err(e) => {
+                    self.state.plugins.clear();
+                    self.state.plugin_error = Some(e);
+                }
+            },
+            AppMessage::LogsLoaded(result) => match result {
+                Ok(lines) => self.state.log_lines = lines,
+                Err(e) => self.state.log_status = Some(e),
+            },
+            AppMessage::WriteDiagnostics => {
+                let dir = self.config_dir();
+                let config = self.state.config.clone();
+
+                return Command::perform(
+                    async move {
+                        let config = config.lock().await;
+                        crate::logs::write_diagnostics(&dir, &config)
+                    },
+                    |result| AppMessage::DiagnosticsWritten(result.map_err(|e| format!("{:#}", e)))
+                );
+            }
+            AppMessage::DiagnosticsWritten(result) => match result {
+                Ok(path) => {
+                    let path = path.to_string_lossy().to_string();
+                    self.state.log_status = Some(format!("Saved {} (path copied to clipboard)", path));
+                    return iced::clipboard::write(path);
+                }
+                Err(e) => self.state.log_status = Some(format!("Failed to write diagnostics: {}", e)),
+            },
             AppMessage::LoadConfig(config) => {
-                // Update State with loaded config
-                self.state = State::new(config.clone());
-                
                 // Load the actual settings values
                 return Command::perform(
                     async move {
-                        let mut state = State::new(config.clone());
+                        let mut state = State::new(config);
                         if let Err(e) = state.load().await {
                             eprintln!("Failed to load settings: {}", e);
                         }
-                        state
+                        Box::new(state)
                     },
-                    |state| {
-                        let message = AppMessage::LoadConfig(state.config.clone());
-                        // Update the app state with the loaded state
-                        message
-                    }
+                    AppMessage::SettingsLoaded
                 );
             }
+            AppMessage::SettingsLoaded(state) => {
+                // Keep what is on screen, everything else comes from the config
+                let active_tab = self.state.active_tab;
+                let status = self.state.status.take();
+                let raw_config = std::mem::take(&mut self.state.raw_config);
+                self.state = *state;
+                self.state.active_tab = active_tab;
+                self.state.status = status;
+                self.state.raw_config = raw_config;
+            }
         }
         Command::none()
     }
@@ -201,6 +1070,30 @@ impl Application for App {
         self.ui.view(&self.state)
     }
 
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![event::listen_with(|event, _status| match event {
+            Event::Window(id, window::Event::CloseRequested) => Some(AppMessage::CloseRequested(id)),
+            _ => None,
+        })];
+
+        if self.state.recording_hotkey {
+            // Captured presses are wanted too, the recorder has no text input
+            subscriptions.push(event::listen_with(|event, _status| match event {
+                Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                    Some(AppMessage::HotkeyKeyPressed(key, modifiers))
+                }
+                _ => None,
+            }));
+        }
+
+        // Follow the log while it's on screen
+        if self.state.active_tab == Tab::Logs {
+            subscriptions.push(iced::time::every(Duration::from_secs(2)).map(|_| AppMessage::RefreshLogs));
+        }
+
+        Subscription::batch(subscriptions)
+    }
+
     fn theme(&self) -> Theme {
         self.state.theme()
     }
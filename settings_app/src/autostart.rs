@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+// Starts the background service at login through the platform's own
+// mechanism: an XDG autostart entry, a LaunchAgent, or the Startup folder
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn entry_path() -> Result<PathBuf> {
+    let dirs = directories::BaseDirs::new().context("Failed to get home directory")?;
+    Ok(dirs.config_dir().join("autostart").join("orion.desktop"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn entry_contents(exe: &str) -> String {
+    format!(
+        "[Desktop Entry]\nType=Application\nName=Orion\nComment=Orion launcher service\nExec=\"{}\"\nX-GNOME-Autostart-enabled=true\n",
+        exe
+    )
+}
+
+#[cfg(target_os = "macos")]
+fn entry_path() -> Result<PathBuf> {
+    let dirs = directories::BaseDirs::new().context("Failed to get home directory")?;
+    Ok(dirs.home_dir().join("Library/LaunchAgents/com.orion.background.plist"))
+}
+
+#[cfg(target_os = "macos")]
+fn entry_contents(exe: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>com.orion.background</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        exe
+    )
+}
+
+#[cfg(windows)]
+fn entry_path() -> Result<PathBuf> {
+    let dirs = directories::BaseDirs::new().context("Failed to get home directory")?;
+    Ok(dirs.config_dir().join(r"Microsoft\Windows\Start Menu\Programs\Startup\orion.cmd"))
+}
+
+#[cfg(windows)]
+fn entry_contents(exe: &str) -> String {
+    format!("@start \"\" \"{}\"\r\n", exe)
+}
+
+// The background binary is installed next to the settings app
+fn background_exe() -> Result<PathBuf> {
+    let exe = std::env::current_exe().context("Failed to locate the settings app")?;
+    let background = exe.with_file_name(format!("background{}", std::env::consts::EXE_SUFFIX));
+    if !background.exists() {
+        return Err(anyhow::anyhow!("Background service not found at {:?}", background));
+    }
+    Ok(background)
+}
+
+pub fn is_installed() -> bool {
+    entry_path().map(|path| path.exists()).unwrap_or(false)
+}
+
+pub fn install() -> Result<()> {
+    let path = entry_path()?;
+    let exe = background_exe()?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, entry_contents(&exe.to_string_lossy()))
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+pub fn uninstall() -> Result<()> {
+    let path = entry_path()?;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove {:?}", path))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Installs or removes the login entry, returning whether it is now installed
+pub fn set_enabled(enabled: bool) -> Result<bool> {
+    if enabled {
+        install()?;
+    } else {
+        uninstall()?;
+    }
+    Ok(is_installed())
+}
@@ -0,0 +1,24 @@
+use anyhow::Result;
+use shared::config::Config;
+use shared::ipc::IpcClient;
+use shared::models::IpcMessage;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+// Sends a one-off message to the background service over its IPC socket
+pub async fn notify(config: &Arc<Mutex<Config>>, message: IpcMessage) -> Result<()> {
+    let socket_path = config.lock().await.ipc_socket_path.clone();
+
+    let mut client = IpcClient::new(&socket_path)?;
+    client.send_message_async(&message).await?;
+    Ok(())
+}
+
+// Sends a message to the background service and waits for its reply
+pub async fn request(config: &Arc<Mutex<Config>>, message: IpcMessage) -> Result<IpcMessage> {
+    let socket_path = config.lock().await.ipc_socket_path.clone();
+
+    let mut client = IpcClient::new(&socket_path)?;
+    client.send_message_async(&message).await?;
+    Ok(client.receive_message_async().await?)
+}
@@ -0,0 +1,39 @@
+use anyhow::Result;
+use shared::bangs::{BangOverlay, BangUsage};
+use shared::config::Config;
+use shared::models::{Bang, BangListQuery, BangPage, IpcMessage};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+// Rows shown at once in the bangs list, the downloaded set has thousands
+pub const PAGE_SIZE: usize = 100;
+
+// The user's overlay, so the manager can tell custom bangs from built-in
+// ones, along with how often each has been used. The bangs themselves are
+// listed by the background, see `list`.
+pub fn load() -> Result<(BangOverlay, BangUsage)> {
+    let overlay = BangOverlay::load()?;
+    let usage = BangUsage::load()?;
+    Ok((overlay, usage))
+}
+
+// A page of bangs from the background, disabled ones included
+pub async fn list(config: &Arc<Mutex<Config>>, query: BangListQuery) -> Result<BangPage> {
+    match crate::background::request(config, IpcMessage::ListBangs(query)).await? {
+        IpcMessage::BangList(page) => Ok(page),
+        IpcMessage::Error(e) => Err(anyhow::anyhow!(e)),
+        other => Err(anyhow::anyhow!("Unexpected reply: {:?}", other)),
+    }
+}
+
+// The bang with exactly this trigger, disabled or not
+pub async fn find(config: &Arc<Mutex<Config>>, trigger: &str) -> Result<Option<Bang>> {
+    let query = BangListQuery {
+        filter: trigger.to_string(),
+        offset: 0,
+        limit: 1,
+    };
+    // Exact trigger matches are listed first
+    let page = list(config, query).await?;
+    Ok(page.bangs.into_iter().find(|b| b.trigger == trigger))
+}
@@ -0,0 +1,52 @@
+use iced::keyboard::{Key, Modifiers};
+use shared::hotkey::KeyCombo;
+
+// Builds a combo from a key pressed while recording. Lone modifier presses
+// give None so recording carries on until the actual key arrives.
+pub fn combo_from_key(key: &Key, modifiers: Modifiers) -> Option<KeyCombo> {
+    let key = match key {
+        Key::Character(c) => KeyCombo::canonical_key(c.as_str())?,
+        // Named keys print as e.g. `ArrowUp` or `PageDown`, which are
+        // accepted aliases of the canonical names
+        Key::Named(named) => KeyCombo::canonical_key(&format!("{:?}", named))?,
+        Key::Unidentified => return None,
+    };
+
+    Some(KeyCombo {
+        ctrl: modifiers.control(),
+        alt: modifiers.alt(),
+        shift: modifiers.shift(),
+        meta: modifiers.logo(),
+        key,
+    })
+}
+
+// Rejects combos that would swallow ordinary typing system-wide
+pub fn check_global(combo: &KeyCombo) -> Result<(), String> {
+    let has_modifier = combo.ctrl || combo.alt || combo.meta;
+    let is_function_key = combo.key.len() > 1 && combo.key.starts_with('F');
+
+    if has_modifier || is_function_key {
+        Ok(())
+    } else {
+        Err(format!("{} needs Ctrl, Alt or Super to work as a global shortcut", combo))
+    }
+}
+
+// Modifier names in the form the background's hotkey listener expects
+pub fn modifier_names(combo: &KeyCombo) -> Vec<String> {
+    let mut names = Vec::new();
+    if combo.ctrl {
+        names.push("Ctrl".to_string());
+    }
+    if combo.alt {
+        names.push("Alt".to_string());
+    }
+    if combo.shift {
+        names.push("Shift".to_string());
+    }
+    if combo.meta {
+        names.push("Super".to_string());
+    }
+    names
+}
@@ -0,0 +1,194 @@
+use anyhow::{Context, Result};
+use shared::config::Config;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// Only the end of a log is read, large logs would stall the viewer
+const TAIL_BYTES: u64 = 256 * 1024;
+pub const TAIL_LINES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogSource {
+    #[default]
+    Background,
+    Popup,
+}
+
+impl LogSource {
+    pub const ALL: [LogSource; 2] = [LogSource::Background, LogSource::Popup];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            LogSource::Background => "background.log",
+            LogSource::Popup => "popup.log",
+        }
+    }
+}
+
+impl std::fmt::Display for LogSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.file_name())
+    }
+}
+
+// Ordered from most to least severe, so a filter keeps levels <= itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const FILTERS: [LogLevel; 3] = [LogLevel::Error, LogLevel::Warn, LogLevel::Info];
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ERROR" => Some(LogLevel::Error),
+            "WARN" => Some(LogLevel::Warn),
+            "INFO" => Some(LogLevel::Info),
+            "DEBUG" => Some(LogLevel::Debug),
+            "TRACE" => Some(LogLevel::Trace),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LogLevel::Error => write!(f, "Errors"),
+            LogLevel::Warn => write!(f, "Warnings and up"),
+            LogLevel::Info => write!(f, "Info and up"),
+            LogLevel::Debug => write!(f, "Debug and up"),
+            LogLevel::Trace => write!(f, "Everything"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    // None for lines not written by shared::logging, e.g. panics
+    pub level: Option<LogLevel>,
+    pub text: String,
+}
+
+impl LogLine {
+    // Lines look like `[2024-01-01 12:00:00] INFO - message`
+    fn parse(text: &str) -> Self {
+        let level = text
+            .split_once("] ")
+            .and_then(|(_, rest)| rest.split_once(" - "))
+            .and_then(|(level, _)| LogLevel::parse(level));
+
+        Self {
+            level,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn matches(&self, filter: LogLevel, search: &str) -> bool {
+        self.level.map_or(true, |level| level <= filter)
+            && (search.is_empty() || self.text.to_lowercase().contains(&search.to_lowercase()))
+    }
+}
+
+// Last lines of a log, an empty list when it hasn't been written yet
+pub fn tail(dir: &Path, source: LogSource) -> Result<Vec<LogLine>> {
+    let path = dir.join(source.file_name());
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {:?}", path)),
+    };
+
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(TAIL_BYTES);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let content = String::from_utf8_lossy(&bytes);
+
+    let mut lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+    // The first line is likely cut in half when reading from an offset
+    if start > 0 && !lines.is_empty() {
+        lines.remove(0);
+    }
+
+    let skip = lines.len().saturating_sub(TAIL_LINES);
+    Ok(lines[skip..].iter().map(|l| LogLine::parse(l)).collect())
+}
+
+// Zips both logs and the config, with paths, provider options and command
+// environments redacted, into the config directory for attaching to bug
+// reports
+pub fn write_diagnostics(dir: &Path, config: &Config) -> Result<PathBuf> {
+    let name = format!("orion-diagnostics-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let path = dir.join(name);
+
+    let mut zip = zip::ZipWriter::new(File::create(&path)?);
+    let options = zip::write::FileOptions::default();
+
+    for source in LogSource::ALL {
+        if let Ok(content) = std::fs::read(dir.join(source.file_name())) {
+            zip.start_file(source.file_name(), options)?;
+            zip.write_all(&content)?;
+        }
+    }
+
+    zip.start_file("config.toml", options)?;
+    zip.write_all(redacted_config(config)?.as_bytes())?;
+    zip.finish()?;
+
+    Ok(path)
+}
+
+fn redacted_config(config: &Config) -> Result<String> {
+    let mut value = toml::Value::try_from(config)?;
+
+    // Provider options may hold tokens or private paths, and environment
+    // variables set for commands are where tokens like GITHUB_TOKEN go
+    redact_values(value.get_mut("exec").and_then(|exec| exec.get_mut("env")));
+    if let Some(profiles) = value.get_mut("profiles").and_then(|p| p.as_array_mut()) {
+        for profile in profiles {
+            let providers = profile.get_mut("providers").and_then(|p| p.as_array_mut());
+            for provider in providers.into_iter().flatten() {
+                redact_values(provider.get_mut("options"));
+            }
+            redact_command_env(profile);
+        }
+    }
+    if let Some(prefixes) = value.get_mut("command_prefixes").and_then(|p| p.as_array_mut()) {
+        for prefix in prefixes {
+            redact_command_env(prefix);
+        }
+    }
+
+    let mut content = toml::to_string_pretty(&value)?;
+    if let Some(home) = directories::BaseDirs::new().map(|d| d.home_dir().to_string_lossy().to_string()) {
+        if !home.is_empty() {
+            content = content.replace(&home, "~");
+        }
+    }
+    Ok(content)
+}
+
+// Keeps the keys of a table, so what's set still shows
+fn redact_values(table: Option<&mut toml::Value>) {
+    let values = table.and_then(|t| t.as_table_mut());
+    for (_, value) in values.into_iter().flatten() {
+        *value = toml::Value::String("<redacted>".to_string());
+    }
+}
+
+fn redact_command_env(parent: &mut toml::Value) {
+    let commands = parent.get_mut("commands").and_then(|c| c.as_array_mut());
+    for command in commands.into_iter().flatten() {
+        redact_values(command.get_mut("env"));
+    }
+}
@@ -2,11 +2,24 @@ use anyhow::Result;
 use iced::{Settings, Application};
 
 mod app;
+mod autostart;
+mod background;
+mod bangs;
+mod hotkey;
+mod logs;
 mod ui;
 mod state;
 mod profiles;
+mod raw_config;
 
 fn main() -> Result<()> {
-    app::App::run(Settings::default())?;
+    app::App::run(Settings {
+        window: iced::window::Settings {
+            // Closing is confirmed first when there are unsaved changes
+            exit_on_close_request: false,
+            ..Default::default()
+        },
+        ..Settings::default()
+    })?;
     Ok(())
 }
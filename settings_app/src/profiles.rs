@@ -1,4 +1,4 @@
-use shared::config::{Profile, Config};
+use shared::config::{Command, Profile, ProviderSettings, Config};
 use anyhow::Result;
 
 #[allow(dead_code)]
@@ -7,11 +7,15 @@ pub fn get_profile_list(profiles: &[Profile]) -> Vec<String> {
 }
 
 pub async fn add_profile(config: &mut Config, name: String) -> Result<()> {
-    config.add_profile(name)
+    Ok(config.add_profile(name)?)
 }
 
 pub async fn remove_profile(config: &mut Config, name: &str) -> Result<()> {
-    config.remove_profile(name)
+    Ok(config.remove_profile(name)?)
+}
+
+pub async fn rename_profile(config: &mut Config, old: &str, new: &str) -> Result<()> {
+    Ok(config.rename_profile(old, new)?)
 }
 
 pub async fn select_profile(config: &mut Config, name: &str) -> Result<()> {
@@ -22,3 +26,75 @@ pub async fn select_profile(config: &mut Config, name: &str) -> Result<()> {
         Err(anyhow::anyhow!("Profile '{}' not found", name))
     }
 }
+
+fn profile_mut<'a>(config: &'a mut Config, name: &str) -> Result<&'a mut Profile> {
+    config.profiles
+        .iter_mut()
+        .find(|p| p.name == name)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))
+}
+
+pub fn profile_commands(config: &Config, name: &str) -> Result<Vec<Command>> {
+    config.profiles
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| p.commands.clone())
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))
+}
+
+// Adds a command, or replaces the one at `index` when editing
+pub fn save_command(config: &mut Config, profile: &str, index: Option<usize>, command: Command) -> Result<()> {
+    command.validate()?;
+    let commands = &mut profile_mut(config, profile)?.commands;
+
+    let duplicate = commands
+        .iter()
+        .enumerate()
+        .any(|(i, c)| Some(i) != index && c.name.eq_ignore_ascii_case(&command.name));
+    if duplicate {
+        return Err(anyhow::anyhow!("A command named '{}' already exists", command.name));
+    }
+
+    match index {
+        Some(i) if i < commands.len() => commands[i] = command,
+        Some(i) => return Err(anyhow::anyhow!("No command at position {}", i)),
+        None => commands.push(command),
+    }
+    Ok(())
+}
+
+pub fn remove_command(config: &mut Config, profile: &str, index: usize) -> Result<()> {
+    let commands = &mut profile_mut(config, profile)?.commands;
+    if index >= commands.len() {
+        return Err(anyhow::anyhow!("No command at position {}", index));
+    }
+    commands.remove(index);
+    Ok(())
+}
+
+// Swaps a command with its neighbour above (`up`) or below
+pub fn move_command(config: &mut Config, profile: &str, index: usize, up: bool) -> Result<()> {
+    let commands = &mut profile_mut(config, profile)?.commands;
+    let target = if up { index.checked_sub(1) } else { Some(index + 1) };
+
+    match target {
+        Some(target) if target < commands.len() && index < commands.len() => {
+            commands.swap(index, target);
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn profile_providers(config: &Config, name: &str) -> Result<Vec<ProviderSettings>> {
+    config.profiles
+        .iter()
+        .find(|p| p.name == name)
+        .map(|p| p.providers.clone())
+        .ok_or_else(|| anyhow::anyhow!("Profile '{}' not found", name))
+}
+
+pub fn set_providers(config: &mut Config, profile: &str, providers: Vec<ProviderSettings>) -> Result<()> {
+    profile_mut(config, profile)?.providers = providers;
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use iced::widget::text_editor;
+use iced::advanced::text::Highlighter;
+use shared::config::Config;
+use std::ops::Range;
+
+// Raw TOML being edited in the Advanced tab, kept with the outcome of the
+// last check so saving can wait for a clean validation
+#[derive(Default)]
+pub struct RawConfig {
+    pub content: text_editor::Content,
+    // Zero-based lines the last check blamed
+    pub error_lines: Vec<usize>,
+    pub message: Option<String>,
+    // True once the current text has passed `check`
+    pub valid: bool,
+}
+
+impl RawConfig {
+    pub fn new(text: &str) -> Self {
+        Self {
+            content: text_editor::Content::with_text(text),
+            ..Default::default()
+        }
+    }
+
+    pub fn perform(&mut self, action: text_editor::Action) {
+        if action.is_edit() {
+            self.valid = false;
+        }
+        self.content.perform(action);
+    }
+
+    // Parses and validates the text, recording which lines are at fault
+    pub fn validate(&mut self) -> Option<Config> {
+        let text = self.content.text();
+        match check(&text) {
+            Ok(config) => {
+                self.error_lines.clear();
+                self.message = Some("Config is valid".to_string());
+                self.valid = true;
+                Some(config)
+            }
+            Err((message, lines)) => {
+                self.error_lines = lines;
+                self.message = Some(message);
+                self.valid = false;
+                None
+            }
+        }
+    }
+}
+
+// The editor content can't be cloned, so copies start from the same text
+impl Clone for RawConfig {
+    fn clone(&self) -> Self {
+        Self {
+            content: text_editor::Content::with_text(&self.content.text()),
+            error_lines: self.error_lines.clone(),
+            message: self.message.clone(),
+            valid: self.valid,
+        }
+    }
+}
+
+impl std::fmt::Debug for RawConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawConfig")
+            .field("error_lines", &self.error_lines)
+            .field("message", &self.message)
+            .field("valid", &self.valid)
+            .finish()
+    }
+}
+
+fn check(text: &str) -> Result<Config, (String, Vec<usize>)> {
+    let config: Config = toml::from_str(text).map_err(|e| {
+        let lines = e.span()
+            .map(|span| vec![text[..span.start.min(text.len())].matches('\n').count()])
+            .unwrap_or_default();
+        (e.message().to_string(), lines)
+    })?;
+
+    config.validate().map_err(|e| {
+        let message = format!("{:#}", e);
+        (message.clone(), lines_for_error(text, &message))
+    })?;
+
+    Ok(config)
+}
+
+// Validation errors carry no position, so blame the lines assigning a key
+// the message names, e.g. `max_results must be between 1 and 100`, or
+// holding a value it quotes, e.g. `Invalid popup key binding 'Ctrl+'`
+fn lines_for_error(text: &str, message: &str) -> Vec<usize> {
+    let words: Vec<&str> = message
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| w.len() > 2)
+        .collect();
+    let quoted: Vec<String> = message
+        .split('\'')
+        .skip(1)
+        .step_by(2)
+        .filter(|q| !q.is_empty())
+        .map(|q| format!("\"{}\"", q))
+        .collect();
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let names_key = line.split_once('=')
+                .map_or(false, |(key, _)| words.contains(&key.trim()));
+            names_key || quoted.iter().any(|q| line.contains(q.as_str()))
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+// Marks whole lines reported by the last check
+pub struct ErrorLines {
+    lines: Vec<usize>,
+    current: usize,
+}
+
+impl Highlighter for ErrorLines {
+    type Settings = Vec<usize>;
+    type Highlight = ();
+    type Iterator<'a> = std::option::IntoIter<(Range<usize>, ())>;
+
+    fn new(settings: &Self::Settings) -> Self {
+        Self {
+            lines: settings.clone(),
+            current: 0,
+        }
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        self.lines = new_settings.clone();
+        self.current = 0;
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current = self.current.min(line);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        let is_error = self.lines.contains(&self.current);
+        self.current += 1;
+        is_error.then_some((0..line.len(), ())).into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.current
+    }
+}
@@ -3,14 +3,27 @@ use shared::config;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use iced::Theme;
+use shared::bangs::{BangOverlay, BangUsage};
+use shared::config::{AppearanceConfig, BangPosition, ProviderSettings, ThemeMode};
+use shared::hotkey::KeyCombo;
+use shared::models::{Bang, BangPage, ExecOptions, ProviderInfo};
+use shared::plugin::PluginStatus;
+use crate::logs::{LogLevel, LogLine, LogSource};
+use crate::raw_config::RawConfig;
+use shared::theme::{PopupTheme, Rgb, DEFAULT_CORNER_RADIUS, DEFAULT_FONT_SIZE};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Tab {
     #[default]
     General,
     Hotkeys,
+    Commands,
+    Providers,
+    Plugins,
+    Bangs,
     Appearance,
     Advanced,
+    Logs,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -21,19 +34,158 @@ pub enum AppTheme {
     Dark,
 }
 
-#[derive(Clone)]
+// Form contents of the command editor, keywords kept as typed
+#[derive(Debug, Clone, Default)]
+pub struct CommandDraft {
+    pub name: String,
+    pub url: String,
+    pub run: String,
+    pub elevated: bool,
+    // Not edited here, but kept when the command is saved
+    pub exec: ExecOptions,
+    pub description: String,
+    pub keywords: String,
+}
+
+impl CommandDraft {
+    pub fn from_command(command: &config::Command) -> Self {
+        Self {
+            name: command.name.clone(),
+            url: command.url.clone(),
+            run: command.run.clone().unwrap_or_default(),
+            elevated: command.elevated,
+            exec: command.exec.clone(),
+            description: command.description.clone(),
+            keywords: command.keywords.join(", "),
+        }
+    }
+
+    pub fn to_command(&self) -> config::Command {
+        config::Command {
+            name: self.name.trim().to_string(),
+            url: self.url.trim().to_string(),
+            run: Some(self.run.trim().to_string()).filter(|run| !run.is_empty()),
+            elevated: self.elevated,
+            exec: self.exec.clone(),
+            description: self.description.trim().to_string(),
+            keywords: self.keywords
+                .split(',')
+                .map(|k| k.trim().to_string())
+                .filter(|k| !k.is_empty())
+                .collect(),
+        }
+    }
+}
+
+// Something put on hold until unsaved changes are saved or discarded
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingAction {
+    Exit,
+    SelectProfile(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BangDraft {
+    pub trigger: String,
+    pub name: String,
+    pub url: String,
+    // Comma-separated extra triggers
+    pub aliases: String,
+}
+
+// Fields of the site search form in the bangs tab
+#[derive(Debug, Clone, Default)]
+pub struct SiteBangDraft {
+    pub domain: String,
+    pub trigger: String,
+}
+
+// Default accent, the first of the appearance tab's swatches
+pub const DEFAULT_ACCENT: Color = Color::from_rgb(0.35, 0.56, 0.98);
+
+pub fn color_to_hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+pub fn color_from_hex(hex: &str) -> Option<Color> {
+    Rgb::from_hex(hex).map(rgb_to_color)
+}
+
+pub fn rgb_to_color(Rgb(r, g, b): Rgb) -> Color {
+    Color::from_rgb8(r, g, b)
+}
+
+#[derive(Debug, Clone)]
 pub struct State {
     pub config: Arc<Mutex<config::Config>>,
     pub active_tab: Tab,
     pub profiles: Vec<String>,
     pub current_profile: String,
     pub new_profile_name: String,
+    // Profile being renamed and the name typed so far
+    pub renaming_profile: Option<(String, String)>,
+    pub profile_error: Option<String>,
     pub voice_enabled: bool,
+    // Whether the login entry is installed, applied right away rather than on save
+    pub autostart: bool,
+    pub autostart_error: Option<String>,
     pub hotkey: String,
+    pub recording_hotkey: bool,
+    // Why the stored or last recorded hotkey can't be used
+    pub hotkey_error: Option<String>,
+    // Profile whose commands and providers are being edited
+    pub editing_profile: String,
+    pub commands: Vec<config::Command>,
+    pub command_draft: CommandDraft,
+    // Index of the command being edited, None while adding a new one
+    pub editing_command: Option<usize>,
+    pub command_error: Option<String>,
+    // Providers registered in the background, empty when it can't be reached
+    pub provider_info: Vec<ProviderInfo>,
+    // Every known provider of the editing profile, in priority order
+    pub provider_settings: Vec<ProviderSettings>,
+    pub provider_status: Option<String>,
+    // Plugins the background found at startup, loaded or not
+    pub plugins: Vec<PluginStatus>,
+    pub plugin_error: Option<String>,
+    // The page of bangs listed by the background for the filter
+    pub bang_page: BangPage,
+    // The bang the default bang setting named when last looked up
+    pub bang_engine: Option<Bang>,
+    pub bang_overlay: BangOverlay,
+    pub bang_usage: BangUsage,
+    pub bang_filter: String,
+    pub bang_draft: BangDraft,
+    pub site_bang_draft: SiteBangDraft,
+    // Outcome of the last bang change or download
+    pub bang_status: Option<String>,
+    pub downloading_bangs: bool,
+    pub importing_bangs: bool,
+    pub log_source: LogSource,
+    pub log_level: LogLevel,
+    pub log_search: String,
+    // Tail of the selected log, refreshed while the logs tab is open
+    pub log_lines: Vec<LogLine>,
+    pub log_status: Option<String>,
     pub theme: AppTheme,
     pub sensitivity: f32,
     pub accent_color: Color,
+    // Popup theme overrides; empty colors and font fall back to the theme's
+    pub popup_background: String,
+    pub popup_text: String,
+    pub popup_font: String,
+    pub popup_font_size: f32,
+    pub popup_radius: f32,
+    pub popup_opacity: f32,
     pub settings: Vec<(String, String)>,
+    // Saved values as of the last load, see `saved_fields`
+    pub baseline: Vec<(String, String)>,
+    pub pending_action: Option<PendingAction>,
+    // config.toml as text, for settings the UI doesn't cover
+    pub raw_config: RawConfig,
+    // Outcome of the last save
+    pub status: Option<String>,
 }
 
 impl State {
@@ -45,12 +197,219 @@ impl State {
             profiles: vec!["Default".to_string()],
             current_profile: "Default".to_string(),
             new_profile_name: String::new(),
-            voice_enabled: true,
+            renaming_profile: None,
+            profile_error: None,
+            voice_enabled: false,
+            autostart: false,
+            autostart_error: None,
             hotkey: "Alt+Space".to_string(),
+            recording_hotkey: false,
+            hotkey_error: None,
+            editing_profile: String::new(),
+            commands: Vec::new(),
+            command_draft: CommandDraft::default(),
+            editing_command: None,
+            command_error: None,
+            provider_info: Vec::new(),
+            provider_settings: Vec::new(),
+            provider_status: None,
+            plugins: Vec::new(),
+            plugin_error: None,
+            bang_page: BangPage::default(),
+            bang_engine: None,
+            bang_overlay: BangOverlay::default(),
+            bang_usage: BangUsage::default(),
+            bang_filter: String::new(),
+            bang_draft: BangDraft::default(),
+            site_bang_draft: SiteBangDraft::default(),
+            bang_status: None,
+            downloading_bangs: false,
+            importing_bangs: false,
+            log_source: LogSource::default(),
+            log_level: LogLevel::default(),
+            log_search: String::new(),
+            log_lines: Vec::new(),
+            log_status: None,
             theme: AppTheme::System,
             sensitivity: 0.7,
-            accent_color: Color::from_rgb(0.4, 0.4, 0.9),
-            settings: Vec::new(),
+            accent_color: DEFAULT_ACCENT,
+            popup_background: String::new(),
+            popup_text: String::new(),
+            popup_font: String::new(),
+            popup_font_size: DEFAULT_FONT_SIZE,
+            popup_radius: DEFAULT_CORNER_RADIUS,
+            popup_opacity: 1.0,
+            settings: vec![
+                ("max_results".to_string(), "10".to_string()),
+                ("search_delay".to_string(), "200".to_string()),
+                ("provider_timeout".to_string(), "1000".to_string()),
+                ("cache_ttl".to_string(), "30000".to_string()),
+                ("default_bang".to_string(), String::new()),
+                ("bang_categories".to_string(), String::new()),
+                ("bang_deny".to_string(), String::new()),
+                ("bang_position".to_string(), BangPosition::default().as_str().to_string()),
+                ("bang_ignore_case".to_string(), "false".to_string()),
+                ("bang_require_prefix".to_string(), "false".to_string()),
+                ("speak_after_voice".to_string(), "false".to_string()),
+                ("speech_rate".to_string(), String::new()),
+            ],
+            baseline: Vec::new(),
+            pending_action: None,
+            raw_config: RawConfig::default(),
+            status: None,
+        }
+    }
+
+    // Writes every setting shown in the UI into the config
+    pub fn apply(&self, config: &mut config::Config) -> anyhow::Result<()> {
+        config.hotkey.key_combination = self.hotkey.clone();
+        if let Ok(combo) = KeyCombo::parse(&self.hotkey) {
+            config.hotkey.modifiers = crate::hotkey::modifier_names(&combo);
+        }
+
+        self.apply_appearance(&mut config.appearance);
+
+        config.voice.enabled = self.voice_enabled;
+        config.voice.sensitivity = self.sensitivity;
+
+        config.update_settings(self.settings.clone())?;
+        Ok(())
+    }
+
+    // Only values that differ from the defaults are written, so theme
+    // defaults can change without touching saved configs
+    fn apply_appearance(&self, appearance: &mut AppearanceConfig) {
+        appearance.theme = match self.theme {
+            AppTheme::System => ThemeMode::System,
+            AppTheme::Light => ThemeMode::Light,
+            AppTheme::Dark => ThemeMode::Dark,
+        };
+        appearance.accent_color = Some(color_to_hex(self.accent_color));
+
+        let hex = |value: &str| Rgb::from_hex(value).map(|rgb| rgb.to_string());
+        appearance.background_color = hex(&self.popup_background);
+        appearance.text_color = hex(&self.popup_text);
+        appearance.font = Some(self.popup_font.trim().to_string()).filter(|f| !f.is_empty());
+        appearance.font_size = Some(self.popup_font_size).filter(|s| *s != DEFAULT_FONT_SIZE);
+        appearance.corner_radius = Some(self.popup_radius).filter(|r| *r != DEFAULT_CORNER_RADIUS);
+        appearance.opacity = Some(self.popup_opacity).filter(|o| *o < 1.0);
+    }
+
+    // The popup look as it would be after saving, for the live preview
+    pub fn popup_theme(&self) -> PopupTheme {
+        let mut appearance = AppearanceConfig::default();
+        self.apply_appearance(&mut appearance);
+        PopupTheme::from_appearance(&appearance)
+    }
+
+    // Every value `apply` writes, as text, keyed by the label shown when
+    // it has unsaved edits
+    fn saved_fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![
+            ("Hotkey".to_string(), self.hotkey.clone()),
+            ("Voice".to_string(), self.voice_enabled.to_string()),
+            ("Voice sensitivity".to_string(), self.sensitivity.to_string()),
+            ("Theme".to_string(), self.theme.to_string()),
+            ("Accent color".to_string(), color_to_hex(self.accent_color)),
+            ("Popup background".to_string(), self.popup_background.clone()),
+            ("Popup text color".to_string(), self.popup_text.clone()),
+            ("Popup font".to_string(), self.popup_font.clone()),
+            ("Popup font size".to_string(), self.popup_font_size.to_string()),
+            ("Corner radius".to_string(), self.popup_radius.to_string()),
+            ("Opacity".to_string(), self.popup_opacity.to_string()),
+        ];
+        fields.extend(self.settings.iter().cloned());
+        fields
+    }
+
+    // Remembers the current values as saved
+    pub fn mark_saved(&mut self) {
+        self.baseline = self.saved_fields();
+    }
+
+    pub fn dirty_fields(&self) -> Vec<String> {
+        self.saved_fields()
+            .into_iter()
+            .filter(|field| !self.baseline.contains(field))
+            .map(|(label, _)| label)
+            .collect()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty_fields().is_empty()
+    }
+
+    // Problem with the hotkey as it stands, None when it can be saved
+    pub fn hotkey_validation(&self) -> Option<String> {
+        match KeyCombo::parse(&self.hotkey) {
+            Ok(combo) => crate::hotkey::check_global(&combo).err(),
+            Err(e) => Some(e.to_string()),
+        }
+    }
+
+    // Checks a numeric setting against the range the config accepts
+    pub fn setting_error(&self, key: &str) -> Option<String> {
+        let value = self.setting(key).trim();
+        match key {
+            "max_results" => (!value.parse().is_ok_and(|v| config::MAX_RESULTS_RANGE.contains(&v)))
+                .then(|| "Enter a whole number from 1 to 100".to_string()),
+            "search_delay" => (!value.parse().is_ok_and(|v| config::SEARCH_DELAY_RANGE.contains(&v)))
+                .then(|| "Enter a delay from 100 to 5000 ms".to_string()),
+            "provider_timeout" => (!value.parse().is_ok_and(|v| config::PROVIDER_TIMEOUT_RANGE.contains(&v)))
+                .then(|| "Enter a timeout from 50 to 4000 ms".to_string()),
+            "cache_ttl" => (!value.parse().is_ok_and(|v| config::CACHE_TTL_RANGE.contains(&v)))
+                .then(|| "Enter a time from 0 to 600000 ms".to_string()),
+            "bang_deny" => value.split(',').any(|t| t.trim().contains(char::is_whitespace))
+                .then(|| "Separate triggers with commas".to_string()),
+            "speech_rate" => (!value.is_empty() && !value.parse().is_ok_and(|v| config::SPEECH_RATE_RANGE.contains(&v)))
+                .then(|| "Enter a rate from 80 to 500 words per minute".to_string()),
+            _ => None,
+        }
+    }
+
+    // Checks a profile name as typed; `current` is the profile's existing
+    // name when renaming, which may be kept as is
+    pub fn profile_name_error(&self, name: &str, current: Option<&str>) -> Option<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            Some("Profile name cannot be empty".to_string())
+        } else if Some(name) != current && self.profiles.iter().any(|p| p == name) {
+            Some(format!("Profile '{}' already exists", name))
+        } else {
+            None
+        }
+    }
+
+    // Save stays disabled while any field shows an error
+    pub fn has_errors(&self) -> bool {
+        self.hotkey_validation().is_some()
+            || self.settings.iter().any(|(key, _)| self.setting_error(key).is_some())
+    }
+
+    // The bang named by the default bang setting, which site searches go
+    // through
+    pub fn default_engine(&self) -> Option<&Bang> {
+        let trigger = self.setting("default_bang").trim().trim_start_matches('!');
+        if trigger.is_empty() || self.bang_overlay.is_disabled(trigger) {
+            return None;
+        }
+        self.bang_overlay.bangs
+            .iter()
+            .chain(self.bang_engine.as_ref())
+            .find(|b| b.trigger == trigger)
+    }
+
+    pub fn setting(&self, key: &str) -> &str {
+        self.settings
+            .iter()
+            .find(|(k, _)| k == key)
+            .map_or("", |(_, v)| v.as_str())
+    }
+
+    pub fn set_setting(&mut self, key: String, value: String) {
+        match self.settings.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.settings.push((key, value)),
         }
     }
 
@@ -79,14 +438,78 @@ impl State {
             self.current_profile = self.profiles.first().unwrap_or(&"Default".to_string()).clone();
         }
             
-        // Load hotkey settings
-        self.hotkey = config.hotkey.key_combination.clone();
+        // Load hotkey settings, shown canonically when they parse
+        match KeyCombo::parse(&config.hotkey.key_combination) {
+            Ok(combo) => {
+                self.hotkey = combo.to_string();
+                self.hotkey_error = None;
+            }
+            Err(e) => {
+                self.hotkey = config.hotkey.key_combination.clone();
+                self.hotkey_error = Some(e.to_string());
+            }
+        }
             
+        // Load appearance and voice settings
+        self.theme = match config.appearance.theme {
+            ThemeMode::System => AppTheme::System,
+            ThemeMode::Light => AppTheme::Light,
+            ThemeMode::Dark => AppTheme::Dark,
+        };
+        self.accent_color = config.appearance.accent_color
+            .as_deref()
+            .and_then(color_from_hex)
+            .unwrap_or(DEFAULT_ACCENT);
+        let popup_theme = PopupTheme::from_appearance(&config.appearance);
+        self.popup_background = config.appearance.background_color.clone().unwrap_or_default();
+        self.popup_text = config.appearance.text_color.clone().unwrap_or_default();
+        self.popup_font = popup_theme.font.unwrap_or_default();
+        self.popup_font_size = popup_theme.font_size;
+        self.popup_radius = popup_theme.corner_radius;
+        self.popup_opacity = popup_theme.opacity;
+        self.voice_enabled = config.voice.enabled;
+        self.autostart = crate::autostart::is_installed();
+        self.sensitivity = config.voice.sensitivity;
+
+        // Load commands of the current profile
+        self.editing_profile = self.current_profile.clone();
+        self.commands = config.get_current_profile()
+            .map(|p| p.commands.clone())
+            .unwrap_or_default();
+
         // Load settings for current profile
+        let profile = config.get_current_profile().ok();
         self.settings = vec![
             ("max_results".to_string(), config.search.max_results.to_string()),
             ("search_delay".to_string(), config.search.search_delay.to_string()),
+            ("provider_timeout".to_string(), config.search.provider_timeout.to_string()),
+            ("cache_ttl".to_string(), config.search.cache_ttl.to_string()),
+            ("default_bang".to_string(), config.search.default_bang.clone().unwrap_or_default()),
+            ("bang_categories".to_string(), config.search.bang_categories.join(", ")),
+            ("bang_deny".to_string(), config.search.bang_deny.join(", ")),
+            (
+                "bang_position".to_string(),
+                profile.map(|p| p.bang_position).unwrap_or_default().as_str().to_string(),
+            ),
+            (
+                "bang_ignore_case".to_string(),
+                profile.map_or(false, |p| p.bang_ignore_case).to_string(),
+            ),
+            (
+                "bang_require_prefix".to_string(),
+                profile.map_or(false, |p| p.bang_require_prefix).to_string(),
+            ),
+            (
+                "speak_after_voice".to_string(),
+                profile.map_or(false, |p| p.speech.after_voice).to_string(),
+            ),
+            (
+                "speech_rate".to_string(),
+                profile.and_then(|p| p.speech.rate).map(|rate| rate.to_string()).unwrap_or_default(),
+            ),
         ];
+        drop(config);
+        self.mark_saved();
 
         Ok(())
     }
@@ -97,8 +520,13 @@ impl std::fmt::Display for Tab {
         match self {
             Tab::General => write!(f, "General"),
             Tab::Hotkeys => write!(f, "Hotkeys"),
+            Tab::Commands => write!(f, "Commands"),
+            Tab::Providers => write!(f, "Providers"),
+            Tab::Plugins => write!(f, "Plugins"),
+            Tab::Bangs => write!(f, "Bangs"),
             Tab::Appearance => write!(f, "Appearance"),
             Tab::Advanced => write!(f, "Advanced"),
+            Tab::Logs => write!(f, "Logs"),
         }
     }
 }
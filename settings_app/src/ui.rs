@@ -1,12 +1,18 @@
 use iced::{alignment, Background, Color, Element, Length, Theme};
 use iced::widget::{
     button, checkbox, column, container, horizontal_space, row, slider, text, text_input, Space,
-    vertical_space, pick_list, scrollable,
+    vertical_space, pick_list, scrollable, text_editor, Row,
 };
 use iced::theme;
+use iced::advanced::text::highlighter;
 
 use crate::app::AppMessage;
-use crate::state::{AppTheme, State, Tab};
+use crate::logs::{LogLevel, LogSource};
+use crate::raw_config::ErrorLines;
+use crate::state::{color_to_hex, rgb_to_color, AppTheme, PendingAction, State, Tab};
+use shared::config::{BangPosition, PROVIDER_WEIGHT_RANGE};
+use shared::plugin::PluginState;
+use shared::theme::Rgb;
 
 // Define Color Constants
 
@@ -147,6 +153,52 @@ impl container::StyleSheet for CardContainerStyle {
     }
 }
 
+// Count of unsaved fields shown on the Save button
+#[derive(Clone, Copy)]
+pub struct BadgeStyle {
+    accent_color: Color,
+}
+
+impl container::StyleSheet for BadgeStyle {
+    type Style = Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(Background::Color(Color::WHITE)),
+            text_color: Some(self.accent_color),
+            border: iced::Border {
+                radius: 8.0.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+// Plain colored box used to mock up the popup in the appearance preview
+#[derive(Clone, Copy)]
+pub struct PopupPreviewStyle {
+    background: Color,
+    text: Color,
+    radius: f32,
+}
+
+impl container::StyleSheet for PopupPreviewStyle {
+    type Style = Theme;
+
+    fn appearance(&self, _style: &Self::Style) -> container::Appearance {
+        container::Appearance {
+            background: Some(Background::Color(self.background)),
+            text_color: Some(self.text),
+            border: iced::Border {
+                radius: self.radius.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct TabButtonStyle {
     theme: AppTheme,
@@ -424,8 +476,13 @@ pub fn view(state: &State) -> Element<AppMessage> {
         vertical_space().height(Length::from(25)),
         tab_button("General", Tab::General, "⚙"),
         tab_button("Hotkeys", Tab::Hotkeys, "⌨"),
+        tab_button("Commands", Tab::Commands, "⚡"),
+        tab_button("Providers", Tab::Providers, "🧩"),
+        tab_button("Plugins", Tab::Plugins, "🔌"),
+        tab_button("Bangs", Tab::Bangs, "❗"),
         tab_button("Appearance", Tab::Appearance, "🎨"),
         tab_button("Advanced", Tab::Advanced, "⚒"),
+        tab_button("Logs", Tab::Logs, "📜"),
         vertical_space().height(Length::Fill),
         row![
             text(format!("v{}", env!("CARGO_PKG_VERSION")))
@@ -455,8 +512,18 @@ pub fn view(state: &State) -> Element<AppMessage> {
     let content = match state.active_tab {
         Tab::General => general_tab(state),
         Tab::Hotkeys => hotkeys_tab(state),
+        Tab::Commands => commands_tab(state),
+        Tab::Providers => providers_tab(state),
+        Tab::Plugins => plugins_tab(state),
+        Tab::Bangs => bangs_tab(state),
         Tab::Appearance => appearance_tab(state),
         Tab::Advanced => advanced_tab(state),
+        Tab::Logs => logs_tab(state),
+    };
+
+    let content: Element<AppMessage> = match &state.pending_action {
+        Some(action) => column![pending_banner(state, action), content].spacing(20).into(),
+        None => content,
     };
 
     let content_container = container(content)
@@ -483,6 +550,48 @@ pub fn view(state: &State) -> Element<AppMessage> {
 
 // --- Helper Widgets ---
 
+// Asks what to do with unsaved changes before closing or switching profiles
+fn pending_banner<'a>(state: &State, action: &PendingAction) -> Element<'a, AppMessage> {
+    let theme = state.theme;
+    let accent_color = state.accent_color;
+    let question = match action {
+        PendingAction::Exit => "Save your changes before closing?".to_string(),
+        PendingAction::SelectProfile(profile) => format!("Save your changes before switching to '{}'?", profile),
+    };
+
+    let action_button = |label: &str, message: Option<AppMessage>, is_primary: bool| {
+        button(text(label).size(14))
+            .on_press_maybe(message)
+            .padding([8, 15])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color,
+                is_primary,
+            })))
+    };
+
+    card_container(
+        row![
+            column![
+                text(question).size(14).style(get_text_color(theme)),
+                text(format!("Unsaved: {}", state.dirty_fields().join(", ")))
+                    .size(12)
+                    .style(get_text_secondary_color(theme)),
+            ]
+            .spacing(4)
+            .width(Length::Fill),
+            action_button("Cancel", Some(AppMessage::CancelPending), false),
+            action_button("Discard", Some(AppMessage::DiscardPending), false),
+            action_button("Save", (!state.has_errors()).then_some(AppMessage::SavePending), true),
+        ]
+        .spacing(10)
+        .padding(5)
+        .align_items(alignment::Alignment::Center)
+        .into(),
+        theme,
+    )
+}
+
 fn section_title(title: &str, theme: AppTheme) -> Element<'static, AppMessage> {
     let text_color = get_text_color(theme);
     row![text(title).size(20).style(text_color),]
@@ -509,6 +618,17 @@ fn setting_row<'a>(
     .into()
 }
 
+// Red text shown under a field whose value can't be saved
+fn field_error<'a>(error: impl ToString) -> Element<'a, AppMessage> {
+    container(
+        text(error.to_string())
+            .size(12)
+            .style(Color::from_rgb(0.9, 0.3, 0.3))
+    )
+    .padding([0, 15, 12, 15])
+    .into()
+}
+
 fn card_container<'a>(
     content: Element<'a, AppMessage>,
     theme: AppTheme,
@@ -546,6 +666,16 @@ fn general_tab(state: &State) -> Element<AppMessage> {
         theme,
     );
 
+    let autostart_toggle = setting_row(
+        "Launch Orion at login",
+        checkbox("", state.autostart)
+            .on_toggle(AppMessage::ToggleAutostart)
+            .into(),
+        theme,
+    );
+    let general = column![voice_toggle, autostart_toggle]
+        .push_maybe(state.autostart_error.as_ref().map(field_error));
+
     // Profile related UI
     let profile_selector = setting_row(
         "Current Profile",
@@ -559,6 +689,7 @@ fn general_tab(state: &State) -> Element<AppMessage> {
         theme,
     );
 
+    let new_profile_error = state.profile_name_error(&state.new_profile_name, None);
     let new_profile_row = setting_row(
         "Add new profile",
         row![
@@ -567,7 +698,7 @@ fn general_tab(state: &State) -> Element<AppMessage> {
                 .padding(10)
                 .width(Length::Fixed(200.0)),
             button(text("Add").size(14))
-                .on_press(AppMessage::AddProfile)
+                .on_press_maybe(new_profile_error.is_none().then_some(AppMessage::AddProfile))
                 .padding([8, 15])
                 .style(theme::Button::Custom(Box::new(ActionButtonStyle {
                     theme,
@@ -580,26 +711,64 @@ fn general_tab(state: &State) -> Element<AppMessage> {
         theme,
     );
 
-    // Profile list with delete buttons
+    let row_button = |label: &str, message: Option<AppMessage>, accent_color: Color| {
+        button(text(label).size(14))
+            .on_press_maybe(message)
+            .padding([7, 12])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color,
+                is_primary: false,
+            })))
+    };
+
+    // Profile list with rename and delete buttons
     let profiles = state.profiles.iter().map(|profile| {
-        setting_row(
-            profile,
-            if profile != "Default" {
-                button(text("Delete").size(14))
-                    .on_press(AppMessage::DeleteProfile(profile.clone()))
-                    .padding([7, 12])
-                    .style(theme::Button::Custom(Box::new(ActionButtonStyle {
-                        theme,
-                        accent_color: Color::from_rgb(0.9, 0.3, 0.3), // Red for delete
-                        is_primary: false,
-                    })))
+        match &state.renaming_profile {
+            Some((old, draft)) if old == profile => {
+                let error = state.profile_name_error(draft, Some(old));
+                let rename = error.is_none().then_some(AppMessage::RenameProfile);
+                let mut input = text_input("Profile name", draft)
+                    .on_input(AppMessage::ProfileRenameChanged)
+                    .padding(8)
+                    .width(Length::Fill);
+                if let Some(message) = rename.clone() {
+                    input = input.on_submit(message);
+                }
+
+                let mut editor = column![
+                    row![
+                        input,
+                        row_button("Save", rename, state.accent_color),
+                        row_button("Cancel", Some(AppMessage::CancelProfileRename), state.accent_color),
+                    ]
+                    .spacing(10)
+                    .align_items(alignment::Alignment::Center),
+                ]
+                .spacing(5)
+                .padding([12, 15, 12, 15]);
+                if let Some(error) = error.as_ref().or(state.profile_error.as_ref()) {
+                    editor = editor.push(text(error).size(12).style(Color::from_rgb(0.9, 0.3, 0.3)));
+                }
+                editor.into()
+            }
+            _ => setting_row(
+                profile,
+                if profile != "Default" {
+                    row![
+                        row_button("Rename", Some(AppMessage::StartProfileRename(profile.clone())), state.accent_color),
+                        // Red for delete
+                        row_button("Delete", Some(AppMessage::DeleteProfile(profile.clone())), Color::from_rgb(0.9, 0.3, 0.3)),
+                    ]
+                    .spacing(10)
                     .into()
-            } else {
-                // Don't allow deleting the Default profile
-                Space::with_width(Length::Shrink).into()
-            },
-            theme,
-        )
+                } else {
+                    // Don't allow renaming or deleting the Default profile
+                    Space::with_width(Length::Shrink).into()
+                },
+                theme,
+            ),
+        }
     }).collect::<Vec<_>>();
 
     let profiles_list: Element<'_, AppMessage> = if !profiles.is_empty() {
@@ -629,14 +798,17 @@ fn general_tab(state: &State) -> Element<AppMessage> {
     };
 
     column![
-        section("General Settings", column![voice_toggle], theme),
+        section("General Settings", general, theme),
         vertical_space().height(Length::Fixed(25.0)),
         section_title("Profile Management", theme),
         card_container(
             column![
                 profile_selector,
                 new_profile_row,
-            ].into(),
+            ]
+            // An empty name just keeps Add disabled, it isn't worth an error
+            .push_maybe(new_profile_error.filter(|_| !state.new_profile_name.trim().is_empty()).map(field_error))
+            .into(),
             theme
         ),
         vertical_space().height(Length::Fixed(20.0)),
@@ -649,18 +821,625 @@ fn general_tab(state: &State) -> Element<AppMessage> {
 
 fn hotkeys_tab(state: &State) -> Element<AppMessage> {
     let theme = state.theme;
+    let (label, action, action_label) = if state.recording_hotkey {
+        ("Press a key combination...".to_string(), AppMessage::CancelHotkeyRecording, "Cancel")
+    } else {
+        (state.hotkey.clone(), AppMessage::RecordHotkey, "Record")
+    };
+
     let hotkey_edit = setting_row(
         "Activation shortcut",
-        text_input("Enter hotkey", &state.hotkey)
-            .padding(10)
-            .width(Length::Fixed(200.0))
-            .on_input(AppMessage::UpdateHotkey)
-            .into(),
+        row![
+            container(text(label).size(14).style(get_text_color(theme)))
+                .padding(10)
+                .width(Length::Fixed(200.0))
+                .style(theme::Container::Custom(Box::new(CardContainerStyle { theme }))),
+            button(text(action_label).size(14))
+                .on_press(action)
+                .padding([8, 15])
+                .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                    theme,
+                    accent_color: state.accent_color,
+                    is_primary: state.recording_hotkey,
+                })))
+        ]
+        .spacing(15)
+        .align_items(alignment::Alignment::Center)
+        .into(),
         theme,
     );
 
+    // A combo rejected while recording, otherwise whatever is wrong with
+    // the current one
+    let error = state.hotkey_error.clone().or_else(|| state.hotkey_validation());
+    let shortcuts = column![hotkey_edit].push_maybe(error.map(field_error));
+
+    column![
+        section("Keyboard Shortcuts", shortcuts, theme),
+    ]
+    .spacing(10)
+    .width(Length::Fill)
+    .into()
+}
+
+fn commands_tab(state: &State) -> Element<AppMessage> {
+    let theme = state.theme;
+    let accent_color = state.accent_color;
+    let text_secondary_color = get_text_secondary_color(theme);
+
+    let small_button = |label: &str, message: Option<AppMessage>, accent: Color| {
+        button(text(label).size(13))
+            .on_press_maybe(message)
+            .padding([6, 10])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color: accent,
+                is_primary: false,
+            })))
+    };
+
+    let profile_selector = setting_row(
+        "Profile",
+        pick_list(
+            state.profiles.clone(),
+            Some(state.editing_profile.clone()),
+            AppMessage::SelectEditingProfile
+        )
+        .width(Length::Fixed(200.0))
+        .into(),
+        theme,
+    );
+
+    let last = state.commands.len().saturating_sub(1);
+    let rows = state.commands.iter().enumerate().map(|(idx, command)| {
+        let target = match &command.run {
+            Some(run) if command.elevated => format!("{} (as administrator)", run),
+            Some(run) => run.clone(),
+            None => command.url.clone(),
+        };
+        row![
+            column![
+                text(&command.name).size(14).style(get_text_color(theme)),
+                text(target).size(12).style(text_secondary_color),
+            ]
+            .spacing(2)
+            .width(Length::Fill),
+            small_button("↑", (idx > 0).then_some(AppMessage::MoveCommand(idx, true)), accent_color),
+            small_button("↓", (idx < last).then_some(AppMessage::MoveCommand(idx, false)), accent_color),
+            small_button("Edit", Some(AppMessage::EditCommand(idx)), accent_color),
+            small_button("Delete", Some(AppMessage::DeleteCommand(idx)), Color::from_rgb(0.9, 0.3, 0.3)),
+        ]
+        .padding([10, 15])
+        .spacing(8)
+        .align_items(alignment::Alignment::Center)
+        .into()
+    }).collect::<Vec<Element<AppMessage>>>();
+
+    let commands_list: Element<'_, AppMessage> = if rows.is_empty() {
+        container(
+            text("No commands in this profile yet")
+                .style(theme::Text::Color(text_secondary_color))
+                .width(Length::Fill)
+                .horizontal_alignment(alignment::Horizontal::Center)
+        )
+        .padding(20)
+        .width(Length::Fill)
+        .into()
+    } else {
+        scrollable(column(rows).width(Length::Fill))
+            .height(Length::Fixed(220.0))
+            .width(Length::Fill)
+            .into()
+    };
+
+    let draft = &state.command_draft;
+    let field = |label: &str, placeholder: &str, value: &str, on_input: fn(String) -> AppMessage| {
+        setting_row(
+            label,
+            text_input(placeholder, value)
+                .on_input(on_input)
+                .padding(10)
+                .width(Length::Fixed(300.0))
+                .into(),
+            theme,
+        )
+    };
+
+    let mut form_buttons = row![
+        button(text(if state.editing_command.is_some() { "Update" } else { "Add" }).size(14))
+            .on_press(AppMessage::SaveCommand)
+            .padding([8, 15])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color,
+                is_primary: true,
+            })))
+    ]
+    .spacing(15);
+    if state.editing_command.is_some() {
+        form_buttons = form_buttons.push(small_button("Cancel", Some(AppMessage::CancelCommandEdit), accent_color));
+    }
+
+    let mut form = column![
+        field("Name", "GitHub", &draft.name, AppMessage::CommandNameChanged),
+        field("URL", "https://github.com", &draft.url, AppMessage::CommandUrlChanged),
+        field("Or run", "systemctl restart nginx", &draft.run, AppMessage::CommandRunChanged),
+        setting_row(
+            "Run as administrator",
+            checkbox("", draft.elevated)
+                .on_toggle(AppMessage::CommandElevatedToggled)
+                .into(),
+            theme,
+        ),
+        field("Description", "Open GitHub", &draft.description, AppMessage::CommandDescriptionChanged),
+        field("Keywords", "git, code", &draft.keywords, AppMessage::CommandKeywordsChanged),
+    ];
+    if let Some(error) = &state.command_error {
+        form = form.push(
+            container(
+                text(format!("Warning: {}", error))
+                    .size(12)
+                    .style(Color::from_rgb(0.9, 0.6, 0.2))
+            )
+            .padding([0, 15])
+        );
+    }
+    form = form.push(container(form_buttons).padding([12, 15]));
+
+    let form_title = if state.editing_command.is_some() { "Edit Command" } else { "New Command" };
+
+    column![
+        section("Commands", column![profile_selector, commands_list], theme),
+        vertical_space().height(Length::Fixed(15.0)),
+        section(form_title, form, theme),
+    ]
+    .spacing(10)
+    .width(Length::Fill)
+    .into()
+}
+
+fn providers_tab(state: &State) -> Element<AppMessage> {
+    let theme = state.theme;
+    let accent_color = state.accent_color;
+    let text_color = get_text_color(theme);
+    let text_secondary_color = get_text_secondary_color(theme);
+
+    let small_button = |label: &str, message: Option<AppMessage>| {
+        button(text(label).size(13))
+            .on_press_maybe(message)
+            .padding([6, 10])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color,
+                is_primary: false,
+            })))
+    };
+
+    let profile_selector = setting_row(
+        "Profile",
+        pick_list(
+            state.profiles.clone(),
+            Some(state.editing_profile.clone()),
+            AppMessage::SelectEditingProfile
+        )
+        .width(Length::Fixed(200.0))
+        .into(),
+        theme,
+    );
+
+    let last = state.provider_settings.len().saturating_sub(1);
+    let rows = state.provider_settings.iter().enumerate().map(|(idx, provider)| {
+        let info = state.provider_info.iter().find(|i| i.name == provider.name);
+        // Only flag missing providers when the registered list is known
+        let unavailable = info.is_none() && !state.provider_info.is_empty();
+
+        let mut label = row![
+            checkbox(&provider.name, provider.enabled)
+                .on_toggle(move |enabled| AppMessage::ToggleProvider(idx, enabled))
+                .text_size(14)
+                .style(theme::Checkbox::Primary),
+        ]
+        .spacing(10)
+        .align_items(alignment::Alignment::Center)
+        .width(Length::Fill);
+        if unavailable {
+            label = label.push(text("not available").size(12).style(text_secondary_color));
+        }
+
+        let mut entry = column![
+            row![
+                label,
+                small_button("↑", (idx > 0).then_some(AppMessage::MoveProvider(idx, true))),
+                small_button("↓", (idx < last).then_some(AppMessage::MoveProvider(idx, false))),
+            ]
+            .spacing(8)
+            .align_items(alignment::Alignment::Center),
+        ]
+        .padding([8, 15]);
+
+        entry = entry.push(
+            row![
+                text("Weight").size(13).style(text_color),
+                horizontal_space().width(Length::Fill),
+                slider(PROVIDER_WEIGHT_RANGE, provider.weight, move |weight| AppMessage::ProviderWeightChanged(idx, weight))
+                    .step(0.1)
+                    .width(Length::Fixed(250.0)),
+                text(format!("{:.1}×", provider.weight)).size(12).style(text_secondary_color).width(Length::Fixed(40.0)),
+            ]
+            .spacing(10)
+            .padding([6, 0, 0, 30])
+            .align_items(alignment::Alignment::Center)
+        );
+
+        for option in info.map(|i| i.options.as_slice()).unwrap_or_default() {
+            let key = option.key.clone();
+            let value = provider.options.get(&option.key).map(String::as_str).unwrap_or_default();
+            entry = entry.push(
+                row![
+                    text(&option.label).size(13).style(text_color),
+                    horizontal_space().width(Length::Fill),
+                    text_input("", value)
+                        .on_input(move |value| AppMessage::ProviderOptionChanged(idx, key.clone(), value))
+                        .padding(8)
+                        .width(Length::Fixed(300.0)),
+                ]
+                .padding([6, 0, 0, 30])
+                .align_items(alignment::Alignment::Center)
+            );
+        }
+
+        entry.into()
+    }).collect::<Vec<Element<AppMessage>>>();
+
+    let mut list = column![profile_selector].push(column(rows).width(Length::Fill));
+    if let Some(status) = &state.provider_status {
+        list = list.push(
+            container(text(status).size(12).style(Color::from_rgb(0.9, 0.6, 0.2)))
+                .padding([8, 15])
+        );
+    }
+    list = list.push(
+        container(
+            button(text("Save").size(14))
+                .on_press(AppMessage::SaveProviders)
+                .padding([8, 15])
+                .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                    theme,
+                    accent_color,
+                    is_primary: true,
+                })))
+        )
+        .padding([12, 15])
+    );
+
+    column![
+        section("Search Providers", list, theme),
+        text("Providers higher in the list win ties between equally scored results.")
+            .size(12)
+            .style(text_secondary_color),
+    ]
+    .spacing(10)
+    .width(Length::Fill)
+    .into()
+}
+
+fn plugins_tab(state: &State) -> Element<AppMessage> {
+    let theme = state.theme;
+    let text_color = get_text_color(theme);
+    let text_secondary_color = get_text_secondary_color(theme);
+    let warning_color = Color::from_rgb(0.9, 0.6, 0.2);
+    let error_color = Color::from_rgb(0.9, 0.3, 0.3);
+
+    let rows = state.plugins.iter().map(|plugin| {
+        let (state_label, state_color) = match &plugin.state {
+            PluginState::Loaded => ("Loaded".to_string(), text_secondary_color),
+            PluginState::Invalid(reason) => (format!("Not loaded: {}", reason), error_color),
+            PluginState::Conflict(reason) => (format!("Not loaded: {}", reason), warning_color),
+        };
+
+        let mut title = row![text(plugin.name()).size(14).style(text_color)]
+            .spacing(10)
+            .align_items(alignment::Alignment::Center);
+        if let Some(manifest) = &plugin.manifest {
+            title = title.push(text(format!("v{}", manifest.version)).size(12).style(text_secondary_color));
+        }
+
+        let mut entry = column![title].spacing(4).padding([8, 15]);
+        if let Some(manifest) = &plugin.manifest {
+            if !manifest.description.is_empty() {
+                entry = entry.push(text(&manifest.description).size(13).style(text_color));
+            }
+            if !manifest.triggers.is_empty() {
+                entry = entry.push(
+                    text(format!("Triggers: {}", manifest.triggers.join(", "))).size(12).style(text_secondary_color)
+                );
+            }
+            if !manifest.permissions.is_empty() {
+                let permissions: Vec<&str> = manifest.permissions.iter().map(|p| p.label()).collect();
+                entry = entry.push(
+                    text(format!("Permissions: {}", permissions.join(", "))).size(12).style(text_secondary_color)
+                );
+            }
+        }
+        entry = entry.push(text(state_label).size(12).style(state_color));
+        for warning in &plugin.warnings {
+            entry = entry.push(text(warning).size(12).style(warning_color));
+        }
+
+        entry.into()
+    }).collect::<Vec<Element<AppMessage>>>();
+
+    let mut list = column(rows).width(Length::Fill);
+    if let Some(error) = &state.plugin_error {
+        list = list.push(container(text(error).size(12).style(warning_color)).padding([8, 15]));
+    } else if state.plugins.is_empty() {
+        list = list.push(
+            container(text("No plugins installed").size(13).style(text_secondary_color)).padding([8, 15])
+        );
+    }
+
+    column![
+        section("Plugins", list, theme),
+        text("Plugins live in folders of their own under plugins/ in the config directory, each with a plugin.toml. \
+              Changes take effect when the background restarts; their settings are on the Providers tab.")
+            .size(12)
+            .style(text_secondary_color),
+    ]
+    .spacing(10)
+    .width(Length::Fill)
+    .into()
+}
+
+fn bangs_tab(state: &State) -> Element<AppMessage> {
+    let theme = state.theme;
+    let accent_color = state.accent_color;
+    let text_color = get_text_color(theme);
+    let text_secondary_color = get_text_secondary_color(theme);
+    let overlay = &state.bang_overlay;
+
+    let small_button = |label: &str, message: AppMessage, accent: Color| {
+        button(text(label).size(13))
+            .on_press(message)
+            .padding([6, 10])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color: accent,
+                is_primary: false,
+            })))
+    };
+
+    // The background lists custom bangs first
+    let page = &state.bang_page;
+    let rows = page.bangs.iter().map(|bang| {
+        let is_custom = overlay.bangs.iter().any(|c| c.trigger == bang.trigger);
+        let disabled = overlay.is_disabled(&bang.trigger);
+        let action = if is_custom {
+            small_button("Remove", AppMessage::RemoveCustomBang(bang.trigger.clone()), Color::from_rgb(0.9, 0.3, 0.3))
+        } else if disabled {
+            small_button("Enable", AppMessage::SetBangDisabled(bang.trigger.clone(), false), accent_color)
+        } else {
+            small_button("Disable", AppMessage::SetBangDisabled(bang.trigger.clone(), true), accent_color)
+        };
+
+        let name_color = if disabled { text_secondary_color } else { text_color };
+        let mut details = bang.domain.clone();
+        if is_custom {
+            details.push_str(" · custom");
+        }
+        let aliases = overlay.aliases_of(&bang.trigger);
+        if !aliases.is_empty() {
+            details.push_str(&format!(" · also !{}", aliases.join(", !")));
+        }
+        match state.bang_usage.count(&bang.trigger) {
+            0 => {}
+            1 => details.push_str(" · used once"),
+            uses => details.push_str(&format!(" · used {} times", uses)),
+        }
+
+        row![
+            text(format!("!{}", bang.trigger)).size(14).style(accent_color).width(Length::Fixed(90.0)),
+            column![
+                text(&bang.display_name).size(14).style(name_color),
+                text(details).size(12).style(text_secondary_color),
+            ]
+            .spacing(2)
+            .width(Length::Fill),
+            action,
+        ]
+        .padding([8, 15])
+        .spacing(10)
+        .align_items(alignment::Alignment::Center)
+        .into()
+    }).collect::<Vec<Element<AppMessage>>>();
+
+    let offset = page.query.offset;
+    let summary = if page.total > page.bangs.len() {
+        format!("Showing {}-{} of {} bangs", offset + 1, offset + page.bangs.len(), page.total)
+    } else {
+        format!("{} bangs", page.total)
+    };
+    let previous = offset.saturating_sub(crate::bangs::PAGE_SIZE);
+    let next = offset + page.bangs.len();
+    let previous_page = small_button("Previous", AppMessage::BangPageRequested(previous), accent_color)
+        .on_press_maybe((offset > 0).then_some(AppMessage::BangPageRequested(previous)));
+    let next_page = small_button("Next", AppMessage::BangPageRequested(next), accent_color)
+        .on_press_maybe((next < page.total).then_some(AppMessage::BangPageRequested(next)));
+
+    let download_button = button(text(if state.downloading_bangs { "Downloading..." } else { "Re-download" }).size(14))
+        .on_press_maybe((!state.downloading_bangs).then_some(AppMessage::DownloadBangs))
+        .padding([8, 15])
+        .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+            theme,
+            accent_color,
+            is_primary: false,
+        })));
+
+    let header = row![
+        text_input("Filter by trigger, name or domain", &state.bang_filter)
+            .on_input(AppMessage::BangFilterChanged)
+            .padding(10)
+            .width(Length::Fill),
+        download_button,
+    ]
+    .spacing(15)
+    .padding([12, 15])
+    .align_items(alignment::Alignment::Center);
+
+    let most_used = state.bang_usage.most_used(5);
+    let mut list = column![
+        header,
+        row![
+            text(summary).size(12).style(text_secondary_color).width(Length::Fill),
+            previous_page,
+            next_page,
+        ]
+        .spacing(10)
+        .padding([0, 15])
+        .align_items(alignment::Alignment::Center),
+    ];
+    if !most_used.is_empty() {
+        let usage = most_used
+            .iter()
+            .map(|(trigger, uses)| format!("!{} ({})", trigger, uses))
+            .collect::<Vec<_>>()
+            .join(", ");
+        list = list.push(
+            container(text(format!("Most used: {}", usage)).size(12).style(text_secondary_color))
+                .padding([4, 15])
+        );
+    }
+    list = list.push(
+        scrollable(column(rows).width(Length::Fill))
+            .height(Length::Fixed(260.0))
+            .width(Length::Fill),
+    );
+    if let Some(status) = &state.bang_status {
+        list = list.push(
+            container(text(status).size(12).style(Color::from_rgb(0.9, 0.6, 0.2)))
+                .padding([8, 15])
+        );
+    }
+
+    let draft = &state.bang_draft;
+    let add_form = column![
+        setting_row(
+            "Trigger",
+            text_input("gh", &draft.trigger)
+                .on_input(AppMessage::BangTriggerChanged)
+                .padding(10)
+                .width(Length::Fixed(300.0))
+                .into(),
+            theme,
+        ),
+        setting_row(
+            "Name",
+            text_input("GitHub", &draft.name)
+                .on_input(AppMessage::BangNameChanged)
+                .padding(10)
+                .width(Length::Fixed(300.0))
+                .into(),
+            theme,
+        ),
+        setting_row(
+            "URL",
+            text_input("https://github.com/search?q={query}", &draft.url)
+                .on_input(AppMessage::BangUrlChanged)
+                .padding(10)
+                .width(Length::Fixed(300.0))
+                .into(),
+            theme,
+        ),
+        setting_row(
+            "Aliases",
+            text_input("github, hub", &draft.aliases)
+                .on_input(AppMessage::BangAliasesChanged)
+                .padding(10)
+                .width(Length::Fixed(300.0))
+                .into(),
+            theme,
+        ),
+        container(
+            button(text("Add").size(14))
+                .on_press(AppMessage::AddCustomBang)
+                .padding([8, 15])
+                .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                    theme,
+                    accent_color,
+                    is_primary: true,
+                })))
+        )
+        .padding([12, 15]),
+    ];
+
+    let site = &state.site_bang_draft;
+    let engine = state.default_engine().map_or("DuckDuckGo", |b| b.display_name.as_str());
+    let site_form = column![
+        container(
+            text(format!("Searches one site through {} by adding site: to the query", engine))
+                .size(12)
+                .style(text_secondary_color)
+        )
+        .padding([8, 15]),
+        setting_row(
+            "Domain",
+            text_input("docs.rs", &site.domain)
+                .on_input(AppMessage::SiteBangDomainChanged)
+                .padding(10)
+                .width(Length::Fixed(300.0))
+                .into(),
+            theme,
+        ),
+        setting_row(
+            "Trigger",
+            text_input("Defaults to the domain's name", &site.trigger)
+                .on_input(AppMessage::SiteBangTriggerChanged)
+                .padding(10)
+                .width(Length::Fixed(300.0))
+                .into(),
+            theme,
+        ),
+        container(
+            button(text("Create").size(14))
+                .on_press(AppMessage::AddSiteBang)
+                .padding([8, 15])
+                .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                    theme,
+                    accent_color,
+                    is_primary: true,
+                })))
+        )
+        .padding([12, 15]),
+    ];
+
+    let import_buttons = shared::models::Browser::ALL.into_iter().map(|browser| {
+        button(text(format!("From {}", browser)).size(14))
+            .on_press_maybe((!state.importing_bangs).then_some(AppMessage::ImportBangs(browser)))
+            .padding([8, 15])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color,
+                is_primary: false,
+            })))
+            .into()
+    });
+    let import_form = column![
+        container(
+            text("Adds the browser's search engines that have a keyword as custom bangs")
+                .size(12)
+                .style(text_secondary_color)
+        )
+        .padding([8, 15]),
+        container(Row::with_children(import_buttons).spacing(10)).padding([12, 15]),
+    ];
+
     column![
-        section("Keyboard Shortcuts", column![hotkey_edit], theme),
+        section("Bangs", list, theme),
+        vertical_space().height(Length::Fixed(15.0)),
+        section("Custom Bang", add_form, theme),
+        vertical_space().height(Length::Fixed(15.0)),
+        section("Site Search", site_form, theme),
+        vertical_space().height(Length::Fixed(15.0)),
+        section("Import", import_form, theme),
     ]
     .spacing(10)
     .width(Length::Fill)
@@ -684,7 +1463,8 @@ fn appearance_tab(state: &State) -> Element<AppMessage> {
     );
 
     let color_button = |color: Color, current_accent: Color| -> Element<AppMessage> {
-        let is_selected = color == current_accent;
+        // Compared as hex since saved colors come back rounded to 8 bits
+        let is_selected = color_to_hex(color) == color_to_hex(current_accent);
         let content = if is_selected {
             container(
                 text("✓")
@@ -729,6 +1509,79 @@ fn appearance_tab(state: &State) -> Element<AppMessage> {
         theme,
     );
 
+    let color_input = |label: &str, value: &str, on_input: fn(String) -> AppMessage| -> Element<AppMessage> {
+        let mut input = column![
+            text_input("Theme default, e.g. #202225", value)
+                .on_input(on_input)
+                .padding(10)
+                .width(Length::Fixed(200.0)),
+        ]
+        .spacing(5);
+        if !value.trim().is_empty() && Rgb::from_hex(value).is_none() {
+            input = input.push(
+                text("Not a #rrggbb color")
+                    .size(12)
+                    .style(Color::from_rgb(0.9, 0.3, 0.3)),
+            );
+        }
+        setting_row(label, input.into(), theme)
+    };
+
+    let labeled_slider = |label: &str, slider: Element<'static, AppMessage>, value: String| {
+        setting_row(
+            label,
+            column![
+                slider,
+                row![text(value)
+                    .size(12)
+                    .style(get_text_secondary_color(theme))]
+                .width(Length::Fixed(200.0))
+                .align_items(alignment::Alignment::Center),
+            ]
+            .spacing(5)
+            .into(),
+            theme,
+        )
+    };
+
+    let popup_controls = column![
+        color_input("Background color", &state.popup_background, AppMessage::PopupBackgroundChanged),
+        color_input("Text color", &state.popup_text, AppMessage::PopupTextChanged),
+        setting_row(
+            "Font",
+            text_input("Default font", &state.popup_font)
+                .on_input(AppMessage::PopupFontChanged)
+                .padding(10)
+                .width(Length::Fixed(200.0))
+                .into(),
+            theme,
+        ),
+        labeled_slider(
+            "Font size",
+            slider(10.0..=32.0, state.popup_font_size, AppMessage::PopupFontSizeChanged)
+                .step(1.0)
+                .width(Length::Fixed(200.0))
+                .into(),
+            format!("{}px", state.popup_font_size as i32),
+        ),
+        labeled_slider(
+            "Corner radius",
+            slider(0.0..=24.0, state.popup_radius, AppMessage::PopupRadiusChanged)
+                .step(1.0)
+                .width(Length::Fixed(200.0))
+                .into(),
+            format!("{}px", state.popup_radius as i32),
+        ),
+        labeled_slider(
+            "Opacity",
+            slider(0.2..=1.0, state.popup_opacity, AppMessage::PopupOpacityChanged)
+                .step(0.05)
+                .width(Length::Fixed(200.0))
+                .into(),
+            format!("{}%", (state.popup_opacity * 100.0).round() as i32),
+        ),
+    ];
+
     column![
         section(
             "Appearance", 
@@ -738,12 +1591,173 @@ fn appearance_tab(state: &State) -> Element<AppMessage> {
             ],
             theme
         ),
+        section("Popup", popup_controls, theme),
+        section("Preview", popup_preview(state), theme),
     ]
     .spacing(10)
     .width(Length::Fill)
     .into()
 }
 
+// Mock popup drawn with the unsaved theme, so edits show up immediately.
+// The font family isn't previewed since iced needs it loaded up front.
+fn popup_preview(state: &State) -> Element<'static, AppMessage> {
+    let popup = state.popup_theme();
+    let background = rgb_to_color(popup.background);
+    let text_color = rgb_to_color(popup.text);
+    let accent = rgb_to_color(popup.accent);
+    let size = popup.font_size;
+
+    let boxed = |content: Element<'static, AppMessage>, background: Color, text: Color, radius: f32| {
+        container(content)
+            .width(Length::Fill)
+            .style(theme::Container::Custom(Box::new(PopupPreviewStyle {
+                background,
+                text,
+                radius,
+            })))
+    };
+
+    let input = boxed(
+        text("Search...").size(size + 4.0).into(),
+        Color { a: 0.06, ..text_color },
+        Color { a: 0.6, ..text_color },
+        popup.corner_radius.min(8.0),
+    )
+    .padding(10);
+
+    let results = [
+        ("Orion settings", "Open the settings app"),
+        ("GitHub", "Search github.com"),
+        ("Calculator", "= 2 + 2"),
+    ];
+    let rows = results.into_iter().enumerate().map(|(idx, (title, description))| {
+        let content = column![
+            text(title).size(size),
+            text(description).size(size - 2.0),
+        ]
+        .spacing(2)
+        .into();
+        if idx == 0 {
+            boxed(content, accent, Color::WHITE, 0.0)
+        } else {
+            boxed(content, Color::TRANSPARENT, text_color, 0.0)
+        }
+        .padding([6, 10])
+        .into()
+    });
+
+    let content = column![input, column(rows.collect::<Vec<_>>()).spacing(2)]
+        .spacing(10)
+        .into();
+
+    boxed(
+        content,
+        Color { a: popup.opacity, ..background },
+        text_color,
+        popup.corner_radius,
+    )
+    .padding(12)
+    .max_width(420)
+    .into()
+}
+
+fn logs_tab(state: &State) -> Element<AppMessage> {
+    let theme = state.theme;
+    let accent_color = state.accent_color;
+    let text_color = get_text_color(theme);
+    let text_secondary_color = get_text_secondary_color(theme);
+
+    let level_color = |level: Option<LogLevel>| match level {
+        Some(LogLevel::Error) => Color::from_rgb(0.9, 0.3, 0.3),
+        Some(LogLevel::Warn) => Color::from_rgb(0.9, 0.6, 0.2),
+        Some(LogLevel::Info) | None => text_color,
+        Some(LogLevel::Debug | LogLevel::Trace) => text_secondary_color,
+    };
+
+    let search = state.log_search.trim();
+    let shown: Vec<_> = state.log_lines
+        .iter()
+        .filter(|line| line.matches(state.log_level, search))
+        .collect();
+
+    let lines = shown.iter().map(|line| {
+        text(&line.text)
+            .size(12)
+            .font(iced::Font::MONOSPACE)
+            .style(level_color(line.level))
+            .into()
+    }).collect::<Vec<Element<AppMessage>>>();
+
+    let summary = if state.log_lines.is_empty() {
+        format!("{} is empty or hasn't been written yet", state.log_source)
+    } else {
+        format!("{} of the last {} lines", shown.len(), state.log_lines.len())
+    };
+
+    let action_button = |label: &str, message: AppMessage| {
+        button(text(label).size(14))
+            .on_press(message)
+            .padding([8, 15])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color,
+                is_primary: false,
+            })))
+    };
+
+    let header = row![
+        pick_list(&LogSource::ALL[..], Some(state.log_source), AppMessage::LogSourceSelected)
+            .width(Length::Fixed(160.0)),
+        pick_list(&LogLevel::FILTERS[..], Some(state.log_level), AppMessage::LogLevelSelected)
+            .width(Length::Fixed(170.0)),
+        text_input("Search", &state.log_search)
+            .on_input(AppMessage::LogSearchChanged)
+            .padding(10)
+            .width(Length::Fill),
+        action_button("Refresh", AppMessage::RefreshLogs),
+    ]
+    .spacing(15)
+    .padding([12, 15])
+    .align_items(alignment::Alignment::Center);
+
+    let mut log = column![
+        header,
+        container(text(summary).size(12).style(text_secondary_color)).padding([0, 15]),
+        scrollable(column(lines).spacing(2).padding([0, 15]).width(Length::Fill))
+            .height(Length::Fixed(360.0))
+            .width(Length::Fill)
+            .direction(scrollable::Direction::Vertical(
+                scrollable::Properties::default().alignment(scrollable::Alignment::End),
+            )),
+    ]
+    .spacing(8);
+
+    let diagnostics = row![
+        text("Zips both logs and the config, with paths and provider options redacted")
+            .size(12)
+            .style(text_secondary_color)
+            .width(Length::Fill),
+        action_button("Copy diagnostics bundle", AppMessage::WriteDiagnostics),
+    ]
+    .spacing(15)
+    .padding([12, 15])
+    .align_items(alignment::Alignment::Center);
+    log = log.push(diagnostics);
+
+    if let Some(status) = &state.log_status {
+        log = log.push(
+            container(text(status).size(12).style(Color::from_rgb(0.9, 0.6, 0.2)))
+                .padding([8, 15])
+        );
+    }
+
+    column![section("Logs", log, theme)]
+        .spacing(10)
+        .width(Length::Fill)
+        .into()
+}
+
 fn advanced_tab(state: &State) -> Element<AppMessage> {
     let theme = state.theme;
     let accent_color = state.accent_color;
@@ -752,6 +1766,7 @@ fn advanced_tab(state: &State) -> Element<AppMessage> {
         "Voice Sensitivity",
         column![
             slider(0.0..=1.0, state.sensitivity, AppMessage::AdjustSensitivity)
+                .step(0.05)
                 .width(Length::Fixed(200.0)),
             row![text(format!(
                 "{}%",
@@ -767,6 +1782,65 @@ fn advanced_tab(state: &State) -> Element<AppMessage> {
         theme,
     );
 
+    let setting_input = |label: &str, key: &'static str, placeholder: &str| {
+        column![
+            setting_row(
+                label,
+                text_input(placeholder, state.setting(key))
+                    .on_input(move |value| AppMessage::UpdateSetting(key.to_string(), value))
+                    .padding(10)
+                    .width(Length::Fixed(200.0))
+                    .into(),
+                theme,
+            )
+        ]
+        .push_maybe(state.setting_error(key).map(field_error))
+    };
+
+    let max_results = setting_input("Maximum results", "max_results", "1 - 100");
+    let search_delay = setting_input("Search delay (ms)", "search_delay", "100 - 5000");
+    let provider_timeout = setting_input("Provider timeout (ms)", "provider_timeout", "50 - 4000");
+    let cache_ttl = setting_input("Reuse results for (ms)", "cache_ttl", "0 - 600000, 0 for never");
+    let default_bang = setting_input("Default bang", "default_bang", "e.g. ddg, empty for none");
+    let bang_categories = setting_input("Bang categories", "bang_categories", "e.g. Tech, Research; empty for all");
+    let bang_deny = setting_input("Blocked triggers", "bang_deny", "e.g. a, i");
+    let bang_position = setting_row(
+        "Bang triggers (this profile)",
+        pick_list(
+            &BangPosition::ALL[..],
+            BangPosition::parse(state.setting("bang_position")),
+            |position| AppMessage::UpdateSetting("bang_position".to_string(), position.as_str().to_string()),
+        )
+        .width(Length::Fixed(200.0))
+        .into(),
+        theme,
+    );
+    let setting_toggle = |label, key: &'static str| {
+        setting_row(
+            label,
+            checkbox("", state.setting(key) == "true")
+                .on_toggle(move |enabled| AppMessage::UpdateSetting(key.to_string(), enabled.to_string()))
+                .into(),
+            theme,
+        )
+    };
+    let bang_ignore_case = setting_toggle("Ignore case in triggers", "bang_ignore_case");
+    let bang_require_prefix = setting_toggle("Only take triggers starting with !", "bang_require_prefix");
+    let speak_after_voice = setting_toggle("Read out voice search results", "speak_after_voice");
+    let speech_rate = setting_input("Speech rate (words/min)", "speech_rate", "80 - 500, empty for default");
+
+    let dirty = state.dirty_fields();
+    let mut save_label = row![text("Save Changes").size(14)]
+        .spacing(8)
+        .align_items(alignment::Alignment::Center);
+    if !dirty.is_empty() {
+        save_label = save_label.push(
+            container(text(dirty.len()).size(11))
+                .padding([1, 6])
+                .style(theme::Container::Custom(Box::new(BadgeStyle { accent_color }))),
+        );
+    }
+
     let action_buttons = row![
         button(text("Reset to Defaults").size(14))
             .on_press(AppMessage::ResetSettings)
@@ -776,8 +1850,8 @@ fn advanced_tab(state: &State) -> Element<AppMessage> {
                 accent_color,
                 is_primary: false,
             }))),
-        button(text("Save Changes").size(14))
-            .on_press(AppMessage::SaveSettings)
+        button(save_label)
+            .on_press_maybe((!state.has_errors()).then_some(AppMessage::SaveSettings))
             .padding([10, 15])
             .style(theme::Button::Custom(Box::new(ActionButtonStyle {
                 theme,
@@ -788,10 +1862,32 @@ fn advanced_tab(state: &State) -> Element<AppMessage> {
     .spacing(15)
     .width(Length::Fill);
 
+    let status = if state.has_errors() {
+        "Fix the fields marked in red, on this tab or Hotkeys, to save".to_string()
+    } else if !dirty.is_empty() {
+        format!("Unsaved changes: {}", dirty.join(", "))
+    } else {
+        state.status.clone().unwrap_or_default()
+    };
+
     column![
         section(
             "Advanced Settings",
-            column![sensitivity_slider],
+            column![
+                max_results,
+                search_delay,
+                provider_timeout,
+                cache_ttl,
+                default_bang,
+                bang_categories,
+                bang_deny,
+                bang_position,
+                bang_ignore_case,
+                bang_require_prefix,
+                speak_after_voice,
+                speech_rate,
+                sensitivity_slider,
+            ],
             theme
         ),
         vertical_space().height(Length::Fixed(15.0)),
@@ -802,18 +1898,82 @@ fn advanced_tab(state: &State) -> Element<AppMessage> {
                     .style(Color::from_rgb(0.9, 0.6, 0.2)),
                 vertical_space().height(Length::Fixed(15.0)),
                 action_buttons,
+                text(status)
+                    .size(12)
+                    .style(get_text_secondary_color(theme)),
             ]
             .spacing(5)
             .padding(15)
         )
         .style(theme::Container::Custom(Box::new(CardContainerStyle { theme })))
         .width(Length::Fill),
+        section("Raw Config", raw_config_editor(state), theme),
     ]
     .spacing(15)
     .width(Length::Fill)
     .into()
 }
 
+// config.toml as text, for settings not surfaced elsewhere. Saving is only
+// offered once the current text has passed Validate.
+fn raw_config_editor(state: &State) -> Element<AppMessage> {
+    let theme = state.theme;
+    let accent_color = state.accent_color;
+    let raw = &state.raw_config;
+
+    let editor = text_editor(&raw.content)
+        .on_action(AppMessage::RawConfigAction)
+        .font(iced::Font::MONOSPACE)
+        .height(Length::Fixed(320.0))
+        .padding(10)
+        .highlight::<ErrorLines>(raw.error_lines.clone(), |_, _| highlighter::Format {
+            color: Some(Color::from_rgb(0.9, 0.3, 0.3)),
+            font: None,
+        });
+
+    let action_button = |label: &str, message: Option<AppMessage>, is_primary: bool| {
+        button(text(label).size(14))
+            .on_press_maybe(message)
+            .padding([8, 15])
+            .style(theme::Button::Custom(Box::new(ActionButtonStyle {
+                theme,
+                accent_color,
+                is_primary,
+            })))
+    };
+
+    let buttons = row![
+        action_button("Revert", Some(AppMessage::RevertRawConfig), false),
+        action_button("Validate", Some(AppMessage::ValidateRawConfig), false),
+        action_button("Save Raw Config", raw.valid.then_some(AppMessage::SaveRawConfig), true),
+    ]
+    .spacing(15);
+
+    let message = raw.message.as_ref().map(|message| {
+        let color = if raw.valid {
+            get_text_secondary_color(theme)
+        } else {
+            Color::from_rgb(0.9, 0.3, 0.3)
+        };
+        let lines = raw.error_lines
+            .iter()
+            .map(|line| (line + 1).to_string())
+            .collect::<Vec<_>>();
+        let message = if lines.is_empty() {
+            message.clone()
+        } else {
+            format!("Line {}: {}", lines.join(", "), message)
+        };
+        text(message).size(12).style(color)
+    });
+
+    column![editor, buttons]
+        .push_maybe(message)
+        .spacing(10)
+        .padding([12, 15])
+        .into()
+}
+
 // TabUI struct to handle UI rendering
 pub struct TabUI {}
 
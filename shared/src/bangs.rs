@@ -0,0 +1,377 @@
+use anyhow::{Context, Result};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::models::Bang;
+use crate::template::{self, TemplateVars};
+use validator::Validate;
+
+pub const BANGS_URL: &str = "https://gist.githubusercontent.com/GrishMahat/9500aa4a883650d21bc428abf1adb0d7/raw/723868e88db267fada918f8143e55cca36d10e97/bangs.json";
+
+fn config_dir() -> Result<PathBuf> {
+    let proj_dirs = directories::ProjectDirs::from("", "", "orion")
+        .context("Failed to get project directories")?;
+
+    Ok(proj_dirs.config_dir().to_path_buf())
+}
+
+// Downloaded bang list
+pub fn bangs_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("bangs.json"))
+}
+
+// User additions and disabled triggers, applied over the downloaded list
+pub fn custom_bangs_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("custom_bangs.json"))
+}
+
+// How often each trigger has been used, kept by the background service
+pub fn usage_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("bang_usage.json"))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BangUsage {
+    pub counts: BTreeMap<String, u64>,
+}
+
+impl BangUsage {
+    // A missing usage file means nothing has been used yet
+    pub fn load() -> Result<Self> {
+        let path = usage_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read bang usage at {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse bang usage at {:?}", path))
+    }
+
+    // Written beside the file and renamed over it, so a crash mid-write
+    // leaves the old counts rather than half a file
+    pub fn save(&self) -> Result<()> {
+        let path = usage_path()?;
+        let temp = path.with_extension("json.tmp");
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&temp, content)
+            .with_context(|| format!("Failed to write bang usage at {:?}", temp))?;
+        fs::rename(&temp, &path)
+            .with_context(|| format!("Failed to replace bang usage at {:?}", path))
+    }
+
+    pub fn record(&mut self, trigger: &str) {
+        *self.counts.entry(trigger.to_string()).or_default() += 1;
+    }
+
+    pub fn count(&self, trigger: &str) -> u64 {
+        self.counts.get(trigger).copied().unwrap_or(0)
+    }
+
+    // Triggers by use, most used first
+    pub fn most_used(&self, limit: usize) -> Vec<(&str, u64)> {
+        let mut counts: Vec<(&str, u64)> = self.counts.iter().map(|(t, c)| (t.as_str(), *c)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts.truncate(limit);
+        counts
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BangOverlay {
+    pub bangs: Vec<Bang>,
+    // Triggers of downloaded bangs that should never match
+    pub disabled: Vec<String>,
+    // Extra triggers, each mapped to the trigger of the bang it stands for
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl BangOverlay {
+    // A missing overlay file is the same as an empty one
+    pub fn load() -> Result<Self> {
+        let path = custom_bangs_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read custom bangs at {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse custom bangs at {:?}", path))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = custom_bangs_path()?;
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write custom bangs at {:?}", path))
+    }
+
+    pub fn is_disabled(&self, trigger: &str) -> bool {
+        self.disabled.iter().any(|t| t == trigger)
+    }
+
+    // Adds a custom bang, replacing any custom one with the same trigger and
+    // re-enabling a downloaded one it would otherwise leave disabled
+    pub fn add(&mut self, bang: Bang) -> Result<()> {
+        if bang.trigger.is_empty() || bang.trigger.contains(char::is_whitespace) {
+            return Err(anyhow::anyhow!("Trigger must be a single word"));
+        }
+        if !template::takes_query(&bang.url_template) {
+            return Err(anyhow::anyhow!("URL must contain {{query}} or {{{{{{s}}}}}} where the search goes"));
+        }
+
+        self.disabled.retain(|t| *t != bang.trigger);
+        self.bangs.retain(|b| b.trigger != bang.trigger);
+        self.bangs.push(bang);
+        Ok(())
+    }
+
+    // Replaces the aliases of a trigger, e.g. `youtube` for `yt`
+    pub fn set_aliases(&mut self, trigger: &str, aliases: &[String]) -> Result<()> {
+        if let Some(alias) = aliases.iter().find(|a| a.is_empty() || a.contains(char::is_whitespace)) {
+            return Err(anyhow::anyhow!("Alias '{}' must be a single word", alias));
+        }
+
+        self.aliases.retain(|_, target| target != trigger);
+        for alias in aliases {
+            self.aliases.insert(alias.clone(), trigger.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn aliases_of(&self, trigger: &str) -> Vec<&str> {
+        self.aliases
+            .iter()
+            .filter(|(_, target)| *target == trigger)
+            .map(|(alias, _)| alias.as_str())
+            .collect()
+    }
+
+    // Drops disabled triggers and lets custom bangs replace downloaded ones
+    // with the same trigger
+    pub fn apply(&self, mut bangs: Vec<Bang>) -> Vec<Bang> {
+        bangs.retain(|b| {
+            !self.is_disabled(&b.trigger) && !self.bangs.iter().any(|c| c.trigger == b.trigger)
+        });
+        bangs.extend(self.bangs.iter().cloned());
+        bangs
+    }
+}
+
+// The downloaded bangs that parsed, along with what was wrong with the rest
+pub fn load_default_bangs() -> Result<(Vec<Bang>, Vec<String>)> {
+    let path = bangs_path()?;
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read bangs file at {:?}", path))?;
+    parse_bangs(&content)
+        .with_context(|| format!("Failed to parse bangs file at {:?}", path))
+}
+
+// Parses a downloaded bang list entry by entry, so one malformed bang
+// doesn't cost the whole list. Entries that fail are described by index
+// and trigger in the returned errors.
+pub fn parse_bangs(content: &str) -> Result<(Vec<Bang>, Vec<String>)> {
+    let entries = serde_json::from_str::<Vec<serde_json::Value>>(content)
+        .context("Bang list is not a JSON array")?;
+
+    let mut bangs = Vec::with_capacity(entries.len());
+    let mut errors = Vec::new();
+    for (idx, entry) in entries.into_iter().enumerate() {
+        let trigger = entry
+            .get("trigger")
+            .or_else(|| entry.get("t"))
+            .and_then(|t| t.as_str())
+            .unwrap_or("?")
+            .to_string();
+        match serde_json::from_value::<Bang>(entry) {
+            Ok(bang) => match bang.validate() {
+                Ok(()) => bangs.push(bang),
+                Err(e) => errors.push(format!("Entry {} (!{}): {}", idx, trigger, e)),
+            },
+            Err(e) => errors.push(format!("Entry {} (!{}): {}", idx, trigger, e)),
+        }
+    }
+    Ok((bangs, errors))
+}
+
+// The downloaded bangs with the user's overlay applied
+#[derive(Debug, Default)]
+pub struct LoadedBangs {
+    pub bangs: Vec<Bang>,
+    pub aliases: BTreeMap<String, String>,
+    // Downloaded bangs the overlay switched off, still listed in the manager
+    pub disabled: Vec<Bang>,
+    // Triggers of the user's own bangs
+    pub custom: HashSet<String>,
+    // Downloaded entries that were skipped, see `parse_bangs`
+    pub errors: Vec<String>,
+}
+
+pub fn load_bangs() -> Result<LoadedBangs> {
+    let (bangs, errors) = load_default_bangs()?;
+    let overlay = BangOverlay::load()?;
+    let custom: HashSet<String> = overlay.bangs.iter().map(|b| b.trigger.clone()).collect();
+    let disabled = bangs
+        .iter()
+        .filter(|b| overlay.is_disabled(&b.trigger) && !custom.contains(&b.trigger))
+        .cloned()
+        .collect();
+
+    Ok(LoadedBangs {
+        bangs: overlay.apply(bangs),
+        aliases: overlay.aliases,
+        disabled,
+        custom,
+        errors,
+    })
+}
+
+// Builds a user bang from the fields of an add form, the name defaulting
+// to the URL's host
+pub fn custom_bang(trigger: &str, name: &str, url_template: &str) -> Result<Bang> {
+    let trigger = trigger.trim().trim_start_matches('!');
+    let url_template = url_template.trim();
+
+    if trigger.is_empty() || trigger.contains(char::is_whitespace) {
+        return Err(anyhow::anyhow!("Trigger must be a single word"));
+    }
+    if !template::takes_query(url_template) {
+        return Err(anyhow::anyhow!("URL must contain {{query}} or {{{{{{s}}}}}} where the search goes"));
+    }
+
+    let domain = url_template
+        .split("://")
+        .nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .filter(|host| !host.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("URL must start with a scheme such as https://"))?;
+
+    let name = if name.trim().is_empty() { domain } else { name.trim() };
+
+    Ok(Bang::new(
+        "Custom".to_string(),
+        domain.to_string(),
+        0,
+        name.to_string(),
+        "Custom".to_string(),
+        trigger.to_string(),
+        url_template.to_string(),
+    ))
+}
+
+// Whether the bang's category or subcategory is one of `categories`,
+// ignoring case and punctuation so `online-services` finds "Online Services"
+pub fn in_category(bang: &Bang, categories: &[String]) -> bool {
+    let normalize = |s: &str| -> String {
+        s.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+    };
+    let category = normalize(&bang.category);
+    let subcategory = normalize(&bang.subcategory);
+    categories.iter().map(|c| normalize(c)).any(|c| c == category || c == subcategory)
+}
+
+// Engine for site searches when there's no default bang to go through
+pub const SITE_SEARCH_TEMPLATE: &str = "https://duckduckgo.com/?q={{{s}}}";
+
+// Builds a bang that searches one site through a search engine bang by
+// putting `site:{domain}` in front of every query. The domain may be given
+// as a URL, and the trigger defaults to the domain's first label.
+pub fn site_search_bang(domain: &str, trigger: &str, engine: Option<&Bang>) -> Result<Bang> {
+    let domain = domain.trim();
+    let domain = domain.split_once("://").map_or(domain, |(_, rest)| rest);
+    let domain = domain.split('/').next().unwrap_or_default();
+    let domain = domain.strip_prefix("www.").unwrap_or(domain);
+    if !domain.contains('.') || domain.contains(char::is_whitespace) {
+        return Err(anyhow::anyhow!("'{}' is not a domain such as example.com", domain));
+    }
+
+    let trigger = trigger.trim().trim_start_matches('!');
+    let trigger = if trigger.is_empty() {
+        domain.split('.').next().unwrap_or(domain)
+    } else {
+        trigger
+    };
+
+    let template = engine.map_or(SITE_SEARCH_TEMPLATE, |e| e.url_template.as_str());
+    let site = format!("site:{} ", domain);
+    let encoded = encode_query(&site);
+    // Query placeholders get the site in front of them, in whichever form
+    // `template::render` knows them. The engine's `{domain}` is filled in
+    // now, as the new bang's domain is the site's; other variables stay
+    // for when the bang is used.
+    let url_template = template::render(template, |name| match name {
+        "s" | "query" => Some(format!("{}{{{}}}", encoded, name)),
+        "query_raw" => Some(format!("{}{{query_raw}}", site)),
+        "domain" => engine.map(|e| e.domain.clone()).filter(|d| !d.is_empty()),
+        _ => None,
+    });
+
+    Ok(Bang::new(
+        "Custom".to_string(),
+        domain.to_string(),
+        0,
+        format!("{} (site search)", domain),
+        "Site search".to_string(),
+        trigger.to_string(),
+        url_template,
+    ))
+}
+
+// Fills a bang's URL template with the search text: `{{{s}}}` and `{query}`
+// get the encoded query, `{query_raw}` the query as typed, `{domain}` the
+// bang's domain, `{lang}` and the config's variables their values. Without
+// any text the bang opens its site instead, so `!gh` alone goes to
+// github.com.
+pub fn expand(bang: &Bang, query: &str, vars: &TemplateVars) -> String {
+    let query = query.trim();
+    if query.is_empty() {
+        return base_url(bang);
+    }
+
+    template::render(&bang.url_template, |name| match name {
+        "s" | "query" => Some(encode_query(query)),
+        "query_raw" => Some(query.to_string()),
+        "domain" => Some(bang.domain.clone()),
+        "lang" => Some(vars.lang.clone()),
+        name => vars.user.get(name).cloned(),
+    })
+}
+
+// Runs a query through a chain of bangs, innermost first, each URL
+// becoming the next bang's query: `!translate !w quantum` translates the
+// Wikipedia search for "quantum"
+pub fn expand_chain(chain: &[Bang], query: &str, vars: &TemplateVars) -> String {
+    chain
+        .iter()
+        .rev()
+        .fold(query.to_string(), |text, bang| expand(bang, &text, vars))
+}
+
+// Site root for a bang, from its domain or else its template's host
+fn base_url(bang: &Bang) -> String {
+    let (scheme, rest) = bang.url_template.split_once("://").unwrap_or(("https", ""));
+    if !bang.domain.is_empty() {
+        return format!("{}://{}", scheme, bang.domain);
+    }
+
+    let host = rest.split('/').next().unwrap_or_default();
+    format!("{}://{}", scheme, host)
+}
+
+// Percent-encodes everything but unreserved characters
+fn encode_query(query: &str) -> String {
+    let mut encoded = String::with_capacity(query.len());
+    for byte in query.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -1,9 +1,15 @@
-use anyhow::{Context, Result};
+use crate::error::{OrionError, Result};
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::fs;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::hotkey::KeyCombo;
+use crate::models::ExecOptions;
+use crate::template;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub hotkey: HotkeyConfig,
     pub search: SearchConfig,
@@ -13,45 +19,692 @@ pub struct Config {
     pub log_file: Option<String>,
     pub ipc_socket_path: String,
     pub command_prefixes: Vec<CommandPrefix>,
+    #[serde(default)]
+    pub popup: PopupConfig,
+    #[serde(default)]
+    pub appearance: AppearanceConfig,
+    #[serde(default)]
+    pub voice: VoiceConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub security: SecurityConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    // How shell commands run unless they say otherwise:
+    //
+    //   [exec]
+    //   shell = ["bash", "-lc"]
+    //   cwd = "~/projects"
+    //   env = { EDITOR = "nvim" }
+    #[serde(default)]
+    pub exec: ExecOptions,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HotkeyConfig {
     pub key_combination: String,
     pub modifiers: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchConfig {
     pub max_results: usize,
     pub search_delay: u64,
+    // How long a provider may take before the search goes on without it,
+    // unless its `ProviderSettings` say otherwise
+    #[serde(default = "default_provider_timeout")]
+    pub provider_timeout: u64,
+    // Milliseconds a provider's results are reused for the same query, so
+    // backspacing and typing a prefix again doesn't search again; 0 turns
+    // caching off
+    #[serde(default = "default_cache_ttl")]
+    pub cache_ttl: u64,
+    // Trigger of the bang offered as the last result of every search,
+    // e.g. `ddg`
+    #[serde(default)]
+    pub default_bang: Option<String>,
+    // Categories or subcategories bangs must belong to, for when triggers
+    // collide; empty allows every category
+    #[serde(default)]
+    pub bang_categories: Vec<String>,
+    // Triggers no profile may use, for short ones like `a` or `i` in the
+    // downloaded list that would otherwise take over ordinary searches
+    #[serde(default)]
+    pub bang_deny: Vec<String>,
+    // Extra triggers, e.g. `youtube = "yt"`, on top of the bang overlay's
+    #[serde(default)]
+    pub bang_aliases: BTreeMap<String, String>,
+    // Language for `{lang}` in bang URLs, e.g. `de`; empty follows the
+    // system locale
+    #[serde(default)]
+    pub lang: String,
+    // Variables bang URLs can use as `{name}`, e.g. `region = "uk"`
+    #[serde(default)]
+    pub template_vars: BTreeMap<String, String>,
+}
+
+pub const MAX_RESULTS_RANGE: RangeInclusive<usize> = 1..=100;
+pub const SEARCH_DELAY_RANGE: RangeInclusive<u64> = 100..=5000;
+pub const PROVIDER_TIMEOUT_RANGE: RangeInclusive<u64> = 50..=4000;
+pub const CACHE_TTL_RANGE: RangeInclusive<u64> = 0..=600_000;
+
+fn default_provider_timeout() -> u64 {
+    1000
+}
+
+fn default_cache_ttl() -> u64 {
+    30_000
 }
 
 impl SearchConfig {
     pub fn validate(&self) -> Result<()> {
-        if self.max_results < 1 || self.max_results > 100 {
-            return Err(anyhow::anyhow!("max_results must be between 1 and 100"));
+        if !MAX_RESULTS_RANGE.contains(&self.max_results) {
+            return Err(OrionError::validation("max_results must be between 1 and 100"));
+        }
+        if !SEARCH_DELAY_RANGE.contains(&self.search_delay) {
+            return Err(OrionError::validation("search_delay must be between 100 and 5000"));
+        }
+        if !PROVIDER_TIMEOUT_RANGE.contains(&self.provider_timeout) {
+            return Err(OrionError::validation("provider_timeout must be between 50 and 4000"));
         }
-        if self.search_delay < 100 || self.search_delay > 5000 {
-            return Err(anyhow::anyhow!("search_delay must be between 100 and 5000"));
+        if !CACHE_TTL_RANGE.contains(&self.cache_ttl) {
+            return Err(OrionError::validation("cache_ttl must be between 0 and 600000"));
+        }
+        for trigger in &self.bang_deny {
+            let trigger = trigger.trim().trim_start_matches('!');
+            if trigger.is_empty() || trigger.contains(char::is_whitespace) {
+                return Err(OrionError::validation(format!("Invalid bang trigger '{}' in bang_deny", trigger)));
+            }
+        }
+        for name in self.template_vars.keys() {
+            if !template::is_variable_name(name) {
+                return Err(OrionError::validation(format!("Invalid template variable name '{}'", name)));
+            }
+            if template::BUILT_IN.contains(&name.as_str()) {
+                return Err(OrionError::validation(format!("Template variable '{}' is built in", name)));
+            }
         }
         Ok(())
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PopupConfig {
+    pub escape_behavior: EscapeBehavior,
+    pub close_on_focus_loss: bool,
+    // Keeps the popup open after every executed result, not just those
+    // run with an `execute_stay_open` key
+    pub stay_open: bool,
+    pub keys: PopupKeysConfig,
+    pub surface: PopupSurface,
+}
+
+impl Default for PopupConfig {
+    fn default() -> Self {
+        Self {
+            escape_behavior: EscapeBehavior::ClearThenClose,
+            close_on_focus_loss: true,
+            stay_open: false,
+            keys: PopupKeysConfig::default(),
+            surface: PopupSurface::default(),
+        }
+    }
+}
+
+// What kind of surface the popup opens as on Wayland
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PopupSurface {
+    // A layer-shell surface on wlroots compositors, a window elsewhere
+    #[default]
+    Auto,
+    // Always a regular window
+    Window,
+    // A layer-shell surface on any Wayland compositor that has the
+    // protocol, such as KDE's
+    LayerShell,
+}
+
+// Key combinations bound to each popup action, e.g. `next = ["Down", "Ctrl+J"]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PopupKeysConfig {
+    pub next: Vec<String>,
+    pub previous: Vec<String>,
+    pub execute: Vec<String>,
+    pub execute_stay_open: Vec<String>,
+    pub complete: Vec<String>,
+    // Lists what else the selected result can do, such as showing a file in
+    // its folder; shares Tab with `complete`, which wins when there's
+    // something to complete
+    pub actions: Vec<String>,
+    // Copies the selected result's text, path or URL
+    pub copy: Vec<String>,
+    pub close: Vec<String>,
+    // Reads the selected result out loud
+    pub speak: Vec<String>,
+}
+
+impl PopupKeysConfig {
+    pub fn validate(&self) -> Result<()> {
+        let all = self.next.iter()
+            .chain(&self.previous)
+            .chain(&self.execute)
+            .chain(&self.execute_stay_open)
+            .chain(&self.complete)
+            .chain(&self.actions)
+            .chain(&self.copy)
+            .chain(&self.close)
+            .chain(&self.speak);
+
+        for combo in all {
+            KeyCombo::parse(combo).map_err(|e| {
+                OrionError::validation(format!("Invalid popup key binding '{}': {:#}", combo, e))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for PopupKeysConfig {
+    fn default() -> Self {
+        let keys = |combos: &[&str]| combos.iter().map(|c| c.to_string()).collect();
+        Self {
+            next: keys(&["Down", "Ctrl+J", "Ctrl+N"]),
+            previous: keys(&["Up", "Ctrl+K", "Ctrl+P"]),
+            execute: keys(&["Enter"]),
+            execute_stay_open: keys(&["Shift+Enter"]),
+            complete: keys(&["Tab"]),
+            actions: keys(&["Tab"]),
+            copy: keys(&["Ctrl+C"]),
+            close: keys(&["Escape"]),
+            speak: keys(&["Ctrl+R"]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscapeBehavior {
+    // First Escape clears the query, a second one closes the popup
+    ClearThenClose,
+    // Escape always closes the popup
+    Close,
+}
+
+// Explicit popup geometry; anything left unset falls back to the size and
+// position remembered from the last session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppearanceConfig {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub y_offset: Option<f32>,
+    // Disables popup open/close and result list animations
+    pub reduce_motion: bool,
+    pub theme: ThemeMode,
+    // Hex colors such as `#598ffa`, unset for the theme's defaults
+    pub accent_color: Option<String>,
+    pub background_color: Option<String>,
+    pub text_color: Option<String>,
+    // Popup font family and base text size
+    pub font: Option<String>,
+    pub font_size: Option<f32>,
+    pub corner_radius: Option<f32>,
+    // Popup window opacity between 0.2 and 1.0
+    pub opacity: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+// Searching by voice, off unless enabled, e.g.
+//
+//   [voice]
+//   enabled = true
+//   model = "/usr/share/whisper/ggml-base.en.bin"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceConfig {
+    pub enabled: bool,
+    // Detection threshold between 0.0 and 1.0
+    pub sensitivity: f32,
+    // Said before a search, as in "hey orion, weather in Paris"
+    pub wake_word: String,
+    // The whisper.cpp model speech is transcribed with
+    pub model: Option<String>,
+    // whisper.cpp's command line program
+    pub whisper: String,
+    // Program and arguments writing 16 kHz mono 16-bit audio to stdout;
+    // arecord on Linux and sox elsewhere when empty
+    pub recorder: Vec<String>,
+}
+
+impl VoiceConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.sensitivity) {
+            return Err(OrionError::validation("voice.sensitivity must be between 0.0 and 1.0"));
+        }
+        if self.wake_word.trim().is_empty() {
+            return Err(OrionError::validation("voice.wake_word cannot be empty"));
+        }
+        if self.whisper.trim().is_empty() {
+            return Err(OrionError::validation("voice.whisper cannot be empty"));
+        }
+        Ok(())
+    }
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sensitivity: 0.7,
+            wake_word: "hey orion".to_string(),
+            model: None,
+            whisper: "whisper-cli".to_string(),
+            recorder: Vec::new(),
+        }
+    }
+}
+
+// When log files are rotated and how many old ones are kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    // Size in MB a log grows to before it's moved aside as `name.log.1`
+    pub max_size_mb: u64,
+    // Rotated logs kept besides the current one, 0 keeping none
+    pub max_files: usize,
+    // Rotated logs older than this many days are removed, unset keeping
+    // them until `max_files` pushes them out
+    pub max_age_days: Option<u64>,
+    // Gzips rotated logs, as `name.log.1.gz`
+    pub compress: bool,
+}
+
+impl LoggingConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=1024).contains(&self.max_size_mb) {
+            return Err(OrionError::validation("logging.max_size_mb must be between 1 and 1024"));
+        }
+        if self.max_files > 100 {
+            return Err(OrionError::validation("logging.max_files must be at most 100"));
+        }
+        if self.max_age_days == Some(0) {
+            return Err(OrionError::validation("logging.max_age_days must be at least 1"));
+        }
+        Ok(())
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            max_size_mb: 10,
+            max_files: 5,
+            max_age_days: None,
+            compress: false,
+        }
+    }
+}
+
+// How much of the audit trail of executed results is kept:
+//
+//   [audit]
+//   max_age_days = 30
+//   max_entries = 5000
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    // Nothing more is recorded when off, what was recorded staying
+    pub enabled: bool,
+    // Entries older than this many days are dropped, unset keeping them
+    // until `max_entries` pushes them out
+    pub max_age_days: Option<u64>,
+    // The oldest entries past this many are dropped
+    pub max_entries: usize,
+}
+
+impl AuditConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.max_age_days == Some(0) {
+            return Err(OrionError::validation("audit.max_age_days must be at least 1"));
+        }
+        if !(1..=1_000_000).contains(&self.max_entries) {
+            return Err(OrionError::validation("audit.max_entries must be between 1 and 1000000"));
+        }
+        Ok(())
+    }
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age_days: Some(90),
+            max_entries: 10_000,
+        }
+    }
+}
+
+// Which shell commands the background runs and which URLs it opens,
+// checked before each:
+//
+//   [security]
+//   commands = "ask"
+//   allow = ["code *", "firefox*"]
+//   deny = ["*rm -rf*", "sudo *"]
+//   url_schemes = ["zoommtg"]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityConfig {
+    // What becomes of commands neither list matches
+    pub commands: CommandPolicy,
+    // Commands run without asking, `*` matching anything
+    pub allow: Vec<String>,
+    // Commands never run, even when `allow` matches them too
+    pub deny: Vec<String>,
+    // Schemes opened without asking besides http, https and mailto; URLs
+    // with any other scheme open once confirmed in the popup
+    pub url_schemes: Vec<String>,
+}
+
+const SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+// Never opened, as they run code rather than lead anywhere. Bang templates
+// come from a downloaded list, so a URL may not be what it seems.
+const BLOCKED_SCHEMES: [&str; 3] = ["javascript", "data", "vbscript"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandPolicy {
+    #[default]
+    Allow,
+    // Run once confirmed in the popup
+    Ask,
+    Deny,
+}
+
+impl SecurityConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.allow.iter().chain(&self.deny).any(|pattern| pattern.trim().is_empty()) {
+            return Err(OrionError::validation("security.allow and security.deny cannot list empty patterns"));
+        }
+        for scheme in &self.url_schemes {
+            if !is_scheme(scheme) {
+                return Err(OrionError::validation(format!("Invalid URL scheme '{}' in security.url_schemes", scheme)));
+            }
+            if BLOCKED_SCHEMES.contains(&scheme.to_lowercase().as_str()) {
+                return Err(OrionError::validation(format!("{}: URLs are never opened", scheme)));
+            }
+        }
+        Ok(())
+    }
+
+    // URLs without a scheme would be taken for files, so they're asked
+    // about like unknown schemes
+    pub fn url_policy(&self, url: &str) -> CommandPolicy {
+        let scheme = url
+            .trim()
+            .split_once(':')
+            .map(|(scheme, _)| scheme.to_lowercase())
+            .filter(|scheme| is_scheme(scheme));
+        match scheme {
+            Some(scheme) if BLOCKED_SCHEMES.contains(&scheme.as_str()) => CommandPolicy::Deny,
+            Some(scheme)
+                if SAFE_SCHEMES.contains(&scheme.as_str())
+                    || self.url_schemes.iter().any(|s| s.eq_ignore_ascii_case(&scheme)) =>
+            {
+                CommandPolicy::Allow
+            }
+            _ => CommandPolicy::Ask,
+        }
+    }
+
+    pub fn policy(&self, command: &str) -> CommandPolicy {
+        let command = command.trim();
+        let matches = |patterns: &[String]| patterns.iter().any(|p| wildcard_match(p.trim(), command));
+        if matches(&self.deny) {
+            CommandPolicy::Deny
+        } else if matches(&self.allow) {
+            CommandPolicy::Allow
+        } else {
+            self.commands
+        }
+    }
+}
+
+fn validate_exec(exec: &ExecOptions, what: &str) -> Result<()> {
+    if exec.shell.first().is_some_and(|program| program.trim().is_empty()) {
+        return Err(OrionError::validation(format!("The shell of {} cannot be empty", what)));
+    }
+    if exec.cwd.as_deref().is_some_and(|cwd| cwd.trim().is_empty()) {
+        return Err(OrionError::validation(format!("The cwd of {} cannot be empty", what)));
+    }
+    if let Some(name) = exec.env.keys().find(|name| name.is_empty() || name.contains('=')) {
+        return Err(OrionError::validation(format!("'{}' in the env of {} isn't a variable name", name, what)));
+    }
+    Ok(())
+}
+
+// A letter, then letters, digits, `+`, `-` or `.`
+fn is_scheme(scheme: &str) -> bool {
+    scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+// `*` matches any run of characters, spaces and slashes included
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*` at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
     pub name: String,
     pub commands: Vec<Command>,
+    // Provider preferences in priority order; providers not listed run
+    // after these, enabled and with default options
+    #[serde(default)]
+    pub providers: Vec<ProviderSettings>,
+    // Bang triggers this profile may use; empty allows every bang
+    #[serde(default)]
+    pub bangs_allow: Vec<String>,
+    // Bang triggers this profile may never use, even when allowed above
+    #[serde(default)]
+    pub bangs_deny: Vec<String>,
+    #[serde(default)]
+    pub bang_position: BangPosition,
+    // Matches `!GH` to the `gh` bang
+    #[serde(default)]
+    pub bang_ignore_case: bool,
+    // Only words starting with `!` count as triggers, so a bare `w` in a
+    // query stays a word
+    #[serde(default)]
+    pub bang_require_prefix: bool,
+    #[serde(default)]
+    pub speech: SpeechConfig,
+}
+
+// Reading results out loud, per profile, e.g.
+//
+//   [profiles.speech]
+//   after_voice = true
+//   rate = 160
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeechConfig {
+    // Reads out the top result of a search started by voice
+    pub after_voice: bool,
+    // A voice the platform's speech synthesizer knows, unset for its default
+    pub voice: Option<String>,
+    // Words per minute, unset for the synthesizer's default
+    pub rate: Option<u32>,
+}
+
+pub const SPEECH_RATE_RANGE: RangeInclusive<u32> = 80..=500;
+
+impl SpeechConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.rate.is_some_and(|rate| !SPEECH_RATE_RANGE.contains(&rate)) {
+            return Err(OrionError::validation("speech.rate must be between 80 and 500 words per minute"));
+        }
+        if self.voice.as_deref().is_some_and(|voice| voice.trim().is_empty()) {
+            return Err(OrionError::validation("speech.voice cannot be empty"));
+        }
+        Ok(())
+    }
+}
+
+// Where in a query a bang trigger is recognised
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BangPosition {
+    // At the start, the end or between any two words
+    #[default]
+    Anywhere,
+    // Only as the first word, so ordinary words that happen to be triggers
+    // don't take over the query
+    PrefixOnly,
+}
+
+impl BangPosition {
+    pub const ALL: [BangPosition; 2] = [BangPosition::Anywhere, BangPosition::PrefixOnly];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BangPosition::Anywhere => "anywhere",
+            BangPosition::PrefixOnly => "prefix_only",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == s)
+    }
+}
+
+impl std::fmt::Display for BangPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BangPosition::Anywhere => write!(f, "Anywhere in the query"),
+            BangPosition::PrefixOnly => write!(f, "First word only"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderSettings {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    // Provider-specific options such as `roots` for the files provider
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+    // Multiplies the provider's scores when results are merged, so a noisy
+    // provider can be turned down without disabling it
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    // Milliseconds, overriding the search's `provider_timeout`
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+pub const PROVIDER_WEIGHT_RANGE: RangeInclusive<f32> = 0.0..=2.0;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_weight() -> f32 {
+    1.0
 }
 
 impl Profile {
     pub fn validate(&self) -> Result<()> {
         if self.name.is_empty() {
-            return Err(anyhow::anyhow!("Profile name cannot be empty"));
+            return Err(OrionError::validation("Profile name cannot be empty"));
+        }
+        for command in &self.commands {
+            command.validate().map_err(|e| {
+                OrionError::validation(format!("Invalid command in profile '{}': {}", self.name, e))
+            })?;
         }
+        for provider in &self.providers {
+            if !PROVIDER_WEIGHT_RANGE.contains(&provider.weight) {
+                return Err(OrionError::validation(format!(
+                    "Weight of provider '{}' in profile '{}' must be between 0.0 and 2.0",
+                    provider.name, self.name
+                )));
+            }
+            if provider.timeout.is_some_and(|t| !PROVIDER_TIMEOUT_RANGE.contains(&t)) {
+                return Err(OrionError::validation(format!(
+                    "Timeout of provider '{}' in profile '{}' must be between 50 and 4000",
+                    provider.name, self.name
+                )));
+            }
+        }
+        for trigger in self.bangs_allow.iter().chain(&self.bangs_deny) {
+            let trigger = trigger.trim().trim_start_matches('!');
+            if trigger.is_empty() || trigger.contains(char::is_whitespace) {
+                return Err(OrionError::validation(format!("Invalid bang trigger '{}' in profile '{}'", trigger, self.name)));
+            }
+        }
+        self.speech.validate().map_err(|e| {
+            OrionError::validation(format!("Invalid speech settings in profile '{}': {}", self.name, e))
+        })?;
         Ok(())
     }
+
+    pub fn provider_settings(&self, name: &str) -> Option<&ProviderSettings> {
+        self.providers.iter().find(|p| p.name == name)
+    }
+
+    pub fn restricts_bangs(&self) -> bool {
+        !self.bangs_allow.is_empty() || !self.bangs_deny.is_empty()
+    }
+
+    pub fn allows_bang(&self, trigger: &str) -> bool {
+        (self.bangs_allow.is_empty() || lists_trigger(&self.bangs_allow, trigger))
+            && !lists_trigger(&self.bangs_deny, trigger)
+    }
+}
+
+// Triggers may be listed with or without their leading `!`
+pub fn lists_trigger(list: &[String], trigger: &str) -> bool {
+    list.iter().any(|t| t.trim().trim_start_matches('!').eq_ignore_ascii_case(trigger))
+}
+
+fn parse_setting<T: std::str::FromStr>(key: &str, value: &str) -> Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|e| OrionError::validation(format!("Invalid value '{}' for {}: {}", value, key, e)))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -74,20 +727,78 @@ pub struct CommandPrefix {
     pub commands: Vec<Command>,
 }
 
+// A command opens its URL, or runs a shell command instead:
+//
+//   [[profiles.commands]]
+//   name = "Restart nginx"
+//   run = "systemctl restart nginx"
+//   elevated = true
+//
+// Commands that aren't elevated can set their own `shell`, `cwd` and `env`,
+// as in `[exec]`.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Command {
     pub name: String,
+    #[serde(default)]
     pub url: String,
+    #[serde(default)]
+    pub run: Option<String>,
+    // Runs `run` as root or administrator, through pkexec or `sudo -A` on
+    // Linux, an authorization dialog on macOS and UAC on Windows, each
+    // asking for a password first
+    #[serde(default)]
+    pub elevated: bool,
+    #[serde(flatten)]
+    pub exec: ExecOptions,
     pub description: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+impl Command {
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(OrionError::validation("Command name cannot be empty"));
+        }
+        let run = self.run.as_deref().map(str::trim).filter(|run| !run.is_empty());
+        match (self.url.trim().is_empty(), run) {
+            (true, None) => {
+                return Err(OrionError::validation(format!("Command '{}' needs a URL or something to run", self.name)));
+            }
+            (false, Some(_)) => {
+                return Err(OrionError::validation(format!("Command '{}' can't both open a URL and run something", self.name)));
+            }
+            _ => {}
+        }
+        if self.elevated && run.is_none() {
+            return Err(OrionError::validation(format!("Command '{}' only runs elevated with something to run", self.name)));
+        }
+        if !self.exec.is_empty() {
+            if run.is_none() {
+                return Err(OrionError::validation(format!("Command '{}' has a shell, cwd or env but nothing to run", self.name)));
+            }
+            // pkexec and UAC start from a clean slate of their own
+            if self.elevated {
+                return Err(OrionError::validation(format!("Command '{}' runs elevated, which can't set shell, cwd or env", self.name)));
+            }
+        }
+        validate_exec(&self.exec, &self.name)
+    }
 }
 
 impl Config {
     pub fn validate(&self) -> Result<()> {
         if self.current_profile.is_empty() {
-            return Err(anyhow::anyhow!("Current profile cannot be empty"));
+            return Err(OrionError::validation("Current profile cannot be empty"));
         }
         
         self.search.validate()?;
+        self.popup.keys.validate()?;
+        self.voice.validate()?;
+        self.logging.validate()?;
+        self.security.validate()?;
+        self.audit.validate()?;
+        validate_exec(&self.exec, "exec")?;
         
         for profile in &self.profiles {
             profile.validate()?;
@@ -98,9 +809,10 @@ impl Config {
 
     pub fn load(path: &PathBuf) -> Result<Self> {
         let content = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read config file at {:?}", path))?;
-        let config: Config = toml::from_str(&content)
-            .with_context(|| format!("Failed to parse config file at {:?}", path))?;
+            .map_err(|e| OrionError::io(format!("Failed to read config file at {:?}", path), e))?;
+        let config: Config = toml::from_str(&content).map_err(|e| {
+            OrionError::Serde(format!("Failed to parse config file at {:?}: {}", path, e.message()))
+        })?;
         config.validate()?;
         Ok(config)
     }
@@ -108,7 +820,8 @@ impl Config {
     pub fn save(&self, path: &PathBuf) -> Result<()> {
         self.validate()?;
         let content = toml::to_string_pretty(self)?;
-        fs::write(path, content)?;
+        fs::write(path, content)
+            .map_err(|e| OrionError::io(format!("Failed to write config file at {:?}", path), e))?;
         Ok(())
     }
 
@@ -116,7 +829,26 @@ impl Config {
         self.profiles
             .iter()
             .find(|p| p.name == self.current_profile)
-            .with_context(|| format!("Current profile '{}' not found", self.current_profile))
+            .ok_or_else(|| OrionError::validation(format!("Current profile '{}' not found", self.current_profile)))
+    }
+
+    // The named profile, or the current one when no name is given
+    pub fn profile_or_current(&self, name: Option<&str>) -> Result<&Profile> {
+        let Some(name) = name else {
+            return self.get_current_profile();
+        };
+        self.profiles
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| OrionError::validation(format!("Profile '{}' not found", name)))
+    }
+
+    fn current_profile_mut(&mut self) -> Result<&mut Profile> {
+        let current = &self.current_profile;
+        self.profiles
+            .iter_mut()
+            .find(|p| p.name == *current)
+            .ok_or_else(|| OrionError::validation(format!("Current profile '{}' not found", current)))
     }
 
     pub fn get_profile_names(&self) -> Vec<String> {
@@ -125,18 +857,47 @@ impl Config {
 
     pub fn add_profile(&mut self, name: String) -> Result<()> {
         if self.profiles.iter().any(|p| p.name == name) {
-            return Err(anyhow::anyhow!("Profile '{}' already exists", name));
+            return Err(OrionError::validation(format!("Profile '{}' already exists", name)));
         }
         self.profiles.push(Profile {
             name,
             commands: Vec::new(),
+            providers: Vec::new(),
+            bangs_allow: Vec::new(),
+            bangs_deny: Vec::new(),
+            bang_position: BangPosition::default(),
+            bang_ignore_case: false,
+            bang_require_prefix: false,
+            speech: SpeechConfig::default(),
         });
         Ok(())
     }
 
+    // Renames a profile, keeping `current_profile` pointing at it
+    pub fn rename_profile(&mut self, old: &str, new: &str) -> Result<()> {
+        let new = new.trim();
+        if new.is_empty() {
+            return Err(OrionError::validation("Profile name cannot be empty"));
+        }
+        if new != old && self.profiles.iter().any(|p| p.name == new) {
+            return Err(OrionError::validation(format!("Profile '{}' already exists", new)));
+        }
+
+        let profile = self.profiles
+            .iter_mut()
+            .find(|p| p.name == old)
+            .ok_or_else(|| OrionError::validation(format!("Profile '{}' not found", old)))?;
+        profile.name = new.to_string();
+
+        if self.current_profile == old {
+            self.current_profile = new.to_string();
+        }
+        Ok(())
+    }
+
     pub fn remove_profile(&mut self, name: &str) -> Result<()> {
         if name == self.current_profile {
-            return Err(anyhow::anyhow!("Cannot remove current profile"));
+            return Err(OrionError::validation("Cannot remove current profile"));
         }
         self.profiles.retain(|p| p.name != name);
         Ok(())
@@ -146,12 +907,56 @@ impl Config {
         for (key, value) in settings {
             match key.as_str() {
                 "max_results" => {
-                    self.search.max_results = value.parse()?;
+                    self.search.max_results = parse_setting(&key, &value)?;
                 }
                 "search_delay" => {
-                    self.search.search_delay = value.parse()?;
+                    self.search.search_delay = parse_setting(&key, &value)?;
+                }
+                "provider_timeout" => {
+                    self.search.provider_timeout = parse_setting(&key, &value)?;
+                }
+                "cache_ttl" => {
+                    self.search.cache_ttl = parse_setting(&key, &value)?;
+                }
+                "default_bang" => {
+                    let trigger = value.trim().trim_start_matches('!');
+                    self.search.default_bang = Some(trigger.to_string()).filter(|t| !t.is_empty());
+                }
+                // These apply to the current profile
+                "bang_position" => {
+                    self.current_profile_mut()?.bang_position = BangPosition::parse(&value)
+                        .ok_or_else(|| OrionError::validation(format!("Unknown bang position '{}'", value)))?;
+                }
+                "bang_ignore_case" => {
+                    self.current_profile_mut()?.bang_ignore_case = parse_setting(&key, &value)?;
+                }
+                "bang_require_prefix" => {
+                    self.current_profile_mut()?.bang_require_prefix = parse_setting(&key, &value)?;
+                }
+                "speak_after_voice" => {
+                    self.current_profile_mut()?.speech.after_voice = parse_setting(&key, &value)?;
+                }
+                "speech_rate" => {
+                    self.current_profile_mut()?.speech.rate = match value.trim() {
+                        "" => None,
+                        rate => Some(parse_setting(&key, rate)?),
+                    };
+                }
+                "bang_categories" => {
+                    self.search.bang_categories = value
+                        .split(',')
+                        .map(|c| c.trim().to_string())
+                        .filter(|c| !c.is_empty())
+                        .collect();
+                }
+                "bang_deny" => {
+                    self.search.bang_deny = value
+                        .split(',')
+                        .map(|t| t.trim().trim_start_matches('!').to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect();
                 }
-                _ => return Err(anyhow::anyhow!("Unknown setting: {}", key)),
+                _ => return Err(OrionError::validation(format!("Unknown setting: {}", key))),
             }
         }
         self.validate()?;
@@ -169,11 +974,26 @@ impl Default for Config {
             search: SearchConfig {
                 max_results: 10,
                 search_delay: 200,
+                provider_timeout: default_provider_timeout(),
+                cache_ttl: default_cache_ttl(),
+                default_bang: None,
+                bang_categories: Vec::new(),
+                bang_deny: Vec::new(),
+                bang_aliases: BTreeMap::new(),
+                lang: String::new(),
+                template_vars: BTreeMap::new(),
             },
             profiles: vec![
                 Profile {
                     name: "Default".to_string(),
                     commands: Vec::new(),
+                    providers: Vec::new(),
+                    bangs_allow: Vec::new(),
+                    bangs_deny: Vec::new(),
+                    bang_position: BangPosition::default(),
+                    bang_ignore_case: false,
+                    bang_require_prefix: false,
+                    speech: SpeechConfig::default(),
                 }
             ],
             current_profile: "Default".to_string(),
@@ -181,6 +1001,13 @@ impl Default for Config {
             log_file: None,
             ipc_socket_path: "orion.sock".to_string(),
             command_prefixes: Vec::new(),
+            popup: PopupConfig::default(),
+            appearance: AppearanceConfig::default(),
+            voice: VoiceConfig::default(),
+            logging: LoggingConfig::default(),
+            security: SecurityConfig::default(),
+            audit: AuditConfig::default(),
+            exec: ExecOptions::default(),
         }
     }
 } 
\ No newline at end of file
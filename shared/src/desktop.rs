@@ -0,0 +1,317 @@
+use anyhow::{Context, Result};
+use crate::models::ExecOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Desktop integration behind the clipboard, paste, open-with, reveal, eject,
+// launch and shell command actions, done through each platform's own tools
+// like opening files is
+
+// Long enough for the popup to close and focus to return to the window the
+// text is pasted into
+#[cfg(not(target_os = "windows"))]
+const PASTE_DELAY: &str = "0.2";
+#[cfg(target_os = "windows")]
+const PASTE_DELAY: &str = "200";
+
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    return pipe_to("powershell", &["-NoProfile", "-Command", "$input | Set-Clipboard"], text);
+
+    #[cfg(target_os = "macos")]
+    return pipe_to("pbcopy", &[], text);
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let tools: &[(&str, &[&str])] = if wayland {
+            &[("wl-copy", &[]), ("xclip", &["-selection", "clipboard"])]
+        } else {
+            &[("xclip", &["-selection", "clipboard"]), ("xsel", &["--clipboard", "--input"])]
+        };
+
+        let mut last_error = None;
+        for (program, args) in tools {
+            match pipe_to(program, args, text) {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No clipboard tool found")))
+            .context("Copying needs wl-copy, xclip or xsel")
+    }
+}
+
+// Copies the text and then presses the platform's paste shortcut once the
+// popup is gone. The keystroke comes from a detached shell, so it still
+// happens when the caller exits first.
+pub fn paste_text(text: &str) -> Result<()> {
+    copy_to_clipboard(text)?;
+
+    #[cfg(target_os = "windows")]
+    let (shell, flag, script) = (
+        "powershell",
+        "-Command",
+        format!(
+            "Start-Sleep -Milliseconds {}; (New-Object -ComObject WScript.Shell).SendKeys('^v')",
+            PASTE_DELAY
+        ),
+    );
+
+    #[cfg(target_os = "macos")]
+    let (shell, flag, script) = (
+        "sh",
+        "-c",
+        format!(
+            "sleep {}; osascript -e 'tell application \"System Events\" to keystroke \"v\" using command down'",
+            PASTE_DELAY
+        ),
+    );
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let (shell, flag, script) = (
+        "sh",
+        "-c",
+        format!(
+            "sleep {}; if [ -n \"$WAYLAND_DISPLAY\" ]; then wtype -M ctrl v -m ctrl; else xdotool key --clearmodifiers ctrl+v; fi",
+            PASTE_DELAY
+        ),
+    );
+
+    Command::new(shell)
+        .arg(flag)
+        .arg(script)
+        .spawn()
+        .context("Failed to send the paste shortcut")?;
+    Ok(())
+}
+
+// Opens a file with a given application, a name like `Preview` on macOS
+// and a command like `gimp` elsewhere
+pub fn open_with(path: &Path, app: &str) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg("-a").arg(app).arg(path).spawn();
+
+    #[cfg(not(target_os = "macos"))]
+    let result = Command::new(app).arg(path).spawn();
+
+    result.with_context(|| format!("Failed to open {:?} with {}", path, app))?;
+    Ok(())
+}
+
+// Shows a file selected in the file manager, or opens its folder where the
+// file manager can't select it
+pub fn reveal_in_folder(path: &Path) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    let result = Command::new("explorer").arg(format!("/select,{}", path.display())).spawn();
+
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg("-R").arg(path).spawn();
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = {
+        let shown = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", file_uri(path)))
+            .arg("string:")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+
+        if shown {
+            return Ok(());
+        }
+        let folder = path.parent().unwrap_or(path);
+        Command::new("xdg-open").arg(folder).spawn()
+    };
+
+    result.with_context(|| format!("Failed to reveal {:?}", path))?;
+    Ok(())
+}
+
+// Unmounts a drive or network share, which for removable drives also makes
+// them safe to unplug
+pub fn eject(path: &Path) -> Result<()> {
+    let mount = path.to_string_lossy();
+
+    #[cfg(target_os = "windows")]
+    return run_checked(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            &format!(
+                "(New-Object -ComObject Shell.Application).Namespace(17).ParseName('{}').InvokeVerb('Eject')",
+                mount.trim_end_matches('\\').replace('\'', "''")
+            ),
+        ],
+    );
+
+    #[cfg(target_os = "macos")]
+    return run_checked("diskutil", &["eject", &mount]);
+
+    // gio goes through the same udisks and gvfs the file manager uses, so no
+    // root is needed; plain umount only works for `user` mounts in fstab
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    run_checked("gio", &["mount", "--eject", &mount])
+        .or_else(|_| run_checked("umount", &[&mount]))
+        .with_context(|| format!("Failed to eject {:?}", path))
+}
+
+// A shell command set up the way `options` say, yet to be started
+pub fn shell_command(command: &str, options: &ExecOptions) -> Command {
+    let mut shell = match options.shell.split_first() {
+        Some((program, args)) => {
+            let mut shell = Command::new(program);
+            shell.args(args);
+            shell
+        }
+        #[cfg(target_os = "windows")]
+        None => {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        }
+        #[cfg(not(target_os = "windows"))]
+        None => {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        }
+    };
+    shell.arg(command).envs(&options.env);
+    if let Some(dir) = working_dir(options.cwd.as_deref()) {
+        shell.current_dir(dir);
+    }
+    shell
+}
+
+// `~` stands for the home directory, which is also where commands start
+// without a directory, rather than wherever the service was started
+fn working_dir(cwd: Option<&str>) -> Option<PathBuf> {
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    match cwd.map(str::trim) {
+        None | Some("~") => home,
+        Some(cwd) => match cwd.strip_prefix("~/") {
+            Some(rest) => home.map(|home| home.join(rest)),
+            None => Some(PathBuf::from(cwd)),
+        },
+    }
+}
+
+// Runs a shell command as root or administrator, the system asking for a
+// password first. Like other commands it isn't waited for, so the prompt
+// stays up after the popup closes.
+pub fn run_elevated(command: &str) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    let result = Command::new("powershell")
+        .args(["-NoProfile", "-Command"])
+        .arg(format!(
+            "Start-Process cmd -Verb RunAs -ArgumentList '/C', '{}'",
+            command.replace('\'', "''")
+        ))
+        .spawn();
+
+    #[cfg(target_os = "macos")]
+    let result = Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "do shell script \"{}\" with administrator privileges",
+            command.replace('\\', "\\\\").replace('"', "\\\"")
+        ))
+        .spawn();
+
+    // pkexec asks through the desktop's polkit agent; without it, sudo asks
+    // through the program SUDO_ASKPASS names, as the popup has no terminal
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = match Command::new("pkexec").args(["sh", "-c", command]).spawn() {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if std::env::var_os("SUDO_ASKPASS").is_none() {
+                anyhow::bail!("Running as root needs pkexec, or sudo with SUDO_ASKPASS set");
+            }
+            Command::new("sudo").args(["-A", "sh", "-c", command]).stdin(Stdio::null()).spawn()
+        }
+        result => result,
+    };
+
+    result.with_context(|| format!("Failed to run '{}' as administrator", command))?;
+    Ok(())
+}
+
+// Starts an application the platform's way. On macOS that is Launch
+// Services through `open -a`, the same as clicking it in the Finder, so a
+// running app is brought to the front rather than started twice.
+pub fn launch_app(app: &str) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg("-a").arg(app).spawn();
+
+    // Explorer hands shortcuts and `shell:AppsFolder\<AUMID>` ids of Store
+    // apps alike to ShellExecute
+    #[cfg(target_os = "windows")]
+    let result = Command::new("explorer").arg(app).spawn();
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = Command::new("xdg-open").arg(app).spawn();
+
+    result.with_context(|| format!("Failed to launch {}", app))?;
+    Ok(())
+}
+
+fn run_checked(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    child
+        .stdin
+        .take()
+        .context("No stdin")?
+        .write_all(text.as_bytes())?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}", program, status));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+
+// Failures from the shared crate, grouped by kind so callers can tell a bad
+// config from a dropped connection. Callers on anyhow still use `?` as before.
+#[derive(Debug, thiserror::Error)]
+pub enum OrionError {
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{0}")]
+    Serde(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    Ipc(String),
+    #[error("{name}: {message}")]
+    Provider { name: String, message: String },
+}
+
+pub type Result<T, E = OrionError> = std::result::Result<T, E>;
+
+impl OrionError {
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        OrionError::Io { context: context.into(), source }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        OrionError::Validation(message.into())
+    }
+
+    pub fn ipc(message: impl Into<String>) -> Self {
+        OrionError::Ipc(message.into())
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            OrionError::Io { .. } => ErrorCode::Io,
+            OrionError::Serde(_) => ErrorCode::Serde,
+            OrionError::Validation(_) => ErrorCode::Validation,
+            OrionError::Ipc(_) => ErrorCode::Ipc,
+            OrionError::Provider { .. } => ErrorCode::Provider,
+        }
+    }
+}
+
+impl From<io::Error> for OrionError {
+    fn from(source: io::Error) -> Self {
+        OrionError::io("I/O error", source)
+    }
+}
+
+impl From<serde_json::Error> for OrionError {
+    fn from(e: serde_json::Error) -> Self {
+        OrionError::Serde(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for OrionError {
+    fn from(e: toml::de::Error) -> Self {
+        OrionError::Serde(e.message().to_string())
+    }
+}
+
+impl From<toml::ser::Error> for OrionError {
+    fn from(e: toml::ser::Error) -> Self {
+        OrionError::Serde(e.to_string())
+    }
+}
+
+// Kind of failure carried by an IPC `Error` reply, `Other` for anything not
+// raised as an `OrionError`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Io,
+    Serde,
+    Validation,
+    Ipc,
+    Provider,
+    Other,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Io => "io",
+            ErrorCode::Serde => "serde",
+            ErrorCode::Validation => "validation",
+            ErrorCode::Ipc => "ipc",
+            ErrorCode::Provider => "provider",
+            ErrorCode::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// The reply sent for a failed request. It is an error itself, so clients
+// can hand it to anyhow and still downcast to it for the code.
+#[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error)]
+#[error("{message}")]
+pub struct ErrorReply {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ErrorReply {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+// Takes the code from the first `OrionError` in the chain and the message
+// from the whole chain
+impl From<&anyhow::Error> for ErrorReply {
+    fn from(e: &anyhow::Error) -> Self {
+        let code = e
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<OrionError>())
+            .map_or(ErrorCode::Other, OrionError::code);
+        Self::new(code, format!("{:#}", e))
+    }
+}
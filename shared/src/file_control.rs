@@ -1,8 +1,13 @@
-use anyhow::{Context, Result};
+use crate::error::{OrionError, Result};
 use std::path::{Path, PathBuf};
-use std::fs;
+use std::fs::{self, File, ReadDir};
+use std::io::{Read, Write};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 use serde::{Serialize, Deserialize};
 
+mod archive;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileInfo {
     pub path: PathBuf,
@@ -10,8 +15,112 @@ pub struct FileInfo {
     pub is_file: bool,
     pub is_directory: bool,
     pub modified: std::time::SystemTime,
+    // Guessed from the extension, `inode/directory` for directories
+    #[serde(default)]
+    pub mime: Option<String>,
+    // Unix permission bits, e.g. 0o644
+    #[serde(default)]
+    pub mode: Option<u32>,
+    #[serde(default)]
+    pub readonly: bool,
+    // User name of the owner where the platform has one
+    #[serde(default)]
+    pub owner: Option<String>,
+    // Icon name from the freedesktop.org naming spec, e.g. `image-x-generic`
+    #[serde(default)]
+    pub icon: String,
+}
+
+impl FileInfo {
+    fn from_metadata(path: PathBuf, metadata: &fs::Metadata) -> Result<Self> {
+        #[cfg(unix)]
+        let (mode, owner) = {
+            use std::os::unix::fs::MetadataExt;
+            let owner = uzers::get_user_by_uid(metadata.uid()).map(|user| user.name().to_string_lossy().into_owned());
+            (Some(metadata.mode() & 0o7777), owner)
+        };
+        #[cfg(not(unix))]
+        let (mode, owner) = (None, None);
+
+        let mut info = Self::described(path, metadata.is_dir(), metadata.len(), metadata.modified()?);
+        // Programs usually have no extension to guess from
+        if info.is_file && info.mime.is_none() && mode.is_some_and(|mode| mode & 0o111 != 0) {
+            info.icon = "application-x-executable".to_string();
+        }
+        info.mode = mode;
+        info.readonly = metadata.permissions().readonly();
+        info.owner = owner;
+        Ok(info)
+    }
+
+    // What can be told from the name alone, for entries without metadata of
+    // their own such as those inside archives
+    fn described(path: PathBuf, is_dir: bool, size: u64, modified: std::time::SystemTime) -> Self {
+        let mime = if is_dir {
+            Some("inode/directory".to_string())
+        } else {
+            mime_guess::from_path(&path).first().map(|mime| mime.essence_str().to_string())
+        };
+        let icon = icon_for(mime.as_deref()).to_string();
+
+        Self {
+            path,
+            size: if is_dir { 0 } else { size },
+            is_file: !is_dir,
+            is_directory: is_dir,
+            modified,
+            mime,
+            mode: None,
+            readonly: false,
+            owner: None,
+            icon,
+        }
+    }
+
+    // `rwxr-xr-x` style, where the platform has permission bits
+    pub fn permissions(&self) -> Option<String> {
+        let mode = self.mode?;
+        let mut text = String::with_capacity(9);
+        for shift in [6, 3, 0] {
+            let bits = (mode >> shift) & 0o7;
+            text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            text.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+        }
+        Some(text)
+    }
+}
+
+// Generic icons by kind of content, which every icon theme has
+fn icon_for(mime: Option<&str>) -> &'static str {
+    let Some(mime) = mime else {
+        return "text-x-generic";
+    };
+    let (kind, subtype) = mime.split_once('/').unwrap_or((mime, ""));
+
+    match (kind, subtype) {
+        ("inode", "directory") => "folder",
+        ("image", _) => "image-x-generic",
+        ("audio", _) => "audio-x-generic",
+        ("video", _) => "video-x-generic",
+        ("font", _) => "font-x-generic",
+        ("application", "pdf") => "application-pdf",
+        ("application", "zip" | "gzip" | "x-tar" | "x-7z-compressed" | "x-bzip2" | "x-xz" | "vnd.rar" | "java-archive") => {
+            "package-x-generic"
+        }
+        ("application", "x-executable" | "x-sharedlib" | "x-msdownload") => "application-x-executable",
+        ("application", "x-sh" | "x-shellscript") => "text-x-script",
+        ("text", "html") => "text-html",
+        _ if subtype.contains("spreadsheet") || subtype.contains("excel") => "x-office-spreadsheet",
+        _ if subtype.contains("presentation") || subtype.contains("powerpoint") => "x-office-presentation",
+        _ if subtype.contains("wordprocessing") || subtype.contains("msword") || subtype.contains("opendocument.text") => {
+            "x-office-document"
+        }
+        _ => "text-x-generic",
+    }
 }
 
+#[derive(Default)]
 pub struct FileControl;
 
 impl FileControl {
@@ -19,58 +128,198 @@ impl FileControl {
         FileControl
     }
 
-    pub fn list_directory(&self, path: &Path) -> Result<Vec<FileInfo>> {
-        let mut files = Vec::new();
+    // Zip and tar archives list like directories, so a path can run through
+    // one, e.g. `docs.zip/images`. Symlinks are described by what they
+    // point at; entries that can't be read are left out and reported in
+    // `skipped` rather than failing the whole listing.
+    pub fn list_directory(&self, path: &Path) -> Result<Listing> {
+        if !path.is_dir() {
+            if let Some(archive) = archive::ArchivePath::split(path) {
+                return archive.list().map(|files| Listing { files, skipped: Vec::new() });
+            }
+        }
+
+        let mut listing = Listing::default();
         
         for entry in fs::read_dir(path)
-            .with_context(|| format!("Failed to read directory: {:?}", path))? {
-            let entry = entry?;
-            let path = entry.path();
-            let metadata = entry.metadata()?;
+            .map_err(|e| OrionError::io(format!("Failed to read directory: {:?}", path), e))? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    listing.skipped.push(Skipped::from_error(path.to_path_buf(), &e));
+                    continue;
+                }
+            };
+            let entry_path = entry.path();
+            let metadata = match fs::metadata(&entry_path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    listing.skipped.push(Skipped::from_error(entry_path, &e));
+                    continue;
+                }
+            };
             
-            files.push(FileInfo {
-                path: path.clone(),
-                size: metadata.len(),
-                is_file: metadata.is_file(),
-                is_directory: metadata.is_dir(),
-                modified: metadata.modified()?,
-            });
+            match FileInfo::from_metadata(entry_path.clone(), &metadata) {
+                Ok(info) => listing.files.push(info),
+                Err(e) => listing.skipped.push(Skipped {
+                    path: entry_path,
+                    reason: SkipReason::Other(format!("{:#}", e)),
+                }),
+            }
         }
         
-        Ok(files)
+        Ok(listing)
+    }
+
+    // Metadata for a single file or directory, following symlinks, or for
+    // an entry inside an archive
+    pub fn stat(&self, path: &Path) -> Result<FileInfo> {
+        match fs::metadata(path) {
+            Ok(metadata) => FileInfo::from_metadata(path.to_path_buf(), &metadata),
+            Err(e) => match archive::ArchivePath::split(path) {
+                Some(archive) => archive.stat(),
+                None => Err(OrionError::io(format!("Failed to read metadata: {:?}", path), e)),
+            },
+        }
+    }
+
+    // Lazily walks everything under `root`, reading directories as the
+    // iterator reaches them so callers can stop early. Directories that
+    // can't be read are passed over and show up in `Walk::skipped`.
+    pub fn walk(&self, root: &Path, options: WalkOptions) -> Walk {
+        let excludes = options.exclude.iter().filter_map(|p| Exclude::parse(p)).collect();
+
+        let mut walk = Walk {
+            root: root.to_path_buf(),
+            options,
+            excludes,
+            open: Vec::new(),
+            yielded: 0,
+            skipped: Vec::new(),
+        };
+        walk.enter(root.to_path_buf(), 1);
+        walk
+    }
+
+    // Copies a file or directory out of an archive into `to`, `path` running
+    // through the archive like in `list_directory`. Returns the copy's path.
+    pub fn extract(&self, path: &Path, to: &Path) -> Result<PathBuf> {
+        let archive = archive::ArchivePath::split(path)
+            .ok_or_else(|| OrionError::validation(format!("Not inside a zip or tar archive: {:?}", path)))?;
+        fs::create_dir_all(to)
+            .map_err(|e| OrionError::io(format!("Failed to create directory: {:?}", to), e))?;
+        archive.extract(to)
     }
 
     pub fn create_directory(&self, path: &Path) -> Result<()> {
         fs::create_dir_all(path)
-            .with_context(|| format!("Failed to create directory: {:?}", path))
+            .map_err(|e| OrionError::io(format!("Failed to create directory: {:?}", path), e))
     }
 
+    // Removes for good, see `trash_path` for a delete that can be undone
     pub fn delete_path(&self, path: &Path) -> Result<()> {
         if path.is_file() {
             fs::remove_file(path)
-                .with_context(|| format!("Failed to delete file: {:?}", path))?;
+                .map_err(|e| OrionError::io(format!("Failed to delete file: {:?}", path), e))?;
         } else if path.is_dir() {
             fs::remove_dir_all(path)
-                .with_context(|| format!("Failed to delete directory: {:?}", path))?;
+                .map_err(|e| OrionError::io(format!("Failed to delete directory: {:?}", path), e))?;
         }
         Ok(())
     }
 
+    // Moves a file or directory to the desktop's trash so it can be
+    // restored: the freedesktop.org trash in the data dir, the Finder's
+    // Trash on macOS and the Recycle Bin on Windows
+    pub fn trash_path(&self, path: &Path) -> Result<()> {
+        let path = std::path::absolute(path)
+            .map_err(|e| OrionError::io(format!("Failed to resolve path: {:?}", path), e))?;
+        if fs::symlink_metadata(&path).is_err() {
+            return Err(OrionError::validation(format!("Nothing to move to the trash at {:?}", path)));
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            let method = if path.is_dir() { "DeleteDirectory" } else { "DeleteFile" };
+            let script = format!(
+                "Add-Type -AssemblyName Microsoft.VisualBasic; [Microsoft.VisualBasic.FileIO.FileSystem]::{}('{}', 'OnlyErrorDialogs', 'SendToRecycleBin')",
+                method,
+                path.display().to_string().replace('\'', "''")
+            );
+            run_trash_tool("powershell", &["-NoProfile", "-Command", &script], &path)
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let script = format!(
+                "tell application \"Finder\" to delete POSIX file \"{}\"",
+                path.display().to_string().replace('\\', "\\\\").replace('"', "\\\"")
+            );
+            run_trash_tool("osascript", &["-e", &script], &path)
+        }
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            let trash = directories::BaseDirs::new()
+                .ok_or_else(|| OrionError::io("Failed to find the home directory", std::io::ErrorKind::NotFound.into()))?
+                .data_dir()
+                .join("Trash");
+            let (name, info) = reserve_trash_name(&trash, &path)?;
+
+            if let Err(e) = fs::rename(&path, trash.join("files").join(&name)) {
+                let _ = fs::remove_file(&info);
+                return Err(OrionError::io(format!("Failed to move {:?} to the trash", path), e));
+            }
+            Ok(())
+        }
+    }
+
     pub fn move_path(&self, from: &Path, to: &Path) -> Result<()> {
         fs::rename(from, to)
-            .with_context(|| format!("Failed to move from {:?} to {:?}", from, to))
+            .map_err(|e| OrionError::io(format!("Failed to move from {:?} to {:?}", from, to), e))
     }
 
     pub fn copy_path(&self, from: &Path, to: &Path) -> Result<()> {
         if from.is_file() {
             fs::copy(from, to)
-                .with_context(|| format!("Failed to copy file from {:?} to {:?}", from, to))?;
+                .map_err(|e| OrionError::io(format!("Failed to copy file from {:?} to {:?}", from, to), e))?;
         } else if from.is_dir() {
             self.copy_directory(from, to)?;
         }
         Ok(())
     }
 
+    // Copies each source into the `to` directory, sending progress while
+    // it goes. Stops at the first failure, leaving what was copied so far.
+    pub fn copy_many(&self, sources: &[PathBuf], to: &Path, progress: &Sender<Progress>) -> Result<()> {
+        let mut batch = Batch::new(sources, progress);
+        for source in sources {
+            let dest = batch.destination(source, to)?;
+            batch.copy_tree(source, &dest)?;
+        }
+        batch.finish();
+        Ok(())
+    }
+
+    // Like `copy_many`, but renames where it can, which is instant on the
+    // same filesystem, and only copies and deletes across filesystems
+    pub fn move_many(&self, sources: &[PathBuf], to: &Path, progress: &Sender<Progress>) -> Result<()> {
+        let mut batch = Batch::new(sources, progress);
+        for (source, (files, bytes)) in sources.iter().zip(batch.sizes.clone()) {
+            let dest = batch.destination(source, to)?;
+            if fs::rename(source, &dest).is_ok() {
+                batch.progress.files_done += files;
+                batch.progress.bytes_done += bytes;
+                batch.report(false);
+                continue;
+            }
+            batch.copy_tree(source, &dest)?;
+            self.delete_path(source)?;
+        }
+        batch.finish();
+        Ok(())
+    }
+
     fn copy_directory(&self, from: &Path, to: &Path) -> Result<()> {
         fs::create_dir_all(to)?;
         
@@ -88,4 +337,484 @@ impl FileControl {
         
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+// How far `copy_many` or `move_many` has got
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub current: Option<PathBuf>,
+}
+
+// Updates are sent at most this often while a file is being copied
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const COPY_BUFFER_SIZE: usize = 256 * 1024;
+
+struct Batch<'a> {
+    progress: Progress,
+    sender: &'a Sender<Progress>,
+    last_sent: Instant,
+    // Files and bytes under each source, for counting a rename all at once
+    sizes: Vec<(usize, u64)>,
+}
+
+impl<'a> Batch<'a> {
+    fn new(sources: &[PathBuf], sender: &'a Sender<Progress>) -> Self {
+        let sizes: Vec<(usize, u64)> = sources.iter().map(|source| tree_size(source)).collect();
+        let mut batch = Self {
+            progress: Progress {
+                files_total: sizes.iter().map(|(files, _)| files).sum(),
+                bytes_total: sizes.iter().map(|(_, bytes)| bytes).sum(),
+                ..Default::default()
+            },
+            sender,
+            last_sent: Instant::now(),
+            sizes,
+        };
+        batch.report(true);
+        batch
+    }
+
+    // Where a source goes, refusing to overwrite anything already there
+    fn destination(&self, source: &Path, to: &Path) -> Result<PathBuf> {
+        let name = source
+            .file_name()
+            .ok_or_else(|| OrionError::validation(format!("Can't copy {:?}", source)))?;
+        let dest = to.join(name);
+        if fs::symlink_metadata(&dest).is_ok() {
+            return Err(OrionError::validation(format!("{:?} already exists", dest)));
+        }
+        if dest.starts_with(source) {
+            return Err(OrionError::validation(format!("Can't copy {:?} into itself", source)));
+        }
+        Ok(dest)
+    }
+
+    fn copy_tree(&mut self, from: &Path, to: &Path) -> Result<()> {
+        if !from.is_dir() {
+            return self.copy_file(from, to);
+        }
+
+        fs::create_dir_all(to).map_err(|e| OrionError::io(format!("Failed to create directory: {:?}", to), e))?;
+        for entry in fs::read_dir(from).map_err(|e| OrionError::io(format!("Failed to read directory: {:?}", from), e))? {
+            let entry = entry?;
+            self.copy_tree(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    }
+
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<()> {
+        self.progress.current = Some(from.to_path_buf());
+        let mut reader = File::open(from).map_err(|e| OrionError::io(format!("Failed to open {:?}", from), e))?;
+        let mut writer = File::create(to).map_err(|e| OrionError::io(format!("Failed to create {:?}", to), e))?;
+        let mut buffer = vec![0; COPY_BUFFER_SIZE];
+
+        loop {
+            let n = reader.read(&mut buffer).map_err(|e| OrionError::io(format!("Failed to read {:?}", from), e))?;
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buffer[..n]).map_err(|e| OrionError::io(format!("Failed to write {:?}", to), e))?;
+            self.progress.bytes_done += n as u64;
+            self.report(false);
+        }
+        if let Ok(metadata) = fs::metadata(from) {
+            let _ = fs::set_permissions(to, metadata.permissions());
+        }
+
+        self.progress.files_done += 1;
+        self.report(false);
+        Ok(())
+    }
+
+    fn finish(&mut self) {
+        self.progress.current = None;
+        self.report(true);
+    }
+
+    // A closed receiver only means nobody is watching
+    fn report(&mut self, force: bool) {
+        if force || self.last_sent.elapsed() >= PROGRESS_INTERVAL {
+            let _ = self.sender.send(self.progress.clone());
+            self.last_sent = Instant::now();
+        }
+    }
+}
+
+// Files and bytes under a path, counting the path itself if it's a file
+fn tree_size(path: &Path) -> (usize, u64) {
+    if !path.is_dir() {
+        return (1, fs::metadata(path).map(|m| m.len()).unwrap_or(0));
+    }
+
+    let options = WalkOptions {
+        include_hidden: true,
+        ..Default::default()
+    };
+    FileControl::new()
+        .walk(path, options)
+        .filter(|entry| !entry.is_dir)
+        .fold((0, 0), |(files, bytes), entry| {
+            (files + 1, bytes + fs::metadata(&entry.path).map(|m| m.len()).unwrap_or(0))
+        })
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn run_trash_tool(program: &str, args: &[&str], path: &Path) -> Result<()> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| OrionError::io(format!("Failed to run {}", program), e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(OrionError::io(
+            format!("Failed to move {:?} to the trash", path),
+            std::io::Error::other(stderr),
+        ));
+    }
+    Ok(())
+}
+
+// Claims a name in the trash by creating its `.trashinfo` file first, as the
+// freedesktop.org spec asks, adding a counter when the name is taken.
+// Returns the name and the info file, to remove if the move fails.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn reserve_trash_name(trash: &Path, path: &Path) -> Result<(String, PathBuf)> {
+    use std::io::Write;
+
+    let info_dir = trash.join("info");
+    fs::create_dir_all(&info_dir)
+        .and_then(|_| fs::create_dir_all(trash.join("files")))
+        .map_err(|e| OrionError::io(format!("Failed to create the trash at {:?}", trash), e))?;
+
+    let base = path
+        .file_name()
+        .ok_or_else(|| OrionError::validation(format!("Can't move {:?} to the trash", path)))?
+        .to_string_lossy()
+        .into_owned();
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_trash_path(path),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+
+    for n in 1.. {
+        let name = if n == 1 { base.clone() } else { format!("{}.{}", base, n) };
+        let info_path = info_dir.join(format!("{}.trashinfo", name));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(mut file) => {
+                file.write_all(info.as_bytes())
+                    .map_err(|e| OrionError::io(format!("Failed to write {:?}", info_path), e))?;
+                return Ok((name, info_path));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(OrionError::io(format!("Failed to write {:?}", info_path), e)),
+        }
+    }
+    unreachable!()
+}
+
+// Percent-encodes the path like a URL path, keeping the slashes
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn encode_trash_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+// What `list_directory` found, along with the entries it had to leave out
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Listing {
+    pub files: Vec<FileInfo>,
+    pub skipped: Vec<Skipped>,
+}
+
+// An entry left out of a listing or walk, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkipReason {
+    PermissionDenied,
+    // The link's target doesn't exist
+    BrokenSymlink,
+    // A symlinked directory leading back to one of its own parents
+    SymlinkCycle,
+    Other(String),
+}
+
+impl Skipped {
+    fn from_error(path: PathBuf, error: &std::io::Error) -> Self {
+        let reason = match error.kind() {
+            std::io::ErrorKind::PermissionDenied => SkipReason::PermissionDenied,
+            // Following the link failed, but the link itself is there
+            std::io::ErrorKind::NotFound if fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink()) => {
+                SkipReason::BrokenSymlink
+            }
+            _ => SkipReason::Other(error.to_string()),
+        };
+        Self { path, reason }
+    }
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::PermissionDenied => write!(f, "permission denied"),
+            SkipReason::BrokenSymlink => write!(f, "broken symlink"),
+            SkipReason::SymlinkCycle => write!(f, "symlink cycle"),
+            SkipReason::Other(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    // Levels below the root to descend into, unlimited when unset; the
+    // root's own entries are at depth 1
+    pub max_depth: Option<usize>,
+    // Whether dot files and dot directories are listed and entered
+    pub include_hidden: bool,
+    // Gitignore-style patterns: `*.log` matches a name anywhere, a pattern
+    // with a `/` such as `build/cache` or `**/target` matches the path
+    // relative to the root, and a trailing `/` only matches directories
+    pub exclude: Vec<String>,
+    // Entries to yield before stopping
+    pub limit: Option<usize>,
+    // Whether symlinked directories are entered; links that lead back to a
+    // directory already being walked are skipped
+    pub follow_symlinks: bool,
+}
+
+impl WalkOptions {
+    // Whether a walk of `root` would leave `path` out, itself or along with
+    // a directory it's in, for paths learned of other than by walking, such
+    // as from a filesystem watcher
+    pub fn skips(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return true;
+        };
+        let components: Vec<_> = relative.components().collect();
+        if self.max_depth.is_some_and(|max| components.len() > max) {
+            return true;
+        }
+
+        let excludes: Vec<Exclude> = self.exclude.iter().filter_map(|p| Exclude::parse(p)).collect();
+        let mut current = root.to_path_buf();
+        for (idx, component) in components.iter().enumerate() {
+            current.push(component);
+            if !self.include_hidden && component.as_os_str().to_string_lossy().starts_with('.') {
+                return true;
+            }
+            // Everything but the path itself is a directory it's in
+            let dir = is_dir || idx + 1 < components.len();
+            if is_excluded(&excludes, root, &current, dir) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkEntry {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+pub struct Walk {
+    root: PathBuf,
+    options: WalkOptions,
+    excludes: Vec<Exclude>,
+    // Directories being read, innermost last, which makes them the
+    // ancestors of whatever is read next
+    open: Vec<OpenDir>,
+    yielded: usize,
+    skipped: Vec<Skipped>,
+}
+
+struct OpenDir {
+    entries: ReadDir,
+    path: PathBuf,
+    // Depth of the directory's entries
+    depth: usize,
+    // Only looked up when following symlinks, to spot cycles
+    id: Option<DirId>,
+}
+
+// Identifies a directory however it was reached
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> Option<DirId> {
+    fs::canonicalize(path).ok()
+}
+
+impl Iterator for Walk {
+    type Item = WalkEntry;
+
+    fn next(&mut self) -> Option<WalkEntry> {
+        if self.options.limit.is_some_and(|limit| self.yielded >= limit) {
+            return None;
+        }
+
+        loop {
+            let dir = self.open.last_mut()?;
+            let depth = dir.depth;
+            let Some(entry) = dir.entries.next() else {
+                self.open.pop();
+                continue;
+            };
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let path = dir.path.clone();
+                    self.skipped.push(Skipped::from_error(path, &e));
+                    continue;
+                }
+            };
+
+            let path = entry.path();
+            let name = entry.file_name();
+            if !self.options.include_hidden && name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => {
+                    self.skipped.push(Skipped::from_error(path, &e));
+                    continue;
+                }
+            };
+            // Symlinks are listed, but only entered with `follow_symlinks`
+            let mut is_dir = file_type.is_dir();
+            if file_type.is_symlink() {
+                match fs::metadata(&path) {
+                    Ok(target) => is_dir = self.options.follow_symlinks && target.is_dir(),
+                    Err(e) => {
+                        self.skipped.push(Skipped::from_error(path, &e));
+                        continue;
+                    }
+                }
+            }
+            if is_excluded(&self.excludes, &self.root, &path, is_dir) {
+                continue;
+            }
+
+            if is_dir && self.options.max_depth.is_none_or(|max| depth < max) {
+                self.enter(path.clone(), depth + 1);
+            }
+
+            self.yielded += 1;
+            return Some(WalkEntry { path, depth, is_dir });
+        }
+    }
+}
+
+impl Walk {
+    // Entries passed over so far because they couldn't be read, were broken
+    // symlinks or led round in a cycle
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.skipped
+    }
+
+    // Starts reading a directory whose entries are at `depth`
+    fn enter(&mut self, path: PathBuf, depth: usize) {
+        let id = if self.options.follow_symlinks { dir_id(&path) } else { None };
+        if id.is_some() && self.open.iter().any(|dir| dir.id == id) {
+            self.skipped.push(Skipped {
+                path,
+                reason: SkipReason::SymlinkCycle,
+            });
+            return;
+        }
+
+        match fs::read_dir(&path) {
+            Ok(entries) => self.open.push(OpenDir { entries, path, depth, id }),
+            Err(e) => self.skipped.push(Skipped::from_error(path, &e)),
+        }
+    }
+}
+
+fn is_excluded(excludes: &[Exclude], root: &Path, path: &Path, is_dir: bool) -> bool {
+    if excludes.is_empty() {
+        return false;
+    }
+    let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+    let name = relative.rsplit('/').next().unwrap_or(&relative);
+
+    excludes.iter().any(|exclude| {
+        (is_dir || !exclude.dirs_only)
+            && glob_match(&exclude.pattern, if exclude.anchored { &relative } else { name })
+    })
+}
+
+struct Exclude {
+    pattern: String,
+    anchored: bool,
+    dirs_only: bool,
+}
+
+impl Exclude {
+    fn parse(pattern: &str) -> Option<Self> {
+        let pattern = pattern.trim();
+        let dirs_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            anchored: pattern.contains('/'),
+            pattern: pattern.trim_start_matches('/').to_string(),
+            dirs_only,
+        })
+    }
+}
+
+// `*` and `?` stay within a path segment, `**` crosses segments
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_chars(&pattern, &text)
+}
+
+fn glob_match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // `**/` also matches no directories at all
+            glob_match_chars(rest, text)
+                || (0..text.len()).any(|i| text[i] == '/' && glob_match_chars(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match_chars(rest, &text[i..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match_chars(rest, &text[i..])),
+        ['?', rest @ ..] => text.first().is_some_and(|&c| c != '/') && glob_match_chars(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && glob_match_chars(rest, &text[1..]),
+    }
+}
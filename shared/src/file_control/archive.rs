@@ -0,0 +1,239 @@
+use crate::error::{OrionError, Result};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::FileInfo;
+
+// Zip and tar archives read as if they were directories: `docs.zip/a/b.txt`
+// names `a/b.txt` inside `docs.zip`. Nothing is ever written to an archive.
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Kind {
+    fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") || name.ends_with(".jar") {
+            Some(Kind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Kind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Kind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+// A path that runs through an archive file
+pub(super) struct ArchivePath {
+    archive: PathBuf,
+    kind: Kind,
+    // `/` separated, empty for the archive's top level
+    inner: String,
+}
+
+impl ArchivePath {
+    // Finds the archive file among the path's ancestors, the path itself
+    // included, as long as the path doesn't exist on disk as a directory
+    pub(super) fn split(path: &Path) -> Option<Self> {
+        let archive = path.ancestors().find(|ancestor| ancestor.is_file())?;
+        let kind = Kind::of(archive)?;
+        let inner = path
+            .strip_prefix(archive)
+            .ok()?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Some(Self {
+            archive: archive.to_path_buf(),
+            kind,
+            inner,
+        })
+    }
+
+    // Entries directly under the inner path, including directories that
+    // only show up as part of deeper entries' names
+    pub(super) fn list(&self) -> Result<Vec<FileInfo>> {
+        let archive_modified = fs::metadata(&self.archive)?.modified()?;
+        let prefix = if self.inner.is_empty() { String::new() } else { format!("{}/", self.inner) };
+        let mut children: BTreeMap<String, FileInfo> = BTreeMap::new();
+        let mut found = self.inner.is_empty();
+
+        self.for_each(|entry, _| {
+            if entry.name == self.inner {
+                if !entry.is_dir {
+                    return Err(OrionError::validation(format!("Not a directory: {:?}", self.archive.join(&self.inner))));
+                }
+                found = true;
+            }
+            let Some(rest) = entry.name.strip_prefix(&prefix).filter(|rest| !rest.is_empty()) else {
+                return Ok(());
+            };
+            found = true;
+
+            let (name, nested) = match rest.split_once('/') {
+                Some((name, _)) => (name, true),
+                None => (rest, false),
+            };
+            let is_dir = nested || entry.is_dir;
+            children.entry(name.to_string()).or_insert_with(|| {
+                let path = self.archive.join(&prefix).join(name);
+                archived(path, is_dir, entry.size, entry.modified.unwrap_or(archive_modified))
+            });
+            Ok(())
+        })?;
+
+        if !found {
+            return Err(OrionError::validation(format!("No {:?} in {:?}", self.inner, self.archive)));
+        }
+        Ok(children.into_values().collect())
+    }
+
+    // The entry itself, a directory when only deeper entries name it
+    pub(super) fn stat(&self) -> Result<FileInfo> {
+        let archive_modified = fs::metadata(&self.archive)?.modified()?;
+        let path = self.archive.join(&self.inner);
+        let prefix = format!("{}/", self.inner);
+        let mut found = None;
+
+        self.for_each(|entry, _| {
+            if found.is_none() && entry.name == self.inner {
+                found = Some(archived(path.clone(), entry.is_dir, entry.size, entry.modified.unwrap_or(archive_modified)));
+            } else if found.is_none() && entry.name.starts_with(&prefix) {
+                found = Some(archived(path.clone(), true, 0, archive_modified));
+            }
+            Ok(())
+        })?;
+
+        found.ok_or_else(|| OrionError::validation(format!("No {:?} in {:?}", self.inner, self.archive)))
+    }
+
+    // Copies the entry, or everything under it for a directory, into `to`,
+    // returning where it ended up
+    pub(super) fn extract(&self, to: &Path) -> Result<PathBuf> {
+        let name = self.inner.rsplit('/').next().filter(|name| !name.is_empty());
+        let target = match name {
+            Some(name) => to.join(name),
+            None => to.join(archive_stem(&self.archive)),
+        };
+        let prefix = if self.inner.is_empty() { String::new() } else { format!("{}/", self.inner) };
+        let mut extracted = false;
+
+        self.for_each(|entry, reader| {
+            let relative = if entry.name == self.inner && !entry.is_dir {
+                ""
+            } else if let Some(rest) = entry.name.strip_prefix(&prefix) {
+                rest
+            } else {
+                return Ok(());
+            };
+            // Names that would climb out of the target are never written
+            let relative = Path::new(relative);
+            if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+                return Ok(());
+            }
+
+            // Joining an empty path would leave a trailing slash
+            let dest = if relative.as_os_str().is_empty() { target.clone() } else { target.join(relative) };
+            if entry.is_dir {
+                fs::create_dir_all(&dest)?;
+            } else {
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut file = File::create(&dest).map_err(|e| OrionError::io(format!("Failed to create {:?}", dest), e))?;
+                io::copy(reader, &mut file).map_err(|e| OrionError::io(format!("Failed to extract {:?}", dest), e))?;
+            }
+            extracted = true;
+            Ok(())
+        })?;
+
+        if !extracted {
+            return Err(OrionError::validation(format!("No {:?} in {:?}", self.inner, self.archive)));
+        }
+        Ok(target)
+    }
+
+    // Calls `visit` with every entry and a reader for its contents
+    fn for_each(&self, mut visit: impl FnMut(&Entry, &mut dyn Read) -> Result<()>) -> Result<()> {
+        let file = File::open(&self.archive).map_err(|e| OrionError::io(format!("Failed to open {:?}", self.archive), e))?;
+        let file = BufReader::new(file);
+
+        match self.kind {
+            Kind::Zip => {
+                let unreadable = |e: zip::result::ZipError| {
+                    OrionError::io(format!("Failed to read zip archive {:?}", self.archive), e.into())
+                };
+                let mut zip = zip::ZipArchive::new(file).map_err(unreadable)?;
+                for i in 0..zip.len() {
+                    let mut zipped = zip.by_index(i).map_err(unreadable)?;
+                    let entry = Entry {
+                        name: zipped.name().trim_end_matches('/').to_string(),
+                        is_dir: zipped.is_dir(),
+                        size: zipped.size(),
+                        modified: None,
+                    };
+                    visit(&entry, &mut zipped)?;
+                }
+            }
+            Kind::Tar => visit_tar(tar::Archive::new(file), &mut visit)?,
+            Kind::TarGz => visit_tar(tar::Archive::new(flate2::read::GzDecoder::new(file)), &mut visit)?,
+        }
+        Ok(())
+    }
+}
+
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    // Taken from the archive file when the entry doesn't say
+    modified: Option<SystemTime>,
+}
+
+fn visit_tar<R: Read>(mut archive: tar::Archive<R>, visit: &mut impl FnMut(&Entry, &mut dyn Read) -> Result<()>) -> Result<()> {
+    for tarred in archive.entries()? {
+        let mut tarred = tarred?;
+        let header = tarred.header();
+        let name = tarred.path()?.to_string_lossy().trim_start_matches("./").trim_end_matches('/').to_string();
+        if name.is_empty() || name == "." {
+            continue;
+        }
+        let entry = Entry {
+            name,
+            is_dir: header.entry_type().is_dir(),
+            size: header.size()?,
+            modified: header.mtime().ok().map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        };
+        visit(&entry, &mut tarred)?;
+    }
+    Ok(())
+}
+
+// Entries can only be read, whatever their permissions in the archive say
+fn archived(path: PathBuf, is_dir: bool, size: u64, modified: SystemTime) -> FileInfo {
+    let mut info = FileInfo::described(path, is_dir, size, modified);
+    info.readonly = true;
+    info
+}
+
+// `docs.tar.gz` extracts to `docs`
+fn archive_stem(archive: &Path) -> String {
+    let name = archive.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let lower = name.to_lowercase();
+    [".tar.gz", ".tgz", ".tar", ".zip", ".jar"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| name[..name.len() - ext.len()].to_string())
+        .unwrap_or(name)
+}
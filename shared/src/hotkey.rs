@@ -0,0 +1,109 @@
+use anyhow::Result;
+use std::fmt;
+
+// Named keys accepted in key combination strings, with their aliases
+const NAMED_KEYS: &[(&str, &[&str])] = &[
+    ("Space", &["space"]),
+    ("Enter", &["enter", "return"]),
+    ("Escape", &["escape", "esc"]),
+    ("Tab", &["tab"]),
+    ("Backspace", &["backspace"]),
+    ("Delete", &["delete", "del"]),
+    ("Up", &["up", "arrowup"]),
+    ("Down", &["down", "arrowdown"]),
+    ("Left", &["left", "arrowleft"]),
+    ("Right", &["right", "arrowright"]),
+    ("Home", &["home"]),
+    ("End", &["end"]),
+    ("PageUp", &["pageup"]),
+    ("PageDown", &["pagedown"]),
+    ("F1", &["f1"]),
+    ("F2", &["f2"]),
+    ("F3", &["f3"]),
+    ("F4", &["f4"]),
+    ("F5", &["f5"]),
+    ("F6", &["f6"]),
+    ("F7", &["f7"]),
+    ("F8", &["f8"]),
+    ("F9", &["f9"]),
+    ("F10", &["f10"]),
+    ("F11", &["f11"]),
+    ("F12", &["f12"]),
+];
+
+// A key combination such as `Ctrl+Shift+K`, parsed from its string form
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+    // Canonical key name: an uppercase character or one of NAMED_KEYS
+    pub key: String,
+}
+
+impl KeyCombo {
+    pub fn parse(combo: &str) -> Result<Self> {
+        let mut result = KeyCombo {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            meta: false,
+            key: String::new(),
+        };
+
+        let parts: Vec<&str> = combo.split('+').map(|p| p.trim()).collect();
+        let (key, modifiers) = parts
+            .split_last()
+            .ok_or_else(|| anyhow::anyhow!("Empty key combination"))?;
+
+        for modifier in modifiers {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => result.ctrl = true,
+                "alt" | "option" => result.alt = true,
+                "shift" => result.shift = true,
+                "meta" | "super" | "cmd" | "win" | "logo" => result.meta = true,
+                "" => return Err(anyhow::anyhow!("Empty modifier in '{}'", combo)),
+                other => return Err(anyhow::anyhow!("Unknown modifier '{}' in '{}'", other, combo)),
+            }
+        }
+
+        result.key = Self::canonical_key(key)
+            .ok_or_else(|| anyhow::anyhow!("Unknown key '{}' in '{}'", key, combo))?;
+
+        Ok(result)
+    }
+
+    // Normalizes a key name, e.g. `esc` -> `Escape`, `j` -> `J`
+    pub fn canonical_key(key: &str) -> Option<String> {
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if !c.is_whitespace() => Some(c.to_uppercase().to_string()),
+            _ => {
+                let lower = key.to_lowercase();
+                NAMED_KEYS
+                    .iter()
+                    .find(|(_, aliases)| aliases.contains(&lower.as_str()))
+                    .map(|(name, _)| name.to_string())
+            }
+        }
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.meta {
+            write!(f, "Super+")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+
+// An inverted index over short documents, for providers with too many
+// entries to fuzzy match one by one. Every query word has to start some word
+// of a document, so `rep fin` finds "Final report".
+//
+// Removing a document only marks it, leaving its postings behind until
+// `compact` rebuilds them; `needs_compaction` tells when that is worth it.
+#[derive(Debug, Default)]
+pub struct Index {
+    docs: Vec<Option<Doc>>,
+    ids: HashMap<String, u32>,
+    terms: BTreeMap<String, Vec<u32>>,
+    removed: usize,
+}
+
+#[derive(Debug)]
+struct Doc {
+    key: String,
+    terms: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit<'a> {
+    pub key: &'a str,
+    // 0.0 to 1.0, higher when the query covers more of the document
+    pub score: f32,
+}
+
+impl Index {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Builds an index in one go, e.g. on a blocking thread at startup
+    pub fn build<K, T>(docs: impl IntoIterator<Item = (K, T)>) -> Self
+    where
+        K: Into<String>,
+        T: AsRef<str>,
+    {
+        let mut index = Self::new();
+        for (key, text) in docs {
+            index.insert(key, text.as_ref());
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    // Adds a document, replacing any with the same key
+    pub fn insert(&mut self, key: impl Into<String>, text: &str) {
+        let key = key.into();
+        self.remove(&key);
+
+        let mut terms = tokenize(text);
+        terms.sort();
+        terms.dedup();
+
+        let id = self.docs.len() as u32;
+        for term in &terms {
+            self.terms.entry(term.clone()).or_default().push(id);
+        }
+        self.ids.insert(key.clone(), id);
+        self.docs.push(Some(Doc { key, terms }));
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        let Some(id) = self.ids.remove(key) else {
+            return false;
+        };
+        self.docs[id as usize] = None;
+        self.removed += 1;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    // Best matches first, newer documents first among equal scores
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Hit<'_>> {
+        let words = tokenize(query);
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let mut candidates: Option<Vec<u32>> = None;
+        for word in &words {
+            let mut ids: Vec<u32> = self.terms
+                .range::<str, _>((Bound::Included(word.as_str()), Bound::Unbounded))
+                .take_while(|(term, _)| term.starts_with(word.as_str()))
+                .flat_map(|(_, ids)| ids.iter().copied())
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+
+            candidates = Some(match candidates {
+                None => ids,
+                Some(previous) => intersect(&previous, &ids),
+            });
+            if candidates.as_ref().is_some_and(Vec::is_empty) {
+                return Vec::new();
+            }
+        }
+
+        let mut hits: Vec<(u32, Hit)> = candidates
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|id| {
+                let doc = self.docs[id as usize].as_ref()?;
+                let coverage = (words.len() as f32 / doc.terms.len().max(1) as f32).min(1.0);
+                Some((id, Hit { key: &doc.key, score: 0.5 + 0.5 * coverage }))
+            })
+            .collect();
+
+        hits.sort_by(|(a_id, a), (b_id, b)| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b_id.cmp(a_id))
+        });
+        hits.truncate(limit);
+        hits.into_iter().map(|(_, hit)| hit).collect()
+    }
+
+    // Worth rebuilding once a quarter of the documents are gone
+    pub fn needs_compaction(&self) -> bool {
+        self.removed > 64 && self.removed * 4 > self.docs.len()
+    }
+
+    // Rebuilds the postings without removed documents, keeping their order
+    pub fn compact(&mut self) {
+        let docs: Vec<Doc> = std::mem::take(&mut self.docs).into_iter().flatten().collect();
+        self.ids.clear();
+        self.terms.clear();
+        self.removed = 0;
+
+        for (id, doc) in docs.into_iter().enumerate() {
+            let id = id as u32;
+            for term in &doc.terms {
+                self.terms.entry(term.clone()).or_default().push(id);
+            }
+            self.ids.insert(doc.key.clone(), id);
+            self.docs.push(Some(doc));
+        }
+    }
+}
+
+// Words folded like the matcher folds them, so accents don't matter
+fn tokenize(text: &str) -> Vec<String> {
+    crate::matcher::normalize(text)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut both = Vec::with_capacity(a.len().min(b.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                both.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    both
+}
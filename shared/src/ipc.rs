@@ -1,20 +1,78 @@
-use anyhow::{Context, Result};
 use std::path::PathBuf;
 use std::net::TcpStream;
 use std::os::unix::net::UnixStream;
 use std::io::{Read, Write};
 use std::time::Duration;
-use tokio::net::{TcpStream as TokioTcpStream, UnixListener};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream as TokioTcpStream, UnixListener, UnixStream as TokioUnixStream};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::time::timeout;
 use std::sync::Arc;
 use directories;
 
+use crate::error::{OrionError, Result};
 use crate::models::IpcMessage;
 
 const IPC_TIMEOUT: Duration = Duration::from_secs(5);
 const MAX_MESSAGE_SIZE: usize = 1024 * 1024; // 1MB
 
+fn project_dirs() -> Result<directories::ProjectDirs> {
+    directories::ProjectDirs::from("", "", "orion").ok_or_else(|| {
+        OrionError::io("Failed to get project directories", std::io::ErrorKind::NotFound.into())
+    })
+}
+
+fn timed_out() -> OrionError {
+    OrionError::ipc(format!("Timed out after {:?}", IPC_TIMEOUT))
+}
+
+// Messages go over the socket as their JSON preceded by its length, four
+// bytes big-endian, as a stream doesn't keep the boundaries of writes and a
+// long reply can arrive in several reads
+fn encode(message: &IpcMessage) -> Result<Vec<u8>> {
+    let serialized = serde_json::to_vec(message)?;
+    if serialized.len() > MAX_MESSAGE_SIZE {
+        return Err(OrionError::ipc(format!("Message too large: {} bytes", serialized.len())));
+    }
+
+    let mut framed = Vec::with_capacity(4 + serialized.len());
+    framed.extend_from_slice(&(serialized.len() as u32).to_be_bytes());
+    framed.extend_from_slice(&serialized);
+    Ok(framed)
+}
+
+fn message_len(header: [u8; 4]) -> Result<usize> {
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(OrionError::ipc(format!("Message too large: {} bytes", len)));
+    }
+    Ok(len)
+}
+
+// None when the other end closed the connection between messages
+async fn read_frame(stream: &mut (impl AsyncRead + Unpin)) -> Result<Option<IpcMessage>> {
+    let mut header = [0u8; 4];
+    match stream.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut buffer = vec![0; message_len(header)?];
+    stream.read_exact(&mut buffer).await?;
+    Ok(Some(serde_json::from_slice(&buffer)?))
+}
+
+fn read_frame_blocking(stream: &mut impl Read) -> Result<Option<IpcMessage>> {
+    let mut header = [0u8; 4];
+    match stream.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut buffer = vec![0; message_len(header)?];
+    stream.read_exact(&mut buffer)?;
+    Ok(Some(serde_json::from_slice(&buffer)?))
+}
+
 // Helper to determine if a path is a Unix socket path
 fn is_unix_socket_path(addr: &str) -> bool {
     addr.starts_with('/') || addr.contains('/')
@@ -33,11 +91,11 @@ impl IpcServer {
         // Remove the socket file if it already exists
         if socket_path.exists() {
             std::fs::remove_file(&socket_path)
-                .with_context(|| format!("Failed to remove existing socket at {:?}", socket_path))?;
+                .map_err(|e| OrionError::io(format!("Failed to remove existing socket at {:?}", socket_path), e))?;
         }
 
         let listener = UnixListener::bind(&socket_path)
-            .with_context(|| format!("Failed to bind to Unix socket at {:?}", socket_path))?;
+            .map_err(|e| OrionError::io(format!("Failed to bind to Unix socket at {:?}", socket_path), e))?;
 
         Ok(IpcServer {
             listener: Arc::new(listener),
@@ -51,14 +109,13 @@ impl IpcServer {
 
     pub fn create_new() -> Result<Self> {
         // Use the XDG config directory for the socket
-        let proj_dirs = directories::ProjectDirs::from("", "", "orion")
-            .context("Failed to get project directories")?;
+        let proj_dirs = project_dirs()?;
 
         let config_dir = proj_dirs.config_dir();
 
         // Ensure the directory exists
         std::fs::create_dir_all(config_dir)
-            .with_context(|| format!("Failed to create config directory at {:?}", config_dir))?;
+            .map_err(|e| OrionError::io(format!("Failed to create config directory at {:?}", config_dir), e))?;
 
         let socket_path = config_dir.join("orion.sock");
         Self::new(socket_path)
@@ -69,27 +126,45 @@ impl IpcServer {
             let (mut socket, _) = self.listener.accept().await?;
 
             tokio::spawn(async move {
-                let mut buf = vec![0; MAX_MESSAGE_SIZE];
-                if let Ok(n) = socket.read(&mut buf).await {
-                    if n > 0 {
-                        if let Ok(message) = serde_json::from_slice::<IpcMessage>(&buf[..n]) {
-                            // Handle message here
-                            let response = serde_json::to_vec(&message)?;
-                            socket.write_all(&response).await?;
-                        }
-                    }
+                if let Ok(Some(message)) = read_frame(&mut socket).await {
+                    // Handle message here
+                    socket.write_all(&encode(&message)?).await?;
                 }
-                Ok::<_, anyhow::Error>(())
+                Ok::<_, OrionError>(())
             });
         }
     }
 
+    pub async fn accept(&self) -> Result<IpcConnection> {
+        let (stream, _) = self.listener.accept().await?;
+        Ok(IpcConnection { stream })
+    }
+
     pub async fn receive_message(&self) -> Result<IpcMessage> {
         let (mut socket, _) = self.listener.accept().await?;
-        let mut buf = vec![0; MAX_MESSAGE_SIZE];
-        let n = socket.read(&mut buf).await?;
-        let message = serde_json::from_slice::<IpcMessage>(&buf[..n])?;
-        Ok(message)
+        read_frame(&mut socket)
+            .await?
+            .ok_or_else(|| OrionError::ipc("Connection closed by client"))
+    }
+}
+
+// Server side of a single client connection, kept open so replies can be
+// written back to the client that sent the request
+#[derive(Debug)]
+pub struct IpcConnection {
+    stream: TokioUnixStream,
+}
+
+impl IpcConnection {
+    // Returns None once the client has closed the connection
+    pub async fn receive_message(&mut self) -> Result<Option<IpcMessage>> {
+        read_frame(&mut self.stream).await
+    }
+
+    pub async fn send_message(&mut self, message: &IpcMessage) -> Result<()> {
+        let framed = encode(message)?;
+        timeout(IPC_TIMEOUT, self.stream.write_all(&framed)).await.map_err(|_| timed_out())??;
+        Ok(())
     }
 }
 
@@ -127,6 +202,7 @@ impl Write for IpcClientStream {
 #[derive(Debug)]
 pub struct IpcClient {
     stream: IpcClientStream,
+    address: String,
 }
 
 impl IpcClient {
@@ -134,54 +210,38 @@ impl IpcClient {
         // Determine if this is a Unix socket path or TCP address
         if is_unix_socket_path(server_addr) {
             let stream = UnixStream::connect(server_addr)
-                .with_context(|| format!("Failed to connect to Unix socket at {}", server_addr))?;
+                .map_err(|e| OrionError::io(format!("Failed to connect to Unix socket at {}", server_addr), e))?;
 
-            Ok(IpcClient { stream: IpcClientStream::Unix(stream) })
+            Ok(IpcClient { stream: IpcClientStream::Unix(stream), address: server_addr.to_string() })
         } else {
             let stream = TcpStream::connect(server_addr)
-                .with_context(|| format!("Failed to connect to TCP server at {}", server_addr))?;
+                .map_err(|e| OrionError::io(format!("Failed to connect to TCP server at {}", server_addr), e))?;
 
-            Ok(IpcClient { stream: IpcClientStream::Tcp(stream) })
+            Ok(IpcClient { stream: IpcClientStream::Tcp(stream), address: server_addr.to_string() })
         }
     }
 
     pub fn send_message(&mut self, message: &IpcMessage) -> Result<()> {
-        let serialized = serde_json::to_vec(message)?;
-        if serialized.len() > MAX_MESSAGE_SIZE {
-            return Err(anyhow::anyhow!("Message too large: {} bytes", serialized.len()));
-        }
-
-        self.stream.write_all(&serialized)?;
+        self.stream.write_all(&encode(message)?)?;
         Ok(())
     }
 
     pub fn receive_message(&mut self) -> Result<IpcMessage> {
-        let mut buffer = vec![0; MAX_MESSAGE_SIZE];
-        let bytes_read = self.stream.read(&mut buffer)?;
-
-        if bytes_read > 0 {
-            let message: IpcMessage = serde_json::from_slice(&buffer[..bytes_read])?;
-            Ok(message)
-        } else {
-            Err(anyhow::anyhow!("Connection closed by server"))
-        }
+        read_frame_blocking(&mut self.stream)?.ok_or_else(|| OrionError::ipc("Connection closed by server"))
     }
 
     pub async fn send_message_async(&mut self, message: &IpcMessage) -> Result<()> {
-        let serialized = serde_json::to_vec(message)?;
-        if serialized.len() > MAX_MESSAGE_SIZE {
-            return Err(anyhow::anyhow!("Message too large: {} bytes", serialized.len()));
-        }
+        let framed = encode(message)?;
 
         match &self.stream {
             IpcClientStream::Tcp(tcp_stream) => {
                 let mut stream = TokioTcpStream::from_std(tcp_stream.try_clone()?)?;
-                timeout(IPC_TIMEOUT, stream.write_all(&serialized)).await??;
+                timeout(IPC_TIMEOUT, stream.write_all(&framed)).await.map_err(|_| timed_out())??;
             },
             IpcClientStream::Unix(_) => {
                 // For Unix sockets, we'll just use the synchronous API
                 // as it's more reliable across platforms
-                self.stream.write_all(&serialized)?;
+                self.stream.write_all(&framed)?;
             }
         }
 
@@ -189,31 +249,23 @@ impl IpcClient {
     }
 
     pub async fn receive_message_async(&mut self) -> Result<IpcMessage> {
-        let mut buffer = vec![0; MAX_MESSAGE_SIZE];
-
-        let bytes_read = match &self.stream {
+        let message = match &self.stream {
             IpcClientStream::Tcp(tcp_stream) => {
                 let mut stream = TokioTcpStream::from_std(tcp_stream.try_clone()?)?;
-                timeout(IPC_TIMEOUT, stream.read(&mut buffer)).await??
+                timeout(IPC_TIMEOUT, read_frame(&mut stream)).await.map_err(|_| timed_out())??
             },
             IpcClientStream::Unix(_) => {
                 // For Unix sockets, we'll just use the synchronous API
-                self.stream.read(&mut buffer)?
+                read_frame_blocking(&mut self.stream)?
             }
         };
 
-        if bytes_read > 0 {
-            let message: IpcMessage = serde_json::from_slice(&buffer[..bytes_read])?;
-            Ok(message)
-        } else {
-            Err(anyhow::anyhow!("Connection closed by server"))
-        }
+        message.ok_or_else(|| OrionError::ipc("Connection closed by server"))
     }
 
     pub fn connect_to_default() -> Result<Self> {
         // Use the XDG config directory for the socket
-        let proj_dirs = directories::ProjectDirs::from("", "", "orion")
-            .context("Failed to get project directories")?;
+        let proj_dirs = project_dirs()?;
 
         let config_dir = proj_dirs.config_dir();
         let socket_path = config_dir.join("orion.sock").to_string_lossy().to_string();
@@ -226,11 +278,13 @@ impl IpcClient {
             IpcClientStream::Tcp(stream) => {
                 stream.peer_addr().ok().map(|addr| addr.to_string())
             },
-            IpcClientStream::Unix(_stream) => {
-                // For Unix sockets, we don't have a direct way to get the path
-                // but we can return a best guess based on what we connected to
-                None
-            }
+            IpcClientStream::Unix(_stream) => Some(self.address.clone()),
         }
     }
+
+    // Another connection to the same server, for a request that shouldn't
+    // wait behind those on this one
+    pub fn connect_again(&self) -> Result<Self> {
+        Self::new(&self.address)
+    }
 }
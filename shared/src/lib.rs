@@ -1,8 +1,21 @@
+pub mod bangs;
 pub mod config;
+pub mod desktop;
+pub mod error;
+pub mod file_control;
+pub mod hotkey;
+pub mod index;
 pub mod ipc;
 pub mod logging;
+pub mod matcher;
 pub mod models;
+pub mod plugin;
+pub mod query;
+pub mod speech;
+pub mod template;
+pub mod theme;
 
+pub use error::{ErrorCode, ErrorReply, OrionError};
 pub use config::{Config, Profile, SearchConfig};
 pub use models::{Action, Bang, Command, IpcMessage, SearchQuery, SearchResponse, SearchResult};
 pub use ipc::IpcServer;
@@ -0,0 +1,184 @@
+// Fuzzy matching shared by the search providers. Match ranges are character
+// offsets into the matched text, `(start, end)` with `end` exclusive.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub score: f32,
+    pub ranges: Vec<(usize, usize)>,
+}
+
+// Text prepared for matching, with where each of its characters came from
+struct Folded {
+    chars: Vec<char>,
+    // Index of the original character each folded one comes from
+    origin: Vec<usize>,
+}
+
+impl Folded {
+    // Maps ranges over the folded text back onto the original characters,
+    // joining ranges that end up touching
+    fn original(&self, ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        let mut mapped: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            let (start, end) = (self.origin[start], self.origin[end - 1] + 1);
+            match mapped.last_mut() {
+                Some(last) if last.1 >= start => last.1 = last.1.max(end),
+                _ => mapped.push((start, end)),
+            }
+        }
+        mapped
+    }
+}
+
+// Decomposes compatibility characters (NFKD), drops the combining marks
+// that leaves, e.g. the accent of `é`, and lowercases, so `Köln` is found by
+// `koln` and `ﬁle` by `file`. With the `transliterate` feature, letters
+// from other scripts are spelled out in Latin, e.g. `北京` as `bei jing`.
+fn fold(text: &str) -> Folded {
+    let mut folded = Folded { chars: Vec::with_capacity(text.len()), origin: Vec::with_capacity(text.len()) };
+
+    for (idx, c) in text.chars().enumerate() {
+        let mut push = |c: char| {
+            for lower in c.to_lowercase() {
+                folded.chars.push(lower);
+                folded.origin.push(idx);
+            }
+        };
+
+        #[cfg(feature = "transliterate")]
+        if !c.is_ascii() && c.is_alphabetic() && !is_latin(c) {
+            if let Some(spelled) = deunicode::deunicode_char(c).filter(|s| !s.trim().is_empty()) {
+                spelled.chars().for_each(&mut push);
+                continue;
+            }
+        }
+
+        unicode_normalization::char::decompose_compatible(c, |d| {
+            if !unicode_normalization::char::is_combining_mark(d) {
+                push(d);
+            }
+        });
+    }
+
+    folded
+}
+
+// Latin letters only lose their accents, spelling them out would turn `ß`
+// into `ss` and shift nothing else
+#[cfg(feature = "transliterate")]
+fn is_latin(c: char) -> bool {
+    matches!(c as u32, 0x00C0..=0x024F | 0x1E00..=0x1EFF)
+}
+
+// The folded form of a text, for comparing or indexing outside the matcher
+pub fn normalize(text: &str) -> String {
+    fold(text).chars.into_iter().collect()
+}
+
+fn is_word_start(text: &[char], idx: usize) -> bool {
+    idx == 0 || !text[idx - 1].is_alphanumeric()
+}
+
+// Case-insensitive match of `query` against `text`. A prefix scores highest,
+// then a substring at a word start, then initials and runs at word starts
+// (`gdc` or `gdcons` for "Google Drive Console"), then a substring inside a
+// word; otherwise every query character has to appear in order.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<Match> {
+    let query = fold(query.trim()).chars;
+    let folded = fold(text);
+
+    let found = match_folded(&query, &folded.chars)?;
+    Some(Match {
+        score: found.score,
+        ranges: folded.original(found.ranges),
+    })
+}
+
+// The items matching the query with their indexes, best first and in their
+// original order among equal scores, the way dmenu style filtering lists them
+pub fn rank<S: AsRef<str>>(query: &str, items: &[S]) -> Vec<(usize, Match)> {
+    let mut matches: Vec<(usize, Match)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, item)| Some((idx, fuzzy_match(query, item.as_ref())?)))
+        .collect();
+    matches.sort_by(|a, b| b.1.score.partial_cmp(&a.1.score).unwrap_or(std::cmp::Ordering::Equal));
+    matches
+}
+
+fn match_folded(query: &[char], text: &[char]) -> Option<Match> {
+
+    if query.is_empty() {
+        return Some(Match { score: 0.0, ranges: Vec::new() });
+    }
+    if query.len() > text.len() {
+        return None;
+    }
+
+    let substring = text.windows(query.len()).position(|w| w == query);
+    match substring {
+        Some(0) => return Some(Match { score: 1.0, ranges: vec![(0, query.len())] }),
+        Some(start) if is_word_start(text, start) => {
+            return Some(Match { score: 0.9, ranges: vec![(start, start + query.len())] });
+        }
+        _ => {}
+    }
+
+    if let Some(ranges) = boundary_match(query, text) {
+        let score = if ranges.iter().all(|(start, end)| end - start == 1) { 0.85 } else { 0.82 };
+        return Some(Match { score, ranges });
+    }
+
+    if let Some(start) = substring {
+        return Some(Match { score: 0.8, ranges: vec![(start, start + query.len())] });
+    }
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut query_chars = query.iter().peekable();
+
+    for (idx, c) in text.iter().enumerate() {
+        match query_chars.peek() {
+            Some(q) if *q == c => {
+                query_chars.next();
+                match ranges.last_mut() {
+                    Some(last) if last.1 == idx => last.1 = idx + 1,
+                    _ => ranges.push((idx, idx + 1)),
+                }
+            }
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    if query_chars.peek().is_some() {
+        return None;
+    }
+
+    // Fewer, longer runs rank higher
+    let score = 0.3 + 0.4 / ranges.len() as f32;
+    Some(Match { score, ranges })
+}
+
+// Matches every query character either at a word start or right after the
+// previous matched character, so each run begins a word
+fn boundary_match(query: &[char], text: &[char]) -> Option<Vec<(usize, usize)>> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut matched = 0;
+
+    for (idx, c) in text.iter().enumerate() {
+        if matched == query.len() {
+            break;
+        }
+        if *c != query[matched] {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(last) if last.1 == idx => last.1 = idx + 1,
+            _ if is_word_start(text, idx) => ranges.push((idx, idx + 1)),
+            _ => continue,
+        }
+        matched += 1;
+    }
+
+    (matched == query.len()).then_some(ranges)
+}
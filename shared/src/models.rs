@@ -1,4 +1,6 @@
 use serde::{Serialize, Deserialize};
+use crate::error::ErrorReply;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use validator::Validate;
 
@@ -9,14 +11,140 @@ pub struct SearchResult {
     pub description: Option<String>,
     pub action: Action,
     pub score: f32,
+    // Matched character ranges in the title, `(start, end)` with `end` exclusive
+    #[serde(default)]
+    pub highlights: Vec<(usize, usize)>,
+    // Icon name from the system theme, or a path to an image
+    #[serde(default)]
+    pub icon: Option<String>,
+    // What kind of result this is, e.g. "Command" or "Bang", for grouping
+    #[serde(default)]
+    pub category: Option<String>,
+    // Short text shown beside the title, `description` being the longer
+    // line that goes with it
+    #[serde(default)]
+    pub subtitle: Option<String>,
+    // Text Tab puts in the input when this result is selected
+    #[serde(default)]
+    pub completion: Option<String>,
+    // Alternatives to `action`, e.g. copying a URL instead of opening it
+    #[serde(default)]
+    pub actions: Vec<ResultAction>,
 }
 
+// A secondary action offered for a result
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultAction {
+    pub label: String,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Action {
     OpenFile(PathBuf),
     ExecuteCommand(String),
+    // A shell command with a shell, directory or environment of its own
+    RunCommand { command: String, exec: ExecOptions },
     OpenUrl(String),
     Custom(String),
+    // Replaces the popup's input with this text instead of running anything
+    CompleteQuery(String),
+    // Opens a resolved bang URL, counting a use of each trigger
+    OpenBang { url: String, triggers: Vec<String> },
+    CopyToClipboard(String),
+    // Copies the text and pastes it into the window the popup was over
+    PasteText(String),
+    // Opens a file with a given application instead of the default one
+    OpenWith { path: PathBuf, app: String },
+    // Shows a file in its folder in the file manager
+    RevealInFolder(PathBuf),
+    // Moves a file or directory to the trash, where it can be restored
+    MoveToTrash(PathBuf),
+    // Deletes a file or directory for good, only run once the popup has
+    // asked for a second confirmation
+    DeletePermanently(PathBuf),
+    // Copies a file or folder out of a zip or tar archive into `to`, `path`
+    // running through the archive like a directory
+    ExtractFile { path: PathBuf, to: PathBuf },
+    // Copies or moves files and directories into the `to` directory on a
+    // background thread, reporting progress through `ListOperations`
+    CopyFiles { sources: Vec<PathBuf>, to: PathBuf },
+    MoveFiles { sources: Vec<PathBuf>, to: PathBuf },
+    // Runs a terminal command from inside `path`, `command` already having
+    // the path filled in wherever its template asked for it
+    OpenInTerminal { path: PathBuf, command: String },
+    // Unmounts the drive or network share mounted at the path
+    EjectVolume(PathBuf),
+    // Hands a result's data back to the external plugin that found it
+    RunPlugin { plugin: String, data: serde_json::Value },
+    // Calls a command function registered by one of the user's scripts
+    RunScript { script: String, function: String },
+    // Runs a shell command as root or administrator, the system asking
+    // for a password first
+    RunElevated(String),
+    // Starts an application, or brings it to the front when it's running:
+    // a bundle path or name on macOS, a path elsewhere
+    LaunchApp(String),
+    // Adds a custom bang searching the URL's site through the default bang
+    AddSiteBang(String),
+}
+
+// Where and how a shell command runs; what's left unset comes from `[exec]`
+// in the config, and from there the platform's defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecOptions {
+    // Program and arguments the command is passed after, e.g.
+    // `["bash", "-lc"]`; `sh -c`, or `cmd /C` on Windows, when empty
+    pub shell: Vec<String>,
+    // Directory the command starts in, `~` being the home directory, which
+    // is where it starts when none is given
+    pub cwd: Option<String>,
+    // Set on top of the service's own environment
+    pub env: BTreeMap<String, String>,
+}
+
+impl ExecOptions {
+    pub fn is_empty(&self) -> bool {
+        self.shell.is_empty() && self.cwd.is_none() && self.env.is_empty()
+    }
+
+    // These options, with `defaults` filling in what they leave unset and
+    // adding to the environment
+    pub fn or(&self, defaults: &ExecOptions) -> ExecOptions {
+        let mut env = defaults.env.clone();
+        env.extend(self.env.clone());
+        ExecOptions {
+            shell: if self.shell.is_empty() { defaults.shell.clone() } else { self.shell.clone() },
+            cwd: self.cwd.clone().or_else(|| defaults.cwd.clone()),
+            env,
+        }
+    }
+}
+
+impl Action {
+    // Long running, so the popup stays open to show their progress
+    pub fn is_file_operation(&self) -> bool {
+        matches!(self, Action::CopyFiles { .. } | Action::MoveFiles { .. })
+    }
+
+    // The URL or file the action opens, or the plugin result it runs,
+    // shared by results that lead to the same place. URLs compare without a
+    // trailing slash, so `https://example.com` and `https://example.com/`
+    // are one target.
+    pub fn target(&self) -> Option<String> {
+        match self {
+            Action::OpenUrl(url) | Action::OpenBang { url, .. } => {
+                Some(format!("url:{}", url.trim().trim_end_matches('/')))
+            }
+            Action::OpenFile(path) => Some(format!("file:{}", path.display())),
+            Action::LaunchApp(app) => Some(format!("app:{}", app)),
+            // Keeps past picks of one plugin's result from boosting another
+            // plugin's with the same title
+            Action::RunPlugin { plugin, data } => Some(format!("plugin:{}:{}", plugin, data)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -26,6 +154,12 @@ pub struct Command {
     pub description: String,
     pub action: Action,
     pub keywords: Vec<String>,
+    // What was typed when the command was picked, kept in the history
+    #[serde(default)]
+    pub query: Option<String>,
+    // Confirmed by the user, for commands `[security]` says to ask about
+    #[serde(default)]
+    pub confirmed: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -34,12 +168,111 @@ pub struct SearchQuery {
     pub text: String,
     #[validate(range(min = 1, max = 100))]
     pub max_results: usize,
+    // Restricts the search to the provider with this name
+    #[serde(default)]
+    pub provider_filter: Option<String>,
+    // Runs the search under this profile instead of the current one
+    #[serde(default)]
+    pub profile: Option<String>,
+    // Identifies the popup window asking, echoed back in the response
+    #[serde(default)]
+    pub session_id: Option<String>,
+    // Results to skip, `max_results` being the page size
+    #[serde(default)]
+    pub offset: usize,
+    // Tags what's logged for this search in both processes, so a slow one
+    // can be followed from the popup's log into the background's
+    #[serde(default)]
+    pub request_id: Option<String>,
+    // Counts up with each search a session sends; a newer one arriving
+    // cancels those from the same session still running
+    #[serde(default)]
+    pub generation: Option<u64>,
+}
+
+// Query prefix that scopes a search to a single provider, e.g. `files report`
+#[derive(Debug, Clone, Copy)]
+pub struct SearchMode {
+    pub prefix: &'static str,
+    pub label: &'static str,
+    pub provider: &'static str,
+}
+
+pub const SEARCH_MODES: &[SearchMode] = &[
+    SearchMode { prefix: "files", label: "Files", provider: "files" },
+    SearchMode { prefix: "apps", label: "Apps", provider: "apps" },
+    SearchMode { prefix: "clip", label: "Clipboard", provider: "clipboard" },
+    SearchMode { prefix: "hist", label: "History", provider: "history" },
+    SearchMode { prefix: "grep", label: "File contents", provider: "grep" },
+    SearchMode { prefix: "cd", label: "Jump", provider: "jump" },
+    SearchMode { prefix: "vol", label: "Volumes", provider: "volumes" },
+];
+
+// A copy or move started by a `CopyFiles` or `MoveFiles` action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationProgress {
+    pub id: u64,
+    // e.g. "Copying 3 items to Documents"
+    pub label: String,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub finished: bool,
+    // Why the operation stopped before finishing
+    pub error: Option<String>,
+}
+
+impl OperationProgress {
+    // 0.0 to 1.0, by bytes, or by files when there are no bytes to copy
+    pub fn fraction(&self) -> f32 {
+        if self.finished {
+            1.0
+        } else if self.bytes_total > 0 {
+            self.bytes_done as f32 / self.bytes_total as f32
+        } else if self.files_total > 0 {
+            self.files_done as f32 / self.files_total as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+// A registered search provider and the options it reads from its
+// `ProviderSettings`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderInfo {
+    pub name: String,
+    pub options: Vec<ProviderOption>,
+    // Weight the provider gets until a profile sets one
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderOption {
+    pub key: String,
+    pub label: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResponse {
     pub results: Vec<SearchResult>,
     pub query: SearchQuery,
+    // Results across all pages
+    #[serde(default)]
+    pub total: usize,
+    // Providers that took too long and are missing from the results
+    #[serde(default)]
+    pub timed_out: Vec<String>,
+    // Given up on for a newer search from the same session, so without
+    // results
+    #[serde(default)]
+    pub cancelled: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,33 +282,169 @@ pub enum IpcMessage {
     Command(Command),
     ConfigUpdate,
     Redirect(String),
-    Error(String),
-}
-// this  json 
-// #[derive(serde::Deserialize)]
-// struct Bang {
-//     c: String,  // category
-//     d: String,  // domain
-//     r: u32,     // rank
-//     s: String,  // site name
-//     sc: String, // subcategory
-//     t: String,  // trigger (prefix)
-//     u: String,  // url template
-// }
+    BangQuery(String),
+    BangSuggestions(Vec<Bang>),
+    // Asks the background to re-read bangs.json and custom_bangs.json,
+    // answered with `BangsRefreshed` or `Error`
+    ReloadBangs,
+    // Adds a custom bang to custom_bangs.json and reloads, replacing any
+    // custom bang with the same trigger
+    AddBang(Bang),
+    // Asks the background to download a fresh bangs.json and swap it in,
+    // answered with `BangsRefreshed` or `Error`
+    RefreshBangs,
+    BangsRefreshed(BangRefresh),
+    // Pages through every bang, disabled ones included, answered with
+    // `BangList` or `Error`
+    ListBangs(BangListQuery),
+    BangList(BangPage),
+    // Adds a browser's keyword search engines as custom bangs, answered
+    // with `BangsImported` or `Error`
+    ImportBangs(Browser),
+    BangsImported(BangRefresh),
+    ListProviders,
+    Providers(Vec<ProviderInfo>),
+    // Every plugin found at startup, loaded or not
+    ListPlugins,
+    Plugins(Vec<crate::plugin::PluginStatus>),
+    // Forgets every executed result, answered with `HistoryCleared` and the
+    // number of entries removed, or `Error`
+    ClearHistory,
+    HistoryCleared(usize),
+    // Asks for copies and moves still running, and those finished since the
+    // last time they were listed, answered with `OperationProgress`
+    ListOperations,
+    OperationProgress(Vec<OperationProgress>),
+    // Changes the background's log level until the config is next loaded,
+    // answered with the level now in use as `LogLevel`, or `Error`
+    SetLogLevel(String),
+    LogLevel(String),
+    // Asks whether the service is up and what it runs, answered with
+    // `Status`
+    GetStatus,
+    Status(ServiceStatus),
+    // Asks the service to exit, answered with `Stopping` just before it does
+    Shutdown,
+    Stopping,
+    // Asks for the most recent entries of the audit trail, newest first,
+    // answered with `Audit` or `Error`
+    ListAudit(usize),
+    Audit(Vec<AuditEntry>),
+    // Opens the popup with the text typed in, replacing one already open,
+    // answered with `PopupOpened` or `Error`
+    OpenPopup(String),
+    PopupOpened,
+    Error(ErrorReply),
+}
 
+// A result that was run, as the audit trail records it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    // Unix timestamp
+    pub at: i64,
+    pub profile: String,
+    pub query: Option<String>,
+    pub title: String,
+    pub action: Action,
+    pub outcome: AuditOutcome,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Succeeded,
+    // Why it didn't run or failed to, refusals by `[security]` included
+    Failed(String),
+}
+
+// A running background service, for `background --status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub pid: u32,
+    pub version: String,
+    pub uptime_secs: u64,
+    pub providers: usize,
+    // Plugins loaded, not counting those that failed to
+    pub plugins: usize,
+}
+// Outcome of loading, downloading or importing bangs; entries that didn't
+// parse or validate are skipped and described in `errors`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BangRefresh {
+    pub count: usize,
+    pub errors: Vec<String>,
+}
+
+// Browsers whose search engines can be imported as bangs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Browser {
+    Firefox,
+    Chrome,
+}
+
+impl Browser {
+    pub const ALL: [Browser; 2] = [Browser::Firefox, Browser::Chrome];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Browser::Firefox => "firefox",
+            Browser::Chrome => "chrome",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.as_str() == s.to_lowercase())
+    }
+}
+
+impl std::fmt::Display for Browser {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Browser::Firefox => write!(f, "Firefox"),
+            Browser::Chrome => write!(f, "Chrome"),
+        }
+    }
+}
+
+// Bangs whose trigger, name or domain contains `filter`, an empty filter
+// lists them all
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BangListQuery {
+    pub filter: String,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+// One page of a `BangListQuery`, `total` counts every matching bang
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BangPage {
+    pub query: BangListQuery,
+    pub bangs: Vec<Bang>,
+    pub total: usize,
+}
+
+// Reads both our own field names and DuckDuckGo's bang.js keys, so a list
+// straight from DuckDuckGo loads as is. Some DuckDuckGo entries have no
+// category or domain, those fall back to empty strings.
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct Bang {
-    #[validate(length(min = 1))]
+    #[serde(alias = "c", default)]
     pub category: String,
-    #[validate(length(min = 1))]
+    #[serde(alias = "d", default)]
     pub domain: String,
+    // DuckDuckGo's rank
+    #[serde(alias = "r", default)]
     pub score: i32,
+    #[serde(alias = "s")]
     #[validate(length(min = 1))]
     pub display_name: String,
-    #[validate(length(min = 1))]
+    #[serde(alias = "sc", default)]
     pub subcategory: String,
+    #[serde(alias = "t")]
     #[validate(length(min = 1))]
     pub trigger: String,
+    #[serde(alias = "u")]
     #[validate(length(min = 1))]
     pub url_template: String,
 }
@@ -116,9 +485,48 @@ impl SearchResult {
             description,
             action,
             score,
+            highlights: Vec::new(),
+            icon: None,
+            category: None,
+            subtitle: None,
+            completion: None,
+            actions: Vec::new(),
         }
     }
 
+    pub fn with_highlights(mut self, highlights: Vec<(usize, usize)>) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    pub fn with_icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    pub fn with_category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    pub fn with_subtitle(mut self, subtitle: impl Into<String>) -> Self {
+        self.subtitle = Some(subtitle.into());
+        self
+    }
+
+    pub fn with_completion(mut self, completion: impl Into<String>) -> Self {
+        self.completion = Some(completion.into());
+        self
+    }
+
+    pub fn with_action(mut self, label: impl Into<String>, action: Action) -> Self {
+        self.actions.push(ResultAction {
+            label: label.into(),
+            action,
+        });
+        self
+    }
+
     pub fn matches_query(&self, query: &str) -> bool {
         let query = query.to_lowercase();
         self.title.to_lowercase().contains(&query) ||
@@ -133,9 +541,21 @@ impl Command {
             description,
             action,
             keywords,
+            query: None,
+            confirmed: false,
         }
     }
 
+    pub fn with_query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    pub fn confirmed(mut self) -> Self {
+        self.confirmed = true;
+        self
+    }
+
     pub fn matches_query(&self, query: &str) -> bool {
         let query = query.to_lowercase();
         self.name.to_lowercase().contains(&query) ||
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{PROVIDER_TIMEOUT_RANGE, PROVIDER_WEIGHT_RANGE};
+use crate::error::{OrionError, Result};
+
+// The protocol external plugins speak: a program described by a
+// `plugin.toml`, started once and kept running, reading requests from its
+// stdin and writing responses to its stdout, one JSON object per line.
+//
+//   -> {"type":"query","id":1,"text":"hello","max_results":10,"settings":{}}
+//   <- {"type":"results","id":1,"results":[{"title":"Say hello","data":"hi"}]}
+//   -> {"type":"execute","data":"hi"}
+//
+// Queries arrive as the user types and a plugin too slow to answer one is
+// sent the next regardless, so responses carry the id of the query they
+// answer and late ones are dropped. `settings` holds the values of the
+// manifest's settings for the profile searched. `execute` gets back the
+// `data` of the result that was picked and expects no reply. Anything a
+// plugin writes to stderr ends up in the background service's output.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginRequest {
+    Query {
+        id: u64,
+        text: String,
+        max_results: usize,
+        settings: HashMap<String, String>,
+    },
+    Execute {
+        data: serde_json::Value,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginResponse {
+    Results {
+        id: u64,
+        results: Vec<PluginResult>,
+    },
+    // A query that failed, or with no id, a problem worth logging
+    Error {
+        #[serde(default)]
+        id: Option<u64>,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginResult {
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub subtitle: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    // 0.0 to 1.0, like every provider's
+    #[serde(default = "default_score")]
+    pub score: f32,
+    // Whatever the plugin needs to act on the result, handed back in
+    // `execute` when it's picked
+    #[serde(default)]
+    pub data: serde_json::Value,
+    // Opened by Orion itself instead, merging with the same URL or file
+    // found by other providers and ranked by the same past picks
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+fn default_score() -> f32 {
+    0.5
+}
+
+// `plugin.toml`, in a directory of its own under the plugins directory:
+//
+//   name = "weather"
+//   version = "1.0.0"
+//   exec = "weather.py"
+//   triggers = ["w"]
+//   permissions = ["network"]
+//   timeout = 2000
+//
+//   [[settings]]
+//   key = "city"
+//   label = "City to show without one typed"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    // Also its provider name, for `provider_filter` and profile settings
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    // The program to run, relative to the plugin's directory unless absolute
+    pub exec: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    // Words that scope a search to the plugin when typed first, as in
+    // `w berlin` or `w: berlin`
+    #[serde(default)]
+    pub triggers: Vec<String>,
+    #[serde(default)]
+    pub settings: Vec<PluginSetting>,
+    // What the plugin says it does beyond answering queries, shown in the
+    // settings so users know what they're running
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    // Used while a profile doesn't set the plugin's own weight or timeout
+    #[serde(default)]
+    pub weight: Option<f32>,
+    // Milliseconds, like `provider_timeout`
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginSetting {
+    pub key: String,
+    pub label: String,
+    // Used while the profile leaves the setting empty
+    #[serde(default)]
+    pub default: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Network,
+    Filesystem,
+    Clipboard,
+    // Starts other programs
+    Run,
+}
+
+impl Permission {
+    pub fn label(self) -> &'static str {
+        match self {
+            Permission::Network => "Network",
+            Permission::Filesystem => "Files",
+            Permission::Clipboard => "Clipboard",
+            Permission::Run => "Runs programs",
+        }
+    }
+}
+
+impl PluginManifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| OrionError::io(format!("Failed to read plugin manifest at {:?}", path), e))?;
+        let manifest: PluginManifest = toml::from_str(&content).map_err(|e| {
+            OrionError::Serde(format!("Failed to parse plugin manifest at {:?}: {}", path, e.message()))
+        })?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let valid_name = !self.name.is_empty()
+            && self.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(OrionError::validation(format!(
+                "Plugin name '{}' must be lowercase letters, digits, '-' or '_'",
+                self.name
+            )));
+        }
+        if self.version.trim().is_empty() {
+            return Err(OrionError::validation(format!("Plugin '{}' has no version", self.name)));
+        }
+        if self.exec.trim().is_empty() {
+            return Err(OrionError::validation(format!("Plugin '{}' has nothing to run in exec", self.name)));
+        }
+        if self.weight.is_some_and(|w| !PROVIDER_WEIGHT_RANGE.contains(&w)) {
+            return Err(OrionError::validation(format!(
+                "Weight of plugin '{}' must be between 0.0 and 2.0",
+                self.name
+            )));
+        }
+        if self.timeout.is_some_and(|t| !PROVIDER_TIMEOUT_RANGE.contains(&t)) {
+            return Err(OrionError::validation(format!(
+                "Timeout of plugin '{}' must be between 50 and 4000",
+                self.name
+            )));
+        }
+        for trigger in &self.triggers {
+            if trigger.is_empty() || trigger.contains(char::is_whitespace) || trigger.contains(':') {
+                return Err(OrionError::validation(format!(
+                    "Plugin '{}' has an invalid trigger '{}'",
+                    self.name, trigger
+                )));
+            }
+        }
+        for (idx, setting) in self.settings.iter().enumerate() {
+            if setting.key.trim().is_empty() {
+                return Err(OrionError::validation(format!("Plugin '{}' has a setting without a key", self.name)));
+            }
+            if self.settings[..idx].iter().any(|s| s.key == setting.key) {
+                return Err(OrionError::validation(format!(
+                    "Plugin '{}' lists the setting '{}' twice",
+                    self.name, setting.key
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn exec_path(&self, dir: &Path) -> PathBuf {
+        dir.join(&self.exec)
+    }
+}
+
+// What became of each plugin directory found at startup, for `ListPlugins`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginStatus {
+    pub dir: PathBuf,
+    // Missing when the manifest couldn't be read
+    pub manifest: Option<PluginManifest>,
+    pub state: PluginState,
+    // Problems that didn't stop it loading, like a trigger already taken
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PluginState {
+    Loaded,
+    // The manifest is missing or invalid
+    Invalid(String),
+    // Left out for clashing with a provider or plugin loaded before it
+    Conflict(String),
+}
+
+impl PluginStatus {
+    pub fn name(&self) -> String {
+        match &self.manifest {
+            Some(manifest) => manifest.name.clone(),
+            None => self.dir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::models::SEARCH_MODES;
+
+// `>` at the start of a query searches commands only, like a command palette
+pub const COMMANDS_PREFIX: char = '>';
+
+// Other names a `name:` prefix accepts for a provider, too short or common
+// to be popup modes typed with a space
+pub const PROVIDER_ALIASES: &[(&str, &str)] = &[("in", "grep"), ("j", "jump")];
+
+// Word that, followed by a category, resolves bangs in that category only,
+// e.g. `!cat:tech`
+pub const CATEGORY_PREFIX: &str = "!cat:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Word(String),
+    // Text typed between double quotes, matched as a whole
+    Phrase(String),
+    // `!w`, without the `!`
+    Bang(String),
+}
+
+// A search query split into what scopes it and what is searched for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedQuery {
+    // Provider named by a `files:` style prefix or `>`
+    pub provider: Option<String>,
+    // Category named by `!cat:`
+    pub category: Option<String>,
+    // `--exact` style flags, without the dashes
+    pub flags: Vec<String>,
+    pub terms: Vec<Term>,
+    // The query without the prefix, category and flags, quotes kept, which
+    // is what bangs are matched against and searched for
+    pub text: String,
+}
+
+impl ParsedQuery {
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f.eq_ignore_ascii_case(flag))
+    }
+
+    // Words and phrases to match results against, leaving out bangs
+    pub fn search_text(&self) -> String {
+        self.terms
+            .iter()
+            .filter_map(|term| match term {
+                Term::Word(text) | Term::Phrase(text) => Some(text.as_str()),
+                Term::Bang(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn phrases(&self) -> impl Iterator<Item = &str> {
+        self.terms.iter().filter_map(|term| match term {
+            Term::Phrase(text) => Some(text.as_str()),
+            _ => None,
+        })
+    }
+}
+
+pub fn parse(input: &str) -> ParsedQuery {
+    let mut parsed = ParsedQuery::default();
+    let mut input = input.trim_start();
+
+    if let Some(rest) = input.strip_prefix(COMMANDS_PREFIX) {
+        parsed.provider = Some("commands".to_string());
+        input = rest;
+    } else if let Some((mode, rest)) = SEARCH_MODES
+        .iter()
+        .find_map(|mode| Some((mode, input.strip_prefix(mode.prefix)?.strip_prefix(':')?)))
+    {
+        parsed.provider = Some(mode.provider.to_string());
+        input = rest;
+    } else if let Some((provider, rest)) = PROVIDER_ALIASES
+        .iter()
+        .find_map(|(alias, provider)| Some((provider, input.strip_prefix(alias)?.strip_prefix(':')?)))
+    {
+        parsed.provider = Some(provider.to_string());
+        input = rest;
+    }
+
+    let mut kept = Vec::new();
+    for token in tokenize(input) {
+        match token {
+            Token::Quoted { raw, inner } => {
+                if !inner.is_empty() {
+                    parsed.terms.push(Term::Phrase(inner.to_string()));
+                }
+                kept.push(raw);
+            }
+            Token::Plain(word) => {
+                if let Some(category) = word.strip_prefix(CATEGORY_PREFIX) {
+                    parsed.category = Some(category.to_string());
+                } else if let Some(flag) = word.strip_prefix("--").filter(|f| !f.is_empty()) {
+                    parsed.flags.push(flag.to_string());
+                } else {
+                    match word.strip_prefix('!').filter(|t| !t.is_empty()) {
+                        Some(trigger) => parsed.terms.push(Term::Bang(trigger.to_string())),
+                        None => parsed.terms.push(Term::Word(word.to_string())),
+                    }
+                    kept.push(word);
+                }
+            }
+        }
+    }
+    parsed.text = kept.join(" ");
+
+    parsed
+}
+
+enum Token<'a> {
+    Plain(&'a str),
+    Quoted { raw: &'a str, inner: &'a str },
+}
+
+// Splits on whitespace outside double quotes; an unclosed quote runs to the
+// end of the input
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let (inner, end) = match quoted.find('"') {
+                Some(idx) => (&quoted[..idx], idx + 2),
+                None => (quoted, rest.len()),
+            };
+            tokens.push(Token::Quoted { raw: &rest[..end], inner: inner.trim() });
+            rest = rest[end..].trim_start();
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            tokens.push(Token::Plain(&rest[..end]));
+            rest = rest[end..].trim_start();
+        }
+    }
+
+    tokens
+}
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use crate::config::SpeechConfig;
+use std::process::{Command, Stdio};
+
+// Reading text out loud through the platform's speech synthesizer, for
+// results read back after a voice search or on a key press. Like the other
+// desktop actions it isn't waited for, so the popup stays responsive and
+// can close while the speech goes on.
+
+// What synthesizers speak at when given no rate, in words per minute
+#[cfg(not(target_os = "macos"))]
+const DEFAULT_RATE: i64 = 180;
+
+pub fn speak(text: &str, config: &SpeechConfig) -> Result<()> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    #[cfg(target_os = "windows")]
+    let result = {
+        let mut script = String::from(
+            "Add-Type -AssemblyName System.Speech; $s = New-Object System.Speech.Synthesis.SpeechSynthesizer; ",
+        );
+        if let Some(voice) = &config.voice {
+            script.push_str(&format!("$s.SelectVoice('{}'); ", voice.replace('\'', "''")));
+        }
+        // SAPI rates go from -10 to 10 rather than words per minute
+        if let Some(rate) = config.rate {
+            script.push_str(&format!("$s.Rate = {}; ", ((rate as i64 - DEFAULT_RATE) / 20).clamp(-10, 10)));
+        }
+        script.push_str(&format!("$s.Speak('{}')", text.replace('\'', "''")));
+        spawn("powershell", &["-NoProfile", "-Command", &script])
+    };
+
+    #[cfg(target_os = "macos")]
+    let result = {
+        let mut args = Vec::new();
+        if let Some(voice) = &config.voice {
+            args.extend(["-v".to_string(), voice.clone()]);
+        }
+        if let Some(rate) = config.rate {
+            args.extend(["-r".to_string(), rate.to_string()]);
+        }
+        args.extend(["--".to_string(), text.to_string()]);
+        spawn("say", &args.iter().map(String::as_str).collect::<Vec<_>>())
+    };
+
+    // espeak-ng and espeak take words per minute; speech-dispatcher's
+    // spd-say takes a rate from -100 to 100 instead
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let result = {
+        let mut espeak = Vec::new();
+        let mut spd = Vec::new();
+        if let Some(voice) = &config.voice {
+            espeak.extend(["-v".to_string(), voice.clone()]);
+            spd.extend(["-y".to_string(), voice.clone()]);
+        }
+        if let Some(rate) = config.rate {
+            espeak.extend(["-s".to_string(), rate.to_string()]);
+            spd.extend(["-r".to_string(), ((rate as i64 - DEFAULT_RATE) / 2).clamp(-100, 100).to_string()]);
+        }
+        espeak.extend(["--".to_string(), text.to_string()]);
+        spd.extend(["--".to_string(), text.to_string()]);
+
+        let tools = [("espeak-ng", &espeak), ("spd-say", &spd), ("espeak", &espeak)];
+        let mut result = Err(anyhow::anyhow!("No speech synthesizer found"));
+        for (program, args) in tools {
+            result = spawn(program, &args.iter().map(String::as_str).collect::<Vec<_>>());
+            if result.is_ok() {
+                break;
+            }
+        }
+        result.context("Speaking needs espeak-ng, spd-say or espeak")
+    };
+
+    result
+}
+
+fn spawn(program: &str, args: &[&str]) -> Result<()> {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    Ok(())
+}
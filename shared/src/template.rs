@@ -0,0 +1,86 @@
+use crate::config::SearchConfig;
+use std::collections::BTreeMap;
+
+// Placeholders filled in for every URL template, which the config's own
+// variables can't shadow. `{s}` is DuckDuckGo's `{{{s}}}`.
+pub const BUILT_IN: [&str; 5] = ["s", "query", "query_raw", "domain", "lang"];
+
+// Values for a template's placeholders besides the query and the bang's
+// own fields
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVars {
+    pub lang: String,
+    pub user: BTreeMap<String, String>,
+}
+
+impl TemplateVars {
+    pub fn from_config(search: &SearchConfig) -> Self {
+        let lang = match search.lang.trim() {
+            "" => system_lang(),
+            lang => lang.to_string(),
+        };
+
+        Self {
+            lang,
+            user: search.template_vars.clone(),
+        }
+    }
+}
+
+// Language of the system locale, `de` for LANG=de_DE.UTF-8, English when
+// there's none
+fn system_lang() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+        .and_then(|locale| locale.split(['_', '.', '@']).next().map(str::to_lowercase))
+        .filter(|lang| !lang.is_empty())
+        .unwrap_or_else(|| "en".to_string())
+}
+
+pub fn is_variable_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Whether a template has somewhere to put the query
+pub fn takes_query(template: &str) -> bool {
+    ["{{{s}}}", "{s}", "{query}", "{query_raw}"]
+        .iter()
+        .any(|placeholder| template.contains(placeholder))
+}
+
+// Replaces every `{name}` in a template with what `lookup` gives for the
+// name, leaving braces that don't name a known variable untouched so URLs
+// that happen to contain them survive
+pub fn render(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let tail = &rest[start..];
+
+        let (name, len) = match tail.strip_prefix("{{{s}}}") {
+            Some(_) => ("s", "{{{s}}}".len()),
+            None => match tail[1..].split_once('}') {
+                Some((name, _)) if is_variable_name(name) => (name, name.len() + 2),
+                _ => ("", 0),
+            },
+        };
+
+        match (len > 0).then(|| lookup(name)).flatten() {
+            Some(value) => {
+                rendered.push_str(&value);
+                rest = &tail[len..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &tail[1..];
+            }
+        }
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
@@ -0,0 +1,79 @@
+use std::fmt;
+
+use crate::config::{AppearanceConfig, ThemeMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    // Parses `#rrggbb`, the leading `#` being optional
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.trim();
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if hex.len() != 6 {
+            return None;
+        }
+
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+pub const DEFAULT_ACCENT: Rgb = Rgb(0x59, 0x8f, 0xfa);
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+pub const DEFAULT_CORNER_RADIUS: f32 = 0.0;
+
+const LIGHT_BACKGROUND: Rgb = Rgb(0xff, 0xff, 0xff);
+const LIGHT_TEXT: Rgb = Rgb(0x1a, 0x1a, 0x1a);
+const DARK_BACKGROUND: Rgb = Rgb(0x20, 0x22, 0x25);
+const DARK_TEXT: Rgb = Rgb(0xf0, 0xf0, 0xf0);
+
+// Popup look resolved from `[appearance]`, with defaults filled in for
+// anything unset or unparsable
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopupTheme {
+    pub background: Rgb,
+    pub text: Rgb,
+    pub accent: Rgb,
+    // Font family name, None for the toolkit default
+    pub font: Option<String>,
+    pub font_size: f32,
+    pub corner_radius: f32,
+    // Window opacity between 0.2 and 1.0
+    pub opacity: f32,
+}
+
+impl PopupTheme {
+    pub fn from_appearance(appearance: &AppearanceConfig) -> Self {
+        let (background, text) = match appearance.theme {
+            ThemeMode::Light => (LIGHT_BACKGROUND, LIGHT_TEXT),
+            // No platform theme detection yet, so System follows the light default
+            ThemeMode::System => (LIGHT_BACKGROUND, LIGHT_TEXT),
+            ThemeMode::Dark => (DARK_BACKGROUND, DARK_TEXT),
+        };
+        let color = |hex: &Option<String>, default: Rgb| {
+            hex.as_deref().and_then(Rgb::from_hex).unwrap_or(default)
+        };
+
+        Self {
+            background: color(&appearance.background_color, background),
+            text: color(&appearance.text_color, text),
+            accent: color(&appearance.accent_color, DEFAULT_ACCENT),
+            font: appearance.font.clone().filter(|f| !f.trim().is_empty()),
+            font_size: appearance.font_size.unwrap_or(DEFAULT_FONT_SIZE).clamp(10.0, 32.0),
+            corner_radius: appearance.corner_radius.unwrap_or(DEFAULT_CORNER_RADIUS).max(0.0),
+            opacity: appearance.opacity.unwrap_or(1.0).clamp(0.2, 1.0),
+        }
+    }
+
+    // Rounded corners and opacity need a transparent window to show
+    pub fn is_translucent(&self) -> bool {
+        self.opacity < 1.0 || self.corner_radius > 0.0
+    }
+}